@@ -0,0 +1,548 @@
+//! Generating random syntax trees for fuzzing.
+//!
+//! Each [Component] is generated together with the source text it was parsed from.
+//! Nodes are added in the order the parser adds them and with the spans it gives them,
+//! so parsing [Component::source] gives back the same tree.
+
+use ::arbitrary::{Arbitrary, Result, Unstructured};
+use claw_common::make_source;
+
+use crate::{
+    merge, BinaryOp, Component, Expression, ExpressionId, ExternalType, FnType, Import, ImportFrom,
+    NameId, PackageName, PlainImport, PrimitiveType, Span, Statement, StatementId, TypeId, UnaryOp,
+    ValType,
+};
+
+/// Identifiers that aren't keywords, including some kebab-case ones.
+const NAMES: &[&str] = &[
+    "a",
+    "b",
+    "c",
+    "x",
+    "y",
+    "n",
+    "foo",
+    "bar",
+    "baz",
+    "count",
+    "total",
+    "value",
+    "next-value",
+    "do-it",
+    "item2",
+];
+
+const PRIMITIVES: &[PrimitiveType] = &[
+    PrimitiveType::Bool,
+    PrimitiveType::U8,
+    PrimitiveType::S8,
+    PrimitiveType::U16,
+    PrimitiveType::S16,
+    PrimitiveType::U32,
+    PrimitiveType::S32,
+    PrimitiveType::U64,
+    PrimitiveType::S64,
+    PrimitiveType::F32,
+    PrimitiveType::F64,
+    PrimitiveType::String,
+    PrimitiveType::V128,
+];
+
+const BINARY_OPERATORS: &[(BinaryOp, &str)] = &[
+    (BinaryOp::Multiply, "*"),
+    (BinaryOp::Divide, "/"),
+    (BinaryOp::Modulo, "%"),
+    (BinaryOp::Add, "+"),
+    (BinaryOp::Subtract, "-"),
+    (BinaryOp::BitShiftL, "<<"),
+    (BinaryOp::BitShiftR, ">>"),
+    (BinaryOp::ArithShiftR, ">>>"),
+    (BinaryOp::LessThan, "<"),
+    (BinaryOp::LessThanEqual, "<="),
+    (BinaryOp::GreaterThan, ">"),
+    (BinaryOp::GreaterThanEqual, ">="),
+    (BinaryOp::Equals, "=="),
+    (BinaryOp::NotEquals, "!="),
+    (BinaryOp::BitOr, "|"),
+    (BinaryOp::BitXor, "^"),
+    (BinaryOp::BitAnd, "&"),
+    (BinaryOp::LogicalOr, "or"),
+    (BinaryOp::LogicalAnd, "and"),
+];
+
+/// The characters used in string literals, none of which need escaping.
+const STRING_CHARS: &[char] = &['a', 'b', 'z', 'A', '0', '9', ' ', '-', '.'];
+
+/// How deeply expressions and blocks can be nested.
+const MAX_DEPTH: u32 = 4;
+
+impl<'a> Arbitrary<'a> for Component {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut generator = Generator {
+            u,
+            comp: Component::new(make_source("arbitrary.claw", "")),
+            text: String::new(),
+            indent: 0,
+        };
+        generator.component()?;
+
+        let Generator { mut comp, text, .. } = generator;
+        comp.set_source(make_source("arbitrary.claw", &text));
+        Ok(comp)
+    }
+}
+
+/// Whether a generated expression is a binary expression,
+/// which has to be parenthesized when it's an operand.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ExpressionKind {
+    Leaf,
+    Call,
+    Unary,
+    Binary,
+}
+
+struct Generator<'u, 'a> {
+    u: &'u mut Unstructured<'a>,
+    comp: Component,
+    /// The source text written so far
+    text: String,
+    indent: usize,
+}
+
+impl Generator<'_, '_> {
+    fn component(&mut self) -> Result<()> {
+        self.repeat(8, |gen| {
+            let has_init = gen.comp.init().is_some();
+            match gen.u.int_in_range(0..=4)? {
+                0 => gen.import()?,
+                1 => gen.global()?,
+                2 if !has_init => gen.init()?,
+                _ => gen.function()?,
+            }
+            gen.text.push('\n');
+            Ok(())
+        })
+    }
+
+    fn import(&mut self) -> Result<()> {
+        self.token("import ");
+        let import = if self.u.arbitrary()? {
+            self.token("{ ");
+            let mut items = Vec::new();
+            self.repeat(3, |gen| {
+                if !items.is_empty() {
+                    gen.token(", ");
+                }
+                let name = gen.name()?;
+                let alias = gen.alias()?;
+                items.push((name, alias));
+                Ok(())
+            })?;
+            self.token(" } from ");
+            let namespace = self.word()?;
+            self.token(":");
+            let name = self.word()?;
+            self.token("/");
+            let interface = self.word()?;
+            Import::ImportFrom(ImportFrom {
+                items,
+                package: PackageName {
+                    namespace,
+                    name,
+                    version: None,
+                },
+                interface,
+            })
+        } else {
+            let ident = self.name()?;
+            let alias = self.alias()?;
+            self.token(": ");
+            let external_type = if self.u.arbitrary()? {
+                self.token("func");
+                ExternalType::Function(self.signature()?)
+            } else {
+                ExternalType::Value(self.valtype()?)
+            };
+            Import::Plain(PlainImport {
+                ident,
+                alias,
+                external_type,
+            })
+        };
+        self.token(";");
+        self.comp.push_import(import);
+        Ok(())
+    }
+
+    fn alias(&mut self) -> Result<Option<NameId>> {
+        if !self.u.arbitrary()? {
+            return Ok(None);
+        }
+        self.token(" as ");
+        Ok(Some(self.name()?))
+    }
+
+    fn global(&mut self) -> Result<()> {
+        let exported = self.export()?;
+        self.token("let ");
+        let mutable = self.mutable()?;
+        let ident = self.name()?;
+        self.token(": ");
+        let type_id = self.valtype()?;
+        self.token(" = ");
+        let init_value = self.expression(0)?;
+        self.token(";");
+        self.comp.push_global(crate::Global {
+            exported,
+            mutable,
+            ident,
+            type_id,
+            init_value,
+        });
+        Ok(())
+    }
+
+    fn function(&mut self) -> Result<()> {
+        let exported = self.export()?;
+        let constant = self.u.arbitrary()?;
+        if constant {
+            self.token("const ");
+        }
+        self.token("func ");
+        let ident = self.name()?;
+        let FnType { params, results } = self.signature()?;
+        self.token(" ");
+        let (body, body_span) = self.block(0)?;
+        self.comp.push_function(crate::Function {
+            exported,
+            constant,
+            ident,
+            params,
+            results,
+            body,
+            body_span,
+        });
+        Ok(())
+    }
+
+    fn init(&mut self) -> Result<()> {
+        let span = self.token("init");
+        let ident = self.comp.new_name("init", span);
+        self.token(" ");
+        let (body, body_span) = self.block(0)?;
+        self.comp.push_init(crate::Function {
+            exported: false,
+            constant: false,
+            ident,
+            params: Vec::new(),
+            results: None,
+            body,
+            body_span,
+        });
+        Ok(())
+    }
+
+    /// The parameters and result of a function, which are the same for imported functions.
+    fn signature(&mut self) -> Result<FnType> {
+        self.token("(");
+        let mut params = Vec::new();
+        self.repeat(4, |gen| {
+            if !params.is_empty() {
+                gen.token(", ");
+            }
+            let name = gen.name()?;
+            gen.token(": ");
+            let type_id = gen.valtype()?;
+            params.push((name, type_id));
+            Ok(())
+        })?;
+        self.token(")");
+        let results = match self.u.arbitrary()? {
+            true => {
+                self.token(" -> ");
+                Some(self.valtype()?)
+            }
+            false => None,
+        };
+        Ok(FnType { params, results })
+    }
+
+    /// A block and the span from its opening brace to its closing brace.
+    fn block(&mut self, depth: u32) -> Result<(Vec<StatementId>, Span)> {
+        let start = self.token("{");
+        self.indent += 1;
+        let mut statements = Vec::new();
+        self.repeat(6, |gen| {
+            gen.newline();
+            statements.push(gen.statement(depth)?);
+            Ok(())
+        })?;
+        self.indent -= 1;
+        if !statements.is_empty() {
+            self.newline();
+        }
+        let end = self.token("}");
+        Ok((statements, merge(&start, &end)))
+    }
+
+    fn statement(&mut self, depth: u32) -> Result<StatementId> {
+        let max = if depth < MAX_DEPTH { 4 } else { 3 };
+        let (statement, span) = match self.u.int_in_range(0..=max)? {
+            0 => {
+                let start = self.token("let ");
+                let mutable = self.mutable()?;
+                let ident = self.name()?;
+                let annotation = match self.u.arbitrary()? {
+                    true => {
+                        self.token(": ");
+                        Some(self.valtype()?)
+                    }
+                    false => None,
+                };
+                let expression = match self.u.ratio(1, 4)? {
+                    true => None,
+                    false => {
+                        self.token(" = ");
+                        Some(self.expression(0)?)
+                    }
+                };
+                let end = self.token(";");
+                let statement = Statement::Let(crate::Let {
+                    mutable,
+                    ident,
+                    annotation,
+                    expression,
+                });
+                (statement, merge(&start, &end))
+            }
+            1 => {
+                let ident = self.name()?;
+                self.token(" = ");
+                let expression = self.expression(0)?;
+                let end = self.token(";");
+                let start = self.comp.name_span(ident);
+                let statement = Statement::Assign(crate::Assign { ident, expression });
+                (statement, merge(&start, &end))
+            }
+            2 => {
+                let start = self.token("return");
+                let expression = match self.u.arbitrary()? {
+                    true => {
+                        self.token(" ");
+                        Some(self.expression(0)?)
+                    }
+                    false => None,
+                };
+                let end = self.token(";");
+                let statement = Statement::Return(crate::Return { expression });
+                (statement, merge(&start, &end))
+            }
+            3 => {
+                let (call, start, _) = self.call(0)?;
+                let end = self.token(";");
+                (Statement::Call(call), merge(&start, &end))
+            }
+            _ => {
+                let start = self.token("if ");
+                let condition = self.expression(0)?;
+                self.token(" ");
+                let (block, end) = self.block(depth + 1)?;
+                let statement = Statement::If(crate::If { condition, block });
+                (statement, merge(&start, &end))
+            }
+        };
+        Ok(self.comp.new_statement(statement, span))
+    }
+
+    fn expression(&mut self, depth: u32) -> Result<ExpressionId> {
+        let kind = self.expression_kind(depth)?;
+        self.expression_of_kind(kind, depth)
+    }
+
+    fn expression_kind(&mut self, depth: u32) -> Result<ExpressionKind> {
+        if depth >= MAX_DEPTH {
+            return Ok(ExpressionKind::Leaf);
+        }
+        let kind = match self.u.int_in_range(0..=5)? {
+            0 | 1 => ExpressionKind::Leaf,
+            2 => ExpressionKind::Call,
+            3 => ExpressionKind::Unary,
+            _ => ExpressionKind::Binary,
+        };
+        Ok(kind)
+    }
+
+    fn expression_of_kind(&mut self, kind: ExpressionKind, depth: u32) -> Result<ExpressionId> {
+        let (expression, span) = match kind {
+            ExpressionKind::Leaf => self.leaf()?,
+            ExpressionKind::Call => {
+                let (call, start, end) = self.call(depth)?;
+                (call.into(), merge(&start, &end))
+            }
+            ExpressionKind::Unary => {
+                let start = self.token("-");
+                let inner = self.operand(depth + 1)?;
+                let end = self.comp.expression_span(inner);
+                let unary = crate::UnaryExpression {
+                    op: UnaryOp::Negate,
+                    inner,
+                };
+                (unary.into(), merge(&start, &end))
+            }
+            ExpressionKind::Binary => {
+                let left = self.operand(depth + 1)?;
+                let (op, text) = *self.u.choose(BINARY_OPERATORS)?;
+                self.token(" ");
+                self.token(text);
+                self.token(" ");
+                let right = self.operand(depth + 1)?;
+                let span = merge(
+                    &self.comp.expression_span(left),
+                    &self.comp.expression_span(right),
+                );
+                let binary = crate::BinaryExpression { op, left, right };
+                (binary.into(), span)
+            }
+        };
+        Ok(self.comp.new_expression(expression, span))
+    }
+
+    /// An operand of an operator, which is parenthesized when it's a binary expression.
+    ///
+    /// Parentheses aren't part of the tree, so its span is the span of the expression inside them.
+    fn operand(&mut self, depth: u32) -> Result<ExpressionId> {
+        let kind = self.expression_kind(depth)?;
+        if kind != ExpressionKind::Binary {
+            return self.expression_of_kind(kind, depth);
+        }
+        self.token("(");
+        let expression = self.expression_of_kind(kind, depth)?;
+        self.token(")");
+        Ok(expression)
+    }
+
+    fn leaf(&mut self) -> Result<(Expression, Span)> {
+        let leaf = match self.u.int_in_range(0..=4)? {
+            0 => {
+                let ident = self.name()?;
+                let span = self.comp.name_span(ident);
+                (crate::Identifier { ident }.into(), span)
+            }
+            1 => {
+                let value: u64 = self.u.arbitrary()?;
+                let span = self.token(&value.to_string());
+                (crate::Literal::Integer(value).into(), span)
+            }
+            2 => {
+                let whole: u16 = self.u.arbitrary()?;
+                let fraction: u16 = self.u.arbitrary()?;
+                let text = format!("{}.{}", whole, fraction);
+                let value = text.parse().expect("Formatted floats are valid");
+                let span = self.token(&text);
+                (crate::Literal::Float(value).into(), span)
+            }
+            3 => {
+                let mut value = String::new();
+                self.repeat(12, |gen| {
+                    value.push(*gen.u.choose(STRING_CHARS)?);
+                    Ok(())
+                })?;
+                let span = self.token(&format!("\"{}\"", value));
+                (crate::Literal::String(value).into(), span)
+            }
+            _ => {
+                let enum_name = self.name()?;
+                self.token("::");
+                let case_name = self.name()?;
+                let span = merge(
+                    &self.comp.name_span(enum_name),
+                    &self.comp.name_span(case_name),
+                );
+                let literal = crate::EnumLiteral {
+                    enum_name,
+                    case_name,
+                };
+                (literal.into(), span)
+            }
+        };
+        Ok(leaf)
+    }
+
+    /// A call, with the spans of its name and its closing parenthesis.
+    fn call(&mut self, depth: u32) -> Result<(crate::Call, Span, Span)> {
+        let ident = self.name()?;
+        self.token("(");
+        let mut args = Vec::new();
+        self.repeat(4, |gen| {
+            if !args.is_empty() {
+                gen.token(", ");
+            }
+            args.push(gen.expression(depth + 1)?);
+            Ok(())
+        })?;
+        let end = self.token(")");
+        let start = self.comp.name_span(ident);
+        Ok((crate::Call { ident, args }, start, end))
+    }
+
+    fn valtype(&mut self) -> Result<TypeId> {
+        let primitive = *self.u.choose(PRIMITIVES)?;
+        let span = self.token(&primitive.to_string());
+        Ok(self.comp.new_type(ValType::Primitive(primitive), span))
+    }
+
+    fn name(&mut self) -> Result<NameId> {
+        let word = self.u.choose(NAMES)?;
+        let span = self.token(word);
+        Ok(self.comp.new_name(word, span))
+    }
+
+    /// A part of a package or interface name, which isn't a node in the tree.
+    fn word(&mut self) -> Result<String> {
+        let word = self.u.choose(NAMES)?;
+        self.token(word);
+        Ok(word.to_string())
+    }
+
+    fn export(&mut self) -> Result<bool> {
+        let exported = self.u.arbitrary()?;
+        if exported {
+            self.token("export ");
+        }
+        Ok(exported)
+    }
+
+    fn mutable(&mut self) -> Result<bool> {
+        let mutable = self.u.arbitrary()?;
+        if mutable {
+            self.token("mut ");
+        }
+        Ok(mutable)
+    }
+
+    /// Generate up to `max` of something, stopping early when the input runs out.
+    fn repeat(&mut self, max: u32, mut f: impl FnMut(&mut Self) -> Result<()>) -> Result<()> {
+        for _ in 0..max {
+            if !self.u.arbitrary::<bool>()? {
+                break;
+            }
+            f(self)?;
+        }
+        Ok(())
+    }
+
+    /// Write some text, returning its span.
+    fn token(&mut self, text: &str) -> Span {
+        let start = self.text.len();
+        self.text.push_str(text);
+        Span::from((start, text.len()))
+    }
+
+    fn newline(&mut self) {
+        self.text.push('\n');
+        for _ in 0..self.indent {
+            self.text.push_str("    ");
+        }
+    }
+}
@@ -2,12 +2,10 @@
 //! of the AST and contains root items (e.g. import, function),
 //! inner AST nodes (e.g. expression), and the source code.
 
-use std::collections::HashMap;
-
-use cranelift_entity::{entity_impl, PrimaryMap};
+use cranelift_entity::{entity_impl, EntityRef, PrimaryMap, SecondaryMap};
 
 use crate::PackageName;
-use claw_common::Source;
+use claw_common::{Interner, Source, Symbol};
 
 use super::{
     expressions::{Expression, ExpressionId},
@@ -21,6 +19,7 @@ use super::{
 /// IDs must only be passed to the [Component] they were
 /// made by and this is not statically or dynamically validated.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ImportId(u32);
 entity_impl!(ImportId, "import");
 
@@ -29,6 +28,7 @@ entity_impl!(ImportId, "import");
 /// IDs must only be passed to the [Component] they were
 /// made by and this is not statically or dynamically validated.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct GlobalId(u32);
 entity_impl!(GlobalId, "global");
 
@@ -37,9 +37,21 @@ entity_impl!(GlobalId, "global");
 /// IDs must only be passed to the [Component] they were
 /// made by and this is not statically or dynamically validated.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FunctionId(u32);
 entity_impl!(FunctionId, "func");
 
+/// The ID of an inner AST node that has a source span.
+///
+/// Returned by [Component::node_at].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum NodeId {
+    Name(NameId),
+    Type(TypeId),
+    Expression(ExpressionId),
+    Statement(StatementId),
+}
+
 /// Each Claw source file represents a Component
 /// and this struct represents the root of the AST.
 ///
@@ -59,19 +71,22 @@ pub struct Component {
     type_defs: PrimaryMap<TypeDefId, TypeDefinition>,
     globals: PrimaryMap<GlobalId, Global>,
     functions: PrimaryMap<FunctionId, Function>,
+    init: Option<FunctionId>,
 
-    // Inner items
+    // Inner items, each with a span for every ID
     types: PrimaryMap<TypeId, ValType>,
-    type_spans: HashMap<TypeId, Span>,
+    type_spans: SecondaryMap<TypeId, Span>,
 
     statements: PrimaryMap<StatementId, Statement>,
-    statement_spans: HashMap<StatementId, Span>,
+    statement_spans: SecondaryMap<StatementId, Span>,
 
     expressions: PrimaryMap<ExpressionId, Expression>,
-    expression_spans: HashMap<ExpressionId, Span>,
+    expression_spans: SecondaryMap<ExpressionId, Span>,
 
-    names: PrimaryMap<NameId, String>,
-    name_spans: HashMap<NameId, Span>,
+    names: PrimaryMap<NameId, Symbol>,
+    name_spans: SecondaryMap<NameId, Span>,
+    /// The text of the names, which lives as long as the AST
+    interner: Interner,
 }
 
 impl Component {
@@ -85,14 +100,16 @@ impl Component {
             type_defs: Default::default(),
             globals: Default::default(),
             functions: Default::default(),
+            init: None,
             types: Default::default(),
-            type_spans: Default::default(),
+            type_spans: span_table(),
             statements: Default::default(),
-            statement_spans: Default::default(),
+            statement_spans: span_table(),
             expressions: Default::default(),
-            expression_spans: Default::default(),
+            expression_spans: span_table(),
             names: Default::default(),
-            name_spans: Default::default(),
+            name_spans: span_table(),
+            interner: Interner::new(),
         }
     }
 
@@ -101,6 +118,12 @@ impl Component {
         self.src.clone()
     }
 
+    /// Replace the source code, for ASTs that are built before their source text is complete.
+    #[cfg(feature = "arbitrary")]
+    pub(crate) fn set_source(&mut self, src: Source) {
+        self.src = src;
+    }
+
     /// Add a top-level import item to the AST.
     pub fn push_import(&mut self, import: Import) -> ImportId {
         self.imports.push(import)
@@ -161,27 +184,52 @@ impl Component {
         &self.functions[function]
     }
 
+    /// Add the `init` block to the AST as a function item.
+    ///
+    /// It's kept with the other functions but can't be called by name,
+    /// and is run once when the component is instantiated.
+    pub fn push_init(&mut self, function: Function) -> FunctionId {
+        let id = self.functions.push(function);
+        self.init = Some(id);
+        id
+    }
+
+    /// The function holding the `init` block, if there is one.
+    pub fn init(&self) -> Option<FunctionId> {
+        self.init
+    }
+
+    /// Whether a function is the `init` block rather than a named function item.
+    pub fn is_init(&self, function: FunctionId) -> bool {
+        self.init == Some(function)
+    }
+
     /// Create a new name AST node.
-    pub fn new_name(&mut self, name: String, span: Span) -> NameId {
-        let id = self.names.push(name);
-        self.name_spans.insert(id, span);
+    pub fn new_name(&mut self, name: &str, span: Span) -> NameId {
+        let id = self.names.push(self.interner.intern(name));
+        self.name_spans[id] = span;
         id
     }
 
     /// Get the value of a name.
     pub fn get_name(&self, id: NameId) -> &str {
-        self.names.get(id).unwrap()
+        self.interner.resolve(self.names[id])
+    }
+
+    /// Get the interned symbol for a name, which is the same for names with the same value.
+    pub fn get_symbol(&self, id: NameId) -> Symbol {
+        self.names[id]
     }
 
     /// Get the source span for this name.
     pub fn name_span(&self, id: NameId) -> Span {
-        *self.name_spans.get(&id).unwrap()
+        self.name_spans[id]
     }
 
     /// Create a new valtype AST node.
     pub fn new_type(&mut self, valtype: ValType, span: Span) -> TypeId {
         let id = self.types.push(valtype);
-        self.type_spans.insert(id, span);
+        self.type_spans[id] = span;
         id
     }
 
@@ -192,13 +240,13 @@ impl Component {
 
     /// Get the source span for this valtype.
     pub fn type_span(&self, id: TypeId) -> Span {
-        *self.type_spans.get(&id).unwrap()
+        self.type_spans[id]
     }
 
     /// Create a new statement AST node.
     pub fn new_statement(&mut self, statement: Statement, span: Span) -> StatementId {
         let id = self.statements.push(statement);
-        self.statement_spans.insert(id, span);
+        self.statement_spans[id] = span;
         id
     }
 
@@ -209,13 +257,13 @@ impl Component {
 
     /// Get the source span for this statement.
     pub fn statement_span(&self, id: StatementId) -> Span {
-        *self.statement_spans.get(&id).unwrap()
+        self.statement_spans[id]
     }
 
     /// Create a new expression AST node.
     pub fn new_expression(&mut self, expression: Expression, span: Span) -> ExpressionId {
         let id = self.expressions.push(expression);
-        self.expression_spans.insert(id, span);
+        self.expression_spans[id] = span;
         id
     }
 
@@ -224,9 +272,95 @@ impl Component {
         self.expressions.get(id).unwrap()
     }
 
+    /// Replace the value of an expression AST node, keeping its span.
+    pub fn replace_expression(&mut self, id: ExpressionId, expression: Expression) {
+        self.expressions[id] = expression;
+    }
+
     /// Get the source span for this expression.
     pub fn expression_span(&self, id: ExpressionId) -> Span {
-        *self.expression_spans.get(&id).unwrap()
+        self.expression_spans[id]
+    }
+
+    /// Get the source span for any inner AST node.
+    pub fn node_span(&self, id: NodeId) -> Span {
+        match id {
+            NodeId::Name(id) => self.name_span(id),
+            NodeId::Type(id) => self.type_span(id),
+            NodeId::Expression(id) => self.expression_span(id),
+            NodeId::Statement(id) => self.statement_span(id),
+        }
+    }
+
+    /// Find the innermost AST node whose span covers a byte offset.
+    ///
+    /// When nodes have the same span, names are preferred over types,
+    /// types over expressions, and expressions over statements.
+    pub fn node_at(&self, offset: usize) -> Option<NodeId> {
+        let names = self
+            .name_spans
+            .iter()
+            .map(|(id, span)| (NodeId::Name(id), span));
+        let types = self
+            .type_spans
+            .iter()
+            .map(|(id, span)| (NodeId::Type(id), span));
+        let expressions = self
+            .expression_spans
+            .iter()
+            .map(|(id, span)| (NodeId::Expression(id), span));
+        let statements = self
+            .statement_spans
+            .iter()
+            .map(|(id, span)| (NodeId::Statement(id), span));
+
+        let mut innermost: Option<(NodeId, usize)> = None;
+        for (id, span) in names.chain(types).chain(expressions).chain(statements) {
+            let covers = span.offset() <= offset && offset < span.offset() + span.len();
+            // Only strictly smaller spans replace earlier, more specific, kinds of node
+            let smaller = innermost.is_none_or(|(_, len)| span.len() < len);
+            if covers && smaller {
+                innermost = Some((id, span.len()));
+            }
+        }
+        innermost.map(|(id, _)| id)
+    }
+}
+
+/// An empty span table. Every node gets its span when it's created,
+/// so the default is never observed.
+fn span_table<K: EntityRef>() -> SecondaryMap<K, Span> {
+    SecondaryMap::with_default(Span::new(0.into(), 0))
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Component {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use crate::serialize::Nodes;
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("Component", 9)?;
+        state.serialize_field("imports", &Nodes(&self.imports, None))?;
+        state.serialize_field("type_defs", &Nodes(&self.type_defs, None))?;
+        state.serialize_field("globals", &Nodes(&self.globals, None))?;
+        state.serialize_field("functions", &Nodes(&self.functions, None))?;
+        state.serialize_field("init", &self.init)?;
+        state.serialize_field("types", &Nodes(&self.types, Some(&self.type_spans)))?;
+        state.serialize_field(
+            "statements",
+            &Nodes(&self.statements, Some(&self.statement_spans)),
+        )?;
+        state.serialize_field(
+            "expressions",
+            &Nodes(&self.expressions, Some(&self.expression_spans)),
+        )?;
+        let names: PrimaryMap<NameId, &str> = self
+            .names
+            .values()
+            .map(|name| self.interner.resolve(*name))
+            .collect();
+        state.serialize_field("names", &Nodes(&names, Some(&self.name_spans)))?;
+        state.end()
     }
 }
 
@@ -234,6 +368,7 @@ impl Component {
 ///
 /// There are two versions: plain and import-from.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Import {
     Plain(PlainImport),
     ImportFrom(ImportFrom),
@@ -245,6 +380,7 @@ pub enum Import {
 /// import foo: func() -> u32;
 /// ```
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PlainImport {
     /// The name of the item to import.
     pub ident: NameId,
@@ -261,11 +397,13 @@ pub struct PlainImport {
 /// import { foo } from bar;
 /// ```
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ImportFrom {
     /// The first name is the imported item's name
     /// The second optional name is an alias
     pub items: Vec<(NameId, Option<NameId>)>,
     /// The package being imported from
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize::display"))]
     pub package: PackageName,
     /// Which interface from the package to import
     pub interface: String,
@@ -276,9 +414,18 @@ pub struct ImportFrom {
 /// ```claw
 /// func(foo: string) -> bool
 /// ```
+///
+/// Or the type of a value provided by the host, which is read
+/// through an imported `get-<name>` function.
+///
+/// ```claw
+/// u32
+/// ```
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ExternalType {
     Function(FnType),
+    Value(TypeId),
 }
 
 /// Global Item AST node (Claw)
@@ -287,6 +434,7 @@ pub enum ExternalType {
 /// let foo: u32 = 1;
 /// ```
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Global {
     /// Whether the global is exported.
     ///
@@ -312,13 +460,21 @@ pub struct Global {
 ///     return false;
 /// }
 /// ```
+///
+/// The `init` block is also stored as a function named `init`
+/// without any parameters or results, see [Component::push_init].
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Function {
     /// Whether the global is exported.
     ///
     /// Indicated by the keyword `export` in front
     /// of the function item.
     pub exported: bool,
+    /// Whether calls to the function can be evaluated at compile time.
+    ///
+    /// Indicated by the keyword `const` before `func`.
+    pub constant: bool,
     /// The name of the function.
     pub ident: NameId,
     /// The function's parameters.
@@ -331,4 +487,7 @@ pub struct Function {
     pub results: Option<TypeId>,
     /// The body of the function.
     pub body: Vec<StatementId>,
+    /// The span of the body, from its opening to its closing brace.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub body_span: Span,
 }
@@ -2,6 +2,7 @@ use super::NameId;
 use cranelift_entity::entity_impl;
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ExpressionId(u32);
 entity_impl!(ExpressionId, "expression");
 
@@ -10,6 +11,7 @@ pub trait ContextEq<Context> {
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Expression {
     Identifier(Identifier),
     Enum(EnumLiteral),
@@ -58,6 +60,7 @@ impl ContextEq<super::Component> for Expression {
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Identifier {
     pub ident: NameId,
 }
@@ -75,6 +78,7 @@ impl ContextEq<super::Component> for Identifier {
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct EnumLiteral {
     pub enum_name: NameId,
     pub case_name: NameId,
@@ -94,6 +98,7 @@ impl ContextEq<super::Component> for EnumLiteral {
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Literal {
     Integer(u64),
     Float(f64),
@@ -113,6 +118,7 @@ impl ContextEq<super::Component> for Literal {
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Call {
     pub ident: NameId,
     pub args: Vec<ExpressionId>,
@@ -141,11 +147,13 @@ impl ContextEq<super::Component> for Call {
 // Unary Operators
 
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum UnaryOp {
     Negate,
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct UnaryExpression {
     pub op: UnaryOp,
     pub inner: ExpressionId,
@@ -168,6 +176,7 @@ impl ContextEq<super::Component> for UnaryExpression {
 // Binary Operators
 
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum BinaryOp {
     // Arithmetic Operations
     Multiply,
@@ -195,11 +204,22 @@ pub enum BinaryOp {
     BitAnd,
 
     // Logical Operations
+    /// `a or b` only evaluates `b` if `a` is false
     LogicalOr,
+    /// `a and b` only evaluates `b` if `a` is true
     LogicalAnd,
 }
 
+impl BinaryOp {
+    /// Whether the right operand is only evaluated when the left one doesn't decide
+    /// the result, so its calls and traps are skipped otherwise.
+    pub fn short_circuits(&self) -> bool {
+        matches!(self, BinaryOp::LogicalOr | BinaryOp::LogicalAnd)
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BinaryExpression {
     pub op: BinaryOp,
     pub left: ExpressionId,
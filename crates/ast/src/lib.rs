@@ -1,7 +1,15 @@
 pub mod component;
 pub mod expressions;
 pub mod statements;
+mod structure;
+pub mod symbols;
 pub mod types;
+pub mod visit;
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+#[cfg(feature = "serde")]
+mod serialize;
 
 use cranelift_entity::entity_impl;
 use miette::SourceSpan;
@@ -13,6 +21,7 @@ pub type Span = SourceSpan;
 pub use component::*;
 pub use expressions::*;
 pub use statements::*;
+pub use symbols::*;
 pub use types::*;
 
 pub fn merge(left: &Span, right: &Span) -> Span {
@@ -23,6 +32,7 @@ pub fn merge(left: &Span, right: &Span) -> Span {
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct NameId(u32);
 entity_impl!(NameId, "name");
 
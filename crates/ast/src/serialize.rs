@@ -0,0 +1,73 @@
+//! Helpers for serializing the AST with serde.
+//!
+//! Nodes are stored in arenas, so each arena is serialized as a list
+//! of entries that pair a node with its ID and source span.
+
+use std::fmt::Display;
+
+use cranelift_entity::{EntityRef, PrimaryMap, SecondaryMap};
+use serde::ser::{SerializeSeq, SerializeStruct};
+use serde::{Serialize, Serializer};
+
+use crate::Span;
+
+/// Serialize a value using its [Display] implementation.
+pub(crate) fn display<T: Display, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+/// The nodes in an arena, with their spans if they have any.
+pub(crate) struct Nodes<'a, K: EntityRef, V>(
+    pub &'a PrimaryMap<K, V>,
+    pub Option<&'a SecondaryMap<K, Span>>,
+);
+
+impl<'a, K, V> Serialize for Nodes<'a, K, V>
+where
+    K: EntityRef + Serialize,
+    V: Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Nodes(nodes, spans) = self;
+        let mut seq = serializer.serialize_seq(Some(nodes.len()))?;
+        for (id, node) in nodes.iter() {
+            let span = spans.and_then(|spans| spans.get(id)).copied();
+            seq.serialize_element(&Entry { id, span, node })?;
+        }
+        seq.end()
+    }
+}
+
+struct Entry<'a, K, V> {
+    id: K,
+    span: Option<Span>,
+    node: &'a V,
+}
+
+impl<'a, K: Serialize, V: Serialize> Serialize for Entry<'a, K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Entry", 3)?;
+        state.serialize_field("id", &self.id)?;
+        state.serialize_field("span", &self.span.map(SpanRepr::from))?;
+        state.serialize_field("node", self.node)?;
+        state.end()
+    }
+}
+
+#[derive(Serialize)]
+struct SpanRepr {
+    offset: usize,
+    len: usize,
+}
+
+impl From<Span> for SpanRepr {
+    fn from(span: Span) -> Self {
+        Self {
+            offset: span.offset(),
+            len: span.len(),
+        }
+    }
+}
@@ -3,10 +3,12 @@ use cranelift_entity::entity_impl;
 use super::{expressions::ExpressionId, types::TypeId, Call, NameId};
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct StatementId(u32);
 entity_impl!(StatementId, "name");
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Statement {
     Let(Let),
     Assign(Assign),
@@ -16,26 +18,31 @@ pub enum Statement {
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Let {
     pub mutable: bool,
     pub ident: NameId,
     pub annotation: Option<TypeId>,
-    pub expression: ExpressionId,
+    /// The initial value, if the local is given one where it's declared
+    pub expression: Option<ExpressionId>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Assign {
     pub ident: NameId,
     pub expression: ExpressionId,
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct If {
     pub condition: ExpressionId,
     pub block: Vec<StatementId>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Return {
     pub expression: Option<ExpressionId>,
 }
@@ -0,0 +1,215 @@
+//! Comparing the structure of two syntax trees, ignoring where their nodes are.
+
+use crate::{
+    Call, Component, Expression, ExpressionId, ExternalType, FnType, Function, Import, NameId,
+    Statement, StatementId, TypeId, ValType,
+};
+
+impl Component {
+    /// Whether two components have the same items and nodes, ignoring spans and the source.
+    ///
+    /// Nodes are compared by following their IDs, so the components can have been built
+    /// in different orders, e.g. from two differently formatted versions of the same code.
+    /// Items of each kind must be in the same order, apart from the `init` block.
+    /// Type definitions, which the parser doesn't produce yet, are only counted.
+    pub fn same_structure(&self, other: &Component) -> bool {
+        let pair = Pair {
+            left: self,
+            right: other,
+        };
+        let init = match (self.init(), other.init()) {
+            (Some(left), Some(right)) => pair.blocks(
+                &self.get_function(left).body,
+                &other.get_function(right).body,
+            ),
+            (None, None) => true,
+            _ => false,
+        };
+
+        init && all(
+            self.iter_imports().map(|(_, import)| import),
+            other.iter_imports().map(|(_, import)| import),
+            |left, right| pair.imports(left, right),
+        ) && self.iter_type_defs().count() == other.iter_type_defs().count()
+            && all(
+                self.iter_globals().map(|(_, global)| global),
+                other.iter_globals().map(|(_, global)| global),
+                |left, right| {
+                    left.exported == right.exported
+                        && left.mutable == right.mutable
+                        && pair.names(left.ident, right.ident)
+                        && pair.types(left.type_id, right.type_id)
+                        && pair.expressions(left.init_value, right.init_value)
+                },
+            )
+            && all(functions(self), functions(other), |left, right| {
+                pair.functions(left, right)
+            })
+    }
+}
+
+/// The functions of a component, without the `init` block.
+fn functions(comp: &Component) -> impl Iterator<Item = &Function> {
+    comp.iter_functions()
+        .filter(move |(id, _)| !comp.is_init(*id))
+        .map(|(_, function)| function)
+}
+
+/// Whether both sequences have the same length and `eq` holds for each pair of elements.
+fn all<T, U>(
+    left: impl IntoIterator<Item = T>,
+    right: impl IntoIterator<Item = U>,
+    eq: impl Fn(T, U) -> bool,
+) -> bool {
+    let mut left = left.into_iter();
+    let mut right = right.into_iter();
+    loop {
+        match (left.next(), right.next()) {
+            (Some(l), Some(r)) => {
+                if !eq(l, r) {
+                    return false;
+                }
+            }
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// Whether both are missing, or both are present and `eq` holds for them.
+fn option<T>(left: Option<T>, right: Option<T>, eq: impl Fn(T, T) -> bool) -> bool {
+    match (left, right) {
+        (Some(l), Some(r)) => eq(l, r),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// The components whose nodes are being compared, on the left and right.
+#[derive(Clone, Copy)]
+struct Pair<'a> {
+    left: &'a Component,
+    right: &'a Component,
+}
+
+impl<'a> Pair<'a> {
+    fn names(&self, left: NameId, right: NameId) -> bool {
+        self.left.get_name(left) == self.right.get_name(right)
+    }
+
+    fn types(&self, left: TypeId, right: TypeId) -> bool {
+        match (self.left.get_type(left), self.right.get_type(right)) {
+            (ValType::Primitive(l), ValType::Primitive(r)) => l == r,
+            (ValType::Result(l), ValType::Result(r)) => {
+                self.types(l.ok, r.ok) && self.types(l.err, r.err)
+            }
+            _ => false,
+        }
+    }
+
+    fn params(&self, left: &[(NameId, TypeId)], right: &[(NameId, TypeId)]) -> bool {
+        all(left, right, |(l_name, l_type), (r_name, r_type)| {
+            self.names(*l_name, *r_name) && self.types(*l_type, *r_type)
+        })
+    }
+
+    fn fn_types(&self, left: &FnType, right: &FnType) -> bool {
+        self.params(&left.params, &right.params)
+            && option(left.results, right.results, |l, r| self.types(l, r))
+    }
+
+    fn imports(&self, left: &Import, right: &Import) -> bool {
+        match (left, right) {
+            (Import::Plain(l), Import::Plain(r)) => {
+                let external_type = match (&l.external_type, &r.external_type) {
+                    (ExternalType::Function(l), ExternalType::Function(r)) => self.fn_types(l, r),
+                    (ExternalType::Value(l), ExternalType::Value(r)) => self.types(*l, *r),
+                    _ => false,
+                };
+                self.names(l.ident, r.ident)
+                    && option(l.alias, r.alias, |l, r| self.names(l, r))
+                    && external_type
+            }
+            (Import::ImportFrom(l), Import::ImportFrom(r)) => {
+                l.package == r.package
+                    && l.interface == r.interface
+                    && all(
+                        &l.items,
+                        &r.items,
+                        |(l_name, l_alias), (r_name, r_alias)| {
+                            self.names(*l_name, *r_name)
+                                && option(*l_alias, *r_alias, |l, r| self.names(l, r))
+                        },
+                    )
+            }
+            _ => false,
+        }
+    }
+
+    fn functions(&self, left: &Function, right: &Function) -> bool {
+        left.exported == right.exported
+            && left.constant == right.constant
+            && self.names(left.ident, right.ident)
+            && self.params(&left.params, &right.params)
+            && option(left.results, right.results, |l, r| self.types(l, r))
+            && self.blocks(&left.body, &right.body)
+    }
+
+    fn blocks(&self, left: &[StatementId], right: &[StatementId]) -> bool {
+        all(left, right, |l, r| self.statements(*l, *r))
+    }
+
+    fn statements(&self, left: StatementId, right: StatementId) -> bool {
+        let expression = |l, r| self.expressions(l, r);
+        match (
+            self.left.get_statement(left),
+            self.right.get_statement(right),
+        ) {
+            (Statement::Let(l), Statement::Let(r)) => {
+                l.mutable == r.mutable
+                    && self.names(l.ident, r.ident)
+                    && option(l.annotation, r.annotation, |l, r| self.types(l, r))
+                    && option(l.expression, r.expression, expression)
+            }
+            (Statement::Assign(l), Statement::Assign(r)) => {
+                self.names(l.ident, r.ident) && self.expressions(l.expression, r.expression)
+            }
+            (Statement::Call(l), Statement::Call(r)) => self.calls(l, r),
+            (Statement::If(l), Statement::If(r)) => {
+                self.expressions(l.condition, r.condition) && self.blocks(&l.block, &r.block)
+            }
+            (Statement::Return(l), Statement::Return(r)) => {
+                option(l.expression, r.expression, expression)
+            }
+            _ => false,
+        }
+    }
+
+    fn calls(&self, left: &Call, right: &Call) -> bool {
+        self.names(left.ident, right.ident)
+            && all(&left.args, &right.args, |l, r| self.expressions(*l, *r))
+    }
+
+    fn expressions(&self, left: ExpressionId, right: ExpressionId) -> bool {
+        match (
+            self.left.get_expression(left),
+            self.right.get_expression(right),
+        ) {
+            (Expression::Identifier(l), Expression::Identifier(r)) => self.names(l.ident, r.ident),
+            (Expression::Enum(l), Expression::Enum(r)) => {
+                self.names(l.enum_name, r.enum_name) && self.names(l.case_name, r.case_name)
+            }
+            (Expression::Literal(l), Expression::Literal(r)) => l == r,
+            (Expression::Call(l), Expression::Call(r)) => self.calls(l, r),
+            (Expression::Unary(l), Expression::Unary(r)) => {
+                l.op == r.op && self.expressions(l.inner, r.inner)
+            }
+            (Expression::Binary(l), Expression::Binary(r)) => {
+                l.op == r.op
+                    && self.expressions(l.left, r.left)
+                    && self.expressions(l.right, r.right)
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! A flat outline of the top-level items in a [Component],
+//! for editor outline views and quick navigation.
+
+use super::{Component, ExternalType, Import, NameId, Span, TypeId};
+
+/// What kind of item a [DocumentSymbol] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Import,
+    Global,
+    Function,
+}
+
+/// A top-level item in a [Component].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentSymbol {
+    /// The name the item is available as
+    pub name: String,
+    pub kind: SymbolKind,
+    /// A short description, e.g. a function's signature or a global's type
+    pub detail: String,
+    /// The span of the item's name
+    pub span: Span,
+    pub exported: bool,
+}
+
+impl Component {
+    /// List the top-level items in the order they appear in the source.
+    pub fn document_symbols(&self) -> Vec<DocumentSymbol> {
+        let mut symbols = Vec::new();
+        for (_, import) in self.iter_imports() {
+            match import {
+                Import::Plain(import) => {
+                    let detail = match &import.external_type {
+                        ExternalType::Function(fn_type) => {
+                            format!("func{}", signature(self, &fn_type.params, fn_type.results))
+                        }
+                        ExternalType::Value(type_id) => self.get_type(*type_id).name(self),
+                    };
+                    let name = import.alias.unwrap_or(import.ident);
+                    symbols.push(self.symbol(name, SymbolKind::Import, detail, false));
+                }
+                Import::ImportFrom(import) => {
+                    for (name, alias) in import.items.iter() {
+                        let detail = format!(
+                            "from {}:{}/{}",
+                            import.package.namespace, import.package.name, import.interface
+                        );
+                        let name = alias.unwrap_or(*name);
+                        symbols.push(self.symbol(name, SymbolKind::Import, detail, false));
+                    }
+                }
+            }
+        }
+        for (_, global) in self.iter_globals() {
+            let mut detail = self.get_type(global.type_id).name(self);
+            if global.mutable {
+                detail.insert_str(0, "mut ");
+            }
+            symbols.push(self.symbol(global.ident, SymbolKind::Global, detail, global.exported));
+        }
+        for (_, function) in self.iter_functions() {
+            let detail = format!(
+                "func{}",
+                signature(self, &function.params, function.results)
+            );
+            let kind = SymbolKind::Function;
+            symbols.push(self.symbol(function.ident, kind, detail, function.exported));
+        }
+        symbols.sort_by_key(|symbol| symbol.span.offset());
+        symbols
+    }
+
+    fn symbol(
+        &self,
+        name: NameId,
+        kind: SymbolKind,
+        detail: String,
+        exported: bool,
+    ) -> DocumentSymbol {
+        DocumentSymbol {
+            name: self.get_name(name).to_owned(),
+            kind,
+            detail,
+            span: self.name_span(name),
+            exported,
+        }
+    }
+}
+
+/// A function signature as it would be written in source code, e.g. `(a: u32) -> u32`.
+pub fn signature(comp: &Component, params: &[(NameId, TypeId)], results: Option<TypeId>) -> String {
+    let params: Vec<String> = params
+        .iter()
+        .map(|(name, type_id)| {
+            format!(
+                "{}: {}",
+                comp.get_name(*name),
+                comp.get_type(*type_id).name(comp)
+            )
+        })
+        .collect();
+    let mut signature = format!("({})", params.join(", "));
+    if let Some(results) = results {
+        signature.push_str(&format!(" -> {}", comp.get_type(results).name(comp)));
+    }
+    signature
+}
@@ -1,23 +1,29 @@
+use std::fmt;
+
 use cranelift_entity::entity_impl;
 
 use super::{Component, NameId};
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TypeId(u32);
 entity_impl!(TypeId, "type");
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TypeDefId(u32);
 entity_impl!(TypeDefId, "typedef");
 
 /// The type for all values
 #[derive(Debug, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ValType {
     Result(ResultType),
     Primitive(PrimitiveType),
 }
 
 #[derive(Debug, Hash, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum PrimitiveType {
     // The boolean type
     Bool,
@@ -38,15 +44,86 @@ pub enum PrimitiveType {
     F64,
     // String type
     String,
+    // 128-bit SIMD vector
+    V128,
+}
+
+impl PrimitiveType {
+    /// The smallest and largest values of an integer type,
+    /// or [None] if this isn't an integer type.
+    pub fn integer_range(&self) -> Option<(i128, i128)> {
+        let range = match self {
+            PrimitiveType::U8 => (0, u8::MAX as i128),
+            PrimitiveType::S8 => (i8::MIN as i128, i8::MAX as i128),
+            PrimitiveType::U16 => (0, u16::MAX as i128),
+            PrimitiveType::S16 => (i16::MIN as i128, i16::MAX as i128),
+            PrimitiveType::U32 => (0, u32::MAX as i128),
+            PrimitiveType::S32 => (i32::MIN as i128, i32::MAX as i128),
+            PrimitiveType::U64 => (0, u64::MAX as i128),
+            PrimitiveType::S64 => (i64::MIN as i128, i64::MAX as i128),
+            _ => return None,
+        };
+        Some(range)
+    }
+
+    /// Whether values of this type are implicitly converted to `other` where it's expected.
+    ///
+    /// Only conversions that can't lose information are implicit: integers widen to
+    /// larger integers of the same signedness, and `f32` widens to `f64`.
+    pub fn widens_to(&self, other: PrimitiveType) -> bool {
+        if *self == PrimitiveType::F32 {
+            return other == PrimitiveType::F64;
+        }
+        match (self.integer_range(), other.integer_range()) {
+            (Some((min, max)), Some((other_min, other_max))) => {
+                (min < 0) == (other_min < 0) && other_min <= min && max < other_max
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for PrimitiveType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PrimitiveType::Bool => "bool",
+            PrimitiveType::U8 => "u8",
+            PrimitiveType::S8 => "s8",
+            PrimitiveType::U16 => "u16",
+            PrimitiveType::S16 => "s16",
+            PrimitiveType::U32 => "u32",
+            PrimitiveType::S32 => "s32",
+            PrimitiveType::U64 => "u64",
+            PrimitiveType::S64 => "s64",
+            PrimitiveType::F32 => "f32",
+            PrimitiveType::F64 => "f64",
+            PrimitiveType::String => "string",
+            PrimitiveType::V128 => "v128",
+        };
+        write!(f, "{}", name)
+    }
 }
 
 #[derive(Debug, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ResultType {
     pub ok: TypeId,
     pub err: TypeId,
 }
 
 impl ValType {
+    /// The name of this type as it would be written in source code.
+    pub fn name(&self, comp: &Component) -> String {
+        match self {
+            ValType::Result(result) => {
+                let ok = comp.get_type(result.ok).name(comp);
+                let err = comp.get_type(result.err).name(comp);
+                format!("result<{}, {}>", ok, err)
+            }
+            ValType::Primitive(primitive) => primitive.to_string(),
+        }
+    }
+
     pub fn eq(&self, other: &Self, comp: &Component) -> bool {
         match (self, other) {
             (ValType::Result(left), ValType::Result(right)) => {
@@ -67,16 +144,19 @@ impl ValType {
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum TypeDefinition {
     Record(RecordTypeDef),
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RecordTypeDef {
     fields: Vec<(NameId, TypeId)>,
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FnType {
     pub params: Vec<(NameId, TypeId)>,
     pub results: Option<TypeId>,
@@ -0,0 +1,201 @@
+//! Contains the [Visitor] trait for traversing the AST.
+//!
+//! Each `visit_*` method defaults to calling the matching `walk_*`
+//! function, which visits the children of the node. Implementations
+//! override the methods for the nodes they care about and call the
+//! `walk_*` function themselves if they want to keep descending.
+
+use crate::{
+    Component, Expression, ExpressionId, ExternalType, Function, FunctionId, Global, GlobalId,
+    Import, ImportId, NameId, Statement, StatementId, TypeId, ValType,
+};
+
+/// A traversal over the nodes of a [Component].
+pub trait Visitor<'ast> {
+    fn visit_import(&mut self, comp: &'ast Component, id: ImportId, import: &'ast Import) {
+        _ = id;
+        walk_import(self, comp, import);
+    }
+
+    fn visit_global(&mut self, comp: &'ast Component, id: GlobalId, global: &'ast Global) {
+        _ = id;
+        walk_global(self, comp, global);
+    }
+
+    fn visit_function(&mut self, comp: &'ast Component, id: FunctionId, function: &'ast Function) {
+        _ = id;
+        walk_function(self, comp, function);
+    }
+
+    fn visit_statement(&mut self, comp: &'ast Component, statement: StatementId) {
+        walk_statement(self, comp, statement);
+    }
+
+    fn visit_expression(&mut self, comp: &'ast Component, expression: ExpressionId) {
+        walk_expression(self, comp, expression);
+    }
+
+    fn visit_type(&mut self, comp: &'ast Component, type_id: TypeId) {
+        walk_type(self, comp, type_id);
+    }
+
+    /// Called for every name, both where it's defined and where it's used.
+    fn visit_name(&mut self, comp: &'ast Component, name: NameId) {
+        _ = (comp, name);
+    }
+}
+
+/// Visit every import, global, and function in the component.
+pub fn walk_component<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, comp: &'ast Component) {
+    for (id, import) in comp.iter_imports() {
+        visitor.visit_import(comp, id, import);
+    }
+    for (id, global) in comp.iter_globals() {
+        visitor.visit_global(comp, id, global);
+    }
+    for (id, function) in comp.iter_functions() {
+        visitor.visit_function(comp, id, function);
+    }
+}
+
+pub fn walk_import<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    comp: &'ast Component,
+    import: &'ast Import,
+) {
+    match import {
+        Import::Plain(import) => {
+            visitor.visit_name(comp, import.ident);
+            if let Some(alias) = import.alias {
+                visitor.visit_name(comp, alias);
+            }
+            match &import.external_type {
+                ExternalType::Function(fn_type) => {
+                    for (name, type_id) in fn_type.params.iter() {
+                        visitor.visit_name(comp, *name);
+                        visitor.visit_type(comp, *type_id);
+                    }
+                    if let Some(results) = fn_type.results {
+                        visitor.visit_type(comp, results);
+                    }
+                }
+                ExternalType::Value(type_id) => visitor.visit_type(comp, *type_id),
+            }
+        }
+        Import::ImportFrom(import) => {
+            for (name, alias) in import.items.iter() {
+                visitor.visit_name(comp, *name);
+                if let Some(alias) = alias {
+                    visitor.visit_name(comp, *alias);
+                }
+            }
+        }
+    }
+}
+
+pub fn walk_global<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    comp: &'ast Component,
+    global: &'ast Global,
+) {
+    visitor.visit_name(comp, global.ident);
+    visitor.visit_type(comp, global.type_id);
+    visitor.visit_expression(comp, global.init_value);
+}
+
+pub fn walk_function<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    comp: &'ast Component,
+    function: &'ast Function,
+) {
+    visitor.visit_name(comp, function.ident);
+    for (name, type_id) in function.params.iter() {
+        visitor.visit_name(comp, *name);
+        visitor.visit_type(comp, *type_id);
+    }
+    if let Some(results) = function.results {
+        visitor.visit_type(comp, results);
+    }
+    for statement in function.body.iter() {
+        visitor.visit_statement(comp, *statement);
+    }
+}
+
+pub fn walk_statement<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    comp: &'ast Component,
+    statement: StatementId,
+) {
+    match comp.get_statement(statement) {
+        Statement::Let(let_) => {
+            visitor.visit_name(comp, let_.ident);
+            if let Some(annotation) = let_.annotation {
+                visitor.visit_type(comp, annotation);
+            }
+            if let Some(expression) = let_.expression {
+                visitor.visit_expression(comp, expression);
+            }
+        }
+        Statement::Assign(assign) => {
+            visitor.visit_name(comp, assign.ident);
+            visitor.visit_expression(comp, assign.expression);
+        }
+        Statement::Call(call) => {
+            visitor.visit_name(comp, call.ident);
+            for arg in call.args.iter() {
+                visitor.visit_expression(comp, *arg);
+            }
+        }
+        Statement::If(if_) => {
+            visitor.visit_expression(comp, if_.condition);
+            for statement in if_.block.iter() {
+                visitor.visit_statement(comp, *statement);
+            }
+        }
+        Statement::Return(return_) => {
+            if let Some(expression) = return_.expression {
+                visitor.visit_expression(comp, expression);
+            }
+        }
+    }
+}
+
+pub fn walk_expression<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    comp: &'ast Component,
+    expression: ExpressionId,
+) {
+    match comp.get_expression(expression) {
+        Expression::Identifier(identifier) => visitor.visit_name(comp, identifier.ident),
+        Expression::Enum(enum_literal) => {
+            visitor.visit_name(comp, enum_literal.enum_name);
+            visitor.visit_name(comp, enum_literal.case_name);
+        }
+        Expression::Literal(_) => {}
+        Expression::Call(call) => {
+            visitor.visit_name(comp, call.ident);
+            for arg in call.args.iter() {
+                visitor.visit_expression(comp, *arg);
+            }
+        }
+        Expression::Unary(unary) => visitor.visit_expression(comp, unary.inner),
+        Expression::Binary(binary) => {
+            visitor.visit_expression(comp, binary.left);
+            visitor.visit_expression(comp, binary.right);
+        }
+    }
+}
+
+pub fn walk_type<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    comp: &'ast Component,
+    type_id: TypeId,
+) {
+    match comp.get_type(type_id) {
+        ValType::Result(result) => {
+            visitor.visit_type(comp, result.ok);
+            visitor.visit_type(comp, result.err);
+        }
+        ValType::Primitive(_) => {}
+    }
+}
@@ -1,7 +1,11 @@
 (module
-    (memory $memory (export "memory") 1)
-    (global $last (mut i32) (i32.const 8))
-    (func $realloc (export "realloc")
+    ;; The memory and the end of the constant data in it come from the data module
+    (import "data" "memory" (memory $memory 1))
+    (import "data" "heap-base" (global $heap_base i32))
+    (export "memory" (memory $memory))
+    (global $last (mut i32) (global.get $heap_base))
+    ;; The canonical ABI allocates the strings passed into the component with this
+    (func $realloc (export "cabi_realloc")
         (param $old_ptr i32)
         (param $old_size i32)
         (param $align i32)
@@ -64,7 +68,7 @@
         local.get $ret
         i32.const 0xde
         local.get $new_size
-        memory.fill
+        call $fill
         ;; If the old pointer is present then that means this was a reallocation
         ;; of an existing chunk which means the existing data must be copied.
         local.get $old_ptr
@@ -72,12 +76,15 @@
             local.get $ret          ;; destination
             local.get $old_ptr      ;; source
             local.get $old_size     ;; size
-            memory.copy
+            call $copy
         end
         local.get $ret
     )
+    ;; `$fill` and `$copy` are filled in by the build script, with either the
+    ;; bulk memory instructions or byte loops for runtimes without them
+    ;; @memory-helpers
     (func $clear (export "clear")
-        i32.const 8
+        global.get $heap_base
         global.set $last
     )
 )
\ No newline at end of file
@@ -2,12 +2,24 @@ use std::{env, fs, path::Path};
 
 fn main() {
     let out_dir = env::var_os("OUT_DIR").unwrap();
-    let dest_path = Path::new(&out_dir).join("allocator.wasm");
 
-    let wat = include_str!("./allocator.wat");
-    let wasm = wat::parse_str(wat).unwrap();
+    // The allocator is built twice, with and without the bulk memory instructions
+    let allocator = include_str!("./allocator.wat");
+    let variants = [
+        ("allocator.wasm", include_str!("./memory-bulk.wat")),
+        (
+            "allocator-no-bulk-memory.wasm",
+            include_str!("./memory-loops.wat"),
+        ),
+    ];
+    for (file_name, helpers) in variants {
+        let wat = allocator.replace("    ;; @memory-helpers", helpers);
+        let wasm = wat::parse_str(wat).unwrap();
+        fs::write(Path::new(&out_dir).join(file_name), wasm).unwrap();
+    }
 
-    fs::write(dest_path, wasm).unwrap();
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rerun-if-changed=allocator.wat");
+    println!("cargo:rerun-if-changed=memory-bulk.wat");
+    println!("cargo:rerun-if-changed=memory-loops.wat");
 }
@@ -0,0 +1,14 @@
+    ;; Set `$size` bytes starting at `$dest` to `$value`
+    (func $fill (param $dest i32) (param $value i32) (param $size i32)
+        local.get $dest
+        local.get $value
+        local.get $size
+        memory.fill
+    )
+    ;; Copy `$size` bytes from `$src` to `$dest`, which must not overlap
+    (func $copy (export "copy") (param $dest i32) (param $src i32) (param $size i32)
+        local.get $dest
+        local.get $src
+        local.get $size
+        memory.copy
+    )
@@ -0,0 +1,21 @@
+    ;; Set `$size` bytes starting at `$dest` to `$value`
+    (func $fill (param $dest i32) (param $value i32) (param $size i32)
+        (block $done
+            (loop $loop
+                (br_if $done (i32.eqz (local.get $size)))
+                (i32.store8 (local.get $dest) (local.get $value))
+                (local.set $dest (i32.add (local.get $dest) (i32.const 1)))
+                (local.set $size (i32.sub (local.get $size) (i32.const 1)))
+                (br $loop)))
+    )
+    ;; Copy `$size` bytes from `$src` to `$dest`, which must not overlap
+    (func $copy (export "copy") (param $dest i32) (param $src i32) (param $size i32)
+        (block $done
+            (loop $loop
+                (br_if $done (i32.eqz (local.get $size)))
+                (i32.store8 (local.get $dest) (i32.load8_u (local.get $src)))
+                (local.set $dest (i32.add (local.get $dest) (i32.const 1)))
+                (local.set $src (i32.add (local.get $src) (i32.const 1)))
+                (local.set $size (i32.sub (local.get $size) (i32.const 1)))
+                (br $loop)))
+    )
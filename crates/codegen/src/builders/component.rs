@@ -150,13 +150,15 @@ impl ComponentBuilder {
         self.next_instance_idx()
     }
 
+    /// Lower a function into core code that passes UTF-8 strings in `memory`.
     pub fn lower_func(
         &mut self,
         func: ComponentFunctionIndex,
         memory: ComponentCoreMemoryIndex,
         realloc: ComponentCoreFunctionIndex,
     ) -> ComponentCoreFunctionIndex {
-        let options: [enc::CanonicalOption; 2] = [
+        let options: [enc::CanonicalOption; 3] = [
+            enc::CanonicalOption::UTF8,
             enc::CanonicalOption::Memory(memory.0),
             enc::CanonicalOption::Realloc(realloc.0),
         ];
@@ -211,6 +213,7 @@ impl ComponentBuilder {
         self.next_func_idx()
     }
 
+    /// Lift core code that passes UTF-8 strings in `memory` into a function.
     pub fn lift_func(
         &mut self,
         func: ComponentCoreFunctionIndex,
@@ -220,7 +223,8 @@ impl ComponentBuilder {
         post_return: ComponentCoreFunctionIndex,
     ) -> ComponentFunctionIndex {
         let mut section = enc::CanonicalFunctionSection::new();
-        let options: [enc::CanonicalOption; 3] = [
+        let options: [enc::CanonicalOption; 4] = [
+            enc::CanonicalOption::UTF8,
             enc::CanonicalOption::Memory(memory.0),
             enc::CanonicalOption::Realloc(realloc.0),
             enc::CanonicalOption::PostReturn(post_return.0),
@@ -247,7 +251,8 @@ impl ComponentBuilder {
         self.next_func_idx()
     }
 
-    pub fn finalize(self) -> enc::Component {
+    pub fn finalize(mut self) -> enc::Component {
+        self.component.section(&crate::producers());
         self.component
     }
 
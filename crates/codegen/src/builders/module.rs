@@ -1,21 +1,33 @@
+use std::collections::HashMap;
+
+use claw_ast::Span;
+use claw_common::Source;
 use wasm_encoder as enc;
 
+use crate::debug::SourceMap;
+
 #[derive(Default)]
 pub struct ModuleBuilder {
     types: enc::TypeSection,
     imports: enc::ImportSection,
     funcs: enc::FunctionSection,
+    memories: enc::MemorySection,
     globals: enc::GlobalSection,
     exports: enc::ExportSection,
+    start: Option<ModuleFunctionIndex>,
     data: enc::DataSection,
 
+    func_names: Vec<(u32, String)>,
+    local_names: Vec<(u32, Vec<(u32, String)>)>,
+    global_names: Vec<(u32, String)>,
+    source_map: Option<SourceMap>,
+
     code: Vec<Option<enc::Function>>,
 
     num_types: u32,
     num_funcs: u32,
     num_memories: u32,
     num_globals: u32,
-    num_data: u32,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -24,25 +36,20 @@ pub struct ModuleTypeIndex(u32);
 #[derive(Clone, Copy, Debug)]
 pub struct ModuleFunctionIndex(u32);
 
-#[allow(dead_code)]
 #[derive(Clone, Copy, Debug)]
 pub struct ModuleMemoryIndex(u32);
 
-#[allow(dead_code)]
 #[derive(Clone, Copy, Debug)]
 pub struct ModuleGlobalIndex(u32);
 
-#[derive(Clone, Copy, Debug)]
-pub struct ModuleDataIndex(u32);
-
-impl From<ModuleFunctionIndex> for u32 {
-    fn from(value: ModuleFunctionIndex) -> Self {
+impl From<ModuleGlobalIndex> for u32 {
+    fn from(value: ModuleGlobalIndex) -> Self {
         value.0
     }
 }
 
-impl From<ModuleDataIndex> for u32 {
-    fn from(value: ModuleDataIndex) -> Self {
+impl From<ModuleFunctionIndex> for u32 {
+    fn from(value: ModuleFunctionIndex) -> Self {
         value.0
     }
 }
@@ -73,6 +80,17 @@ impl ModuleBuilder {
         self.next_memory_idx()
     }
 
+    pub fn memory(&mut self, minimum: u64) -> ModuleMemoryIndex {
+        self.memories.memory(enc::MemoryType {
+            minimum,
+            maximum: None,
+            memory64: false,
+            shared: false,
+            page_size_log2: None,
+        });
+        self.next_memory_idx()
+    }
+
     pub fn import_func(
         &mut self,
         module: &str,
@@ -120,9 +138,47 @@ impl ModuleBuilder {
         self.exports.export(name, enc::ExportKind::Func, func.0);
     }
 
-    pub fn data(&mut self, data: &[u8]) -> ModuleDataIndex {
-        self.data.passive(data.iter().copied());
-        self.next_data_idx()
+    pub fn export_memory(&mut self, name: &str, memory: ModuleMemoryIndex) {
+        self.exports.export(name, enc::ExportKind::Memory, memory.0);
+    }
+
+    pub fn export_global(&mut self, name: &str, global: ModuleGlobalIndex) {
+        self.exports.export(name, enc::ExportKind::Global, global.0);
+    }
+
+    pub fn start(&mut self, func: ModuleFunctionIndex) {
+        self.start = Some(func);
+    }
+
+    pub fn name_func(&mut self, func: ModuleFunctionIndex, name: &str) {
+        self.func_names.push((func.0, name.to_owned()));
+    }
+
+    /// Name the locals of a function, by local index.
+    pub fn name_locals(&mut self, func: ModuleFunctionIndex, names: &[(u32, String)]) {
+        self.local_names.push((func.0, names.to_vec()));
+    }
+
+    pub fn name_global(&mut self, global: ModuleGlobalIndex, name: &str) {
+        self.global_names.push((global.0, name.to_owned()));
+    }
+
+    /// Map the code of the module back to `source` with a source map.
+    pub fn enable_source_map(&mut self, source: Source) {
+        self.source_map = Some(SourceMap::new(source));
+    }
+
+    /// Record the spans of a function's statements, by offset into its body.
+    pub fn source_spans(&mut self, func: ModuleFunctionIndex, spans: &[(u32, Span)]) {
+        if let Some(source_map) = &mut self.source_map {
+            source_map.function(func.0, spans);
+        }
+    }
+
+    /// Initialize memory at `offset` with an active data segment.
+    pub fn data(&mut self, memory: ModuleMemoryIndex, offset: u32, data: &[u8]) {
+        let offset = enc::ConstExpr::i32_const(offset as i32);
+        self.data.active(memory.0, &offset, data.iter().copied());
     }
 
     pub fn finalize(self) -> enc::Module {
@@ -130,30 +186,58 @@ impl ModuleBuilder {
         module.section(&self.types);
         module.section(&self.imports);
         module.section(&self.funcs);
+        if !self.memories.is_empty() {
+            module.section(&self.memories);
+        }
         module.section(&self.globals);
         module.section(&self.exports);
-
-        if self.num_data > 0 {
-            module.section(&enc::DataCountSection {
-                count: self.num_data,
+        if let Some(start) = self.start {
+            module.section(&enc::StartSection {
+                function_index: start.0,
             });
         }
 
         // Encode code sections
+        let code_start = module.as_slice().len() as u32;
         let mut code = enc::CodeSection::new();
-        for func in self.code.into_iter() {
+        let mut body_offsets = HashMap::new();
+        for (index, func) in self.code.into_iter().enumerate() {
             match func {
                 Some(func) => {
+                    let size_len = leb128_len(func.byte_len() as u32);
+                    body_offsets.insert(index as u32, (code.byte_len() + size_len) as u32);
                     code.function(&func);
                 }
                 None => {}
             }
         }
+        // Make the offsets relative to the module, past the section id, size, and count
+        let count_len = leb128_len(code.len());
+        let section_len = (count_len + code.byte_len()) as u32;
+        let header_len = (1 + leb128_len(section_len) + count_len) as u32;
+        for offset in body_offsets.values_mut() {
+            *offset += code_start + header_len;
+        }
         module.section(&code);
-        if self.num_data > 0 {
+        if !self.data.is_empty() {
             module.section(&self.data);
         }
 
+        let has_names = !(self.func_names.is_empty()
+            && self.local_names.is_empty()
+            && self.global_names.is_empty());
+        if has_names {
+            module.section(&encode_names(
+                self.func_names,
+                self.local_names,
+                self.global_names,
+            ));
+        }
+        if let Some(source_map) = &self.source_map {
+            module.section(&source_map.encode(&body_offsets));
+        }
+        module.section(&crate::producers());
+
         module
     }
 
@@ -180,10 +264,43 @@ impl ModuleBuilder {
         self.num_globals += 1;
         index
     }
+}
 
-    fn next_data_idx(&mut self) -> ModuleDataIndex {
-        let index = ModuleDataIndex(self.num_data);
-        self.num_data += 1;
-        index
+/// The number of bytes in the unsigned LEB128 encoding of `value`.
+fn leb128_len(value: u32) -> usize {
+    let bits = 32 - value.leading_zeros() as usize;
+    std::cmp::max(1, bits.div_ceil(7))
+}
+
+/// Encode the name section, whose names must be sorted by index.
+fn encode_names(
+    mut func_names: Vec<(u32, String)>,
+    mut local_names: Vec<(u32, Vec<(u32, String)>)>,
+    mut global_names: Vec<(u32, String)>,
+) -> enc::NameSection {
+    fn name_map(names: &mut [(u32, String)]) -> enc::NameMap {
+        names.sort_by_key(|(index, _)| *index);
+        let mut map = enc::NameMap::new();
+        for (index, name) in names.iter() {
+            map.append(*index, name);
+        }
+        map
+    }
+
+    let mut section = enc::NameSection::new();
+    if !func_names.is_empty() {
+        section.functions(&name_map(&mut func_names));
+    }
+    if !local_names.is_empty() {
+        local_names.sort_by_key(|(index, _)| *index);
+        let mut locals = enc::IndirectNameMap::new();
+        for (index, names) in local_names.iter_mut() {
+            locals.append(*index, &name_map(names));
+        }
+        section.locals(&locals);
+    }
+    if !global_names.is_empty() {
+        section.globals(&name_map(&mut global_names));
     }
+    section
 }
@@ -0,0 +1,222 @@
+use claw_resolver::{mir::TempId, Builtin, Shape, SimdOp};
+
+use crate::code::CodeGenerator;
+use crate::types::{FieldInfo, Signedness, STRING_LENGTH_FIELD};
+use crate::GenerationError;
+
+use wasm_encoder as enc;
+
+#[derive(Clone, Copy)]
+enum Keep {
+    Smaller,
+    Larger,
+}
+
+/// Encode a call to a builtin whose arguments were already encoded.
+pub fn encode_builtin_call(
+    builtin: Builtin,
+    args: &[TempId],
+    expression: TempId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    match builtin {
+        Builtin::Min => encode_min_max(Keep::Smaller, args[0], args[1], expression, code_gen),
+        Builtin::Max => encode_min_max(Keep::Larger, args[0], args[1], expression, code_gen),
+        Builtin::Clamp => {
+            // A `min` larger than `max` clamps everything to `max`
+            encode_min_max(Keep::Larger, args[0], args[1], expression, code_gen)?;
+            encode_min_max(Keep::Smaller, expression, args[2], expression, code_gen)
+        }
+        Builtin::Abs => encode_abs(args[0], expression, code_gen),
+        Builtin::Len => {
+            let field = code_gen.one_field(expression);
+            code_gen.read_temp_field(args[0], &STRING_LENGTH_FIELD);
+            code_gen.write_temp_field(expression, &field);
+            Ok(())
+        }
+        Builtin::IsEmpty => {
+            let field = code_gen.one_field(expression);
+            code_gen.read_temp_field(args[0], &STRING_LENGTH_FIELD);
+            code_gen.instruction(&enc::Instruction::I32Eqz);
+            code_gen.write_temp_field(expression, &field);
+            Ok(())
+        }
+        Builtin::Simd(op, shape) => encode_simd(op, shape, args, expression, code_gen),
+    }
+}
+
+/// Read one lane of a vector, see [claw_resolver::mir::Inst::ExtractLane].
+pub fn encode_extract_lane(
+    shape: Shape,
+    vector: TempId,
+    lane: u8,
+    expression: TempId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    use enc::Instruction::*;
+    let field = code_gen.one_field(expression);
+    let vector_field = code_gen.one_field(vector);
+    code_gen.read_temp_field(vector, &vector_field);
+    code_gen.instruction(&match shape {
+        Shape::I32x4 => I32x4ExtractLane(lane),
+        Shape::I64x2 => I64x2ExtractLane(lane),
+        Shape::F32x4 => F32x4ExtractLane(lane),
+        Shape::F64x2 => F64x2ExtractLane(lane),
+    });
+    code_gen.write_temp_field(expression, &field);
+    Ok(())
+}
+
+fn encode_simd(
+    op: SimdOp,
+    shape: Shape,
+    args: &[TempId],
+    expression: TempId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    use enc::Instruction::*;
+    let field = code_gen.one_field(expression);
+    let arg_field = code_gen.one_field(args[0]);
+    code_gen.read_temp_field(args[0], &arg_field);
+    let instruction = match op {
+        SimdOp::Splat => match shape {
+            Shape::I32x4 => I32x4Splat,
+            Shape::I64x2 => I64x2Splat,
+            Shape::F32x4 => F32x4Splat,
+            Shape::F64x2 => F64x2Splat,
+        },
+        SimdOp::ExtractLane => unreachable!("Lanes are extracted by their own instruction"),
+        SimdOp::Add | SimdOp::Sub | SimdOp::Mul => {
+            code_gen.read_temp_field(args[1], &arg_field);
+            match (op, shape) {
+                (SimdOp::Add, Shape::I32x4) => I32x4Add,
+                (SimdOp::Add, Shape::I64x2) => I64x2Add,
+                (SimdOp::Add, Shape::F32x4) => F32x4Add,
+                (SimdOp::Add, Shape::F64x2) => F64x2Add,
+                (SimdOp::Sub, Shape::I32x4) => I32x4Sub,
+                (SimdOp::Sub, Shape::I64x2) => I64x2Sub,
+                (SimdOp::Sub, Shape::F32x4) => F32x4Sub,
+                (SimdOp::Sub, Shape::F64x2) => F64x2Sub,
+                (_, Shape::I32x4) => I32x4Mul,
+                (_, Shape::I64x2) => I64x2Mul,
+                (_, Shape::F32x4) => F32x4Mul,
+                (_, Shape::F64x2) => F64x2Mul,
+            }
+        }
+    };
+    code_gen.instruction(&instruction);
+    code_gen.write_temp_field(expression, &field);
+    Ok(())
+}
+
+fn encode_min_max(
+    keep: Keep,
+    left: TempId,
+    right: TempId,
+    expression: TempId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    let field = code_gen.one_field(expression);
+    code_gen.read_temp_field(left, &field);
+    code_gen.read_temp_field(right, &field);
+
+    let float_instruction = match (keep, field.stack_type) {
+        (Keep::Smaller, enc::ValType::F32) => Some(enc::Instruction::F32Min),
+        (Keep::Smaller, enc::ValType::F64) => Some(enc::Instruction::F64Min),
+        (Keep::Larger, enc::ValType::F32) => Some(enc::Instruction::F32Max),
+        (Keep::Larger, enc::ValType::F64) => Some(enc::Instruction::F64Max),
+        _ => None,
+    };
+    if let Some(instruction) = float_instruction {
+        code_gen.instruction(&instruction);
+    } else {
+        // Keep the left operand if it compares the right way, otherwise the right one
+        read_comparable(left, &field, code_gen);
+        read_comparable(right, &field, code_gen);
+        code_gen.instruction(&match (keep, field.stack_type, field.signedness) {
+            (Keep::Smaller, enc::ValType::I32, Signedness::Signed) => enc::Instruction::I32LtS,
+            (Keep::Smaller, enc::ValType::I32, Signedness::Unsigned) => enc::Instruction::I32LtU,
+            (Keep::Smaller, _, Signedness::Signed) => enc::Instruction::I64LtS,
+            (Keep::Smaller, _, Signedness::Unsigned) => enc::Instruction::I64LtU,
+            (Keep::Larger, enc::ValType::I32, Signedness::Signed) => enc::Instruction::I32GtS,
+            (Keep::Larger, enc::ValType::I32, Signedness::Unsigned) => enc::Instruction::I32GtU,
+            (Keep::Larger, _, Signedness::Signed) => enc::Instruction::I64GtS,
+            (Keep::Larger, _, Signedness::Unsigned) => enc::Instruction::I64GtU,
+        });
+        code_gen.instruction(&enc::Instruction::Select);
+    }
+    code_gen.write_temp_field(expression, &field);
+    Ok(())
+}
+
+fn encode_abs(
+    arg: TempId,
+    expression: TempId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    let field = code_gen.one_field(expression);
+    match (field.stack_type, field.signedness) {
+        (enc::ValType::F32, _) => {
+            code_gen.read_temp_field(arg, &field);
+            code_gen.instruction(&enc::Instruction::F32Abs);
+        }
+        (enc::ValType::F64, _) => {
+            code_gen.read_temp_field(arg, &field);
+            code_gen.instruction(&enc::Instruction::F64Abs);
+        }
+        (_, Signedness::Unsigned) => code_gen.read_temp_field(arg, &field),
+        (valtype, Signedness::Signed) => {
+            let is_64 = valtype == enc::ValType::I64;
+            // Negate the value and keep it if the original was negative
+            if is_64 {
+                code_gen.instruction(&enc::Instruction::I64Const(0));
+                code_gen.read_temp_field(arg, &field);
+                code_gen.instruction(&enc::Instruction::I64Sub);
+            } else {
+                code_gen.const_i32(0);
+                code_gen.read_temp_field(arg, &field);
+                code_gen.instruction(&enc::Instruction::I32Sub);
+                if let Some(mask) = field.arith_mask {
+                    code_gen.const_i32(mask);
+                    code_gen.instruction(&enc::Instruction::I32And);
+                }
+            }
+            code_gen.read_temp_field(arg, &field);
+            read_comparable(arg, &field, code_gen);
+            encode_is_negative(is_64, code_gen);
+            code_gen.instruction(&enc::Instruction::Select);
+        }
+    }
+    code_gen.write_temp_field(expression, &field);
+
+    // Only the most negative value has no positive counterpart
+    if code_gen.overflow_checks() && field.signedness == Signedness::Signed {
+        if let enc::ValType::I32 | enc::ValType::I64 = field.stack_type {
+            read_comparable(expression, &field, code_gen);
+            encode_is_negative(field.stack_type == enc::ValType::I64, code_gen);
+            code_gen.trap_if();
+        }
+    }
+    Ok(())
+}
+
+/// Compare the integer on top of the stack to zero.
+fn encode_is_negative(is_64: bool, code_gen: &mut CodeGenerator) {
+    if is_64 {
+        code_gen.instruction(&enc::Instruction::I64Const(0));
+        code_gen.instruction(&enc::Instruction::I64LtS);
+    } else {
+        code_gen.const_i32(0);
+        code_gen.instruction(&enc::Instruction::I32LtS);
+    }
+}
+
+/// Read an integer field, sign extending values narrower than 32 bits
+/// so they can be compared as 32 bit integers.
+fn read_comparable(expression: TempId, field: &FieldInfo, code_gen: &mut CodeGenerator) {
+    code_gen.read_temp_field(expression, field);
+    match (field.signedness, field.mems_size) {
+        (Signedness::Signed, bytes @ (1 | 2)) => code_gen.sign_extend_i32(bytes),
+        _ => {}
+    }
+}
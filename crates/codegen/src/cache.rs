@@ -0,0 +1,97 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use claw_ast::{FunctionId, Span};
+use wasm_encoder as enc;
+
+/// Code generated for functions by earlier compilations, so that
+/// functions which haven't changed don't have to be generated again.
+///
+/// Each function is looked up by a key that must change whenever anything
+/// its code depends on changes. Entries that aren't used by a compilation
+/// are dropped at the end of it.
+#[derive(Default)]
+pub struct CodeCache {
+    entries: HashMap<u64, CachedCode>,
+    used: HashSet<u64>,
+    reused: usize,
+    generated: usize,
+}
+
+#[derive(Clone)]
+pub(crate) struct CachedCode {
+    pub body: enc::Function,
+    /// The names of the function's locals, by local index
+    pub local_names: Vec<(u32, String)>,
+    /// The spans of the function's statements, by offset into its body
+    pub source_spans: Vec<(u32, Span)>,
+}
+
+/// A [CodeCache] along with the key of each function for one compilation.
+pub(crate) struct CacheContext<'a> {
+    pub cache: &'a mut CodeCache,
+    pub keys: &'a HashMap<FunctionId, u64>,
+}
+
+impl CodeCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many functions had their code reused by the last compilation.
+    pub fn reused(&self) -> usize {
+        self.reused
+    }
+
+    /// How many functions had their code generated by the last compilation.
+    pub fn generated(&self) -> usize {
+        self.generated
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub(crate) fn start(&mut self) {
+        self.used.clear();
+        self.reused = 0;
+        self.generated = 0;
+    }
+
+    pub(crate) fn finish(&mut self) {
+        let used = &self.used;
+        self.entries.retain(|key, _| used.contains(key));
+    }
+
+    pub(crate) fn get(&mut self, key: u64) -> Option<CachedCode> {
+        let code = self.entries.get(&key)?.clone();
+        self.used.insert(key);
+        self.reused += 1;
+        Some(code)
+    }
+
+    pub(crate) fn insert(&mut self, key: u64, code: CachedCode) {
+        self.used.insert(key);
+        self.generated += 1;
+        self.entries.insert(key, code);
+    }
+}
+
+impl<'a> CacheContext<'a> {
+    /// The cache key for a function whose code also depends on `dependencies`.
+    ///
+    /// These are the things outside of the function's own key that change its
+    /// code, like the indices of the globals holding the addresses of its constants.
+    pub fn key(&self, id: FunctionId, dependencies: impl Hash) -> Option<u64> {
+        let key = self.keys.get(&id)?;
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        dependencies.hash(&mut hasher);
+        Some(hasher.finish())
+    }
+}
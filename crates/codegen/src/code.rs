@@ -1,28 +1,28 @@
 use std::collections::HashMap;
 
-use ast::{ExpressionId, FunctionId, NameId, StatementId};
+use ast::{FunctionId, GlobalId};
 use claw_ast as ast;
 
 use crate::{
-    builders::module::{ModuleBuilder, ModuleDataIndex, ModuleFunctionIndex},
-    expression::EncodeExpression,
+    builders::module::{ModuleFunctionIndex, ModuleGlobalIndex},
+    builtin,
+    data::DataLayout,
+    expression,
     function::{self, EncodedFuncs, EncodedFunction},
     imports::{self, EncodedImports},
-    statement::EncodeStatement,
+    statement,
     types::{EncodeType, FieldInfo, Signedness},
-    GenerationError,
+    GenerationError, GenerationOptions,
 };
 use claw_resolver::{
-    types::ResolvedType, ImportFuncId, ImportType, ImportTypeId, ItemId, LocalId, ParamId,
-    ResolvedComponent, ResolvedFunction,
+    mir::{Body, Callee, Inst, TempId},
+    types::ResolvedType,
+    ImportFuncId, LocalId, ParamId, ResolvedComponent, ResolvedFunction,
 };
 use cranelift_entity::EntityRef;
 use wasm_encoder as enc;
 
 pub struct CodeGenerator<'gen> {
-    // Parent output (currently just for data segments)
-    mod_builder: &'gen mut ModuleBuilder,
-
     // Context
     comp: &'gen ast::Component,
     rcomp: &'gen ResolvedComponent,
@@ -30,10 +30,16 @@ pub struct CodeGenerator<'gen> {
     functions: &'gen EncodedFuncs,
     func_idx_for_import: &'gen HashMap<ImportFuncId, ModuleFunctionIndex>,
     func_idx_for_func: &'gen HashMap<FunctionId, ModuleFunctionIndex>,
+    global_idx_for_global: &'gen HashMap<GlobalId, ModuleGlobalIndex>,
+    layout: &'gen DataLayout,
+    global_idx_for_constant: &'gen [ModuleGlobalIndex],
 
     realloc: ModuleFunctionIndex,
+    /// The allocator's `copy`, used instead of `memory.copy` without bulk memory
+    copy: Option<ModuleFunctionIndex>,
+    options: &'gen GenerationOptions,
     // Function structs
-    function: &'gen ast::Function,
+    body: &'gen Body,
     resolved_func: &'gen ResolvedFunction,
     encoded_func: &'gen EncodedFunction,
 
@@ -47,7 +53,8 @@ pub struct CodeGenerator<'gen> {
     call_params_index: u32,
     call_results_index: u32,
     index_for_local: HashMap<LocalId, CoreLocalId>,
-    index_for_expr: HashMap<ExpressionId, CoreLocalId>,
+    index_for_temp: HashMap<TempId, CoreLocalId>,
+    source_spans: Vec<(u32, ast::Span)>,
 }
 pub struct CoreLocalId(u32);
 
@@ -60,18 +67,22 @@ impl From<u32> for CoreLocalId {
 impl<'gen> CodeGenerator<'gen> {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
-        mod_builder: &'gen mut ModuleBuilder,
         comp: &'gen ast::Component,
         rcomp: &'gen ResolvedComponent,
         imports: &'gen EncodedImports,
         functions: &'gen EncodedFuncs,
         func_idx_for_import: &'gen HashMap<ImportFuncId, ModuleFunctionIndex>,
         func_idx_for_func: &'gen HashMap<FunctionId, ModuleFunctionIndex>,
+        global_idx_for_global: &'gen HashMap<GlobalId, ModuleGlobalIndex>,
+        layout: &'gen DataLayout,
+        global_idx_for_constant: &'gen [ModuleGlobalIndex],
+        body: &'gen Body,
         encoded_func: &'gen EncodedFunction,
         id: FunctionId,
         realloc: ModuleFunctionIndex,
+        copy: Option<ModuleFunctionIndex>,
+        options: &'gen GenerationOptions,
     ) -> Result<Self, GenerationError> {
-        let function = &comp.get_function(id);
         let resolved_func = &rcomp.funcs[&id];
 
         let mut local_space = encoded_func.flat_params.clone();
@@ -104,18 +115,19 @@ impl<'gen> CodeGenerator<'gen> {
         }
         local_space.extend(locals);
 
-        // Layout expressions
-        let mut index_for_expr = HashMap::new();
-        let mut allocator = ExpressionAllocator::new(
+        // Layout temporaries
+        let mut index_for_temp = HashMap::new();
+        let mut allocator = TempAllocator::new(
             comp,
             rcomp,
-            resolved_func,
+            body,
             &mut local_space,
-            &mut index_for_expr,
+            &mut index_for_temp,
+            options.reuse_locals,
         );
-        for statement in function.body.iter() {
-            let statement = comp.get_statement(*statement);
-            statement.alloc_expr_locals(&mut allocator)?;
+        for statement in body.block.split(|inst| matches!(inst, Inst::Statement(_))) {
+            allocator.start_statement();
+            allocator.alloc_statement(statement);
         }
 
         let locals = &local_space[locals_start..];
@@ -142,15 +154,19 @@ impl<'gen> CodeGenerator<'gen> {
         }
 
         Ok(Self {
-            mod_builder,
             comp,
             rcomp,
             imports,
             functions,
             realloc,
+            copy,
+            options,
             func_idx_for_import,
             func_idx_for_func,
-            function,
+            global_idx_for_global,
+            layout,
+            global_idx_for_constant,
+            body,
             resolved_func,
             encoded_func,
             builder,
@@ -159,18 +175,61 @@ impl<'gen> CodeGenerator<'gen> {
             call_params_index,
             call_results_index,
             index_for_local,
-            index_for_expr,
+            index_for_temp,
+            source_spans: Vec::new(),
         })
     }
 
-    pub fn encode_statement(&mut self, statement: StatementId) -> Result<(), GenerationError> {
-        let stmt = self.comp.get_statement(statement);
-        stmt.encode(self)
+    pub fn encode_block(&mut self, block: &[Inst]) -> Result<(), GenerationError> {
+        for inst in block.iter() {
+            self.encode_inst(inst)?;
+        }
+        Ok(())
     }
 
-    pub fn encode_child(&mut self, expression: ExpressionId) -> Result<(), GenerationError> {
-        let expr = self.comp.get_expression(expression);
-        expr.encode(expression, self)
+    fn encode_inst(&mut self, inst: &Inst) -> Result<(), GenerationError> {
+        match inst {
+            Inst::Statement(span) => {
+                let offset = self.builder.byte_len() as u32;
+                self.source_spans.push((offset, *span));
+                Ok(())
+            }
+            Inst::Const { dest, value } => expression::encode_const(*dest, value, self),
+            Inst::Read { dest, place } => expression::encode_read(*dest, *place, self),
+            Inst::Write { place, src } => statement::encode_write(*place, *src, self),
+            Inst::Call { dest, callee, args } => self.encode_call(*callee, args, *dest),
+            Inst::Builtin {
+                dest,
+                builtin,
+                args,
+            } => builtin::encode_builtin_call(*builtin, args, *dest, self),
+            Inst::ExtractLane {
+                dest,
+                shape,
+                vector,
+                lane,
+            } => builtin::encode_extract_lane(*shape, *vector, *lane, *dest, self),
+            Inst::Widen { dest, src } => expression::encode_widen(*dest, *src, self),
+            Inst::Unary { dest, operand, .. } => expression::encode_negate(*dest, *operand, self),
+            Inst::Binary {
+                dest,
+                op,
+                left,
+                right,
+            } => expression::encode_binary(*op, *dest, *left, *right, self),
+            Inst::ShortCircuit {
+                dest,
+                op,
+                left,
+                block,
+                right,
+            } => expression::encode_short_circuit(*op, *dest, *left, block, *right, self),
+            Inst::If { condition, block } => statement::encode_if(*condition, block, self),
+            Inst::Return { value } => statement::encode_return(*value, self),
+            Inst::ReturnCall { dest, callee, args } => {
+                self.encode_return_call(*dest, *callee, args)
+            }
+        }
     }
 
     pub fn instruction(&mut self, instruction: &enc::Instruction) {
@@ -182,19 +241,27 @@ impl<'gen> CodeGenerator<'gen> {
             .instruction(&enc::Instruction::I32Const(constant));
     }
 
-    pub fn expression_type(
-        &self,
-        expression: ExpressionId,
-    ) -> Result<ResolvedType, GenerationError> {
-        let type_id = self.resolved_func.expression_type(expression, self.comp)?;
-        Ok(type_id)
+    /// Trap if the i32 condition on top of the stack is true
+    pub fn trap_if(&mut self) {
+        self.instruction(&enc::Instruction::If(enc::BlockType::Empty));
+        self.instruction(&enc::Instruction::Unreachable);
+        self.instruction(&enc::Instruction::End);
     }
 
-    pub fn get_ptype(
-        &self,
-        expression: ExpressionId,
-    ) -> Result<Option<ast::PrimitiveType>, GenerationError> {
-        let rtype = self.expression_type(expression)?;
+    pub fn global_index(&self, global: GlobalId) -> u32 {
+        self.global_idx_for_global[&global].into()
+    }
+
+    pub fn overflow_checks(&self) -> bool {
+        self.options.overflow_checks
+    }
+
+    pub fn temp_type(&self, temp: TempId) -> ResolvedType {
+        self.body.temps[temp]
+    }
+
+    pub fn get_ptype(&self, temp: TempId) -> Option<ast::PrimitiveType> {
+        let rtype = self.temp_type(temp);
         let ptype = match rtype {
             ResolvedType::Primitive(ptype) => Some(ptype),
             ResolvedType::Import(_) => todo!(),
@@ -206,35 +273,17 @@ impl<'gen> CodeGenerator<'gen> {
                 }
             }
         };
-        Ok(ptype)
-    }
-
-    pub fn one_field(&self, expression: ExpressionId) -> Result<FieldInfo, GenerationError> {
-        let rtype = self.expression_type(expression)?;
-        let mut fields = rtype.fields(self.comp, self.rcomp);
-        assert_eq!(
-            fields.len(),
-            1,
-            "Expected expression to only have one field"
-        );
-        Ok(fields.remove(0))
-    }
-
-    pub fn fields(&self, expression: ExpressionId) -> Result<Vec<FieldInfo>, GenerationError> {
-        let rtype = self.expression_type(expression)?;
-        Ok(rtype.fields(self.comp, self.rcomp))
-    }
-
-    pub fn lookup_name(&self, ident: NameId) -> ItemId {
-        self.resolved_func.bindings[&ident]
+        ptype
     }
 
-    pub fn lookup_name_str(&self, ident: NameId) -> &str {
-        self.comp.get_name(ident)
+    pub fn one_field(&self, temp: TempId) -> FieldInfo {
+        let mut fields = self.fields(temp);
+        assert_eq!(fields.len(), 1, "Expected temporary to only have one field");
+        fields.remove(0)
     }
 
-    pub fn lookup_import_type(&self, id: ImportTypeId) -> &ImportType {
-        &self.rcomp.imports.types[id]
+    pub fn fields(&self, temp: TempId) -> Vec<FieldInfo> {
+        self.temp_type(temp).fields(self.comp, self.rcomp)
     }
 
     pub fn spill_return(&self) -> bool {
@@ -249,24 +298,52 @@ impl<'gen> CodeGenerator<'gen> {
         self.instruction(&enc::Instruction::Call(self.realloc.into()))
     }
 
-    pub fn encode_call(
+    /// Copy bytes within memory, taking the destination, source, and size from the stack.
+    pub fn copy_memory(&mut self) {
+        match self.copy {
+            Some(copy) => self.instruction(&enc::Instruction::Call(copy.into())),
+            None => self.instruction(&enc::Instruction::MemoryCopy {
+                src_mem: 0,
+                dst_mem: 0,
+            }),
+        }
+    }
+
+    /// Sign extend the low `bytes` bytes of the i32 on top of the stack.
+    ///
+    /// Without the sign extension proposal, the value is shifted to the top of the
+    /// i32 and arithmetically shifted back down.
+    pub fn sign_extend_i32(&mut self, bytes: u32) {
+        match (self.options.features.sign_ext, bytes) {
+            (true, 1) => self.instruction(&enc::Instruction::I32Extend8S),
+            (true, 2) => self.instruction(&enc::Instruction::I32Extend16S),
+            (_, bytes) => {
+                let shift = 32 - 8 * bytes as i32;
+                self.const_i32(shift);
+                self.instruction(&enc::Instruction::I32Shl);
+                self.const_i32(shift);
+                self.instruction(&enc::Instruction::I32ShrS);
+            }
+        }
+    }
+
+    fn encode_call(
         &mut self,
-        item: ItemId,
-        args: &[ExpressionId],
-        expression: Option<ExpressionId>,
+        callee: Callee,
+        args: &[TempId],
+        dest: Option<TempId>,
     ) -> Result<(), GenerationError> {
-        match item {
-            ItemId::ImportFunc(id) => self.encode_import_call(id, args, expression),
-            ItemId::Function(id) => self.encode_func_call(id, args, expression),
-            _ => panic!(""),
+        match callee {
+            Callee::Import(id) => self.encode_import_call(id, args, dest),
+            Callee::Function(id) => self.encode_func_call(id, args, dest),
         }
     }
 
     fn encode_import_call(
         &mut self,
         id: ImportFuncId,
-        args: &[ExpressionId],
-        expression: Option<ExpressionId>,
+        args: &[TempId],
+        dest: Option<TempId>,
     ) -> Result<(), GenerationError> {
         let enc_import_func = self.imports.funcs.get(&id).unwrap();
         // Prepare arguments
@@ -275,9 +352,9 @@ impl<'gen> CodeGenerator<'gen> {
         } else {
             // Push all the field values onto the stack
             for arg in args.iter().copied() {
-                let fields = self.fields(arg)?;
+                let fields = self.fields(arg);
                 for field in fields.iter() {
-                    self.read_expr_field(arg, field);
+                    self.read_temp_field(arg, field);
                 }
             }
         }
@@ -290,8 +367,8 @@ impl<'gen> CodeGenerator<'gen> {
         let index = *index.unwrap();
         self.instruction(&enc::Instruction::Call(index.into()));
         // Write expression output if needed
-        if let Some(expression) = expression {
-            let fields = self.fields(expression)?;
+        if let Some(dest) = dest {
+            let fields = self.fields(dest);
             for field in fields.iter() {
                 if enc_import_func.spill_results.is_some() {
                     // spilled value is read from return area
@@ -299,7 +376,12 @@ impl<'gen> CodeGenerator<'gen> {
                 } else {
                     // value is already on stack
                 }
-                self.write_expr_field(expression, field);
+                self.write_temp_field(dest, field);
+            }
+        } else {
+            // The results aren't used, unless they were written to the return area
+            for _ in enc_import_func.core_results.iter() {
+                self.instruction(&enc::Instruction::Drop);
             }
         }
         Ok(())
@@ -308,7 +390,7 @@ impl<'gen> CodeGenerator<'gen> {
     fn prepare_import_spilled_args(
         &mut self,
         spilled_params: &imports::SpilledParams,
-        args: &[ExpressionId],
+        args: &[TempId],
     ) -> Result<(), GenerationError> {
         // Allocate spilled parameters
         self.const_i32(0);
@@ -322,13 +404,13 @@ impl<'gen> CodeGenerator<'gen> {
         let args_iter = args.iter().copied();
         let params_iter = spilled_params.params.iter();
         for (arg, param_info) in args_iter.zip(params_iter) {
-            let fields = self.fields(arg)?;
+            let fields = self.fields(arg);
             for field in fields.iter() {
                 self.local_get(self.call_params_index);
                 let mem_offset = param_info.mem_offset + field.mem_offset;
                 self.const_i32(mem_offset as i32);
                 self.instruction(&enc::Instruction::I32Add);
-                self.read_expr_field(arg, field);
+                self.read_temp_field(arg, field);
                 self.write_mem(field);
             }
         }
@@ -357,30 +439,19 @@ impl<'gen> CodeGenerator<'gen> {
     fn encode_func_call(
         &mut self,
         id: FunctionId,
-        args: &[ExpressionId],
-        expression: Option<ExpressionId>,
+        args: &[TempId],
+        dest: Option<TempId>,
     ) -> Result<(), GenerationError> {
         let encoded_func = self.functions.funcs.get(&id).unwrap();
-        // Prepare arguments
-        if let Some(spilled_params) = &encoded_func.spill_params {
-            self.prepare_function_spilled_args(spilled_params, &encoded_func.params, args)?;
-        } else {
-            // Push all the field values onto the stack
-            for arg in args.iter().copied() {
-                let fields = self.fields(arg)?;
-                for field in fields.iter() {
-                    self.read_expr_field(arg, field);
-                }
-            }
-        }
+        self.prepare_function_args(encoded_func, args)?;
 
         // Encode call instruction
         let index = self.func_idx_for_func.get(&id);
         let index = *index.unwrap();
         self.instruction(&enc::Instruction::Call(index.into()));
         // Write expression output if needed
-        if let Some(expression) = expression {
-            let fields = self.fields(expression)?;
+        if let Some(dest) = dest {
+            let fields = self.fields(dest);
             if let Some(results) = &encoded_func.results {
                 if results.spill() {
                     // Save the results pointer
@@ -388,24 +459,70 @@ impl<'gen> CodeGenerator<'gen> {
                     // Write the fields from return area
                     for field in fields.iter() {
                         self.read_return_area(field);
-                        self.write_expr_field(expression, field);
+                        self.write_temp_field(dest, field);
                     }
                 } else {
-                    // Write the fields from the stack
-                    for field in fields.iter() {
-                        self.write_expr_field(expression, field);
+                    // Write the fields from the stack, where the last one is on top
+                    for field in fields.iter().rev() {
+                        self.write_temp_field(dest, field);
                     }
                 }
             }
+        } else if let Some(results) = &encoded_func.results {
+            // The results aren't used
+            for _ in results.spill.valtypes() {
+                self.instruction(&enc::Instruction::Drop);
+            }
+        }
+        Ok(())
+    }
+
+    /// Encode a recursive call in tail position with `return_call` if the function
+    /// being called returns the same core values, otherwise call it and return its result.
+    fn encode_return_call(
+        &mut self,
+        dest: TempId,
+        callee: FunctionId,
+        args: &[TempId],
+    ) -> Result<(), GenerationError> {
+        let encoded_func = self.functions.funcs.get(&callee).unwrap();
+        let valtypes =
+            |func: &EncodedFunction| func.results.as_ref().map(|info| info.spill.valtypes());
+        if valtypes(encoded_func) != valtypes(self.encoded_func) {
+            self.encode_func_call(callee, args, Some(dest))?;
+            return statement::encode_return(Some(dest), self);
         }
+
+        self.prepare_function_args(encoded_func, args)?;
+        let index = self.func_idx_for_func[&callee];
+        self.instruction(&enc::Instruction::ReturnCall(index.into()));
         Ok(())
     }
 
+    fn prepare_function_args(
+        &mut self,
+        encoded_func: &EncodedFunction,
+        args: &[TempId],
+    ) -> Result<(), GenerationError> {
+        if let Some(spilled_params) = &encoded_func.spill_params {
+            self.prepare_function_spilled_args(spilled_params, &encoded_func.params, args)
+        } else {
+            // Push all the field values onto the stack
+            for arg in args.iter().copied() {
+                let fields = self.fields(arg);
+                for field in fields.iter() {
+                    self.read_temp_field(arg, field);
+                }
+            }
+            Ok(())
+        }
+    }
+
     fn prepare_function_spilled_args(
         &mut self,
         spilled_params: &function::SpilledParams,
         params: &[function::ParamInfo],
-        args: &[ExpressionId],
+        args: &[TempId],
     ) -> Result<(), GenerationError> {
         // Allocate spilled parameters
         self.const_i32(0);
@@ -419,13 +536,13 @@ impl<'gen> CodeGenerator<'gen> {
         let args_iter = args.iter().copied();
         let params_iter = params.iter();
         for (arg, param_info) in args_iter.zip(params_iter) {
-            let fields = self.fields(arg)?;
+            let fields = self.fields(arg);
             for field in fields.iter() {
                 self.local_get(self.call_params_index);
                 let mem_offset = param_info.mem_offset + field.mem_offset;
                 self.const_i32(mem_offset as i32);
                 self.instruction(&enc::Instruction::I32Add);
-                self.read_expr_field(arg, field);
+                self.read_temp_field(arg, field);
                 self.write_mem(field);
             }
         }
@@ -460,14 +577,14 @@ impl<'gen> CodeGenerator<'gen> {
         self.local_set(local_index);
     }
 
-    pub fn read_expr_field(&mut self, expression: ExpressionId, field: &FieldInfo) {
-        let local_index = &self.index_for_expr[&expression];
+    pub fn read_temp_field(&mut self, temp: TempId, field: &FieldInfo) {
+        let local_index = &self.index_for_temp[&temp];
         let local_index = local_index.0 + field.index_offset;
         self.local_get(local_index);
     }
 
-    pub fn write_expr_field(&mut self, expression: ExpressionId, field: &FieldInfo) {
-        let local_index = &self.index_for_expr[&expression];
+    pub fn write_temp_field(&mut self, temp: TempId, field: &FieldInfo) {
+        let local_index = &self.index_for_temp[&temp];
         let local_index = local_index.0 + field.index_offset;
         self.local_set(local_index);
     }
@@ -495,8 +612,11 @@ impl<'gen> CodeGenerator<'gen> {
         self.store_field(field);
     }
 
-    pub fn encode_const_bytes(&mut self, data: &[u8]) -> ModuleDataIndex {
-        self.mod_builder.data(data)
+    /// Push the address of constant data planned by the [DataLayout].
+    pub fn encode_const_address(&mut self, data: &[u8]) {
+        let index = self.layout.constant_index(data);
+        let global = self.global_idx_for_constant[index];
+        self.instruction(&enc::Instruction::GlobalGet(global.into()));
     }
 
     pub fn encode_const_int(&mut self, int: u64, field: &FieldInfo) {
@@ -541,6 +661,8 @@ impl<'gen> CodeGenerator<'gen> {
             // Floats
             (enc::ValType::F32, _, 4) => enc::Instruction::F32Load(mem_arg),
             (enc::ValType::F64, _, 8) => enc::Instruction::F64Load(mem_arg),
+            // Vectors
+            (enc::ValType::V128, _, 16) => enc::Instruction::V128Load(mem_arg),
             // Fallback error
             (valtype, s, size) => panic!(
                 "Cannot load value type {:?} with signedness {:?} and size {}",
@@ -557,71 +679,124 @@ impl<'gen> CodeGenerator<'gen> {
             enc::ValType::I64 => enc::Instruction::I64Store(mem_arg),
             enc::ValType::F32 => enc::Instruction::F32Store(mem_arg),
             enc::ValType::F64 => enc::Instruction::F64Store(mem_arg),
+            enc::ValType::V128 => enc::Instruction::V128Store(mem_arg),
             valtype => panic!("Cannot store value type {:?}", valtype),
         };
         self.builder.instruction(&instruction);
     }
 
-    pub fn finalize(mut self) -> Result<enc::Function, GenerationError> {
-        for statement in self.function.body.iter() {
-            self.encode_statement(*statement)?;
+    /// The names of the locals holding parameters and variables, by local index.
+    pub fn local_names(&self) -> Vec<(u32, String)> {
+        let mut names = Vec::new();
+        if self.encoded_func.spill_params.is_some() {
+            names.push((0, "params".to_owned()));
+        } else {
+            for param in self.encoded_func.params.iter() {
+                names.push((param.index_offset, param.name.clone()));
+            }
         }
+        if let Some(return_index) = self.return_index {
+            names.push((return_index, "return".to_owned()));
+        }
+        for (id, local) in self.resolved_func.locals.iter() {
+            let index = self.index_for_local[&id].0;
+            names.push((index, self.comp.get_name(local.ident).to_owned()));
+        }
+        names
+    }
+
+    /// Generate the function's code, along with the span of each statement by its offset.
+    pub fn finalize(mut self) -> Result<(enc::Function, Vec<(u32, ast::Span)>), GenerationError> {
+        self.encode_block(&self.body.block)?;
         self.builder.instruction(&enc::Instruction::End);
-        Ok(self.builder)
+        Ok((self.builder, self.source_spans))
     }
 }
 
-pub struct ExpressionAllocator<'a> {
+pub struct TempAllocator<'a> {
     // Context
     comp: &'a ast::Component,
     rcomp: &'a ResolvedComponent,
-    func: &'a ResolvedFunction,
+    body: &'a Body,
+    reuse_locals: bool,
     // State
     local_space: &'a mut Vec<enc::ValType>,
-    index_for_expr: &'a mut HashMap<ExpressionId, CoreLocalId>,
+    index_for_temp: &'a mut HashMap<TempId, CoreLocalId>,
+    /// Where the temporary locals start
+    start: usize,
+    /// Where the next temporary local is allocated
+    cursor: usize,
 }
 
-impl<'a> ExpressionAllocator<'a> {
+impl<'a> TempAllocator<'a> {
     pub fn new(
         comp: &'a ast::Component,
         rcomp: &'a ResolvedComponent,
-        func: &'a ResolvedFunction,
+        body: &'a Body,
         local_space: &'a mut Vec<enc::ValType>,
-        index_for_expr: &'a mut HashMap<ExpressionId, CoreLocalId>,
+        index_for_temp: &'a mut HashMap<TempId, CoreLocalId>,
+        reuse_locals: bool,
     ) -> Self {
+        let start = local_space.len();
         Self {
             comp,
             rcomp,
-            func,
+            body,
+            reuse_locals,
             local_space,
-            index_for_expr,
+            index_for_temp,
+            start,
+            cursor: start,
         }
     }
 
-    pub fn alloc(&mut self, expression: ExpressionId) -> Result<(), GenerationError> {
-        // Record index
-        let index = self.local_space.len() as u32;
-        let index = CoreLocalId(index);
-        self.index_for_expr.insert(expression, index);
-        // Allocate locals
-        let rtype = self.func.expression_type(expression, self.comp)?;
-        rtype.append_flattened(self.comp, self.rcomp, self.local_space);
-        Ok(())
+    /// Begin allocating the temporaries of a top-level statement.
+    ///
+    /// Temporaries are only read within the statement that computes them,
+    /// so when reusing locals each statement may overwrite the previous one's.
+    pub fn start_statement(&mut self) {
+        if self.reuse_locals {
+            self.cursor = self.start;
+        }
     }
 
-    #[allow(dead_code)]
-    pub fn alloc_extra(&mut self, valtype: enc::ValType) -> Result<(), GenerationError> {
-        self.local_space.push(valtype);
-        Ok(())
+    /// Allocate the temporaries set by the instructions of a statement,
+    /// including those in nested blocks, in the order they were created.
+    pub fn alloc_statement(&mut self, insts: &[Inst]) {
+        let mut temps = Vec::new();
+        collect_temps(insts, &mut temps);
+        temps.sort();
+        for temp in temps {
+            self.alloc(temp);
+        }
     }
 
-    pub fn alloc_child(&mut self, expression: ExpressionId) -> Result<(), GenerationError> {
-        let expr = self.comp.get_expression(expression);
-        expr.alloc_expr_locals(expression, self)
+    fn alloc(&mut self, temp: TempId) {
+        let rtype = self.body.temps[temp];
+        let mut valtypes = Vec::new();
+        rtype.append_flattened(self.comp, self.rcomp, &mut valtypes);
+        let index = self.alloc_valtypes(&valtypes);
+        self.index_for_temp.insert(temp, index);
     }
 
-    pub fn alloc_statement(&mut self, statement: StatementId) -> Result<(), GenerationError> {
-        let statement = self.comp.get_statement(statement);
-        statement.alloc_expr_locals(self)
+    /// Allocate consecutive locals, reusing ones from previous statements if their types match.
+    fn alloc_valtypes(&mut self, valtypes: &[enc::ValType]) -> CoreLocalId {
+        let end = self.cursor + valtypes.len();
+        if self.local_space.get(self.cursor..end) != Some(valtypes) {
+            self.cursor = self.local_space.len();
+            self.local_space.extend_from_slice(valtypes);
+        }
+        let index = CoreLocalId(self.cursor as u32);
+        self.cursor += valtypes.len();
+        index
+    }
+}
+
+fn collect_temps(insts: &[Inst], temps: &mut Vec<TempId>) {
+    for inst in insts.iter() {
+        temps.extend(inst.dest());
+        if let Inst::If { block, .. } | Inst::ShortCircuit { block, .. } = inst {
+            collect_temps(block, temps);
+        }
     }
 }
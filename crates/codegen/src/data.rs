@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+use ast::visit::{walk_expression, walk_function, Visitor};
+use ast::{ExpressionId, FunctionId};
+use claw_ast as ast;
+use claw_resolver::ResolvedComponent;
+use wasm_encoder as enc;
+
+use crate::{builders::module::ModuleBuilder, types::STRING_CONTENTS_ALIGNMENT, GenerationOptions};
+
+/// Where the constant data starts, leaving the null pointer unused.
+const DATA_START: u32 = 8;
+/// The alignment of the heap, which starts right after the constant data.
+const HEAP_ALIGNMENT: u32 = 8;
+const PAGE_SIZE: u32 = 65536;
+
+/// The layout of constant data, like the contents of string literals, in linear memory.
+///
+/// Constants are deduplicated by their contents and given offsets in the order
+/// they're first used, so the layout only depends on the source. The heap starts
+/// at the heap base, after all of them.
+#[derive(Default)]
+pub struct DataLayout {
+    constants: Vec<Constant>,
+    index_for_bytes: HashMap<Vec<u8>, usize>,
+    constants_for_func: HashMap<FunctionId, Vec<usize>>,
+    heap_base: u32,
+}
+
+pub struct Constant {
+    pub bytes: Vec<u8>,
+    pub offset: u32,
+    align: u32,
+}
+
+impl DataLayout {
+    /// Plan the constant data of the functions that weren't eliminated as dead code.
+    pub fn plan(comp: &ast::Component, rcomp: &ResolvedComponent) -> Self {
+        let mut collector = ConstantCollector {
+            layout: DataLayout::default(),
+            constants: Vec::new(),
+        };
+        for (id, function) in comp.iter_functions() {
+            if rcomp.funcs.contains_key(&id) {
+                collector.visit_function(comp, id, function);
+            }
+        }
+        let mut layout = collector.layout;
+
+        let mut offset = DATA_START;
+        for constant in layout.constants.iter_mut() {
+            offset = align_to(offset, constant.align);
+            constant.offset = offset;
+            offset += constant.bytes.len() as u32;
+        }
+        layout.heap_base = align_to(offset, HEAP_ALIGNMENT);
+        layout
+    }
+
+    /// The constants in the order they're first used.
+    pub fn constants(&self) -> &[Constant] {
+        &self.constants
+    }
+
+    /// The index of a planned constant in [DataLayout::constants].
+    pub fn constant_index(&self, bytes: &[u8]) -> usize {
+        self.index_for_bytes[bytes]
+    }
+
+    /// The constants a function uses, by index, in the order it uses them.
+    pub fn func_constants(&self, id: FunctionId) -> &[usize] {
+        self.constants_for_func
+            .get(&id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// The number of pages the memory needs to start with to hold the constant data.
+    pub fn min_pages(&self) -> u64 {
+        std::cmp::max(1, self.heap_base.div_ceil(PAGE_SIZE)) as u64
+    }
+
+    /// Generate the module that defines the memory and initializes it with the constant data.
+    ///
+    /// It's instantiated before the allocator, which imports its `memory` and starts
+    /// allocating at its `heap-base`.
+    pub fn generate_module(&self, options: &GenerationOptions) -> enc::Module {
+        let mut module = ModuleBuilder::default();
+        let memory = module.memory(self.min_pages());
+        module.export_memory("memory", memory);
+
+        let heap_base = enc::ConstExpr::i32_const(self.heap_base as i32);
+        let heap_base = module.global(false, enc::ValType::I32, &heap_base);
+        module.export_global("heap-base", heap_base);
+        if options.debug_names {
+            module.name_global(heap_base, "heap-base");
+        }
+
+        for constant in self.constants.iter() {
+            if !constant.bytes.is_empty() {
+                module.data(memory, constant.offset, &constant.bytes);
+            }
+        }
+        module.finalize()
+    }
+
+    fn add(&mut self, bytes: &[u8], align: u32) -> usize {
+        if let Some(index) = self.index_for_bytes.get(bytes) {
+            let constant = &mut self.constants[*index];
+            constant.align = std::cmp::max(constant.align, align);
+            return *index;
+        }
+        let index = self.constants.len();
+        self.constants.push(Constant {
+            bytes: bytes.to_vec(),
+            offset: 0,
+            align,
+        });
+        self.index_for_bytes.insert(bytes.to_vec(), index);
+        index
+    }
+}
+
+/// Collects the constants used by each function.
+struct ConstantCollector {
+    layout: DataLayout,
+    /// The constants used by the function being visited
+    constants: Vec<usize>,
+}
+
+impl<'ast> Visitor<'ast> for ConstantCollector {
+    fn visit_function(
+        &mut self,
+        comp: &'ast ast::Component,
+        id: FunctionId,
+        function: &'ast ast::Function,
+    ) {
+        walk_function(self, comp, function);
+        let constants = std::mem::take(&mut self.constants);
+        self.layout.constants_for_func.insert(id, constants);
+    }
+
+    fn visit_expression(&mut self, comp: &'ast ast::Component, expression: ExpressionId) {
+        if let ast::Expression::Literal(ast::Literal::String(string)) =
+            comp.get_expression(expression)
+        {
+            let align = 2u32.pow(STRING_CONTENTS_ALIGNMENT);
+            let index = self.layout.add(string.as_bytes(), align);
+            self.constants.push(index);
+        }
+        walk_expression(self, comp, expression);
+    }
+}
+
+fn align_to(offset: u32, align: u32) -> u32 {
+    offset.div_ceil(align) * align
+}
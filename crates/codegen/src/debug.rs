@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use claw_ast::Span;
+use claw_common::{LineIndex, Source};
+use serde_json::json;
+use wasm_encoder::{self as enc, Encode};
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Maps the code of a module back to the Claw source it was generated from.
+///
+/// It's encoded as a [source map](https://sourcemaps.info/spec.html) embedded in
+/// the module's `sourceMappingURL` section, whose columns are byte offsets into
+/// the module. Each statement is mapped from the first instruction generated for it.
+pub struct SourceMap {
+    source: Source,
+    /// The statement spans of each function, by offset into its body
+    functions: Vec<(u32, Vec<(u32, Span)>)>,
+}
+
+impl SourceMap {
+    pub fn new(source: Source) -> Self {
+        Self {
+            source,
+            functions: Vec::new(),
+        }
+    }
+
+    pub fn function(&mut self, func: u32, spans: &[(u32, Span)]) {
+        self.functions.push((func, spans.to_vec()));
+    }
+
+    /// Encode the source map, given the offset of each function's body in the module.
+    pub fn encode(&self, body_offsets: &HashMap<u32, u32>) -> enc::CustomSection<'static> {
+        let text = self.source.inner();
+        let line_index = LineIndex::new(text);
+
+        let mut entries = Vec::new();
+        for (func, spans) in self.functions.iter() {
+            let body_offset = body_offsets[func];
+            for (offset, span) in spans.iter() {
+                let position = line_index.to_utf16(line_index.line_col(span.offset()));
+                entries.push((body_offset + offset, position.line, position.column));
+            }
+        }
+        // Statements that generate no code share an offset with the next
+        // one, which is the statement that instruction belongs to
+        entries.sort_by_key(|(offset, ..)| *offset);
+        entries.reverse();
+        entries.dedup_by_key(|(offset, ..)| *offset);
+        entries.reverse();
+
+        let mut mappings = String::new();
+        let mut previous = (0, 0, 0);
+        for (offset, line, column) in entries {
+            if !mappings.is_empty() {
+                mappings.push(',');
+            }
+            encode_vlq(&mut mappings, offset as i64 - previous.0 as i64);
+            // Everything comes from the first and only source
+            encode_vlq(&mut mappings, 0);
+            encode_vlq(&mut mappings, line as i64 - previous.1 as i64);
+            encode_vlq(&mut mappings, column as i64 - previous.2 as i64);
+            previous = (offset, line, column);
+        }
+
+        let map = json!({
+            "version": 3,
+            "sources": [self.source.name()],
+            "sourcesContent": [text],
+            "names": [],
+            "mappings": mappings,
+        });
+        let url = format!(
+            "data:application/json;base64,{}",
+            encode_base64(map.to_string().as_bytes())
+        );
+        let mut data = Vec::new();
+        url.as_str().encode(&mut data);
+        enc::CustomSection {
+            name: "sourceMappingURL".into(),
+            data: data.into(),
+        }
+    }
+}
+
+/// Append a base64 VLQ encoded number, as used by source map mappings.
+fn encode_vlq(output: &mut String, value: i64) {
+    let mut vlq = match value < 0 {
+        true => ((-value as u64) << 1) | 1,
+        false => (value as u64) << 1,
+    };
+    loop {
+        let mut digit = (vlq & 0b11111) as usize;
+        vlq >>= 5;
+        if vlq > 0 {
+            // Continuation bit
+            digit |= 0b100000;
+        }
+        output.push(BASE64[digit] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut output = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let triple = chunk.iter().enumerate().fold(0u32, |triple, (i, byte)| {
+            triple | (*byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (triple >> (18 - 6 * i)) & 0b111111;
+                output.push(BASE64[index as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
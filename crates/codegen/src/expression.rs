@@ -1,317 +1,205 @@
-use ast::ExpressionId;
 use claw_ast as ast;
-use claw_resolver::{ItemId, ResolvedType};
+use claw_resolver::mir::{Constant, Inst, Place, TempId};
 
-use crate::code::{CodeGenerator, ExpressionAllocator};
+use crate::code::CodeGenerator;
 use crate::types::{
-    Signedness, STRING_CONTENTS_ALIGNMENT, STRING_LENGTH_FIELD, STRING_OFFSET_FIELD,
+    FieldInfo, Signedness, STRING_CONTENTS_ALIGNMENT, STRING_LENGTH_FIELD, STRING_OFFSET_FIELD,
 };
 use crate::GenerationError;
 
-use cranelift_entity::EntityRef;
 use wasm_encoder as enc;
 use wasm_encoder::Instruction;
 
-pub trait EncodeExpression {
-    fn alloc_expr_locals(
-        &self,
-        expression: ExpressionId,
-        allocator: &mut ExpressionAllocator,
-    ) -> Result<(), GenerationError>;
-
-    fn encode(
-        &self,
-        expression: ExpressionId,
-        code_gen: &mut CodeGenerator,
-    ) -> Result<(), GenerationError>;
-}
-
-impl EncodeExpression for ast::Expression {
-    fn alloc_expr_locals(
-        &self,
-        expression: ExpressionId,
-        allocator: &mut ExpressionAllocator,
-    ) -> Result<(), GenerationError> {
-        let expr: &dyn EncodeExpression = match self {
-            ast::Expression::Identifier(expr) => expr,
-            ast::Expression::Enum(expr) => expr,
-            ast::Expression::Literal(expr) => expr,
-            ast::Expression::Call(expr) => expr,
-            ast::Expression::Unary(expr) => expr,
-            ast::Expression::Binary(expr) => expr,
-        };
-        expr.alloc_expr_locals(expression, allocator)
-    }
-
-    fn encode(
-        &self,
-        expression: ExpressionId,
-        code_gen: &mut CodeGenerator,
-    ) -> Result<(), GenerationError> {
-        let expr: &dyn EncodeExpression = match self {
-            ast::Expression::Identifier(expr) => expr,
-            ast::Expression::Enum(expr) => expr,
-            ast::Expression::Literal(expr) => expr,
-            ast::Expression::Call(expr) => expr,
-            ast::Expression::Unary(expr) => expr,
-            ast::Expression::Binary(expr) => expr,
-        };
-        expr.encode(expression, code_gen)?;
-        Ok(())
+pub fn encode_const(
+    dest: TempId,
+    value: &Constant,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    match value {
+        Constant::String(string) => {
+            // Strings are immutable, so they point straight at their constant data
+            code_gen.encode_const_address(string.as_bytes());
+            code_gen.write_temp_field(dest, &STRING_OFFSET_FIELD);
+            code_gen.const_i32(string.len() as i32);
+            code_gen.write_temp_field(dest, &STRING_LENGTH_FIELD);
+        }
+        Constant::Integer(int) => {
+            let field = code_gen.one_field(dest);
+            code_gen.encode_const_int(*int, &field);
+            code_gen.write_temp_field(dest, &field);
+        }
+        Constant::Float(float) => {
+            let field = code_gen.one_field(dest);
+            code_gen.encode_const_float(*float, &field);
+            code_gen.write_temp_field(dest, &field);
+        }
     }
+    Ok(())
 }
 
-impl EncodeExpression for ast::Identifier {
-    fn alloc_expr_locals(
-        &self,
-        expression: ExpressionId,
-        allocator: &mut ExpressionAllocator,
-    ) -> Result<(), GenerationError> {
-        allocator.alloc(expression)
-    }
-
-    fn encode(
-        &self,
-        expression: ExpressionId,
-        code_gen: &mut CodeGenerator,
-    ) -> Result<(), GenerationError> {
-        let fields = code_gen.fields(expression)?;
-        match code_gen.lookup_name(self.ident) {
-            ItemId::ImportFunc(_) => panic!("Cannot use imported function as value!!"),
-            ItemId::Type(_) => panic!("Cannot use type as value!!"),
-            ItemId::Global(global) => {
-                // TODO handle composite globals
-                let field = code_gen.one_field(expression)?;
-                code_gen.instruction(&Instruction::GlobalGet(global.index() as u32));
-                code_gen.write_expr_field(expression, &field);
-            }
-            ItemId::Param(param) => {
-                for field in fields.iter() {
-                    code_gen.read_param_field(param, field);
-                    code_gen.write_expr_field(expression, field);
-                }
+pub fn encode_read(
+    dest: TempId,
+    place: Place,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    let fields = code_gen.fields(dest);
+    match place {
+        Place::Global(global) => {
+            // TODO handle composite globals
+            let field = code_gen.one_field(dest);
+            code_gen.instruction(&Instruction::GlobalGet(code_gen.global_index(global)));
+            code_gen.write_temp_field(dest, &field);
+        }
+        Place::Param(param) => {
+            for field in fields.iter() {
+                code_gen.read_param_field(param, field);
+                code_gen.write_temp_field(dest, field);
             }
-            ItemId::Local(local) => {
-                for field in fields.iter() {
-                    code_gen.read_local_field(local, field);
-                    code_gen.write_expr_field(expression, field);
-                }
+        }
+        Place::Local(local) => {
+            for field in fields.iter() {
+                code_gen.read_local_field(local, field);
+                code_gen.write_temp_field(dest, field);
             }
-            ItemId::Function(_) => panic!("Cannot use function as value!!"),
         }
-        Ok(())
     }
+    Ok(())
 }
 
-impl EncodeExpression for ast::EnumLiteral {
-    fn alloc_expr_locals(
-        &self,
-        expression: ExpressionId,
-        allocator: &mut ExpressionAllocator,
-    ) -> Result<(), GenerationError> {
-        allocator.alloc(expression)
+pub fn encode_negate(
+    dest: TempId,
+    operand: TempId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    code_gen.const_i32(0); // TODO support 64 bit ints
+    for field in code_gen.fields(operand).iter() {
+        code_gen.read_temp_field(operand, field);
     }
-
-    fn encode(
-        &self,
-        expression: ExpressionId,
-        code_gen: &mut CodeGenerator,
-    ) -> Result<(), GenerationError> {
-        match code_gen.lookup_name(self.enum_name) {
-            ItemId::Type(ResolvedType::Import(import_type)) => {
-                let import_type = code_gen.lookup_import_type(import_type);
-                match import_type {
-                    claw_resolver::ImportType::Enum(enum_type) => {
-                        let case_name = code_gen.lookup_name_str(self.case_name);
-                        // TODO nice error instead of unwrap
-                        let case_index =
-                            enum_type.cases.iter().position(|c| c == case_name).unwrap();
-                        code_gen.const_i32(case_index as i32);
-                        let field = code_gen.one_field(expression)?;
-                        code_gen.write_expr_field(expression, &field);
-                    }
-                }
-            }
-            _ => unreachable!(),
-        }
-        Ok(())
+    code_gen.instruction(&enc::Instruction::I32Sub);
+    for field in code_gen.fields(dest).iter() {
+        code_gen.write_temp_field(dest, field);
+    }
+    if code_gen.overflow_checks() {
+        let field = code_gen.one_field(dest);
+        encode_overflow_check(
+            ast::BinaryOp::Subtract,
+            dest,
+            None,
+            operand,
+            &field,
+            code_gen,
+        );
     }
+    Ok(())
 }
 
-impl EncodeExpression for ast::Literal {
-    fn alloc_expr_locals(
-        &self,
-        expression: ExpressionId,
-        allocator: &mut ExpressionAllocator,
-    ) -> Result<(), GenerationError> {
-        allocator.alloc(expression)
+/// Convert `src` to the wider type of `dest`, see [claw_resolver::mir::Inst::Widen].
+pub fn encode_widen(
+    dest: TempId,
+    src: TempId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    let src_field = code_gen.one_field(src);
+    let field = code_gen.one_field(dest);
+    code_gen.read_temp_field(src, &src_field);
+    if src_field.stack_type == enc::ValType::F32 {
+        code_gen.instruction(&enc::Instruction::F64PromoteF32);
+        code_gen.write_temp_field(dest, &field);
+        return Ok(());
     }
-
-    fn encode(
-        &self,
-        expression: ExpressionId,
-        code_gen: &mut CodeGenerator,
-    ) -> Result<(), GenerationError> {
-        match self {
-            ast::Literal::String(string) => {
-                // Allocate string pointer
-                code_gen.const_i32(0);
-                code_gen.const_i32(0);
-                code_gen.const_i32(2i32.pow(STRING_CONTENTS_ALIGNMENT));
-                code_gen.const_i32(string.len() as i32);
-                code_gen.allocate();
-                code_gen.write_expr_field(expression, &STRING_OFFSET_FIELD);
-                // Store the string length
-                code_gen.const_i32(string.len() as i32);
-                code_gen.write_expr_field(expression, &STRING_LENGTH_FIELD);
-                // Copy in the data segment
-                let index = code_gen.encode_const_bytes(string.as_bytes());
-                code_gen.read_expr_field(expression, &STRING_OFFSET_FIELD);
-                code_gen.const_i32(0);
-                code_gen.read_expr_field(expression, &STRING_LENGTH_FIELD);
-                code_gen.instruction(&enc::Instruction::MemoryInit {
-                    mem: 0,
-                    data_index: index.into(),
-                })
-            }
-            ast::Literal::Integer(int) => {
-                let field = code_gen.one_field(expression)?;
-                code_gen.encode_const_int(*int, &field);
-                code_gen.write_expr_field(expression, &field);
-            }
-            ast::Literal::Float(float) => {
-                let field = code_gen.one_field(expression)?;
-                code_gen.encode_const_float(*float, &field);
-                code_gen.write_expr_field(expression, &field);
-            }
-        }
-        Ok(())
+    // Narrow signed values are stored masked, so their sign has to be restored
+    if let (S, bytes @ (1 | 2)) = (src_field.signedness, src_field.mems_size) {
+        code_gen.sign_extend_i32(bytes);
     }
-}
-
-impl EncodeExpression for ast::Call {
-    fn alloc_expr_locals(
-        &self,
-        expression: ExpressionId,
-        allocator: &mut ExpressionAllocator,
-    ) -> Result<(), GenerationError> {
-        allocator.alloc(expression)?;
-        for arg in self.args.iter() {
-            allocator.alloc_child(*arg)?;
-        }
-        Ok(())
+    if src_field.stack_type == enc::ValType::I32 && field.stack_type == enc::ValType::I64 {
+        code_gen.instruction(&match src_field.signedness {
+            S => enc::Instruction::I64ExtendI32S,
+            U => enc::Instruction::I64ExtendI32U,
+        });
     }
-
-    fn encode(
-        &self,
-        expression: ExpressionId,
-        code_gen: &mut CodeGenerator,
-    ) -> Result<(), GenerationError> {
-        for arg in self.args.iter() {
-            code_gen.encode_child(*arg)?;
-        }
-        let item = code_gen.lookup_name(self.ident);
-        code_gen.encode_call(item, &self.args, Some(expression))
+    if let Some(mask) = field.arith_mask {
+        code_gen.const_i32(mask);
+        code_gen.instruction(&enc::Instruction::I32And);
     }
+    code_gen.write_temp_field(dest, &field);
+    Ok(())
 }
 
-impl EncodeExpression for ast::UnaryExpression {
-    fn alloc_expr_locals(
-        &self,
-        expression: ExpressionId,
-        allocator: &mut ExpressionAllocator,
-    ) -> Result<(), GenerationError> {
-        allocator.alloc(expression)?;
-        allocator.alloc_child(self.inner)
-    }
-
-    fn encode(
-        &self,
-        expression: ExpressionId,
-        code_gen: &mut CodeGenerator,
-    ) -> Result<(), GenerationError> {
-        code_gen.const_i32(0); // TODO support 64 bit ints
-        code_gen.encode_child(self.inner)?;
-        for field in code_gen.fields(self.inner)?.iter() {
-            code_gen.read_expr_field(self.inner, field);
-        }
-        code_gen.instruction(&enc::Instruction::I32Sub);
-        for field in code_gen.fields(expression)?.iter() {
-            code_gen.write_expr_field(expression, field);
+pub fn encode_binary(
+    op: ast::BinaryOp,
+    dest: TempId,
+    left: TempId,
+    right: TempId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    let ptype = code_gen.get_ptype(dest);
+    if ptype == Some(ast::PrimitiveType::String) {
+        if op == ast::BinaryOp::Add {
+            encode_string_concatenation(dest, left, right, code_gen)
+        } else {
+            panic!("Strings can only be concatenated with '+'");
         }
-        Ok(())
+    } else {
+        encode_binary_arithmetic(op, dest, left, right, code_gen)
     }
 }
 
-impl EncodeExpression for ast::BinaryExpression {
-    fn alloc_expr_locals(
-        &self,
-        expression: ExpressionId,
-        allocator: &mut ExpressionAllocator,
-    ) -> Result<(), GenerationError> {
-        allocator.alloc(expression)?;
-        allocator.alloc_child(self.left)?;
-        allocator.alloc_child(self.right)?;
-        Ok(())
-    }
-
-    fn encode(
-        &self,
-        expression: ExpressionId,
-        code_gen: &mut CodeGenerator,
-    ) -> Result<(), GenerationError> {
-        code_gen.encode_child(self.left)?;
-        code_gen.encode_child(self.right)?;
+/// Evaluate the right operand of `and` or `or` only if the left one doesn't decide
+/// the result, see [claw_resolver::mir::Inst::ShortCircuit].
+pub fn encode_short_circuit(
+    op: ast::BinaryOp,
+    dest: TempId,
+    left: TempId,
+    block: &[Inst],
+    right: TempId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    let field = code_gen.one_field(dest);
+    code_gen.read_temp_field(left, &code_gen.one_field(left));
+    code_gen.write_temp_field(dest, &field);
 
-        let ptype = code_gen.get_ptype(expression)?;
-        if ptype == Some(ast::PrimitiveType::String) {
-            if self.op == ast::BinaryOp::Add {
-                encode_string_concatenation(expression, self.left, self.right, code_gen)
-            } else {
-                panic!("Strings can only be concatenated with '+'");
-            }
-        } else {
-            encode_binary_arithmetic(self.op, expression, self.left, self.right, code_gen)
-        }
+    code_gen.read_temp_field(dest, &field);
+    if op == ast::BinaryOp::LogicalOr {
+        code_gen.instruction(&enc::Instruction::I32Eqz);
     }
+    code_gen.instruction(&enc::Instruction::If(enc::BlockType::Empty));
+    code_gen.encode_block(block)?;
+    code_gen.read_temp_field(right, &code_gen.one_field(right));
+    code_gen.write_temp_field(dest, &field);
+    code_gen.instruction(&enc::Instruction::End);
+    Ok(())
 }
 
 fn encode_string_concatenation(
-    expression: ExpressionId,
-    left: ExpressionId,
-    right: ExpressionId,
+    dest: TempId,
+    left: TempId,
+    right: TempId,
     code_gen: &mut CodeGenerator,
 ) -> Result<(), GenerationError> {
     // Compute new length
-    code_gen.read_expr_field(left, &STRING_LENGTH_FIELD);
-    code_gen.read_expr_field(right, &STRING_LENGTH_FIELD);
+    code_gen.read_temp_field(left, &STRING_LENGTH_FIELD);
+    code_gen.read_temp_field(right, &STRING_LENGTH_FIELD);
     code_gen.instruction(&enc::Instruction::I32Add);
-    code_gen.write_expr_field(expression, &STRING_LENGTH_FIELD);
+    code_gen.write_temp_field(dest, &STRING_LENGTH_FIELD);
     // Allocate new string
     code_gen.const_i32(0);
     code_gen.const_i32(0);
     code_gen.const_i32(2i32.pow(STRING_CONTENTS_ALIGNMENT));
-    code_gen.read_expr_field(expression, &STRING_LENGTH_FIELD);
+    code_gen.read_temp_field(dest, &STRING_LENGTH_FIELD);
     code_gen.allocate();
-    code_gen.write_expr_field(expression, &STRING_OFFSET_FIELD);
+    code_gen.write_temp_field(dest, &STRING_OFFSET_FIELD);
     // Copy in the left string
-    code_gen.read_expr_field(expression, &STRING_OFFSET_FIELD);
-    code_gen.read_expr_field(left, &STRING_OFFSET_FIELD);
-    code_gen.read_expr_field(left, &STRING_LENGTH_FIELD);
-    code_gen.instruction(&enc::Instruction::MemoryCopy {
-        src_mem: 0,
-        dst_mem: 0,
-    });
+    code_gen.read_temp_field(dest, &STRING_OFFSET_FIELD);
+    code_gen.read_temp_field(left, &STRING_OFFSET_FIELD);
+    code_gen.read_temp_field(left, &STRING_LENGTH_FIELD);
+    code_gen.copy_memory();
     // Copy in the right string
-    code_gen.read_expr_field(expression, &STRING_OFFSET_FIELD);
-    code_gen.read_expr_field(left, &STRING_LENGTH_FIELD);
+    code_gen.read_temp_field(dest, &STRING_OFFSET_FIELD);
+    code_gen.read_temp_field(left, &STRING_LENGTH_FIELD);
     code_gen.instruction(&enc::Instruction::I32Add);
-    code_gen.read_expr_field(right, &STRING_OFFSET_FIELD);
-    code_gen.read_expr_field(right, &STRING_LENGTH_FIELD);
-    code_gen.instruction(&enc::Instruction::MemoryCopy {
-        src_mem: 0,
-        dst_mem: 0,
-    });
+    code_gen.read_temp_field(right, &STRING_OFFSET_FIELD);
+    code_gen.read_temp_field(right, &STRING_LENGTH_FIELD);
+    code_gen.copy_memory();
     Ok(())
 }
 
@@ -320,21 +208,21 @@ const U: Signedness = Signedness::Unsigned;
 
 fn encode_binary_arithmetic(
     op: ast::BinaryOp,
-    expression: ExpressionId,
-    left: ExpressionId,
-    right: ExpressionId,
+    dest: TempId,
+    left: TempId,
+    right: TempId,
     code_gen: &mut CodeGenerator,
 ) -> Result<(), GenerationError> {
-    let left_field = code_gen.one_field(left)?;
-    let right_field = code_gen.one_field(right)?;
-    let field = code_gen.one_field(expression)?;
+    let left_field = code_gen.one_field(left);
+    let right_field = code_gen.one_field(right);
+    let field = code_gen.one_field(dest);
 
     let valtype = left_field.stack_type;
     let signedness = left_field.signedness;
     let mask = left_field.arith_mask;
 
-    code_gen.read_expr_field(left, &left_field);
-    code_gen.read_expr_field(right, &right_field);
+    code_gen.read_temp_field(left, &left_field);
+    code_gen.read_temp_field(right, &right_field);
 
     let instruction = match (op, valtype, signedness) {
         // Multiply
@@ -437,6 +325,148 @@ fn encode_binary_arithmetic(
         code_gen.instruction(&enc::Instruction::I32And);
     }
 
-    code_gen.write_expr_field(expression, &field);
+    code_gen.write_temp_field(dest, &field);
+
+    if code_gen.overflow_checks() {
+        encode_overflow_check(op, dest, Some(left), right, &field, code_gen);
+    }
     Ok(())
 }
+
+/// Emit a runtime check that traps if the integer `op` which produced
+/// `dest` overflowed. A `left` of `None` stands for the constant zero.
+fn encode_overflow_check(
+    op: ast::BinaryOp,
+    dest: TempId,
+    left: Option<TempId>,
+    right: TempId,
+    field: &FieldInfo,
+    code_gen: &mut CodeGenerator,
+) {
+    match (op, field.stack_type) {
+        (
+            ast::BinaryOp::Add | ast::BinaryOp::Subtract | ast::BinaryOp::Multiply,
+            enc::ValType::I32,
+        ) => encode_overflow_check_i32(op, dest, left, right, field, code_gen),
+        (
+            ast::BinaryOp::Add | ast::BinaryOp::Subtract | ast::BinaryOp::Multiply,
+            enc::ValType::I64,
+        ) => encode_overflow_check_i64(op, dest, left, right, field, code_gen),
+        _ => {}
+    }
+}
+
+/// Recompute the operation exactly in 64 bits and trap if it
+/// differs from the (possibly wrapped) 32 bit result.
+fn encode_overflow_check_i32(
+    op: ast::BinaryOp,
+    dest: TempId,
+    left: Option<TempId>,
+    right: TempId,
+    field: &FieldInfo,
+    code_gen: &mut CodeGenerator,
+) {
+    match left {
+        Some(left) => read_widened(left, field, code_gen),
+        None => code_gen.instruction(&enc::Instruction::I64Const(0)),
+    }
+    read_widened(right, field, code_gen);
+    code_gen.instruction(&match op {
+        ast::BinaryOp::Add => enc::Instruction::I64Add,
+        ast::BinaryOp::Subtract => enc::Instruction::I64Sub,
+        ast::BinaryOp::Multiply => enc::Instruction::I64Mul,
+        _ => unreachable!(),
+    });
+    read_widened(dest, field, code_gen);
+    code_gen.instruction(&enc::Instruction::I64Ne);
+    code_gen.trap_if();
+}
+
+/// Read a 32 bit field and extend it to an i64 according to its type.
+fn read_widened(temp: TempId, field: &FieldInfo, code_gen: &mut CodeGenerator) {
+    code_gen.read_temp_field(temp, field);
+    match (field.signedness, field.mems_size) {
+        (S, bytes @ (1 | 2)) => code_gen.sign_extend_i32(bytes),
+        _ => {}
+    }
+    code_gen.instruction(&match field.signedness {
+        S => enc::Instruction::I64ExtendI32S,
+        U => enc::Instruction::I64ExtendI32U,
+    });
+}
+
+/// Check 64 bit results using the operands and the wrapped result.
+fn encode_overflow_check_i64(
+    op: ast::BinaryOp,
+    dest: TempId,
+    left: Option<TempId>,
+    right: TempId,
+    field: &FieldInfo,
+    code_gen: &mut CodeGenerator,
+) {
+    let read_left = |code_gen: &mut CodeGenerator| match left {
+        Some(left) => code_gen.read_temp_field(left, field),
+        None => code_gen.instruction(&enc::Instruction::I64Const(0)),
+    };
+    match (op, field.signedness) {
+        // Unsigned addition overflowed if the result is less than an operand
+        (ast::BinaryOp::Add, U) => {
+            code_gen.read_temp_field(dest, field);
+            read_left(code_gen);
+            code_gen.instruction(&enc::Instruction::I64LtU);
+        }
+        // Unsigned subtraction overflowed if the right operand is larger
+        (ast::BinaryOp::Subtract, U) => {
+            read_left(code_gen);
+            code_gen.read_temp_field(right, field);
+            code_gen.instruction(&enc::Instruction::I64LtU);
+        }
+        // Signed addition overflowed if the result's sign differs from both operands
+        (ast::BinaryOp::Add, S) => {
+            read_left(code_gen);
+            code_gen.read_temp_field(dest, field);
+            code_gen.instruction(&enc::Instruction::I64Xor);
+            code_gen.read_temp_field(right, field);
+            code_gen.read_temp_field(dest, field);
+            code_gen.instruction(&enc::Instruction::I64Xor);
+            code_gen.instruction(&enc::Instruction::I64And);
+            code_gen.instruction(&enc::Instruction::I64Const(0));
+            code_gen.instruction(&enc::Instruction::I64LtS);
+        }
+        // Signed subtraction overflowed if the operands' signs differ
+        // and the result's sign differs from the left operand
+        (ast::BinaryOp::Subtract, S) => {
+            read_left(code_gen);
+            code_gen.read_temp_field(right, field);
+            code_gen.instruction(&enc::Instruction::I64Xor);
+            read_left(code_gen);
+            code_gen.read_temp_field(dest, field);
+            code_gen.instruction(&enc::Instruction::I64Xor);
+            code_gen.instruction(&enc::Instruction::I64And);
+            code_gen.instruction(&enc::Instruction::I64Const(0));
+            code_gen.instruction(&enc::Instruction::I64LtS);
+        }
+        // Multiplication overflowed if dividing the result by a non-zero left
+        // operand doesn't give back the right operand. Signed division traps
+        // itself for `MIN / -1`, which is only reachable on overflow.
+        (ast::BinaryOp::Multiply, signedness) => {
+            read_left(code_gen);
+            code_gen.instruction(&enc::Instruction::I64Eqz);
+            code_gen.instruction(&enc::Instruction::I32Eqz);
+            code_gen.instruction(&enc::Instruction::If(enc::BlockType::Empty));
+            code_gen.read_temp_field(dest, field);
+            read_left(code_gen);
+            code_gen.instruction(&match signedness {
+                S => enc::Instruction::I64DivS,
+                U => enc::Instruction::I64DivU,
+            });
+            code_gen.read_temp_field(right, field);
+            code_gen.instruction(&enc::Instruction::I64Ne);
+            code_gen.trap_if();
+            code_gen.instruction(&enc::Instruction::End);
+            return;
+        }
+        _ => unreachable!(),
+    }
+    code_gen.trap_if();
+}
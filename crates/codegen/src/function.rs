@@ -11,12 +11,13 @@ use crate::{
         module::{ModuleBuilder, ModuleTypeIndex},
     },
     types::{align_to, EncodeType},
-    GenerationError, MAX_FLAT_PARAMS, MAX_FLAT_RESULTS,
+    GenerationError, GenerationOptions, MAX_FLAT_PARAMS, MAX_FLAT_RESULTS,
 };
 
 pub struct FunctionEncoder<'gen> {
     comp: &'gen ast::Component,
     rcomp: &'gen ResolvedComponent,
+    multi_value: bool,
 
     funcs: HashMap<FunctionId, EncodedFunction>,
 }
@@ -45,15 +46,29 @@ pub struct ParamInfo {
 }
 
 impl<'gen> FunctionEncoder<'gen> {
-    pub fn new(comp: &'gen ast::Component, rcomp: &'gen ResolvedComponent) -> Self {
+    pub fn new(
+        comp: &'gen ast::Component,
+        rcomp: &'gen ResolvedComponent,
+        options: &GenerationOptions,
+    ) -> Self {
         let funcs = HashMap::new();
+        let multi_value = options.features.multi_value;
 
-        Self { comp, rcomp, funcs }
+        Self {
+            comp,
+            rcomp,
+            multi_value,
+            funcs,
+        }
     }
 
     pub fn encode(mut self) -> Result<EncodedFuncs, GenerationError> {
         // Encode function
         for (id, function) in self.comp.iter_functions() {
+            if !self.rcomp.funcs.contains_key(&id) {
+                // Eliminated as dead code
+                continue;
+            }
             let func = self.encode_func(function)?;
             self.funcs.insert(id, func);
         }
@@ -76,7 +91,9 @@ impl<'gen> FunctionEncoder<'gen> {
             .collect();
         let results = function.results.map(ResolvedType::Defined);
 
-        let func = EncodedFunction::new(params, results, self.comp, self.rcomp);
+        // Without multi-value, every function returns its results like exported ones do
+        let limit_results = function.exported || !self.multi_value;
+        let func = EncodedFunction::new(params, results, limit_results, self.comp, self.rcomp);
         Ok(func)
     }
 }
@@ -85,6 +102,7 @@ impl EncodedFunction {
     pub fn new(
         params: Vec<(String, ResolvedType)>,
         results: Option<ResolvedType>,
+        limit_results: bool,
         comp: &ast::Component,
         rcomp: &ResolvedComponent,
     ) -> Self {
@@ -93,7 +111,7 @@ impl EncodedFunction {
         // Layout return types
         let results = results.map(|results| ResultsInfo {
             rtype: results,
-            spill: ResultSpillInfo::new(results, comp, rcomp),
+            spill: ResultSpillInfo::new(results, limit_results, comp, rcomp),
         });
 
         Self {
@@ -124,9 +142,8 @@ impl EncodedFunction {
 
     pub fn encode_mod_type(&self, builder: &mut ModuleBuilder) -> ModuleTypeIndex {
         let params = self.flat_params.iter().copied();
-        match self.results.as_ref().map(|info| &info.spill) {
-            Some(ResultSpillInfo::Flat { valtype }) => builder.func_type(params, [*valtype]),
-            Some(ResultSpillInfo::Spilled) => builder.func_type(params, [enc::ValType::I32]),
+        match &self.results {
+            Some(info) => builder.func_type(params, info.spill.valtypes()),
             None => builder.func_type(params, []),
         }
     }
@@ -183,19 +200,26 @@ impl ResultsInfo {
 }
 
 pub enum ResultSpillInfo {
-    Flat { valtype: enc::ValType },
+    Flat { valtypes: Vec<enc::ValType> },
     Spilled,
 }
 
 impl ResultSpillInfo {
-    pub fn new(rtype: ResolvedType, comp: &ast::Component, rcomp: &ResolvedComponent) -> Self {
-        if rtype.flat_size(comp, rcomp) > MAX_FLAT_RESULTS as u32 {
+    /// Results are only spilled when the canonical ABI requires it, which is
+    /// when an exported function returns more than [MAX_FLAT_RESULTS] values,
+    /// or when multi-value is disabled. Otherwise functions return all of
+    /// their values with multi-value.
+    pub fn new(
+        rtype: ResolvedType,
+        limit_results: bool,
+        comp: &ast::Component,
+        rcomp: &ResolvedComponent,
+    ) -> Self {
+        if limit_results && rtype.flat_size(comp, rcomp) > MAX_FLAT_RESULTS as u32 {
             ResultSpillInfo::Spilled
         } else {
-            let result_types = rtype.flatten(comp, rcomp);
-            assert_eq!(result_types.len(), 1);
-            let valtype = result_types[0];
-            ResultSpillInfo::Flat { valtype }
+            let valtypes = rtype.flatten(comp, rcomp);
+            ResultSpillInfo::Flat { valtypes }
         }
     }
 
@@ -203,10 +227,11 @@ impl ResultSpillInfo {
         matches!(self, ResultSpillInfo::Spilled)
     }
 
-    pub fn valtype(&self) -> enc::ValType {
+    /// The core types of the values a function returns.
+    pub fn valtypes(&self) -> Vec<enc::ValType> {
         match self {
-            ResultSpillInfo::Flat { valtype } => *valtype,
-            ResultSpillInfo::Spilled => enc::ValType::I32,
+            ResultSpillInfo::Flat { valtypes } => valtypes.clone(),
+            ResultSpillInfo::Spilled => vec![enc::ValType::I32],
         }
     }
 }
@@ -239,7 +239,7 @@ impl<'a, 'b, 'c> ImportInterfaceEncoder<'a, 'b, 'c> {
                 ImportItemId::Type(rtype) => {
                     self.encode_rtype(*rtype);
                 }
-                ImportItemId::Func(id) => {
+                ImportItemId::Func(id) | ImportItemId::Value(id) => {
                     self.encode_func(*id);
                 }
             }
@@ -335,7 +335,7 @@ impl<'a, 'b, 'c> ImportInterfaceEncoder<'a, 'b, 'c> {
         for item in &self.interface.items {
             match item {
                 ImportItemId::Type(_) => {}
-                ImportItemId::Func(id) => {
+                ImportItemId::Func(id) | ImportItemId::Value(id) => {
                     let import_func = &self.parent.rcomp.imports.funcs[*id];
                     let func_idx = self
                         .parent
@@ -1,7 +1,11 @@
 #![allow(clippy::single_match)]
 
 mod builders;
+mod builtin;
+mod cache;
 mod code;
+mod data;
+mod debug;
 mod expression;
 mod function;
 mod imports;
@@ -10,52 +14,105 @@ mod statement;
 mod types;
 
 use builders::component::*;
+use cache::CacheContext;
+
+pub use cache::CodeCache;
+pub use claw_common::WasmFeatures;
+
+use std::collections::HashMap;
 
 use claw_ast as ast;
-use claw_resolver::{ResolvedComponent, ResolverError};
+use claw_ast::FunctionId;
+use claw_resolver::{AccessorKind, GlobalAccessor, ResolvedComponent, ResolverError};
 use miette::Diagnostic;
 use thiserror::Error;
 use types::EncodeType;
+use wasm_encoder as enc;
 
-#[derive(Error, Debug, Diagnostic)]
+#[derive(Error, Debug, Diagnostic, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum GenerationError {
     #[error(transparent)]
     #[diagnostic(transparent)]
     Resolver(#[from] ResolverError),
 }
 
+/// Options controlling the generated code.
+#[derive(Debug, Clone, Default)]
+pub struct GenerationOptions {
+    /// Trap when integer arithmetic overflows instead of wrapping
+    pub overflow_checks: bool,
+    /// Let expressions in different statements share locals
+    pub reuse_locals: bool,
+    /// Name functions, locals, and globals in the name section of the core module
+    pub debug_names: bool,
+    /// Embed a source map from the core module's code to the Claw source
+    pub debug_info: bool,
+    /// The WebAssembly proposals the generated code may use
+    pub features: WasmFeatures,
+}
+
 pub const MAX_FLAT_PARAMS: u8 = 16;
 pub const MAX_FLAT_RESULTS: u8 = 1;
 
 pub fn generate(
     comp: &ast::Component,
     rcomp: &ResolvedComponent,
+    options: &GenerationOptions,
 ) -> Result<Vec<u8>, GenerationError> {
-    let builder = generate_component(comp, rcomp)?;
+    let builder = generate_component(comp, rcomp, options, None)?;
     Ok(builder.finalize().finish())
 }
 
+/// Generate a component, reusing the code in `cache` for functions whose key hasn't changed.
+///
+/// The key of each function must change whenever anything its code depends on does.
+/// Functions without a key are always generated.
+pub fn generate_cached(
+    comp: &ast::Component,
+    rcomp: &ResolvedComponent,
+    options: &GenerationOptions,
+    cache: &mut CodeCache,
+    keys: &HashMap<FunctionId, u64>,
+) -> Result<Vec<u8>, GenerationError> {
+    cache.start();
+    let context = CacheContext { cache, keys };
+    let builder = generate_component(comp, rcomp, options, Some(context))?;
+    let output = builder.finalize().finish();
+    cache.finish();
+    Ok(output)
+}
+
 fn generate_component(
     comp: &ast::Component,
     rcomp: &ResolvedComponent,
+    options: &GenerationOptions,
+    cache: Option<CacheContext>,
 ) -> Result<ComponentBuilder, GenerationError> {
     let mut builder = ComponentBuilder::default();
 
-    let alloc_module = builder.module_bytes(gen_allocator());
-
+    let layout = data::DataLayout::plan(comp, rcomp);
+    let data_module = builder.module(layout.generate_module(options));
     let args: Vec<(&str, ModuleInstantiateArgs)> = vec![];
+    let data_instance = builder.instantiate(data_module, args);
+
+    let alloc_module = builder.module_bytes(gen_allocator(options.features.bulk_memory));
+
+    let args = vec![("data", ModuleInstantiateArgs::Instance(data_instance))];
     let alloc_instance = builder.instantiate(alloc_module, args);
 
     let memory = builder.alias_memory(alloc_instance, "memory");
-    let realloc = builder.alias_core_func(alloc_instance, "realloc");
+    let realloc = builder.alias_core_func(alloc_instance, "cabi_realloc");
 
     let import_encoder = imports::ImportEncoder::new(&mut builder, comp, rcomp, memory, realloc);
     let imports = import_encoder.encode()?;
 
-    let function_encoder = function::FunctionEncoder::new(comp, rcomp);
+    let function_encoder = function::FunctionEncoder::new(comp, rcomp, options);
     let functions = function_encoder.encode()?;
 
-    let code_module = builder.module(module::generate(comp, rcomp, &imports, &functions)?);
+    let code_module = builder.module(module::generate(
+        comp, rcomp, &imports, &functions, &layout, options, cache,
+    )?);
 
     let args = vec![
         ("alloc", ModuleInstantiateArgs::Instance(alloc_instance)),
@@ -87,10 +144,45 @@ impl<'ctx> ExportGenerator<'ctx> {
                 self.generate_function_export(function, builder)?;
             }
         }
+        for accessor in self.rcomp.global_accessors(self.comp) {
+            self.generate_accessor_export(&accessor, builder);
+        }
 
         Ok(())
     }
 
+    fn generate_accessor_export(
+        &mut self,
+        accessor: &GlobalAccessor,
+        builder: &mut ComponentBuilder,
+    ) {
+        let name = accessor.name.as_str();
+        let core_func_idx = builder.alias_core_func(self.code_instance, name);
+        let post_return_idx =
+            builder.alias_core_func(self.code_instance, format!("{}_post_return", name).as_str());
+
+        let global = self.comp.get_global(accessor.global);
+        let valtype = match self.comp.get_type(global.type_id) {
+            ast::ValType::Result(_) => {
+                unreachable!("The resolver rejects exported globals of result types")
+            }
+            ast::ValType::Primitive(ptype) => ptype.to_comp_valtype(self.comp, self.rcomp),
+        };
+        let type_idx = match accessor.kind {
+            AccessorKind::Get => builder.func_type([], Some(valtype)),
+            AccessorKind::Set => builder.func_type([("value", valtype)], None),
+        };
+
+        let func_idx = builder.lift_func(
+            core_func_idx,
+            type_idx,
+            self.memory,
+            self.realloc,
+            post_return_idx,
+        );
+        builder.export_func(name, func_idx, type_idx);
+    }
+
     fn generate_function_export(
         &mut self,
         function: &ast::Function,
@@ -155,9 +247,25 @@ fn generate_exports(
     gen.generate(builder)
 }
 
+/// The producers section recording that the code was compiled from Claw.
+pub(crate) fn producers() -> enc::ProducersSection {
+    let mut language = enc::ProducersField::new();
+    language.value("Claw", "");
+    let mut processed_by = enc::ProducersField::new();
+    processed_by.value("claw", env!("CARGO_PKG_VERSION"));
+
+    let mut producers = enc::ProducersSection::new();
+    producers.field("language", &language);
+    producers.field("processed-by", &processed_by);
+    producers
+}
+
 // ValType
 
-pub fn gen_allocator() -> &'static [u8] {
-    let allocator_wasm = include_bytes!(concat!(env!("OUT_DIR"), "/allocator.wasm"));
-    allocator_wasm
+/// The allocator module, using the bulk memory instructions if `bulk_memory` is set.
+pub fn gen_allocator(bulk_memory: bool) -> &'static [u8] {
+    match bulk_memory {
+        true => include_bytes!(concat!(env!("OUT_DIR"), "/allocator.wasm")),
+        false => include_bytes!(concat!(env!("OUT_DIR"), "/allocator-no-bulk-memory.wasm")),
+    }
 }
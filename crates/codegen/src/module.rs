@@ -1,17 +1,23 @@
 use std::collections::HashMap;
 
-use ast::{FunctionId, NameId};
+use ast::{FunctionId, GlobalId, NameId};
 use claw_ast as ast;
-use claw_resolver::{ImportFuncId, ImportFunction, ResolvedComponent};
+use claw_resolver::{
+    lower_function, recursive_tail_calls, AccessorKind, ConstValue, GlobalAccessor, ImportFuncId,
+    ImportFunction, ResolvedComponent, TailCall,
+};
+use cranelift_entity::EntityRef;
 use wasm_encoder as enc;
 
 use crate::{
     builders::module::*,
+    cache::{CacheContext, CachedCode},
     code::CodeGenerator,
+    data::DataLayout,
     function::{EncodedFuncs, EncodedFunction},
     imports::{EncodedImportFunc, EncodedImports},
     types::EncodeType,
-    GenerationError,
+    GenerationError, GenerationOptions,
 };
 
 pub(crate) fn generate(
@@ -19,8 +25,11 @@ pub(crate) fn generate(
     rcomp: &ResolvedComponent,
     imports: &EncodedImports,
     functions: &EncodedFuncs,
+    layout: &DataLayout,
+    options: &GenerationOptions,
+    cache: Option<CacheContext>,
 ) -> Result<enc::Module, GenerationError> {
-    ModuleGenerator::new(comp, rcomp, imports, functions).generate()
+    ModuleGenerator::new(comp, rcomp, imports, functions, layout, options, cache).generate()
 }
 
 pub struct ModuleGenerator<'gen> {
@@ -28,10 +37,16 @@ pub struct ModuleGenerator<'gen> {
     pub rcomp: &'gen ResolvedComponent,
     imports: &'gen EncodedImports,
     functions: &'gen EncodedFuncs,
+    layout: &'gen DataLayout,
+    options: &'gen GenerationOptions,
+    cache: Option<CacheContext<'gen>>,
     pub module: ModuleBuilder,
 
     func_idx_for_import: HashMap<ImportFuncId, ModuleFunctionIndex>,
     func_idx_for_func: HashMap<FunctionId, ModuleFunctionIndex>,
+    global_idx_for_global: HashMap<GlobalId, ModuleGlobalIndex>,
+    global_idx_for_constant: Vec<ModuleGlobalIndex>,
+    tail_calls: HashMap<FunctionId, Vec<TailCall>>,
 }
 
 impl<'gen> ModuleGenerator<'gen> {
@@ -40,21 +55,34 @@ impl<'gen> ModuleGenerator<'gen> {
         rcomp: &'gen ResolvedComponent,
         imports: &'gen EncodedImports,
         functions: &'gen EncodedFuncs,
+        layout: &'gen DataLayout,
+        options: &'gen GenerationOptions,
+        cache: Option<CacheContext<'gen>>,
     ) -> Self {
         Self {
             comp,
             rcomp,
             imports,
             functions,
+            layout,
+            options,
+            cache,
             module: Default::default(),
             func_idx_for_import: Default::default(),
             func_idx_for_func: Default::default(),
+            global_idx_for_global: Default::default(),
+            global_idx_for_constant: Default::default(),
+            tail_calls: Default::default(),
         }
     }
 
     pub fn generate(mut self) -> Result<enc::Module, GenerationError> {
+        if self.options.debug_info {
+            self.module.enable_source_map(self.comp.source());
+        }
+
         // There is only ever one memory, memory zero
-        let (_memory, realloc, clear) = self.encode_import_allocator();
+        let (_memory, realloc, clear, copy) = self.encode_import_allocator();
 
         for (id, import_func) in self.rcomp.imports.funcs.iter() {
             let encoded_import_func = self.imports.funcs.get(&id).unwrap();
@@ -63,38 +91,114 @@ impl<'gen> ModuleGenerator<'gen> {
         }
 
         self.encode_globals()?;
+        self.encode_constants();
+        if self.options.features.tail_call {
+            self.tail_calls = recursive_tail_calls(self.comp, self.rcomp);
+        }
 
         // Encode functions
         for (id, function) in self.comp.iter_functions() {
-            let encoded_func = self.functions.funcs.get(&id).unwrap();
+            let encoded_func = match self.functions.funcs.get(&id) {
+                Some(encoded_func) => encoded_func,
+                None => continue,
+            };
             let func_idx = self.encode_func(function, encoded_func)?;
             self.func_idx_for_func.insert(id, func_idx);
         }
+        // The init block runs when the module is instantiated, before any export is called
+        if let Some(init) = self.comp.init() {
+            self.module.start(self.func_idx_for_func[&init]);
+        }
         // Encode function code
-        for (id, encoded_func) in self.functions.funcs.iter() {
-            let id = *id;
+        for (id, _) in self.comp.iter_functions() {
+            let encoded_func = match self.functions.funcs.get(&id) {
+                Some(encoded_func) => encoded_func,
+                None => continue,
+            };
+            let mod_func_idx = self.func_idx_for_func[&id];
+            // Code refers to constants through the globals holding their addresses
+            let constants: Vec<u32> = self
+                .layout
+                .func_constants(id)
+                .iter()
+                .map(|index| self.global_idx_for_constant[*index].into())
+                .collect();
+            // Whether a call becomes a tail call depends on the body of the callee
+            let tail_calls = self
+                .tail_calls
+                .get(&id)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let callees: Vec<u32> = tail_calls
+                .iter()
+                .map(|tail_call| tail_call.callee.index() as u32)
+                .collect();
+            let key = self
+                .cache
+                .as_ref()
+                .and_then(|cache| cache.key(id, (&constants, &callees)));
+            let cached = match (&mut self.cache, key) {
+                (Some(context), Some(key)) => context.cache.get(key),
+                _ => None,
+            };
+            if let Some(cached) = cached {
+                if self.options.debug_names {
+                    self.module.name_locals(mod_func_idx, &cached.local_names);
+                }
+                self.module.source_spans(mod_func_idx, &cached.source_spans);
+                self.module.code(mod_func_idx, cached.body);
+                continue;
+            }
+
+            let body = lower_function(self.comp, self.rcomp, id, tail_calls)?;
             let code_gen = CodeGenerator::new(
-                &mut self.module,
                 self.comp,
                 self.rcomp,
                 self.imports,
                 self.functions,
                 &self.func_idx_for_import,
                 &self.func_idx_for_func,
+                &self.global_idx_for_global,
+                self.layout,
+                &self.global_idx_for_constant,
+                &body,
                 encoded_func,
                 id,
                 realloc,
+                copy,
+                self.options,
             )?;
-            let builder = code_gen.finalize()?;
-            let mod_func_idx = self.func_idx_for_func[&id];
+            let local_names = code_gen.local_names();
+            let (builder, source_spans) = code_gen.finalize()?;
+            if self.options.debug_names {
+                self.module.name_locals(mod_func_idx, &local_names);
+            }
+            self.module.source_spans(mod_func_idx, &source_spans);
+            if let (Some(context), Some(key)) = (&mut self.cache, key) {
+                let body = builder.clone();
+                context.cache.insert(
+                    key,
+                    CachedCode {
+                        body,
+                        local_names,
+                        source_spans,
+                    },
+                );
+            }
             self.module.code(mod_func_idx, builder);
         }
 
         // Encode post returns
         for (id, function) in self.comp.iter_functions() {
+            if self.comp.is_init(id) {
+                continue;
+            }
             // Encode function
             let ident = function.ident;
-            let encoded_func = self.functions.funcs.get(&id).unwrap();
+            let encoded_func = match self.functions.funcs.get(&id) {
+                Some(encoded_func) => encoded_func,
+                None => continue,
+            };
             let post_return = self.encode_post_return_func(ident, encoded_func)?;
             // Encode code
             let mut builder = enc::Function::new(vec![]);
@@ -103,23 +207,47 @@ impl<'gen> ModuleGenerator<'gen> {
             self.module.code(post_return, builder);
         }
 
+        for accessor in self.rcomp.global_accessors(self.comp) {
+            self.encode_global_accessor(&accessor);
+        }
+
         Ok(self.module.finalize())
     }
 
     fn encode_import_allocator(
         &mut self,
-    ) -> (ModuleMemoryIndex, ModuleFunctionIndex, ModuleFunctionIndex) {
+    ) -> (
+        ModuleMemoryIndex,
+        ModuleFunctionIndex,
+        ModuleFunctionIndex,
+        Option<ModuleFunctionIndex>,
+    ) {
         let memory: ModuleMemoryIndex = self.module.import_memory("alloc", "memory");
 
         let realloc_type = self
             .module
             .func_type(vec![enc::ValType::I32; 4], vec![enc::ValType::I32; 1]);
-        let realloc = self.module.import_func("alloc", "realloc", realloc_type);
+        let realloc = self
+            .module
+            .import_func("alloc", "cabi_realloc", realloc_type);
+        self.name_func(realloc, "cabi_realloc");
 
         let clear_type = self.module.func_type(vec![], vec![]);
         let clear = self.module.import_func("alloc", "clear", clear_type);
+        self.name_func(clear, "clear");
 
-        (memory, realloc, clear)
+        // Without bulk memory, copies call the allocator's byte loop instead
+        let copy = match self.options.features.bulk_memory {
+            true => None,
+            false => {
+                let copy_type = self.module.func_type(vec![enc::ValType::I32; 3], vec![]);
+                let copy = self.module.import_func("alloc", "copy", copy_type);
+                self.name_func(copy, "copy");
+                Some(copy)
+            }
+        };
+
+        (memory, realloc, clear, copy)
     }
 
     fn encode_import_func(
@@ -129,7 +257,9 @@ impl<'gen> ModuleGenerator<'gen> {
     ) -> ModuleFunctionIndex {
         let type_idx = encoded_import_func.encode_mod_type(&mut self.module);
         let import_alias = import_func.alias.as_str();
-        self.module.import_func("claw", import_alias, type_idx)
+        let func_idx = self.module.import_func("claw", import_alias, type_idx);
+        self.name_func(func_idx, import_alias);
+        func_idx
     }
 
     fn encode_globals(&mut self) -> Result<(), GenerationError> {
@@ -142,17 +272,38 @@ impl<'gen> ModuleGenerator<'gen> {
                 let valtype = self.comp.get_type(global.type_id);
                 match valtype {
                     ast::ValType::Result(_) => todo!(),
-                    ast::ValType::Primitive(ptype) => literal_to_const_expr(init_value, *ptype),
+                    ast::ValType::Primitive(ptype) => const_value_to_const_expr(init_value, *ptype),
                 }
             } else {
-                panic!("Cannot generate WASM for unresolved global")
+                // Eliminated as dead code
+                continue;
             };
 
-            self.module.global(global.mutable, valtype, &init_expr);
+            let global_idx = self.module.global(global.mutable, valtype, &init_expr);
+            if self.options.debug_names {
+                let name = self.comp.get_name(global.ident);
+                self.module.name_global(global_idx, name);
+            }
+            self.global_idx_for_global.insert(id, global_idx);
         }
         Ok(())
     }
 
+    /// Encode a global holding the address of each constant, which is where
+    /// the data module puts it. Code doesn't use the addresses directly so that
+    /// it can be reused when the constants of other functions move.
+    fn encode_constants(&mut self) {
+        for (index, constant) in self.layout.constants().iter().enumerate() {
+            let init_expr = enc::ConstExpr::i32_const(constant.offset as i32);
+            let global_idx = self.module.global(false, enc::ValType::I32, &init_expr);
+            if self.options.debug_names {
+                self.module
+                    .name_global(global_idx, &format!("constant{}", index));
+            }
+            self.global_idx_for_constant.push(global_idx);
+        }
+    }
+
     fn encode_post_return_func(
         &mut self,
         ident: NameId,
@@ -160,7 +311,7 @@ impl<'gen> ModuleGenerator<'gen> {
     ) -> Result<ModuleFunctionIndex, GenerationError> {
         let return_type = &encoded_func.results;
         let type_idx = match return_type {
-            Some(info) => self.module.func_type([info.spill.valtype()], []),
+            Some(info) => self.module.func_type(info.spill.valtypes(), []),
             None => self.module.func_type([], []),
         };
         let func_idx = self.module.function(type_idx);
@@ -168,10 +319,49 @@ impl<'gen> ModuleGenerator<'gen> {
         let name = self.comp.get_name(ident);
         let name = format!("{}_post_return", name);
         self.module.export_func(name.as_str(), func_idx);
+        self.name_func(func_idx, &name);
 
         Ok(func_idx)
     }
 
+    /// Encode and export a function that reads or writes an exported global,
+    /// along with an empty post return since it doesn't allocate anything.
+    fn encode_global_accessor(&mut self, accessor: &GlobalAccessor) {
+        let global = self.comp.get_global(accessor.global);
+        let valtype = global.type_id.flatten(self.comp, self.rcomp)[0];
+        let global_idx = self.global_idx_for_global[&accessor.global].into();
+        let (params, results) = match accessor.kind {
+            AccessorKind::Get => (vec![], vec![valtype]),
+            AccessorKind::Set => (vec![valtype], vec![]),
+        };
+
+        let type_idx = self.module.func_type(params, results.clone());
+        let func_idx = self.module.function(type_idx);
+        self.module.export_func(&accessor.name, func_idx);
+        self.name_func(func_idx, &accessor.name);
+        let mut builder = enc::Function::new(vec![]);
+        match accessor.kind {
+            AccessorKind::Get => {
+                builder.instruction(&enc::Instruction::GlobalGet(global_idx));
+            }
+            AccessorKind::Set => {
+                builder.instruction(&enc::Instruction::LocalGet(0));
+                builder.instruction(&enc::Instruction::GlobalSet(global_idx));
+            }
+        }
+        builder.instruction(&enc::Instruction::End);
+        self.module.code(func_idx, builder);
+
+        let post_return_type = self.module.func_type(results, []);
+        let post_return = self.module.function(post_return_type);
+        let name = format!("{}_post_return", accessor.name);
+        self.module.export_func(&name, post_return);
+        self.name_func(post_return, &name);
+        let mut builder = enc::Function::new(vec![]);
+        builder.instruction(&enc::Instruction::End);
+        self.module.code(post_return, builder);
+    }
+
     fn encode_func(
         &mut self,
         function: &ast::Function,
@@ -180,30 +370,43 @@ impl<'gen> ModuleGenerator<'gen> {
         let type_idx = encoded_func.encode_mod_type(&mut self.module);
         let func_idx = self.module.function(type_idx);
 
+        let name = self.comp.get_name(function.ident);
+        self.name_func(func_idx, name);
         if function.exported {
-            let ident = function.ident;
-            let name = self.comp.get_name(ident);
             // Export function from module
             self.module.export_func(name, func_idx);
         }
 
         Ok(func_idx)
     }
+
+    fn name_func(&mut self, func: ModuleFunctionIndex, name: &str) {
+        if self.options.debug_names {
+            self.module.name_func(func, name);
+        }
+    }
 }
 
-// Literal
+// Constant
 
-fn literal_to_const_expr(literal: &ast::Literal, ptype: ast::PrimitiveType) -> enc::ConstExpr {
-    use ast::{Literal, PrimitiveType};
-    match (ptype, literal) {
-        (PrimitiveType::S32 | PrimitiveType::U32, Literal::Integer(value)) => {
-            enc::ConstExpr::i32_const(*value as i32)
-        }
-        (PrimitiveType::S64 | PrimitiveType::U64, Literal::Integer(value)) => {
+fn const_value_to_const_expr(value: &ConstValue, ptype: ast::PrimitiveType) -> enc::ConstExpr {
+    use ast::PrimitiveType;
+    match (ptype, value) {
+        (
+            PrimitiveType::U8
+            | PrimitiveType::S8
+            | PrimitiveType::U16
+            | PrimitiveType::S16
+            | PrimitiveType::U32
+            | PrimitiveType::S32,
+            ConstValue::Integer(value),
+        ) => enc::ConstExpr::i32_const(*value as i32),
+        (PrimitiveType::S64 | PrimitiveType::U64, ConstValue::Integer(value)) => {
             enc::ConstExpr::i64_const(*value as i64)
         }
-        (PrimitiveType::F32, Literal::Float(value)) => enc::ConstExpr::f32_const(*value as f32),
-        (PrimitiveType::F64, Literal::Float(value)) => enc::ConstExpr::f64_const(*value),
+        (PrimitiveType::F32, ConstValue::Float(value)) => enc::ConstExpr::f32_const(*value as f32),
+        (PrimitiveType::F64, ConstValue::Float(value)) => enc::ConstExpr::f64_const(*value),
+        (PrimitiveType::Bool, ConstValue::Bool(value)) => enc::ConstExpr::i32_const(*value as i32),
         _ => todo!(),
     }
 }
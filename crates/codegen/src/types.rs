@@ -318,6 +318,7 @@ impl EncodeType for ast::PrimitiveType {
             ast::PrimitiveType::U64 | ast::PrimitiveType::S64 => enc::ValType::I64,
             ast::PrimitiveType::F32 => enc::ValType::F32,
             ast::PrimitiveType::F64 => enc::ValType::F64,
+            ast::PrimitiveType::V128 => enc::ValType::V128,
             ast::PrimitiveType::String => {
                 string_append_flatten(out);
                 return;
@@ -339,6 +340,7 @@ impl EncodeType for ast::PrimitiveType {
             ast::PrimitiveType::S64 => S64_FIELD,
             ast::PrimitiveType::F32 => F32_FIELD,
             ast::PrimitiveType::F64 => F64_FIELD,
+            ast::PrimitiveType::V128 => V128_FIELD,
             ast::PrimitiveType::String => {
                 string_append_fields(out);
                 return;
@@ -367,6 +369,7 @@ fn ptype_align(ptype: ast::PrimitiveType) -> u32 {
         ast::PrimitiveType::U32 | ast::PrimitiveType::S32 | ast::PrimitiveType::F32 => 2,
         ast::PrimitiveType::U64 | ast::PrimitiveType::S64 | ast::PrimitiveType::F64 => 3,
         ast::PrimitiveType::String => STRING_ALIGNMENT,
+        ast::PrimitiveType::V128 => 4,
     }
 }
 
@@ -377,6 +380,7 @@ fn ptype_mem_size(ptype: ast::PrimitiveType) -> u32 {
         ast::PrimitiveType::U32 | ast::PrimitiveType::S32 | ast::PrimitiveType::F32 => 4,
         ast::PrimitiveType::U64 | ast::PrimitiveType::S64 | ast::PrimitiveType::F64 => 8,
         ast::PrimitiveType::String => STRING_MEM_SIZE,
+        ast::PrimitiveType::V128 => 16,
     }
 }
 
@@ -395,10 +399,13 @@ pub fn ptype_to_pvaltype(ptype: ast::PrimitiveType) -> enc::PrimitiveValType {
         PType::F64 => enc::PrimitiveValType::F64,
         PType::Bool => enc::PrimitiveValType::Bool,
         PType::String => enc::PrimitiveValType::String,
+        PType::V128 => unreachable!("`v128` values can't be passed between components"),
     }
 }
 
+/// Round `offset` up to a multiple of `2^alignment` bytes.
 pub fn align_to(offset: u32, alignment: u32) -> u32 {
+    let alignment = 1 << alignment;
     offset.div_ceil(alignment) * alignment
 }
 
@@ -546,6 +553,16 @@ pub const F64_FIELD: FieldInfo = FieldInfo {
     mems_size: 8,
 };
 
+pub const V128_FIELD: FieldInfo = FieldInfo {
+    stack_type: enc::ValType::V128,
+    signedness: Signedness::Unsigned,
+    arith_mask: None,
+    index_offset: 0,
+    mem_offset: 0,
+    align: 4,
+    mems_size: 16,
+};
+
 pub const STRING_OFFSET_FIELD: FieldInfo = FieldInfo {
     stack_type: enc::ValType::I32,
     signedness: Signedness::Unsigned,
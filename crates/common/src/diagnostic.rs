@@ -1,4 +1,6 @@
-use miette::{Diagnostic, Report};
+use std::fmt;
+
+use miette::{Diagnostic, Report, SourceSpan};
 
 pub trait UnwrapPretty {
     type Output;
@@ -44,3 +46,151 @@ where
         }
     }
 }
+
+/// How serious a diagnostic is.
+///
+/// Errors stop compilation, while warnings and notes are
+/// collected and reported alongside a successful result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Note,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// The severity of a diagnostic, defaulting to [Severity::Error]
+    /// when the diagnostic doesn't specify one.
+    pub fn of(diagnostic: &dyn Diagnostic) -> Self {
+        diagnostic
+            .severity()
+            .map(Severity::from)
+            .unwrap_or(Severity::Error)
+    }
+}
+
+impl From<miette::Severity> for Severity {
+    fn from(severity: miette::Severity) -> Self {
+        match severity {
+            miette::Severity::Advice => Severity::Note,
+            miette::Severity::Warning => Severity::Warning,
+            miette::Severity::Error => Severity::Error,
+        }
+    }
+}
+
+impl From<Severity> for miette::Severity {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Note => miette::Severity::Advice,
+            Severity::Warning => miette::Severity::Warning,
+            Severity::Error => miette::Severity::Error,
+        }
+    }
+}
+
+/// The non-fatal diagnostics (warnings and notes) produced while compiling.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    reports: Vec<Report>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push<D>(&mut self, diagnostic: D)
+    where
+        D: Diagnostic + Sync + Send + 'static,
+    {
+        self.reports.push(Report::new(diagnostic));
+    }
+
+    pub fn extend(&mut self, other: Diagnostics) {
+        self.reports.extend(other.reports);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Report> {
+        self.reports.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.reports.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reports.is_empty()
+    }
+
+    /// The number of collected diagnostics with the given severity.
+    pub fn count(&self, severity: Severity) -> usize {
+        self.reports
+            .iter()
+            .filter(|report| Severity::of(report.as_ref()) == severity)
+            .count()
+    }
+
+    pub fn into_reports(self) -> Vec<Report> {
+        self.reports
+    }
+}
+
+/// A suggested edit which fixes the problem reported by a diagnostic.
+///
+/// Suggestions are shown as the help text of the diagnostic they're attached to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Suggestion {
+    /// A short description of the edit
+    pub message: String,
+    /// The source code to replace, empty for insertions
+    pub span: SourceSpan,
+    /// The text to replace the span with
+    pub replacement: String,
+}
+
+impl Suggestion {
+    pub fn replace(span: SourceSpan, replacement: &str, message: &str) -> Self {
+        Suggestion {
+            message: message.to_owned(),
+            span,
+            replacement: replacement.to_owned(),
+        }
+    }
+
+    pub fn insert(offset: usize, text: &str, message: &str) -> Self {
+        Self::replace(SourceSpan::new(offset.into(), 0), text, message)
+    }
+
+    /// Apply the suggested edit to the source code it was made for.
+    pub fn apply(&self, source: &str) -> String {
+        let start = self.span.offset();
+        let end = start + self.span.len();
+        let mut result = String::with_capacity(source.len() + self.replacement.len());
+        result.push_str(&source[..start]);
+        result.push_str(&self.replacement);
+        result.push_str(&source[end..]);
+        result
+    }
+}
+
+impl fmt::Display for Suggestion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_suggestion() {
+        let insert = Suggestion::insert(9, ";", "Add a semicolon");
+        assert_eq!(insert.apply("let a = 1\n"), "let a = 1;\n");
+
+        let replace = Suggestion::replace(SourceSpan::new(4.into(), 1), "b", "Rename");
+        assert_eq!(replace.apply("let a = 1;"), "let b = 1;");
+    }
+}
@@ -0,0 +1,81 @@
+/// The WebAssembly proposals that generated code may use.
+///
+/// When a feature is disabled the code generator falls back to instructions
+/// from the MVP instead, except for SIMD, which can't be used without it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WasmFeatures {
+    /// Importing and exporting mutable globals, which generated code never does,
+    /// so this only changes what the output is validated against
+    pub mutable_globals: bool,
+    /// Sign extension instructions like `i32.extend8_s`, instead of pairs of shifts
+    pub sign_ext: bool,
+    /// `memory.copy` and `memory.fill`, instead of byte loops
+    pub bulk_memory: bool,
+    /// The `v128` type and SIMD builtins
+    pub simd: bool,
+    /// `return_call` for recursive calls in tail position
+    pub tail_call: bool,
+    /// Returning several values from functions that aren't exported,
+    /// instead of returning them through memory
+    pub multi_value: bool,
+}
+
+impl WasmFeatures {
+    /// The names of the features, as they're written on the command line and in `claw.toml`.
+    pub const NAMES: [&'static str; 6] = [
+        "mutable-globals",
+        "sign-ext",
+        "bulk-memory",
+        "simd",
+        "tail-call",
+        "multi-value",
+    ];
+
+    /// None of the features, for runtimes that only support the WebAssembly MVP.
+    pub fn mvp() -> Self {
+        Self {
+            mutable_globals: false,
+            sign_ext: false,
+            bulk_memory: false,
+            simd: false,
+            tail_call: false,
+            multi_value: false,
+        }
+    }
+
+    /// Enable or disable a feature by its name.
+    pub fn set(&mut self, name: &str, enabled: bool) -> Result<(), String> {
+        let feature = match name {
+            "mutable-globals" => &mut self.mutable_globals,
+            "sign-ext" => &mut self.sign_ext,
+            "bulk-memory" => &mut self.bulk_memory,
+            "simd" => &mut self.simd,
+            "tail-call" => &mut self.tail_call,
+            "multi-value" => &mut self.multi_value,
+            _ => {
+                return Err(format!(
+                    "Unknown feature \"{}\", expected one of {}",
+                    name,
+                    Self::NAMES.join(", ")
+                ))
+            }
+        };
+        *feature = enabled;
+        Ok(())
+    }
+}
+
+/// The features that are widely supported are enabled,
+/// and SIMD and tail calls have to be asked for.
+impl Default for WasmFeatures {
+    fn default() -> Self {
+        Self {
+            mutable_globals: true,
+            sign_ext: true,
+            bulk_memory: true,
+            simd: false,
+            tail_call: false,
+            multi_value: true,
+        }
+    }
+}
@@ -1,14 +1,179 @@
 mod diagnostic;
+mod features;
+mod line_index;
+mod similar;
 mod stack_map;
+mod symbol;
 
-use miette::NamedSource;
+use miette::{MietteError, MietteSpanContents, NamedSource, SourceCode, SourceSpan, SpanContents};
+use std::ops::Deref;
 use std::sync::Arc;
 
 pub use diagnostic::*;
+pub use features::*;
+pub use line_index::*;
+pub use similar::*;
 pub use stack_map::*;
+pub use symbol::*;
 
-pub type Source = Arc<NamedSource<String>>;
+/// A named source file that diagnostics point into.
+///
+/// Several files can be compiled together as one source, laid out one after
+/// another with a newline between them. A span's offset then identifies both
+/// the file and the position in it, and diagnostics show the file it's in.
+///
+/// Clones share the same contents, so diagnostics can hold onto one cheaply.
+/// With the `serde` feature it serializes as just its name,
+/// since whoever receives a diagnostic can look the contents up themselves.
+#[derive(Debug, Clone)]
+pub struct Source(Arc<SourceData>);
+
+#[derive(Debug)]
+struct SourceData {
+    /// The contents of every file together
+    whole: NamedSource<String>,
+    files: Vec<SourceFile>,
+}
+
+/// One of the files that make up a [Source].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+    /// The index of the file in [Source::files]
+    pub id: usize,
+    pub name: String,
+    /// Where the file starts in the source
+    pub start: usize,
+    pub len: usize,
+}
+
+impl Source {
+    pub fn new(name: &str, source: String) -> Self {
+        let file = SourceFile {
+            id: 0,
+            name: name.to_owned(),
+            start: 0,
+            len: source.len(),
+        };
+        Source(Arc::new(SourceData {
+            whole: NamedSource::new(name, source),
+            files: vec![file],
+        }))
+    }
+
+    /// Lay out several named files one after another as one source called `name`.
+    pub fn from_files(name: &str, files: &[(String, String)]) -> Self {
+        let mut whole = String::new();
+        let mut source_files = Vec::new();
+        for (id, (file_name, contents)) in files.iter().enumerate() {
+            source_files.push(SourceFile {
+                id,
+                name: file_name.clone(),
+                start: whole.len(),
+                len: contents.len(),
+            });
+            whole.push_str(contents);
+            // Keep a comment at the end of one file from running into the next
+            whole.push('\n');
+        }
+        Source(Arc::new(SourceData {
+            whole: NamedSource::new(name, whole),
+            files: source_files,
+        }))
+    }
+
+    /// The files that make up the source, in order.
+    pub fn files(&self) -> &[SourceFile] {
+        &self.0.files
+    }
+
+    /// The file containing an offset into the source.
+    pub fn file_at(&self, offset: usize) -> &SourceFile {
+        let index = self.0.files.partition_point(|file| file.start <= offset);
+        &self.0.files[index.saturating_sub(1)]
+    }
+}
+
+impl Deref for Source {
+    type Target = NamedSource<String>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0.whole
+    }
+}
+
+impl SourceCode for Source {
+    fn read_span<'a>(
+        &'a self,
+        span: &SourceSpan,
+        context_lines_before: usize,
+        context_lines_after: usize,
+    ) -> Result<Box<dyn SpanContents<'a> + 'a>, MietteError> {
+        if self.0.files.len() <= 1 {
+            return self
+                .0
+                .whole
+                .read_span(span, context_lines_before, context_lines_after);
+        }
+
+        // Read from the file the span starts in, so line numbers and context are its own
+        let file = self.file_at(span.offset());
+        let text = &self.0.whole.inner()[file.start..file.start + file.len];
+        let offset = (span.offset() - file.start).min(file.len);
+        let len = span.len().min(file.len.saturating_sub(offset));
+        let contents = text.read_span(
+            &SourceSpan::new(offset.into(), len),
+            context_lines_before,
+            context_lines_after,
+        )?;
+        let span = SourceSpan::new(
+            (contents.span().offset() + file.start).into(),
+            contents.span().len(),
+        );
+        Ok(Box::new(MietteSpanContents::new_named(
+            file.name.clone(),
+            contents.data(),
+            span,
+            contents.line(),
+            contents.column(),
+            contents.line_count(),
+        )))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Source {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
 
 pub fn make_source(name: &str, source: &str) -> Source {
-    Arc::new(NamedSource::new(name, source.to_owned()))
+    Source::new(name, source.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_files() {
+        let files = [
+            ("a.claw".to_owned(), "let a = 1;".to_owned()),
+            ("b.claw".to_owned(), "let b = 2;\nlet c = 3;".to_owned()),
+        ];
+        let src = Source::from_files("project", &files);
+        assert_eq!(src.name(), "project");
+        assert_eq!(src.inner(), "let a = 1;\nlet b = 2;\nlet c = 3;\n");
+        assert_eq!(src.file_at(0).name, "a.claw");
+        assert_eq!(src.file_at(10).name, "a.claw");
+        assert_eq!(src.file_at(11).name, "b.claw");
+        assert_eq!(src.file_at(100).id, 1);
+
+        // `c` on the second line of b.claw
+        let contents = src.read_span(&SourceSpan::from(26..27), 0, 0).unwrap();
+        assert_eq!(contents.name(), Some("b.claw"));
+        assert_eq!(contents.line(), 1);
+        assert_eq!(contents.column(), 4);
+        assert_eq!(*contents.span(), SourceSpan::from(26..27));
+    }
 }
@@ -0,0 +1,143 @@
+use miette::SourceSpan;
+
+/// A zero-based line and column in a source file.
+///
+/// Columns count bytes from the start of the line,
+/// use [LineIndex::to_utf16] for editors that count UTF-16 code units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineCol {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Converts between byte offsets and line/column positions in a source file.
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    text: String,
+    /// The byte offset where each line starts
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        Self {
+            text: text.to_owned(),
+            line_starts,
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The position of a byte offset, clamped to the end of the text.
+    pub fn line_col(&self, offset: usize) -> LineCol {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        LineCol {
+            line,
+            column: offset - self.line_starts[line],
+        }
+    }
+
+    /// The byte offset of a position, if it's within the text.
+    pub fn offset(&self, position: LineCol) -> Option<usize> {
+        let start = *self.line_starts.get(position.line)?;
+        let end = self.line_end(position.line);
+        let offset = start + position.column;
+        (offset <= end).then_some(offset)
+    }
+
+    /// The start and end positions of a span.
+    pub fn range(&self, span: SourceSpan) -> (LineCol, LineCol) {
+        let start = span.offset();
+        (self.line_col(start), self.line_col(start + span.len()))
+    }
+
+    /// The span between two positions, if they're in order and within the text.
+    pub fn span(&self, start: LineCol, end: LineCol) -> Option<SourceSpan> {
+        let start = self.offset(start)?;
+        let end = self.offset(end)?;
+        (start <= end).then(|| SourceSpan::from(start..end))
+    }
+
+    /// Convert a position to count its column in UTF-16 code units.
+    pub fn to_utf16(&self, position: LineCol) -> LineCol {
+        let start = self.line_starts[position.line];
+        let prefix = &self.text[start..start + position.column];
+        LineCol {
+            line: position.line,
+            column: prefix.encode_utf16().count(),
+        }
+    }
+
+    /// Convert a position with its column in UTF-16 code units to count bytes.
+    pub fn from_utf16(&self, position: LineCol) -> Option<LineCol> {
+        let start = *self.line_starts.get(position.line)?;
+        let line = &self.text[start..self.line_end(position.line)];
+        let mut units = 0;
+        for (index, c) in line.char_indices() {
+            if units >= position.column {
+                return (units == position.column).then_some(LineCol {
+                    line: position.line,
+                    column: index,
+                });
+            }
+            units += c.len_utf16();
+        }
+        (units == position.column).then_some(LineCol {
+            line: position.line,
+            column: line.len(),
+        })
+    }
+
+    /// The byte offset of the end of a line, before its line break.
+    pub fn line_end(&self, line: usize) -> usize {
+        match self.line_starts.get(line + 1) {
+            Some(next) => next - 1,
+            None => self.text.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(line: usize, column: usize) -> LineCol {
+        LineCol { line, column }
+    }
+
+    #[test]
+    fn test_line_col() {
+        let index = LineIndex::new("let a = 1;\n\nlet b = 2;");
+        assert_eq!(index.line_count(), 3);
+        assert_eq!(index.line_col(0), pos(0, 0));
+        assert_eq!(index.line_col(10), pos(0, 10));
+        assert_eq!(index.line_col(11), pos(1, 0));
+        assert_eq!(index.line_col(16), pos(2, 4));
+        assert_eq!(index.line_col(100), pos(2, 10));
+
+        assert_eq!(index.offset(pos(2, 4)), Some(16));
+        assert_eq!(index.offset(pos(1, 1)), None);
+        assert_eq!(index.offset(pos(3, 0)), None);
+
+        let span = SourceSpan::from(4..16);
+        assert_eq!(index.range(span), (pos(0, 4), pos(2, 4)));
+        assert_eq!(index.span(pos(0, 4), pos(2, 4)), Some(span));
+    }
+
+    #[test]
+    fn test_utf16_columns() {
+        let index = LineIndex::new("x\n\"é😀\" + y");
+        let y = index.line_col(index.text.len() - 1);
+        assert_eq!(y, pos(1, 11));
+        assert_eq!(index.to_utf16(y), pos(1, 8));
+        assert_eq!(index.from_utf16(pos(1, 8)), Some(y));
+        // The middle of the surrogate pair isn't a valid position
+        assert_eq!(index.from_utf16(pos(1, 3)), None);
+    }
+}
@@ -0,0 +1,61 @@
+use std::cmp;
+
+/// The Levenshtein distance between two strings.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, a_char) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            let insertion = current[j] + 1;
+            let deletion = previous[j + 1] + 1;
+            current[j + 1] = cmp::min(substitution, cmp::min(insertion, deletion));
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+/// Find the candidate most similar to `name`, if any is close enough
+/// to plausibly be a typo of it.
+///
+/// Ties are broken alphabetically so the result doesn't depend on
+/// the iteration order of the candidates.
+pub fn closest_match<'a, I>(name: &str, candidates: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let max_distance = cmp::max(name.chars().count() / 3, 1);
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("counter", "conuter"), 2);
+    }
+
+    #[test]
+    fn test_closest_match() {
+        let candidates = ["counter", "count", "amount"];
+        assert_eq!(closest_match("countr", candidates), Some("count"));
+        assert_eq!(closest_match("xyz", candidates), None);
+        assert_eq!(closest_match("a", ["b", "c"]), Some("b"));
+    }
+}
@@ -63,6 +63,11 @@ where
     pub fn lookup(&self, key: &K) -> Option<&V> {
         self.mapping.get(key)
     }
+
+    /// Iterate over the current entries in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.mapping.iter()
+    }
 }
 
 #[cfg(test)]
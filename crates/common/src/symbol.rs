@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+
+/// An interned string, which is cheap to copy, compare, and hash.
+///
+/// A symbol only means something to the [Interner] that made it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Symbol(u32);
+
+/// Owns the strings that [Symbol]s stand for.
+///
+/// Each source file gets its own interner, which frees its strings
+/// when whatever owns it (e.g. the tokens or the AST) is dropped.
+#[derive(Clone, Debug, Default)]
+pub struct Interner {
+    symbols: HashMap<Box<str>, Symbol>,
+    strings: Vec<Box<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Get the symbol for a string, interning it if it hasn't been seen before.
+    pub fn intern(&mut self, text: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(text) {
+            return *symbol;
+        }
+        let symbol = Symbol(self.strings.len() as u32);
+        self.strings.push(text.into());
+        self.symbols.insert(text.into(), symbol);
+        symbol
+    }
+
+    /// The string a symbol from this interner was interned from.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.strings[symbol.0 as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern() {
+        let mut interner = Interner::new();
+        let a = interner.intern("interned-a");
+        let b = interner.intern("interned-b");
+        assert_ne!(a, b);
+        assert_eq!(interner.intern("interned-a"), a);
+        assert_eq!(interner.resolve(a), "interned-a");
+        assert_eq!(interner.resolve(b), "interned-b");
+    }
+
+    #[test]
+    fn test_separate_interners() {
+        let mut first = Interner::new();
+        let mut second = Interner::new();
+        second.intern("other");
+        let a = first.intern("shared");
+        let b = second.intern("shared");
+        assert_eq!(first.resolve(a), second.resolve(b));
+    }
+}
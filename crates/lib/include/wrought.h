@@ -0,0 +1,48 @@
+/*
+ * C bindings for the Claw compiler, built from the compile-claw crate with
+ * `cargo rustc -p compile-claw --release --features capi --crate-type cdylib`.
+ */
+
+#ifndef WROUGHT_H
+#define WROUGHT_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The outcome of compiling a source file. */
+typedef struct WroughtResult WroughtResult;
+
+/*
+ * Compile a Claw source file into a component with the default options.
+ * Never returns null; release the result with wrought_free.
+ */
+WroughtResult *wrought_compile(const char *name, const char *source);
+
+/* Release a result. Does nothing if result is null. */
+void wrought_free(WroughtResult *result);
+
+/* Whether compilation succeeded, i.e. there's an output. */
+bool wrought_succeeded(const WroughtResult *result);
+
+/*
+ * The binary encoding of the component, or null if compilation failed.
+ * Its length is written to len, which may be null. Owned by result.
+ */
+const uint8_t *wrought_output(const WroughtResult *result, size_t *len);
+
+/* The number of diagnostics: the warnings, followed by the error if compilation failed. */
+size_t wrought_diagnostic_count(const WroughtResult *result);
+
+/* The diagnostic at index rendered as text, or null if out of range. Owned by result. */
+const char *wrought_diagnostic(const WroughtResult *result, size_t index);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* WROUGHT_H */
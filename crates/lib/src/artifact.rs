@@ -0,0 +1,53 @@
+use claw_common::Diagnostics;
+
+use crate::{print_wat, CompileTimings, Error};
+
+/// Everything one compilation produces, so build tools, tests, and IDEs
+/// can each get the format they need without compiling again.
+///
+/// Returned by [compile_artifact](crate::compile_artifact).
+#[derive(Debug)]
+pub struct CompileArtifact {
+    pub(crate) source_name: String,
+    pub(crate) wasm: Vec<u8>,
+    pub(crate) wit: String,
+    pub(crate) diagnostics: Diagnostics,
+    pub(crate) timings: CompileTimings,
+}
+
+impl CompileArtifact {
+    /// The name of the source file that was compiled.
+    pub fn source_name(&self) -> &str {
+        &self.source_name
+    }
+
+    /// The binary encoding of the component, after post-processing.
+    pub fn wasm_bytes(&self) -> &[u8] {
+        &self.wasm
+    }
+
+    /// Take the binary encoding of the component without copying it.
+    pub fn into_wasm_bytes(self) -> Vec<u8> {
+        self.wasm
+    }
+
+    /// Print the component in the text format.
+    pub fn wat(&self) -> Result<String, Error> {
+        print_wat(&self.wasm)
+    }
+
+    /// The WIT world describing the component's imports and exports.
+    pub fn wit(&self) -> &str {
+        &self.wit
+    }
+
+    /// The warnings and notes reported while compiling.
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+
+    /// How long each phase of the compilation took.
+    pub fn timings(&self) -> &CompileTimings {
+        &self.timings
+    }
+}
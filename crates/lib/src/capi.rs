@@ -0,0 +1,144 @@
+//! C bindings for embedding the compiler in build systems and editors
+//! without spawning `claw-cli`. `include/wrought.h` declares them.
+//!
+//! Build a shared or static library with
+//! `cargo rustc -p compile-claw --release --features capi --crate-type cdylib`
+//! (or `staticlib`).
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use crate::embed;
+
+/// The outcome of [wrought_compile]. Opaque to C.
+pub struct WroughtResult {
+    output: Option<Vec<u8>>,
+    diagnostics: Vec<CString>,
+}
+
+impl WroughtResult {
+    fn failed(message: &str) -> Self {
+        WroughtResult {
+            output: None,
+            diagnostics: vec![CString::new(message).unwrap_or_default()],
+        }
+    }
+}
+
+/// Compile a Claw source file into a component with the default options.
+///
+/// Returns a result to inspect with the other functions and release with
+/// [wrought_free]. Never returns null: invalid arguments and internal
+/// errors are reported as a failed compilation with a diagnostic.
+///
+/// # Safety
+///
+/// `name` and `source` must be null or point to NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn wrought_compile(
+    name: *const c_char,
+    source: *const c_char,
+) -> *mut WroughtResult {
+    let result = match (str_arg(name), str_arg(source)) {
+        (Some(name), Some(source)) => {
+            match panic::catch_unwind(AssertUnwindSafe(|| embed::compile(name, source))) {
+                Ok(compiled) => WroughtResult {
+                    output: compiled.output,
+                    diagnostics: compiled
+                        .diagnostics
+                        .into_iter()
+                        .map(|diagnostic| CString::new(diagnostic).unwrap_or_default())
+                        .collect(),
+                },
+                Err(_) => WroughtResult::failed("The compiler panicked"),
+            }
+        }
+        _ => WroughtResult::failed("The name and source must be non-null UTF-8 strings"),
+    };
+    Box::into_raw(Box::new(result))
+}
+
+/// Release a result returned by [wrought_compile]. Does nothing if `result` is null.
+///
+/// # Safety
+///
+/// `result` must be null or a result from [wrought_compile] that hasn't been freed.
+/// Pointers previously borrowed from it are invalidated.
+#[no_mangle]
+pub unsafe extern "C" fn wrought_free(result: *mut WroughtResult) {
+    if !result.is_null() {
+        drop(Box::from_raw(result));
+    }
+}
+
+/// Whether compilation succeeded, i.e. there's an output.
+///
+/// # Safety
+///
+/// `result` must be a live result from [wrought_compile].
+#[no_mangle]
+pub unsafe extern "C" fn wrought_succeeded(result: *const WroughtResult) -> bool {
+    let result = &*result;
+    result.output.is_some()
+}
+
+/// The binary encoding of the component, or null if compilation failed.
+/// Its length is written to `len`, which may be null.
+///
+/// # Safety
+///
+/// `result` must be a live result from [wrought_compile],
+/// and `len` must be null or valid to write to.
+#[no_mangle]
+pub unsafe extern "C" fn wrought_output(
+    result: *const WroughtResult,
+    len: *mut usize,
+) -> *const u8 {
+    let result = &*result;
+    let (data, size) = match &result.output {
+        Some(output) => (output.as_ptr(), output.len()),
+        None => (ptr::null(), 0),
+    };
+    if !len.is_null() {
+        *len = size;
+    }
+    data
+}
+
+/// The number of diagnostics: the warnings, followed by the error if compilation failed.
+///
+/// # Safety
+///
+/// `result` must be a live result from [wrought_compile].
+#[no_mangle]
+pub unsafe extern "C" fn wrought_diagnostic_count(result: *const WroughtResult) -> usize {
+    let result = &*result;
+    result.diagnostics.len()
+}
+
+/// The diagnostic at `index`, rendered as text without colors,
+/// or null if `index` is out of range. It's owned by `result`.
+///
+/// # Safety
+///
+/// `result` must be a live result from [wrought_compile].
+#[no_mangle]
+pub unsafe extern "C" fn wrought_diagnostic(
+    result: *const WroughtResult,
+    index: usize,
+) -> *const c_char {
+    let result = &*result;
+    match result.diagnostics.get(index) {
+        Some(diagnostic) => diagnostic.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+unsafe fn str_arg<'a>(arg: *const c_char) -> Option<&'a str> {
+    if arg.is_null() {
+        return None;
+    }
+    CStr::from_ptr(arg).to_str().ok()
+}
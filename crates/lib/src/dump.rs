@@ -0,0 +1,315 @@
+//! Human-readable dumps of how a source file was lexed and parsed, for debugging.
+
+use ast::{
+    Component, Expression, ExpressionId, ExternalType, FnType, Import, Literal, NameId, Span,
+    Statement, StatementId, TypeId,
+};
+use claw_common::{make_source, Interner, LineIndex};
+use claw_parser::{tokenize_with_errors, Token};
+
+use crate::ast;
+
+/// List the tokens of a source file, one per line, with where each one is.
+///
+/// Characters that aren't part of any token are listed as errors instead of
+/// stopping the listing, so the tokens around them can be seen too.
+pub fn dump_tokens(source_name: String, source_code: &str) -> String {
+    let src = make_source(source_name.as_str(), source_code);
+    let mut interner = Interner::new();
+    let (tokens, _) = tokenize_with_errors(src, source_code, &mut interner);
+    let index = LineIndex::new(source_code);
+
+    let mut out = String::new();
+    for token in tokens {
+        let span = token.span;
+        let text = &source_code[span.offset()..span.offset() + span.len()];
+        let value = match &token.token {
+            Token::IntLiteral(value) => format!(" = {}", value),
+            Token::FloatLiteral(value) => format!(" = {:?}", value),
+            Token::StringLiteral(value) => format!(" = {:?}", interner.resolve(*value)),
+            _ => String::new(),
+        };
+        let class = format!("{:?}", token.token.class()).to_lowercase();
+        out.push_str(&format!(
+            "{:<8}{:<12}{:<12}{}{}\n",
+            position(&index, span),
+            format!("{}..{}", span.offset(), span.offset() + span.len()),
+            class,
+            text,
+            value
+        ));
+    }
+    out
+}
+
+/// Draw the syntax tree of a component, one node per line, with where each one starts.
+///
+/// Items are listed by kind: imports, globals, then functions.
+pub fn dump_ast(comp: &Component) -> String {
+    let mut dumper = Dumper {
+        comp,
+        index: LineIndex::new(comp.source().inner()),
+        out: String::new(),
+        depth: 0,
+    };
+    dumper.component();
+    dumper.out
+}
+
+struct Dumper<'a> {
+    comp: &'a Component,
+    index: LineIndex,
+    out: String,
+    depth: usize,
+}
+
+impl<'a> Dumper<'a> {
+    fn component(&mut self) {
+        let comp = self.comp;
+        self.line(format!("Component {}", comp.source().name()), None);
+        self.nested(|dumper| {
+            for (_, import) in comp.iter_imports() {
+                dumper.import(import);
+            }
+            for (_, global) in comp.iter_globals() {
+                let label = format!(
+                    "Global {}{}: {}",
+                    flags(global.exported, global.mutable),
+                    comp.get_name(global.ident),
+                    dumper.type_name(global.type_id)
+                );
+                dumper.line(label, Some(comp.name_span(global.ident)));
+                dumper.nested(|dumper| dumper.expression(global.init_value));
+            }
+            for (id, function) in comp.iter_functions() {
+                let span = comp.name_span(function.ident);
+                let label = match comp.is_init(id) {
+                    true => "Init".to_owned(),
+                    false => {
+                        let results = match function.results {
+                            Some(results) => format!(" -> {}", dumper.type_name(results)),
+                            None => String::new(),
+                        };
+                        let constant = match function.constant {
+                            true => "const ",
+                            false => "",
+                        };
+                        format!(
+                            "Function {}{}{}({}){}",
+                            flags(function.exported, false),
+                            constant,
+                            comp.get_name(function.ident),
+                            dumper.params(&function.params),
+                            results
+                        )
+                    }
+                };
+                dumper.line(label, Some(span));
+                dumper.nested(|dumper| dumper.block(&function.body));
+            }
+        });
+    }
+
+    fn import(&mut self, import: &Import) {
+        let comp = self.comp;
+        match import {
+            Import::Plain(import) => {
+                let alias = match import.alias {
+                    Some(alias) => format!(" as {}", comp.get_name(alias)),
+                    None => String::new(),
+                };
+                let external_type = match &import.external_type {
+                    ExternalType::Function(fn_type) => self.fn_type(fn_type),
+                    ExternalType::Value(type_id) => self.type_name(*type_id),
+                };
+                let label = format!(
+                    "Import {}{}: {}",
+                    comp.get_name(import.ident),
+                    alias,
+                    external_type
+                );
+                self.line(label, Some(comp.name_span(import.ident)));
+            }
+            Import::ImportFrom(import) => {
+                let label = format!("Import from {}/{}", import.package, import.interface);
+                let span = import.items.first().map(|(name, _)| comp.name_span(*name));
+                self.line(label, span);
+                self.nested(|dumper| {
+                    for (name, alias) in import.items.iter() {
+                        let label = match alias {
+                            Some(alias) => format!(
+                                "Item {} as {}",
+                                comp.get_name(*name),
+                                comp.get_name(*alias)
+                            ),
+                            None => format!("Item {}", comp.get_name(*name)),
+                        };
+                        dumper.line(label, Some(comp.name_span(*name)));
+                    }
+                });
+            }
+        }
+    }
+
+    fn block(&mut self, block: &[StatementId]) {
+        for statement in block {
+            self.statement(*statement);
+        }
+    }
+
+    fn statement(&mut self, statement: StatementId) {
+        let comp = self.comp;
+        let span = Some(comp.statement_span(statement));
+        match comp.get_statement(statement) {
+            Statement::Let(let_) => {
+                let annotation = match let_.annotation {
+                    Some(annotation) => format!(": {}", self.type_name(annotation)),
+                    None => String::new(),
+                };
+                let label = format!(
+                    "Let {}{}{}",
+                    flags(false, let_.mutable),
+                    comp.get_name(let_.ident),
+                    annotation
+                );
+                self.line(label, span);
+                if let Some(expression) = let_.expression {
+                    self.nested(|dumper| dumper.expression(expression));
+                }
+            }
+            Statement::Assign(assign) => {
+                self.line(format!("Assign {}", comp.get_name(assign.ident)), span);
+                self.nested(|dumper| dumper.expression(assign.expression));
+            }
+            Statement::Call(call) => {
+                self.line(format!("Call {}", comp.get_name(call.ident)), span);
+                self.nested(|dumper| dumper.expressions(&call.args));
+            }
+            Statement::If(if_) => {
+                self.line("If".to_owned(), span);
+                self.nested(|dumper| {
+                    dumper.expression(if_.condition);
+                    dumper.line("Then".to_owned(), None);
+                    dumper.nested(|dumper| dumper.block(&if_.block));
+                });
+            }
+            Statement::Return(return_) => {
+                self.line("Return".to_owned(), span);
+                if let Some(expression) = return_.expression {
+                    self.nested(|dumper| dumper.expression(expression));
+                }
+            }
+        }
+    }
+
+    fn expressions(&mut self, expressions: &[ExpressionId]) {
+        for expression in expressions {
+            self.expression(*expression);
+        }
+    }
+
+    fn expression(&mut self, expression: ExpressionId) {
+        let comp = self.comp;
+        let span = Some(comp.expression_span(expression));
+        match comp.get_expression(expression) {
+            Expression::Identifier(identifier) => {
+                self.line(
+                    format!("Identifier {}", comp.get_name(identifier.ident)),
+                    span,
+                );
+            }
+            Expression::Enum(literal) => {
+                let label = format!(
+                    "Enum {}::{}",
+                    comp.get_name(literal.enum_name),
+                    comp.get_name(literal.case_name)
+                );
+                self.line(label, span);
+            }
+            Expression::Literal(literal) => {
+                let label = match literal {
+                    Literal::Integer(value) => format!("Integer {}", value),
+                    Literal::Float(value) => format!("Float {:?}", value),
+                    Literal::String(value) => format!("String {:?}", value),
+                };
+                self.line(label, span);
+            }
+            Expression::Call(call) => {
+                self.line(format!("Call {}", comp.get_name(call.ident)), span);
+                self.nested(|dumper| dumper.expressions(&call.args));
+            }
+            Expression::Unary(unary) => {
+                self.line(format!("Unary {:?}", unary.op), span);
+                self.nested(|dumper| dumper.expression(unary.inner));
+            }
+            Expression::Binary(binary) => {
+                self.line(format!("Binary {:?}", binary.op), span);
+                self.nested(|dumper| {
+                    dumper.expression(binary.left);
+                    dumper.expression(binary.right);
+                });
+            }
+        }
+    }
+
+    fn type_name(&self, type_id: TypeId) -> String {
+        self.comp.get_type(type_id).name(self.comp)
+    }
+
+    fn params(&self, params: &[(NameId, TypeId)]) -> String {
+        let params: Vec<String> = params
+            .iter()
+            .map(|(name, type_id)| {
+                format!(
+                    "{}: {}",
+                    self.comp.get_name(*name),
+                    self.type_name(*type_id)
+                )
+            })
+            .collect();
+        params.join(", ")
+    }
+
+    fn fn_type(&self, fn_type: &FnType) -> String {
+        let results = match fn_type.results {
+            Some(results) => format!(" -> {}", self.type_name(results)),
+            None => String::new(),
+        };
+        format!("func({}){}", self.params(&fn_type.params), results)
+    }
+
+    /// Write a node, followed by the line and column it starts at if it has a span.
+    fn line(&mut self, label: String, span: Option<Span>) {
+        self.out.push_str(&"  ".repeat(self.depth));
+        self.out.push_str(&label);
+        if let Some(span) = span {
+            self.out
+                .push_str(&format!(" @ {}", position(&self.index, span)));
+        }
+        self.out.push('\n');
+    }
+
+    fn nested(&mut self, dump: impl FnOnce(&mut Self)) {
+        self.depth += 1;
+        dump(self);
+        self.depth -= 1;
+    }
+}
+
+/// The `export` and `mut` keywords that apply, each followed by a space.
+fn flags(exported: bool, mutable: bool) -> String {
+    let mut flags = String::new();
+    if exported {
+        flags.push_str("export ");
+    }
+    if mutable {
+        flags.push_str("mut ");
+    }
+    flags
+}
+
+/// The one-based line and column where `span` starts, as editors show them.
+fn position(index: &LineIndex, span: Span) -> String {
+    let start = index.line_col(span.offset());
+    format!("{}:{}", start.line + 1, start.column + 1)
+}
@@ -0,0 +1,45 @@
+//! What the language bindings have in common: compiling a single source
+//! with the default options and rendering its diagnostics as text.
+
+use claw_common::Diagnostics;
+use wit_parser::Resolve;
+
+use crate::{compile_with_options, render_diagnostic, CompileOptions, RenderOptions};
+
+/// The outcome of [compile], ready to hand across a language boundary.
+pub(crate) struct Compiled {
+    /// The binary encoding of the component, if compilation succeeded
+    pub output: Option<Vec<u8>>,
+    /// The warnings, followed by the error if compilation failed
+    pub diagnostics: Vec<String>,
+}
+
+/// Compile a Claw source file into a component with the default options.
+///
+/// There's no WIT to resolve imports against, so the source can't import anything.
+pub(crate) fn compile(name: &str, source: &str) -> Compiled {
+    let mut diagnostics = Diagnostics::new();
+    let result = compile_with_options(
+        name.to_owned(),
+        source,
+        Resolve::new(),
+        &CompileOptions::default(),
+        &mut diagnostics,
+    );
+
+    let mut rendered: Vec<String> = diagnostics
+        .iter()
+        .map(|report| render_diagnostic(report.as_ref(), RenderOptions::default()))
+        .collect();
+    let output = match result {
+        Ok(output) => Some(output),
+        Err(error) => {
+            rendered.push(render_diagnostic(&error, RenderOptions::default()));
+            None
+        }
+    };
+    Compiled {
+        output,
+        diagnostics: rendered,
+    }
+}
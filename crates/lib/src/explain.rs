@@ -0,0 +1,31 @@
+macro_rules! explanations {
+    ($( $code:ident ),* $(,)?) => {
+        /// Every diagnostic code that has an extended explanation.
+        pub const CODES: &[&str] = &[$( stringify!($code) ),*];
+
+        /// Look up the extended explanation for a diagnostic code (e.g. `E0203`).
+        ///
+        /// Returns [None] if the code is unknown.
+        pub fn explain(code: &str) -> Option<&'static str> {
+            match code {
+                $(stringify!($code) => Some(include_str!(concat!(
+                    "explanations/",
+                    stringify!($code),
+                    ".md"
+                ))),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+explanations!(
+    E0001, // Lexer
+    E0101, E0102, E0103, E0104, W0101, W0102, // Parser
+    E0201, E0202, E0203, E0204, E0205, E0206, E0207, E0208, E0209, E0210, E0211, E0212, E0213,
+    E0214, E0215, E0216, E0217, E0218, E0219, E0220, E0221, E0222, E0223, E0224, E0225, E0226,
+    E0227, E0228, W0201, W0202, W0203, // Resolver
+    E0301, E0302, E0303, // WIT
+    E0501, E0502, E0503, E0504, E0505, E0506, E0507, E0508, E0509, E0510, E0511, // Driver
+    E0601, E0602, E0603, E0604, E0605, // Linker
+);
@@ -0,0 +1,214 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+
+use ast::FunctionId;
+use claw_codegen::{generate_cached, CodeCache};
+use claw_common::{make_source, Diagnostics, Interner};
+use claw_parser::{tokenize, Token, TokenData};
+use claw_resolver::{ImportType, ResolvedComponent};
+use wit_parser::Resolve;
+
+use crate::{ast, finish_output, optimize, CompileOptions, Error, PassKind};
+
+/// Compiles successive versions of a Claw source file,
+/// reusing the code generated for functions that haven't changed.
+///
+/// A function's code is reused when its body has the same tokens as before
+/// and nothing outside of function bodies changed, including the options and imports.
+/// When functions are inlined, the bodies of the functions it calls must be unchanged too.
+pub struct IncrementalCompiler {
+    options: CompileOptions,
+    cache: CodeCache,
+}
+
+impl IncrementalCompiler {
+    pub fn new(options: CompileOptions) -> Self {
+        Self {
+            options,
+            cache: CodeCache::new(),
+        }
+    }
+
+    /// Compile a version of the source file, collecting any warnings and notes into `diagnostics`.
+    ///
+    /// The output is the same as [crate::compile_with_options] would produce.
+    pub fn compile(
+        &mut self,
+        source_name: String,
+        source_code: &str,
+        wit: Resolve,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<Vec<u8>, Error> {
+        let src = make_source(source_name.as_str(), source_code);
+        let mut interner = Interner::new();
+        let tokens = tokenize(src, source_code, &mut interner)?;
+
+        let options = &self.options;
+        let (comp, rcomp) = optimize(source_name.clone(), source_code, wit, options, diagnostics)?;
+
+        let mut context = DefaultHasher::new();
+        format!("{:?}", options).hash(&mut context);
+        hash_imports(&rcomp, &mut context);
+        let keys = function_keys(
+            &comp,
+            &rcomp,
+            &tokens,
+            &interner,
+            source_code,
+            options,
+            context,
+        );
+
+        let generation_options = options.generation_options();
+        let output = generate_cached(&comp, &rcomp, &generation_options, &mut self.cache, &keys)?;
+        finish_output(output, &comp, &rcomp, &[&source_name], options)
+    }
+
+    /// How many functions had their code reused by the last compilation.
+    pub fn reused(&self) -> usize {
+        self.cache.reused()
+    }
+
+    /// How many functions had their code generated by the last compilation.
+    pub fn generated(&self) -> usize {
+        self.cache.generated()
+    }
+}
+
+/// Compute the cache key of each function.
+///
+/// Every key includes `context` and all of the tokens outside of function bodies,
+/// which covers signatures, globals, imports, and the order of the functions.
+fn function_keys(
+    comp: &ast::Component,
+    rcomp: &ResolvedComponent,
+    tokens: &[TokenData],
+    interner: &Interner,
+    source_code: &str,
+    options: &CompileOptions,
+    mut context: DefaultHasher,
+) -> HashMap<FunctionId, u64> {
+    let text = |data: &TokenData| {
+        let start = data.span.offset();
+        &source_code[start..start + data.span.len()]
+    };
+
+    let functions: HashMap<&str, FunctionId> = comp
+        .iter_functions()
+        .map(|(id, function)| (comp.get_name(function.ident), id))
+        .collect();
+
+    let mut bodies: HashMap<FunctionId, u64> = HashMap::new();
+    // The functions named in each body, which may be inlined into it
+    let mut calls: HashMap<FunctionId, Vec<FunctionId>> = HashMap::new();
+    let mut in_body = vec![false; tokens.len()];
+    for (id, function) in comp.iter_functions() {
+        let name_span = comp.name_span(function.ident);
+        let range = body_range(tokens, name_span.offset() + name_span.len());
+        let mut hasher = DefaultHasher::new();
+        let mut called = Vec::new();
+        for index in range {
+            let data = &tokens[index];
+            text(data).hash(&mut hasher);
+            if options.debug_info {
+                // The source map records where the code came from
+                data.span.offset().hash(&mut hasher);
+            }
+            in_body[index] = true;
+            if let Token::Identifier(name) = data.token {
+                called.extend(functions.get(interner.resolve(name)));
+            }
+        }
+        bodies.insert(id, hasher.finish());
+        calls.insert(id, called);
+    }
+
+    for (data, in_body) in tokens.iter().zip(in_body) {
+        if !in_body {
+            text(data).hash(&mut context);
+        }
+    }
+    // Dead code elimination depends on the bodies,
+    // so the live items need to be included separately
+    for (id, _) in comp.iter_functions() {
+        rcomp.funcs.contains_key(&id).hash(&mut context);
+    }
+    for (id, _) in comp.iter_globals() {
+        format!("{:?}", rcomp.global_vals.get(&id)).hash(&mut context);
+    }
+
+    let inlining = options.selected_passes().contains(&PassKind::Inline);
+    let mut keys = HashMap::new();
+    for (id, _) in comp.iter_functions() {
+        let mut hasher = context.clone();
+        let dependencies = match inlining {
+            true => callees(&calls, id),
+            false => vec![id],
+        };
+        for dependency in dependencies {
+            bodies[&dependency].hash(&mut hasher);
+        }
+        keys.insert(id, hasher.finish());
+    }
+    keys
+}
+
+/// The indices of the tokens of the first braced block that starts at or after `offset`.
+fn body_range(tokens: &[TokenData], offset: usize) -> Range<usize> {
+    let start = match tokens
+        .iter()
+        .position(|data| data.span.offset() >= offset && data.token == Token::LBrace)
+    {
+        Some(start) => start,
+        None => return 0..0,
+    };
+    let mut depth = 0;
+    for (index, data) in tokens.iter().enumerate().skip(start) {
+        match data.token {
+            Token::LBrace => depth += 1,
+            Token::RBrace => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            return start..index + 1;
+        }
+    }
+    start..tokens.len()
+}
+
+/// A function and every function it calls, directly or indirectly, in a stable order.
+///
+/// Calls are found before optimizing, since inlining removes them.
+fn callees(calls: &HashMap<FunctionId, Vec<FunctionId>>, function: FunctionId) -> Vec<FunctionId> {
+    let mut found = vec![function];
+    let mut next = 0;
+    while let Some(id) = found.get(next).copied() {
+        next += 1;
+        for callee in calls[&id].iter() {
+            if !found.contains(callee) {
+                found.push(*callee);
+            }
+        }
+    }
+    found
+}
+
+/// Hash what the imports resolved to in the WIT.
+fn hash_imports(rcomp: &ResolvedComponent, hasher: &mut DefaultHasher) {
+    let imports = &rcomp.imports;
+    for (_, function) in imports.funcs.iter() {
+        format!("{:?}", function).hash(hasher);
+    }
+    for (_, import_type) in imports.types.iter() {
+        let ImportType::Enum(import_enum) = import_type;
+        import_enum.name.hash(hasher);
+        import_enum.cases.hash(hasher);
+    }
+    for interface in imports.interfaces.iter() {
+        interface.name.hash(hasher);
+        format!("{:?}", interface.items).hash(hasher);
+    }
+    format!("{:?}", imports.loose_funcs).hash(hasher);
+}
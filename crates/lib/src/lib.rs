@@ -1,13 +1,95 @@
-use claw_codegen::{generate, GenerationError};
-use claw_common::make_source;
-use claw_parser::{parse, tokenize, LexerError, ParserError};
+#[cfg(feature = "codegen")]
+mod artifact;
+#[cfg(feature = "capi")]
+pub mod capi;
+mod dump;
+#[cfg(any(feature = "web", feature = "capi"))]
+mod embed;
+mod explain;
+#[cfg(feature = "codegen")]
+mod incremental;
+#[cfg(feature = "codegen")]
+mod link;
+#[cfg(feature = "codegen")]
+mod metadata;
+mod no_alloc;
+#[cfg(feature = "codegen")]
+mod post_process;
+#[cfg(feature = "codegen")]
+mod project;
+mod provider;
+mod render;
+mod semantic;
+mod simd;
+mod target;
+#[cfg(feature = "codegen")]
+mod timings;
+#[cfg(feature = "validate")]
+mod validate;
+#[cfg(feature = "web")]
+pub mod web;
+mod world;
+
+use std::str::FromStr;
+
+#[cfg(feature = "codegen")]
+use claw_codegen::{generate, GenerationError, GenerationOptions};
+use claw_common::{make_source, Diagnostics, Interner, Source};
+#[cfg(feature = "codegen")]
+use claw_parser::tokenize;
+use claw_parser::{
+    format_component, parse, parse_streaming, tokenize_lossless, LexerError, ParserError,
+};
+#[cfg(feature = "codegen")]
+use claw_resolver::passes::{EliminateDeadItems, FoldConstants, InlineFunctions, PassManager};
 use claw_resolver::{resolve, wit::ResolvedWit, ResolverError};
 use wit_parser::Resolve;
 
-use miette::Diagnostic;
+use miette::{Diagnostic, SourceSpan};
+#[cfg(feature = "codegen")]
+use rayon::prelude::*;
 use thiserror::Error;
 
-#[derive(Error, Debug, Diagnostic)]
+#[cfg(feature = "codegen")]
+pub use artifact::CompileArtifact;
+pub use dump::{dump_ast, dump_tokens};
+pub use explain::{explain, CODES};
+#[cfg(feature = "codegen")]
+pub use incremental::IncrementalCompiler;
+#[cfg(feature = "codegen")]
+pub use link::{compose, link, Dependency, LinkError};
+#[cfg(feature = "codegen")]
+pub use metadata::METADATA_SECTION;
+#[cfg(feature = "codegen")]
+pub use post_process::{PostProcess, PostProcessFn};
+#[cfg(feature = "codegen")]
+pub use project::compile_project;
+pub use provider::{load_sources, load_wit, FsProvider, MemoryProvider, SourceProvider};
+pub use render::{render_diagnostic, render_diagnostics, RenderOptions};
+pub use semantic::{semantic_tokens, SemanticKind, SemanticToken};
+pub use target::TargetWorld;
+#[cfg(feature = "codegen")]
+pub use timings::CompileTimings;
+#[cfg(feature = "validate")]
+pub use validate::validate_output;
+pub use world::generate_wit;
+
+#[cfg(feature = "codegen")]
+use metadata::add_metadata;
+use no_alloc::check_no_alloc;
+use simd::check_simd;
+use target::check_target_world;
+#[cfg(feature = "codegen")]
+use timings::time;
+
+pub use claw_ast as ast;
+pub use claw_common::{LineCol, LineIndex, WasmFeatures};
+pub use claw_resolver::{
+    Builtin, ConstValue, ItemId, ResolvedComponent, ResolvedFunction, ResolvedType,
+};
+
+#[derive(Error, Debug, Diagnostic, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Error {
     #[error(transparent)]
     #[diagnostic(transparent)]
@@ -21,23 +103,666 @@ pub enum Error {
     #[diagnostic(transparent)]
     Resolver(#[from] ResolverError),
 
+    #[cfg(feature = "codegen")]
     #[error(transparent)]
     #[diagnostic(transparent)]
     Generator(#[from] GenerationError),
+
+    #[cfg(feature = "codegen")]
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Link(#[from] LinkError),
+
+    #[error("Aborting due to {count} denied warning(s)")]
+    #[diagnostic(code(E0501))]
+    DeniedWarnings { count: usize },
+
+    #[error("Failed to print the component as text: {message}")]
+    #[diagnostic(code(E0502))]
+    PrintWat { message: String },
+
+    #[error("Internal compiler error, generated invalid code: {message}")]
+    #[diagnostic(code(E0503), help("This is a bug in the compiler, please report it"))]
+    InvalidOutput {
+        message: String,
+        /// The function whose code is invalid, if it's known
+        function: Option<String>,
+        #[source_code]
+        src: Source,
+        #[label("Invalid code generated for this function")]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Post-processing with {stage} failed: {message}")]
+    #[diagnostic(code(E0504))]
+    PostProcess { stage: String, message: String },
+
+    #[error("Heap allocated values can't be used without an allocator")]
+    #[diagnostic(
+        code(E0505),
+        help("Strings are allocated on the heap, which `no-alloc` mode rejects")
+    )]
+    HeapAllocation {
+        #[source_code]
+        src: Source,
+        #[label("This is allocated on the heap")]
+        span: SourceSpan,
+    },
+
+    #[error("SIMD needs the `simd` target feature")]
+    #[diagnostic(
+        code(E0506),
+        help("Compile with `--simd`, or set `simd = true` in `claw.toml`")
+    )]
+    SimdDisabled {
+        #[source_code]
+        src: Source,
+        #[label("This uses SIMD")]
+        span: SourceSpan,
+    },
+
+    #[error("`v128` values can't be passed between components")]
+    #[diagnostic(
+        code(E0507),
+        help("Exported functions and imports can only use types the component model has")
+    )]
+    VectorAtBoundary {
+        #[source_code]
+        src: Source,
+        #[label("This is part of the component's interface")]
+        span: SourceSpan,
+    },
+
+    #[error("World `{world}` expects an export named `{name}`")]
+    #[diagnostic(
+        code(E0508),
+        help("Export a function named `{name}` with the signature the world gives it")
+    )]
+    MissingExport { name: String, world: String },
+
+    #[error("Export `{name}` doesn't match its signature in world `{world}`")]
+    #[diagnostic(code(E0509))]
+    ExportMismatch {
+        #[source_code]
+        src: Source,
+        #[label("{reason}")]
+        span: SourceSpan,
+
+        name: String,
+        world: String,
+        reason: String,
+    },
+
+    #[error("Could not read {path}: {message}")]
+    #[diagnostic(code(E0510))]
+    ReadSource { path: String, message: String },
+
+    #[error("Could not load WIT from {path}: {message}")]
+    #[diagnostic(code(E0511))]
+    LoadWit { path: String, message: String },
 }
 
+/// The default for [CompileOptions::inline_threshold].
+pub const DEFAULT_INLINE_THRESHOLD: usize = 8;
+
+/// Options controlling how a Claw source file is compiled.
+#[derive(Debug, Clone)]
+pub struct CompileOptions {
+    /// How warnings are treated
+    pub warnings: WarningLevel,
+    /// Trap on integer overflow instead of wrapping
+    pub overflow_checks: bool,
+    /// How much the generated code is optimized
+    pub opt_level: OptLevel,
+    /// Report notes about what the optimizations did
+    pub verbose: bool,
+    /// The largest function, in expression nodes, that is inlined into its callers
+    pub inline_threshold: usize,
+    /// Run these passes in order instead of the ones chosen by [CompileOptions::opt_level]
+    pub passes: Option<Vec<PassKind>>,
+    /// Emit a name section so disassemblies and stack traces show source names
+    pub debug_names: bool,
+    /// Embed a source map from the generated code back to the Claw source
+    pub debug_info: bool,
+    /// Record the compiler version, options, and source files in a [METADATA_SECTION] section
+    pub metadata: bool,
+    /// Stages run in order on the compiled component to produce the final output
+    #[cfg(feature = "codegen")]
+    pub post_process: Vec<PostProcess>,
+    /// Reject values that are allocated on the heap, like strings
+    pub no_alloc: bool,
+    /// The WebAssembly proposals the generated code may use, which it's validated against
+    pub features: WasmFeatures,
+    /// A world whose exports the component must have
+    pub world: Option<TargetWorld>,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self {
+            warnings: Default::default(),
+            overflow_checks: false,
+            opt_level: Default::default(),
+            verbose: false,
+            inline_threshold: DEFAULT_INLINE_THRESHOLD,
+            passes: None,
+            debug_names: false,
+            debug_info: false,
+            metadata: false,
+            #[cfg(feature = "codegen")]
+            post_process: Vec::new(),
+            no_alloc: false,
+            features: WasmFeatures::default(),
+            world: None,
+        }
+    }
+}
+
+impl CompileOptions {
+    /// The passes that are run, in order.
+    #[cfg(feature = "codegen")]
+    fn selected_passes(&self) -> Vec<PassKind> {
+        match &self.passes {
+            Some(passes) => passes.clone(),
+            None => self.opt_level.passes(),
+        }
+    }
+
+    /// Create the pass manager for the selected passes.
+    #[cfg(feature = "codegen")]
+    fn pass_manager(&self) -> PassManager {
+        let mut manager = PassManager::new();
+        for pass in self.selected_passes() {
+            match pass {
+                PassKind::Inline => manager.add(Box::new(InlineFunctions {
+                    threshold: self.inline_threshold,
+                })),
+                PassKind::Fold => manager.add(Box::new(FoldConstants)),
+                PassKind::DeadCode => manager.add(Box::new(EliminateDeadItems)),
+            }
+        }
+        manager
+    }
+
+    #[cfg(feature = "codegen")]
+    fn generation_options(&self) -> GenerationOptions {
+        GenerationOptions {
+            overflow_checks: self.overflow_checks,
+            reuse_locals: self.opt_level.reuse_locals(),
+            debug_names: self.debug_names,
+            debug_info: self.debug_info,
+            features: self.features,
+        }
+    }
+}
+
+/// How much the generated code is optimized.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OptLevel {
+    /// No optimizations
+    #[default]
+    O0,
+    /// Inline small functions, fold constant expressions,
+    /// and remove unused functions and globals
+    O1,
+    /// Everything in [OptLevel::O1], and reuse locals between statements
+    O2,
+}
+
+impl OptLevel {
+    /// The passes run at this level, in order.
+    pub fn passes(&self) -> Vec<PassKind> {
+        match self {
+            OptLevel::O0 => vec![],
+            OptLevel::O1 | OptLevel::O2 => {
+                vec![PassKind::Inline, PassKind::Fold, PassKind::DeadCode]
+            }
+        }
+    }
+
+    /// Whether expressions in different statements share locals at this level.
+    pub fn reuse_locals(&self) -> bool {
+        *self >= OptLevel::O2
+    }
+}
+
+impl FromStr for OptLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0" => Ok(OptLevel::O0),
+            "1" => Ok(OptLevel::O1),
+            "2" => Ok(OptLevel::O2),
+            _ => Err(format!(
+                "Unknown optimization level \"{}\", expected 0, 1, or 2",
+                s
+            )),
+        }
+    }
+}
+
+/// An optimization pass that can be selected with [CompileOptions::passes].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PassKind {
+    /// Inline small functions into their callers
+    Inline,
+    /// Replace constant expressions with their values
+    Fold,
+    /// Remove functions and globals that aren't reachable from an export
+    DeadCode,
+}
+
+impl FromStr for PassKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "inline" => Ok(PassKind::Inline),
+            "fold" => Ok(PassKind::Fold),
+            "dce" => Ok(PassKind::DeadCode),
+            _ => Err(format!(
+                "Unknown pass \"{}\", expected one of inline, fold, or dce",
+                s
+            )),
+        }
+    }
+}
+
+/// How warnings are treated during compilation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WarningLevel {
+    /// Warnings are silently discarded
+    Allow,
+    /// Warnings are reported but don't affect the result
+    #[default]
+    Warn,
+    /// Warnings are reported and cause compilation to fail
+    Deny,
+}
+
+impl FromStr for WarningLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(WarningLevel::Allow),
+            "warn" => Ok(WarningLevel::Warn),
+            "deny" => Ok(WarningLevel::Deny),
+            _ => Err(format!(
+                "Unknown warning level \"{}\", expected one of allow, warn, or deny",
+                s
+            )),
+        }
+    }
+}
+
+/// The format a compiled component is written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The binary encoding
+    #[default]
+    Wasm,
+    /// The text format
+    Wat,
+}
+
+impl OutputFormat {
+    /// Encode a compiled component, which is always binary, in this format.
+    #[cfg(feature = "codegen")]
+    pub fn encode(&self, wasm: Vec<u8>) -> Result<Vec<u8>, Error> {
+        match self {
+            OutputFormat::Wasm => Ok(wasm),
+            OutputFormat::Wat => Ok(print_wat(&wasm)?.into_bytes()),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wasm" => Ok(OutputFormat::Wasm),
+            "wat" => Ok(OutputFormat::Wat),
+            _ => Err(format!(
+                "Unknown output format \"{}\", expected wasm or wat",
+                s
+            )),
+        }
+    }
+}
+
+/// Print a compiled component in the text format.
+#[cfg(feature = "codegen")]
+pub fn print_wat(wasm: &[u8]) -> Result<String, Error> {
+    wasmprinter::print_bytes(wasm).map_err(|err| Error::PrintWat {
+        message: err.to_string(),
+    })
+}
+
+/// Compile a Claw source file into the binary encoding of a component.
+#[cfg(feature = "codegen")]
 pub fn compile(source_name: String, source_code: &str, wit: Resolve) -> Result<Vec<u8>, Error> {
+    let options = CompileOptions::default();
+    let mut diagnostics = Diagnostics::new();
+    compile_with_options(source_name, source_code, wit, &options, &mut diagnostics)
+}
+
+/// Parse a Claw source file into its syntax tree, collecting any warnings into `diagnostics`.
+pub fn parse_component(
+    source_name: String,
+    source_code: &str,
+    diagnostics: &mut Diagnostics,
+) -> Result<ast::Component, Error> {
     let src = make_source(source_name.as_str(), source_code);
 
-    let tokens = tokenize(src.clone(), source_code)?;
+    Ok(parse_streaming(src, diagnostics)?)
+}
 
-    let comp = parse(src.clone(), tokens)?;
+/// Format a Claw source file into its canonical form, keeping its comments.
+pub fn format_source(source_name: String, source_code: &str) -> Result<String, Error> {
+    let src = make_source(source_name.as_str(), source_code);
+
+    let tokens = tokenize_lossless(src.clone(), source_code)?;
+
+    let interner = tokens.interner().clone();
+    let comp = parse(src, tokens.to_tokens(), interner, &mut Diagnostics::new())?;
+
+    Ok(format_component(&comp, &tokens))
+}
+
+/// Parse and resolve a Claw source file without generating code.
+///
+/// This gives tools access to the syntax tree and what each name and expression
+/// resolved to. Any warnings are collected into `diagnostics`.
+pub fn analyze(
+    source_name: String,
+    source_code: &str,
+    wit: Resolve,
+    diagnostics: &mut Diagnostics,
+) -> Result<(ast::Component, ResolvedComponent), Error> {
+    let comp = parse_component(source_name, source_code, diagnostics)?;
 
     let wit = ResolvedWit::new(wit);
 
-    let rcomp = resolve(&comp, wit)?;
+    let rcomp = resolve(&comp, wit, diagnostics)?;
+
+    Ok((comp, rcomp))
+}
+
+/// Compile a Claw source file into the binary encoding of a component,
+/// collecting any warnings and notes into `diagnostics`.
+#[cfg(feature = "codegen")]
+pub fn compile_with_options(
+    source_name: String,
+    source_code: &str,
+    wit: Resolve,
+    options: &CompileOptions,
+    diagnostics: &mut Diagnostics,
+) -> Result<Vec<u8>, Error> {
+    let (comp, rcomp) = optimize(source_name.clone(), source_code, wit, options, diagnostics)?;
+
+    let output = generate(&comp, &rcomp, &options.generation_options())?;
+    finish_output(output, &comp, &rcomp, &[&source_name], options)
+}
+
+/// Compile a Claw source file like [compile_with_options], recording how long each phase takes.
+///
+/// The timings are recorded even if compilation fails.
+#[cfg(feature = "codegen")]
+pub fn compile_with_timings(
+    source_name: String,
+    source_code: &str,
+    wit: Resolve,
+    options: &CompileOptions,
+    diagnostics: &mut Diagnostics,
+    timings: &mut CompileTimings,
+) -> Result<Vec<u8>, Error> {
+    let (_, _, output) = compile_timed(
+        &source_name,
+        source_code,
+        wit,
+        options,
+        diagnostics,
+        timings,
+    )?;
+    Ok(output)
+}
+
+/// Compile a Claw source file into a [CompileArtifact] holding the component,
+/// its WIT world, and the warnings, notes, and timings from compiling it.
+///
+/// Unlike [compile_with_options], warnings aren't returned if compilation fails.
+#[cfg(feature = "codegen")]
+pub fn compile_artifact(
+    source_name: String,
+    source_code: &str,
+    wit: Resolve,
+    options: &CompileOptions,
+) -> Result<CompileArtifact, Error> {
+    let mut diagnostics = Diagnostics::new();
+    let mut timings = CompileTimings::default();
+    let (comp, rcomp, wasm) = compile_timed(
+        &source_name,
+        source_code,
+        wit,
+        options,
+        &mut diagnostics,
+        &mut timings,
+    )?;
+    let wit = generate_wit(&comp, &rcomp, &source_name);
+    Ok(CompileArtifact {
+        source_name,
+        wasm,
+        wit,
+        diagnostics,
+        timings,
+    })
+}
+
+/// Compile a Claw source file, timing each phase,
+/// and return the final output along with the trees it was generated from.
+#[cfg(feature = "codegen")]
+fn compile_timed(
+    source_name: &str,
+    source_code: &str,
+    wit: Resolve,
+    options: &CompileOptions,
+    diagnostics: &mut Diagnostics,
+    timings: &mut CompileTimings,
+) -> Result<(ast::Component, ResolvedComponent, Vec<u8>), Error> {
+    let src = make_source(source_name, source_code);
+
+    let mut interner = Interner::new();
+    let tokens = time(&mut timings.lexing, || {
+        tokenize(src.clone(), source_code, &mut interner)
+    })?;
+
+    let mut warnings = Diagnostics::new();
+    let comp = time(&mut timings.parsing, || {
+        parse(src, tokens, interner, &mut warnings)
+    })?;
+
+    let (comp, rcomp) = resolve_and_optimize(comp, wit, options, warnings, diagnostics, timings)?;
 
-    let output = generate(&comp, &rcomp)?;
+    let output = time(&mut timings.codegen, || {
+        generate(&comp, &rcomp, &options.generation_options())
+    })?;
+    let output = finish_output(output, &comp, &rcomp, &[source_name], options)?;
+    Ok((comp, rcomp, output))
+}
+
+/// Analyze a Claw source file and run the selected optimization passes on it.
+#[cfg(feature = "codegen")]
+fn optimize(
+    source_name: String,
+    source_code: &str,
+    wit: Resolve,
+    options: &CompileOptions,
+    diagnostics: &mut Diagnostics,
+) -> Result<(ast::Component, ResolvedComponent), Error> {
+    let mut warnings = Diagnostics::new();
 
+    let comp = parse_component(source_name, source_code, &mut warnings)?;
+
+    let mut timings = CompileTimings::default();
+    resolve_and_optimize(comp, wit, options, warnings, diagnostics, &mut timings)
+}
+
+/// Validate generated output and add its metadata, then run the post-processing stages on it.
+#[cfg(feature = "codegen")]
+fn finish_output(
+    output: Vec<u8>,
+    comp: &ast::Component,
+    rcomp: &ResolvedComponent,
+    sources: &[&str],
+    options: &CompileOptions,
+) -> Result<Vec<u8>, Error> {
+    #[cfg(feature = "validate")]
+    validate_output(&output, comp, rcomp, &options.features)?;
+    #[cfg(not(feature = "validate"))]
+    let _ = (comp, rcomp);
+
+    let mut output = add_metadata(output, sources, options);
+    for stage in options.post_process.iter() {
+        output = stage.run(output)?;
+    }
     Ok(output)
 }
+
+/// Check a Claw source file for errors without generating code.
+///
+/// Warnings are reported into `diagnostics` according to [CompileOptions::warnings],
+/// the same as they are when compiling.
+pub fn check_with_options(
+    source_name: String,
+    source_code: &str,
+    wit: Resolve,
+    options: &CompileOptions,
+    diagnostics: &mut Diagnostics,
+) -> Result<(), Error> {
+    let mut warnings = Diagnostics::new();
+
+    let comp = parse_component(source_name, source_code, &mut warnings)?;
+
+    let wit = ResolvedWit::new(wit);
+
+    let rcomp = resolve(&comp, wit, &mut warnings)?;
+    if options.no_alloc {
+        check_no_alloc(&comp, &rcomp)?;
+    }
+    check_simd(&comp, &rcomp, options.features.simd)?;
+    if let Some(world) = &options.world {
+        check_target_world(&comp, &rcomp, world)?;
+    }
+
+    // Generating code fails on locals whose type couldn't be inferred
+    for (id, _) in comp.iter_functions() {
+        if let Some(function) = rcomp.function(id) {
+            for (local, _) in function.locals.iter() {
+                function.local_type(local, &comp)?;
+            }
+        }
+    }
+
+    report_warnings(warnings, options, diagnostics)
+}
+
+/// Report warnings into `diagnostics` as allowed by the warning level.
+fn report_warnings(
+    warnings: Diagnostics,
+    options: &CompileOptions,
+    diagnostics: &mut Diagnostics,
+) -> Result<(), Error> {
+    match options.warnings {
+        WarningLevel::Allow => {}
+        WarningLevel::Warn => diagnostics.extend(warnings),
+        WarningLevel::Deny => {
+            let count = warnings.len();
+            diagnostics.extend(warnings);
+            if count > 0 {
+                return Err(Error::DeniedWarnings { count });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Resolve a parsed component and run the selected optimization passes on it.
+///
+/// The `warnings` found so far are reported along with the resolver's.
+#[cfg(feature = "codegen")]
+fn resolve_and_optimize(
+    mut comp: ast::Component,
+    wit: Resolve,
+    options: &CompileOptions,
+    mut warnings: Diagnostics,
+    diagnostics: &mut Diagnostics,
+    timings: &mut CompileTimings,
+) -> Result<(ast::Component, ResolvedComponent), Error> {
+    let mut rcomp = time(&mut timings.resolution, || {
+        let wit = ResolvedWit::new(wit);
+        let rcomp = resolve(&comp, wit, &mut warnings)?;
+        report_warnings(warnings, options, diagnostics)?;
+        if options.no_alloc {
+            check_no_alloc(&comp, &rcomp)?;
+        }
+        check_simd(&comp, &rcomp, options.features.simd)?;
+        if let Some(world) = &options.world {
+            check_target_world(&comp, &rcomp, world)?;
+        }
+        Ok::<_, Error>(rcomp)
+    })?;
+
+    let mut notes = Diagnostics::new();
+    time(&mut timings.optimization, || {
+        options
+            .pass_manager()
+            .run(&mut comp, &mut rcomp, &mut notes)
+    });
+    if options.verbose {
+        diagnostics.extend(notes);
+    }
+
+    Ok((comp, rcomp))
+}
+
+/// A Claw source file to compile with [compile_all].
+#[cfg(feature = "codegen")]
+pub struct CompileInput {
+    pub source_name: String,
+    pub source_code: String,
+    pub wit: Resolve,
+}
+
+/// The result of compiling a [CompileInput], along with its warnings and notes.
+#[cfg(feature = "codegen")]
+pub struct CompileOutput {
+    pub source_name: String,
+    pub result: Result<Vec<u8>, Error>,
+    pub diagnostics: Diagnostics,
+}
+
+/// Compile independent source files in parallel, returning their outputs in the same order.
+#[cfg(feature = "codegen")]
+pub fn compile_all(inputs: Vec<CompileInput>, options: &CompileOptions) -> Vec<CompileOutput> {
+    inputs
+        .into_par_iter()
+        .map(|input| {
+            let mut diagnostics = Diagnostics::new();
+            let result = compile_with_options(
+                input.source_name.clone(),
+                &input.source_code,
+                input.wit,
+                options,
+                &mut diagnostics,
+            );
+            CompileOutput {
+                source_name: input.source_name,
+                result,
+                diagnostics,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,285 @@
+use miette::Diagnostic;
+use thiserror::Error;
+use wasm_encoder::{
+    Alias, ComponentAliasSection, ComponentExportKind, ComponentExportSection,
+    ComponentInstanceSection, ComponentSectionId, RawSection,
+};
+use wasmparser::{ComponentExternalKind, ComponentTypeRef, Encoding, Parser, Payload, Validator};
+
+/// A compiled component that another component can be linked against.
+#[derive(Debug, Clone)]
+pub struct Dependency {
+    pub name: String,
+    pub component: Vec<u8>,
+}
+
+#[derive(Error, Debug, Diagnostic, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum LinkError {
+    #[error("`{name}` is not a valid component: {message}")]
+    #[diagnostic(code(E0601))]
+    InvalidComponent { name: String, message: String },
+
+    #[error("No dependency exports the {kind} `{import}`")]
+    #[diagnostic(code(E0602), help("Add a dependency that exports it"))]
+    UnsatisfiedImport { import: String, kind: &'static str },
+
+    #[error("Dependency `{dependency}` exports `{import}` as a {found}, but it's imported as a {expected}")]
+    #[diagnostic(code(E0603))]
+    KindMismatch {
+        dependency: String,
+        import: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+
+    #[error("Dependency `{dependency}` has imports of its own, so it can't be composed")]
+    #[diagnostic(code(E0604))]
+    DependencyHasImports { dependency: String },
+
+    #[error("The composed component is invalid: {message}")]
+    #[diagnostic(code(E0605))]
+    InvalidComposition { message: String },
+}
+
+/// Check that every import of `component` is exported by one of the dependencies.
+///
+/// Imports are matched to exports by name and kind,
+/// their types are only checked by [compose].
+pub fn link(component: &[u8], dependencies: &[Dependency]) -> Result<(), LinkError> {
+    let interface = read_interface("component", component)?;
+    providers(&interface, dependencies)?;
+    Ok(())
+}
+
+/// Compose `component` with the dependencies that provide its imports into one component.
+///
+/// The dependencies used must not have imports of their own.
+/// The composed component has the same exports as `component` and no imports.
+pub fn compose(component: &[u8], dependencies: &[Dependency]) -> Result<Vec<u8>, LinkError> {
+    let interface = read_interface("component", component)?;
+    let providers = providers(&interface, dependencies)?;
+
+    // The dependencies that provide an import, in the order they were given
+    let mut used: Vec<usize> = providers.iter().map(|(_, _, index)| *index).collect();
+    used.sort_unstable();
+    used.dedup();
+    for index in used.iter() {
+        let dependency = &dependencies[*index];
+        let dependency_interface = read_interface(&dependency.name, &dependency.component)?;
+        if !dependency_interface.imports.is_empty() {
+            return Err(LinkError::DependencyHasImports {
+                dependency: dependency.name.clone(),
+            });
+        }
+    }
+
+    let mut composed = wasm_encoder::Component::new();
+    let mut spaces = IndexSpaces::default();
+    for index in used.iter() {
+        composed.section(&RawSection {
+            id: ComponentSectionId::Component as u8,
+            data: &dependencies[*index].component,
+        });
+    }
+    composed.section(&RawSection {
+        id: ComponentSectionId::Component as u8,
+        data: component,
+    });
+    spaces.components = used.len() as u32 + 1;
+
+    let mut instances = ComponentInstanceSection::new();
+    for position in 0..used.len() {
+        instances.instantiate(
+            position as u32,
+            std::iter::empty::<(&str, ComponentExportKind, u32)>(),
+        );
+        spaces.next(ComponentExportKind::Instance);
+    }
+    composed.section(&instances);
+
+    let mut aliases = ComponentAliasSection::new();
+    let mut args = Vec::new();
+    for (import, kind, index) in providers.iter() {
+        let position = used.iter().position(|used| used == index).unwrap();
+        aliases.alias(Alias::InstanceExport {
+            instance: position as u32,
+            kind: *kind,
+            name: import,
+        });
+        args.push((*import, *kind, spaces.next(*kind)));
+    }
+    composed.section(&aliases);
+
+    let mut instances = ComponentInstanceSection::new();
+    instances.instantiate(used.len() as u32, args);
+    let instance = spaces.next(ComponentExportKind::Instance);
+    composed.section(&instances);
+
+    let mut aliases = ComponentAliasSection::new();
+    let mut exports = ComponentExportSection::new();
+    for (name, kind) in interface.exports.iter() {
+        aliases.alias(Alias::InstanceExport {
+            instance,
+            kind: *kind,
+            name,
+        });
+        exports.export(name, *kind, spaces.next(*kind), None);
+    }
+    composed.section(&aliases);
+    composed.section(&exports);
+
+    let bytes = composed.finish();
+    Validator::new()
+        .validate_all(&bytes)
+        .map_err(|err| LinkError::InvalidComposition {
+            message: err.to_string(),
+        })?;
+    Ok(bytes)
+}
+
+/// The names and kinds of the imports and exports of a component.
+struct Interface<'a> {
+    imports: Vec<(&'a str, ComponentExportKind)>,
+    exports: Vec<(&'a str, ComponentExportKind)>,
+}
+
+fn read_interface<'a>(name: &str, bytes: &'a [u8]) -> Result<Interface<'a>, LinkError> {
+    let invalid = |message: String| LinkError::InvalidComponent {
+        name: name.to_owned(),
+        message,
+    };
+
+    let mut interface = Interface {
+        imports: Vec::new(),
+        exports: Vec::new(),
+    };
+    // Only the sections of the outermost component are read
+    let mut depth = 0;
+    for payload in Parser::new(0).parse_all(bytes) {
+        match payload.map_err(|err| invalid(err.to_string()))? {
+            Payload::Version { encoding, .. } => {
+                if depth == 0 && encoding != Encoding::Component {
+                    return Err(invalid("it is a core module".to_owned()));
+                }
+                depth += 1;
+            }
+            Payload::End(_) => depth -= 1,
+            Payload::ComponentImportSection(reader) if depth == 1 => {
+                for import in reader {
+                    let import = import.map_err(|err| invalid(err.to_string()))?;
+                    interface
+                        .imports
+                        .push((import.name.0, type_ref_kind(import.ty)));
+                }
+            }
+            Payload::ComponentExportSection(reader) if depth == 1 => {
+                for export in reader {
+                    let export = export.map_err(|err| invalid(err.to_string()))?;
+                    interface
+                        .exports
+                        .push((export.name.0, external_kind(export.kind)));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(interface)
+}
+
+/// Find the dependency that provides each import, by its index.
+fn providers<'a>(
+    interface: &Interface<'a>,
+    dependencies: &[Dependency],
+) -> Result<Vec<(&'a str, ComponentExportKind, usize)>, LinkError> {
+    let mut exports = Vec::new();
+    for dependency in dependencies.iter() {
+        exports.push(read_interface(&dependency.name, &dependency.component)?.exports);
+    }
+
+    let mut providers = Vec::new();
+    for (import, kind) in interface.imports.iter() {
+        let provider = exports.iter().enumerate().find_map(|(index, exports)| {
+            let (_, found) = exports.iter().find(|(name, _)| name == import)?;
+            Some((index, *found))
+        });
+        match provider {
+            Some((index, found)) if found == *kind => providers.push((*import, *kind, index)),
+            Some((index, found)) => {
+                return Err(LinkError::KindMismatch {
+                    dependency: dependencies[index].name.clone(),
+                    import: import.to_string(),
+                    expected: kind_name(*kind),
+                    found: kind_name(found),
+                })
+            }
+            None => {
+                return Err(LinkError::UnsatisfiedImport {
+                    import: import.to_string(),
+                    kind: kind_name(*kind),
+                })
+            }
+        }
+    }
+    Ok(providers)
+}
+
+/// The next index in each of a component's index spaces.
+#[derive(Default)]
+struct IndexSpaces {
+    modules: u32,
+    funcs: u32,
+    values: u32,
+    types: u32,
+    instances: u32,
+    components: u32,
+}
+
+impl IndexSpaces {
+    /// Allocate the next index of a kind.
+    fn next(&mut self, kind: ComponentExportKind) -> u32 {
+        let space = match kind {
+            ComponentExportKind::Module => &mut self.modules,
+            ComponentExportKind::Func => &mut self.funcs,
+            ComponentExportKind::Value => &mut self.values,
+            ComponentExportKind::Type => &mut self.types,
+            ComponentExportKind::Instance => &mut self.instances,
+            ComponentExportKind::Component => &mut self.components,
+        };
+        *space += 1;
+        *space - 1
+    }
+}
+
+fn type_ref_kind(ty: ComponentTypeRef) -> ComponentExportKind {
+    match ty {
+        ComponentTypeRef::Module(_) => ComponentExportKind::Module,
+        ComponentTypeRef::Func(_) => ComponentExportKind::Func,
+        ComponentTypeRef::Value(_) => ComponentExportKind::Value,
+        ComponentTypeRef::Type(_) => ComponentExportKind::Type,
+        ComponentTypeRef::Instance(_) => ComponentExportKind::Instance,
+        ComponentTypeRef::Component(_) => ComponentExportKind::Component,
+    }
+}
+
+fn external_kind(kind: ComponentExternalKind) -> ComponentExportKind {
+    match kind {
+        ComponentExternalKind::Module => ComponentExportKind::Module,
+        ComponentExternalKind::Func => ComponentExportKind::Func,
+        ComponentExternalKind::Value => ComponentExportKind::Value,
+        ComponentExternalKind::Type => ComponentExportKind::Type,
+        ComponentExternalKind::Instance => ComponentExportKind::Instance,
+        ComponentExternalKind::Component => ComponentExportKind::Component,
+    }
+}
+
+fn kind_name(kind: ComponentExportKind) -> &'static str {
+    match kind {
+        ComponentExportKind::Module => "module",
+        ComponentExportKind::Func => "function",
+        ComponentExportKind::Value => "value",
+        ComponentExportKind::Type => "type",
+        ComponentExportKind::Instance => "instance",
+        ComponentExportKind::Component => "component",
+    }
+}
@@ -0,0 +1,41 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use serde_json::json;
+use wasm_encoder::{CustomSection, Section};
+
+use crate::CompileOptions;
+
+/// The name of the custom section describing how a component was built.
+pub const METADATA_SECTION: &str = "claw.meta";
+
+/// Append the metadata section to a component if [CompileOptions::metadata] is set.
+///
+/// It holds a JSON object with the compiler version, a hash of the options,
+/// and the names of the source files, in the order they were compiled.
+pub(crate) fn add_metadata(
+    mut component: Vec<u8>,
+    sources: &[&str],
+    options: &CompileOptions,
+) -> Vec<u8> {
+    if !options.metadata {
+        return component;
+    }
+
+    let mut hasher = DefaultHasher::new();
+    format!("{:?}", options).hash(&mut hasher);
+    let metadata = json!({
+        "compiler": format!("compile-claw {}", env!("CARGO_PKG_VERSION")),
+        "options": format!("{:016x}", hasher.finish()),
+        "sources": sources,
+    });
+    let data = metadata.to_string();
+    let section = CustomSection {
+        name: METADATA_SECTION.into(),
+        data: data.as_bytes().into(),
+    };
+
+    // Custom sections can go anywhere, including after every other section
+    section.append_to(&mut component);
+    component
+}
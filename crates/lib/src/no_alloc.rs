@@ -0,0 +1,74 @@
+use ast::visit::{walk_expression, walk_import, walk_type, Visitor};
+use ast::{ExpressionId, Import, ImportId, PrimitiveType, Span, TypeId, ValType};
+use claw_resolver::{ImportItemId, ResolvedComponent, ResolvedType};
+
+use crate::{ast, Error};
+
+/// Reject values that are allocated on the heap, for [crate::CompileOptions::no_alloc].
+///
+/// Strings are the only heap allocated values so far. They're found wherever
+/// their type is written, in literals, and in functions imported from interfaces.
+pub(crate) fn check_no_alloc(
+    comp: &ast::Component,
+    rcomp: &ResolvedComponent,
+) -> Result<(), Error> {
+    let mut finder = HeapFinder { rcomp, found: None };
+    ast::visit::walk_component(&mut finder, comp);
+    match finder.found {
+        Some(span) => Err(Error::HeapAllocation {
+            src: comp.source(),
+            span,
+        }),
+        None => Ok(()),
+    }
+}
+
+struct HeapFinder<'a> {
+    rcomp: &'a ResolvedComponent,
+    /// The first heap allocated value that was found
+    found: Option<Span>,
+}
+
+impl HeapFinder<'_> {
+    fn found(&mut self, span: Span) {
+        self.found.get_or_insert(span);
+    }
+}
+
+impl<'ast> Visitor<'ast> for HeapFinder<'_> {
+    fn visit_import(&mut self, comp: &'ast ast::Component, id: ImportId, import: &'ast Import) {
+        _ = id;
+        if let Import::ImportFrom(import) = import {
+            for (name, alias) in import.items.iter() {
+                let binding = comp.get_name(alias.unwrap_or(*name));
+                let func = match self.rcomp.imports.mapping.get(binding) {
+                    Some(ImportItemId::Func(func)) => &self.rcomp.imports.funcs[*func],
+                    _ => continue,
+                };
+                let types = func.params.iter().map(|(_, rtype)| rtype);
+                if types.chain(func.results.iter()).any(is_string) {
+                    self.found(comp.name_span(*name));
+                }
+            }
+        }
+        walk_import(self, comp, import);
+    }
+
+    fn visit_type(&mut self, comp: &'ast ast::Component, type_id: TypeId) {
+        if let ValType::Primitive(PrimitiveType::String) = comp.get_type(type_id) {
+            self.found(comp.type_span(type_id));
+        }
+        walk_type(self, comp, type_id);
+    }
+
+    fn visit_expression(&mut self, comp: &'ast ast::Component, expression: ExpressionId) {
+        if let ast::Expression::Literal(ast::Literal::String(_)) = comp.get_expression(expression) {
+            self.found(comp.expression_span(expression));
+        }
+        walk_expression(self, comp, expression);
+    }
+}
+
+fn is_string(rtype: &ResolvedType) -> bool {
+    matches!(rtype, ResolvedType::Primitive(PrimitiveType::String))
+}
@@ -0,0 +1,149 @@
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::{env, fs, process};
+
+use wasm_encoder::{RawSection, Section};
+use wasmparser::{Parser, Payload};
+
+use crate::Error;
+
+/// A function that transforms a compiled component.
+pub type PostProcessFn = dyn Fn(&[u8]) -> Result<Vec<u8>, String> + Send + Sync;
+
+/// A stage that transforms the compiled component before it's returned.
+///
+/// See [crate::CompileOptions::post_process].
+#[derive(Clone)]
+pub enum PostProcess {
+    /// Pass the component to a function and use the bytes it returns instead
+    Callback(Arc<PostProcessFn>),
+    /// Optimize the core module holding the generated code with Binaryen's `wasm-opt`,
+    /// which is run as `<program> <input> <args...> -o <output>`
+    WasmOpt { program: PathBuf, args: Vec<String> },
+}
+
+impl PostProcess {
+    /// Run `wasm-opt` from the `PATH` with these arguments, like `-O2`.
+    pub fn wasm_opt(args: Vec<String>) -> Self {
+        PostProcess::WasmOpt {
+            program: PathBuf::from("wasm-opt"),
+            args,
+        }
+    }
+
+    pub(crate) fn run(&self, component: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let result = match self {
+            PostProcess::Callback(callback) => callback(&component),
+            PostProcess::WasmOpt { program, args } => run_wasm_opt(&component, program, args),
+        };
+        result.map_err(|message| Error::PostProcess {
+            stage: self.to_string(),
+            message,
+        })
+    }
+}
+
+impl fmt::Display for PostProcess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PostProcess::Callback(_) => write!(f, "a callback"),
+            PostProcess::WasmOpt { program, .. } => write!(f, "`{}`", program.display()),
+        }
+    }
+}
+
+impl fmt::Debug for PostProcess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PostProcess::Callback(_) => f.write_str("Callback(..)"),
+            PostProcess::WasmOpt { program, args } => f
+                .debug_struct("WasmOpt")
+                .field("program", program)
+                .field("args", args)
+                .finish(),
+        }
+    }
+}
+
+/// Distinguishes the files of concurrent `wasm-opt` runs.
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+fn run_wasm_opt(component: &[u8], program: &Path, args: &[String]) -> Result<Vec<u8>, String> {
+    let module = code_module(component)?;
+
+    let run = RUNS.fetch_add(1, Ordering::Relaxed);
+    let dir = env::temp_dir().join(format!("claw-wasm-opt-{}-{}", process::id(), run));
+    fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+    let input = dir.join("input.wasm");
+    let output = dir.join("output.wasm");
+
+    let result = fs::write(&input, &component[module.clone()])
+        .map_err(|err| err.to_string())
+        .and_then(|_| {
+            Command::new(program)
+                .arg(&input)
+                .args(args)
+                .arg("-o")
+                .arg(&output)
+                .output()
+                .map_err(|err| format!("could not run it: {}", err))
+        })
+        .and_then(|result| match result.status.success() {
+            true => fs::read(&output).map_err(|err| err.to_string()),
+            false => Err(String::from_utf8_lossy(&result.stderr).trim().to_owned()),
+        });
+    let _ = fs::remove_dir_all(&dir);
+
+    replace_section(component, module, &result?)
+}
+
+/// The range of the core module holding the generated code,
+/// which is the last one in the component, after the allocator's.
+fn code_module(component: &[u8]) -> Result<Range<usize>, String> {
+    let mut depth = 0;
+    let mut module = None;
+    for payload in Parser::new(0).parse_all(component) {
+        match payload.map_err(|err| err.to_string())? {
+            Payload::Version { .. } => depth += 1,
+            Payload::End(_) => depth -= 1,
+            Payload::ModuleSection {
+                unchecked_range, ..
+            } if depth == 1 => module = Some(unchecked_range),
+            _ => {}
+        }
+    }
+    module.ok_or_else(|| "the component has no core module".to_owned())
+}
+
+/// Copy a component, replacing the contents of the top level section at `target`.
+fn replace_section(
+    component: &[u8],
+    target: Range<usize>,
+    contents: &[u8],
+) -> Result<Vec<u8>, String> {
+    // The magic number, version, and layer come before any sections
+    let mut output = component[..8].to_vec();
+    let mut depth = 0;
+    for payload in Parser::new(0).parse_all(component) {
+        let payload = payload.map_err(|err| err.to_string())?;
+        match payload {
+            Payload::Version { .. } => depth += 1,
+            Payload::End(_) => depth -= 1,
+            _ if depth == 1 => {
+                if let Some((id, range)) = payload.as_section() {
+                    let data = match range == target {
+                        true => contents,
+                        false => &component[range],
+                    };
+                    RawSection { id, data }.append_to(&mut output);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(output)
+}
@@ -0,0 +1,42 @@
+use claw_codegen::generate;
+use claw_common::{Diagnostics, Source};
+use claw_parser::parse_streaming;
+use wit_parser::Resolve;
+
+use crate::{
+    finish_output, parse_component, resolve_and_optimize, CompileOptions, CompileTimings, Error,
+};
+
+/// Compile several Claw source files into one component,
+/// collecting any warnings and notes into `diagnostics`.
+///
+/// Each source is a pair of its name and its code. The items of every source
+/// share one namespace, so functions and globals can be used from any of them.
+///
+/// The sources are compiled together as `project_name`,
+/// but diagnostics point into the file they're about.
+pub fn compile_project(
+    project_name: String,
+    sources: &[(String, String)],
+    wit: Resolve,
+    options: &CompileOptions,
+    diagnostics: &mut Diagnostics,
+) -> Result<Vec<u8>, Error> {
+    // Parse each file on its own first, so a syntax error can't spill into the next file
+    for (source_name, source_code) in sources {
+        parse_component(source_name.clone(), source_code, &mut Diagnostics::new())?;
+    }
+
+    let src = Source::from_files(&project_name, sources);
+    let mut warnings = Diagnostics::new();
+    let comp = parse_streaming(src, &mut warnings)?;
+
+    let mut timings = CompileTimings::default();
+    let (comp, rcomp) =
+        resolve_and_optimize(comp, wit, options, warnings, diagnostics, &mut timings)?;
+
+    let output = generate(&comp, &rcomp, &options.generation_options())?;
+
+    let names: Vec<&str> = sources.iter().map(|(name, _)| name.as_str()).collect();
+    finish_output(output, &comp, &rcomp, &names, options)
+}
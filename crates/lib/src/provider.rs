@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use wit_parser::{Resolve, UnresolvedPackage};
+
+use crate::Error;
+
+/// Where source files and WIT are read from, by logical path.
+///
+/// Build systems, tests, and the web playground can supply sources
+/// from memory instead of the filesystem.
+pub trait SourceProvider {
+    /// Read the file at `path`.
+    fn read(&self, path: &Path) -> io::Result<String>;
+
+    /// Add the WIT package at `path` to `wit`.
+    ///
+    /// By default `path` is read as a single WIT file.
+    fn push_wit(&self, wit: &mut Resolve, path: &Path) -> Result<(), Error> {
+        let load_error = |message: String| Error::LoadWit {
+            path: path.display().to_string(),
+            message,
+        };
+        let contents = self.read(path).map_err(|err| load_error(err.to_string()))?;
+        let package = UnresolvedPackage::parse(path, &contents)
+            .map_err(|err| load_error(format!("{:#}", err)))?;
+        wit.push(package)
+            .map_err(|err| load_error(format!("{:#}", err)))?;
+        Ok(())
+    }
+}
+
+/// Reads files from disk, relative to a root directory.
+#[derive(Debug, Clone, Default)]
+pub struct FsProvider {
+    /// Relative paths are resolved against this, or the working directory if it's empty
+    root: PathBuf,
+}
+
+impl FsProvider {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl SourceProvider for FsProvider {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        std::fs::read_to_string(self.root.join(path))
+    }
+
+    /// Unlike the default, `path` can also be a directory with a `deps` folder.
+    fn push_wit(&self, wit: &mut Resolve, path: &Path) -> Result<(), Error> {
+        match wit.push_path(self.root.join(path)) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(Error::LoadWit {
+                path: path.display().to_string(),
+                message: format!("{:#}", err),
+            }),
+        }
+    }
+}
+
+/// Serves files added to it from memory.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryProvider {
+    files: HashMap<PathBuf, String>,
+}
+
+impl MemoryProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file, replacing any that was already at `path`.
+    pub fn insert(&mut self, path: impl Into<PathBuf>, contents: impl Into<String>) {
+        self.files.insert(path.into(), contents.into());
+    }
+}
+
+impl SourceProvider for MemoryProvider {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        match self.files.get(path) {
+            Some(contents) => Ok(contents.clone()),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, "no such file")),
+        }
+    }
+}
+
+/// Read source files, returning each one's file name and code
+/// in the form [compile_project](crate::compile_project) takes them.
+pub fn load_sources(
+    provider: &dyn SourceProvider,
+    paths: &[PathBuf],
+) -> Result<Vec<(String, String)>, Error> {
+    paths
+        .iter()
+        .map(|path| {
+            let code = provider.read(path).map_err(|err| Error::ReadSource {
+                path: path.display().to_string(),
+                message: err.to_string(),
+            })?;
+            let name = match path.file_name() {
+                Some(name) => name.to_string_lossy().to_string(),
+                None => path.display().to_string(),
+            };
+            Ok((name, code))
+        })
+        .collect()
+}
+
+/// Load the WIT packages at each of the paths.
+pub fn load_wit(provider: &dyn SourceProvider, paths: &[PathBuf]) -> Result<Resolve, Error> {
+    let mut wit = Resolve::new();
+    for path in paths {
+        provider.push_wit(&mut wit, path)?;
+    }
+    Ok(wit)
+}
@@ -0,0 +1,49 @@
+use claw_common::Diagnostics;
+use miette::{Diagnostic, GraphicalReportHandler, GraphicalTheme};
+
+/// How [render_diagnostics] draws reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// Style the output with ANSI escape codes
+    pub color: bool,
+    /// The column that text wraps at
+    pub width: usize,
+    /// Draw labels with box-drawing characters instead of ASCII
+    pub unicode: bool,
+}
+
+impl Default for RenderOptions {
+    /// Like the CLI's output to a terminal, but without colors.
+    fn default() -> Self {
+        Self {
+            color: false,
+            width: 200,
+            unicode: true,
+        }
+    }
+}
+
+/// Render diagnostics as graphical reports, the same way the CLI does.
+pub fn render_diagnostics(diagnostics: &Diagnostics, options: RenderOptions) -> String {
+    diagnostics
+        .iter()
+        .map(|report| render_diagnostic(report.as_ref(), options))
+        .collect()
+}
+
+/// Render one diagnostic, e.g. an [Error](crate::Error), as a graphical report.
+pub fn render_diagnostic(diagnostic: &dyn Diagnostic, options: RenderOptions) -> String {
+    let theme = match (options.color, options.unicode) {
+        (true, true) => GraphicalTheme::unicode(),
+        (true, false) => GraphicalTheme::ascii(),
+        (false, true) => GraphicalTheme::unicode_nocolor(),
+        (false, false) => GraphicalTheme::none(),
+    };
+    let mut output = String::new();
+    GraphicalReportHandler::new_themed(theme)
+        .with_width(options.width)
+        .with_links(options.color)
+        .render_report(&mut output, diagnostic)
+        .expect("writing to a string can't fail");
+    output
+}
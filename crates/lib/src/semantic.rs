@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use ast::visit::{walk_component, walk_expression, walk_function, walk_import, Visitor};
+use ast::{Expression, ExpressionId, Function, FunctionId, Import, ImportId, NameId, Span};
+use claw_parser::{tokenize_lossless, TokenClass, TriviaKind};
+use claw_resolver::{ImportItemId, ItemId, ResolvedComponent};
+
+use crate::ast;
+
+/// What a token is, for syntax highlighting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SemanticKind {
+    Keyword,
+    Type,
+    Function,
+    Parameter,
+    Variable,
+    EnumMember,
+    Number,
+    String,
+    Operator,
+    Comment,
+}
+
+/// A classified span of the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SemanticToken {
+    pub span: Span,
+    pub kind: SemanticKind,
+}
+
+/// Classify the tokens and comments of a component's source in order.
+///
+/// Names are classified by what they resolve to, so a parameter and a function
+/// with the same spelling are told apart. Punctuation isn't included.
+pub fn semantic_tokens(comp: &ast::Component, rcomp: &ResolvedComponent) -> Vec<SemanticToken> {
+    let src = comp.source();
+    let tokens = match tokenize_lossless(src.clone(), src.inner()) {
+        Ok(tokens) => tokens,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut classifier = Classifier {
+        rcomp,
+        function: None,
+        names: HashMap::new(),
+    };
+    walk_component(&mut classifier, comp);
+
+    let mut semantic = Vec::new();
+    let mut push = |span: Span, kind: SemanticKind| semantic.push(SemanticToken { span, kind });
+    for token in tokens.iter() {
+        for trivia in token.leading.iter() {
+            if trivia.kind == TriviaKind::Comment {
+                push(trivia.span, SemanticKind::Comment);
+            }
+        }
+        let span = token.data.span;
+        let kind = match token.data.token.class() {
+            TokenClass::Keyword => SemanticKind::Keyword,
+            TokenClass::Type => SemanticKind::Type,
+            TokenClass::Identifier => match classifier.names.get(&span.offset()) {
+                Some(kind) => *kind,
+                None => SemanticKind::Variable,
+            },
+            TokenClass::Number => SemanticKind::Number,
+            TokenClass::String => SemanticKind::String,
+            TokenClass::Operator => SemanticKind::Operator,
+            TokenClass::Punctuation | TokenClass::Error => continue,
+        };
+        push(span, kind);
+        for trivia in token.trailing.iter() {
+            if trivia.kind == TriviaKind::Comment {
+                push(trivia.span, SemanticKind::Comment);
+            }
+        }
+    }
+    for trivia in tokens.end_trivia() {
+        if trivia.kind == TriviaKind::Comment {
+            push(trivia.span, SemanticKind::Comment);
+        }
+    }
+    semantic
+}
+
+/// Finds the kind of each name, keyed by where it starts.
+struct Classifier<'a> {
+    rcomp: &'a ResolvedComponent,
+    /// The function being visited
+    function: Option<FunctionId>,
+    names: HashMap<usize, SemanticKind>,
+}
+
+impl<'a> Classifier<'a> {
+    fn set(&mut self, comp: &ast::Component, name: NameId, kind: SemanticKind) {
+        self.names.insert(comp.name_span(name).offset(), kind);
+    }
+}
+
+impl<'a, 'ast> Visitor<'ast> for Classifier<'a> {
+    fn visit_import(&mut self, comp: &'ast ast::Component, _: ImportId, import: &'ast Import) {
+        match import {
+            Import::Plain(import) => {
+                let kind = match &import.external_type {
+                    ast::ExternalType::Function(fn_type) => {
+                        for (param, _) in fn_type.params.iter() {
+                            self.set(comp, *param, SemanticKind::Parameter);
+                        }
+                        SemanticKind::Function
+                    }
+                    ast::ExternalType::Value(_) => SemanticKind::Variable,
+                };
+                self.set(comp, import.ident, kind);
+                if let Some(alias) = import.alias {
+                    self.set(comp, alias, kind);
+                }
+            }
+            Import::ImportFrom(import) => {
+                for (name, alias) in import.items.iter() {
+                    let local_name = comp.get_name(alias.unwrap_or(*name));
+                    let kind = match self.rcomp.imports.mapping.get(local_name) {
+                        Some(ImportItemId::Type(_)) => SemanticKind::Type,
+                        _ => SemanticKind::Function,
+                    };
+                    self.set(comp, *name, kind);
+                    if let Some(alias) = alias {
+                        self.set(comp, *alias, kind);
+                    }
+                }
+            }
+        }
+        walk_import(self, comp, import);
+    }
+
+    fn visit_function(
+        &mut self,
+        comp: &'ast ast::Component,
+        id: FunctionId,
+        function: &'ast Function,
+    ) {
+        self.set(comp, function.ident, SemanticKind::Function);
+        for (param, _) in function.params.iter() {
+            self.set(comp, *param, SemanticKind::Parameter);
+        }
+        self.function = Some(id);
+        walk_function(self, comp, function);
+        self.function = None;
+    }
+
+    fn visit_expression(&mut self, comp: &'ast ast::Component, expression: ExpressionId) {
+        if let Expression::Enum(enum_literal) = comp.get_expression(expression) {
+            self.set(comp, enum_literal.case_name, SemanticKind::EnumMember);
+        }
+        walk_expression(self, comp, expression);
+    }
+
+    fn visit_name(&mut self, comp: &'ast ast::Component, name: NameId) {
+        let item = self
+            .function
+            .and_then(|function| self.rcomp.binding(function, name));
+        let kind = match item {
+            Some(ItemId::Function(_)) | Some(ItemId::ImportFunc(_)) | Some(ItemId::Builtin(_)) => {
+                SemanticKind::Function
+            }
+            Some(ItemId::Param(_)) => SemanticKind::Parameter,
+            Some(ItemId::Type(_)) => SemanticKind::Type,
+            Some(ItemId::Global(_))
+            | Some(ItemId::ImportValue(_))
+            | Some(ItemId::Local(_))
+            | None => SemanticKind::Variable,
+        };
+        self.names
+            .entry(comp.name_span(name).offset())
+            .or_insert(kind);
+    }
+}
@@ -0,0 +1,108 @@
+use ast::visit::{walk_expression, walk_function, walk_type, Visitor};
+use ast::{ExpressionId, FunctionId, Import, PrimitiveType, Span, TypeId, ValType};
+use claw_resolver::{ItemId, ResolvedComponent};
+
+use crate::{ast, Builtin, Error};
+
+/// Check how `v128` values and SIMD builtins are used, for [crate::WasmFeatures::simd].
+///
+/// Without the `simd` target feature they can't be used at all. With it they
+/// still can't cross the component boundary, since the component model has no
+/// vector type, so exported functions and imports can't have them in their signatures.
+pub(crate) fn check_simd(
+    comp: &ast::Component,
+    rcomp: &ResolvedComponent,
+    enabled: bool,
+) -> Result<(), Error> {
+    if !enabled {
+        let mut finder = SimdFinder {
+            rcomp,
+            function: None,
+            found: None,
+        };
+        ast::visit::walk_component(&mut finder, comp);
+        return match finder.found {
+            Some(span) => Err(Error::SimdDisabled {
+                src: comp.source(),
+                span,
+            }),
+            None => Ok(()),
+        };
+    }
+
+    let exported = comp
+        .iter_functions()
+        .filter(|(_, function)| function.exported)
+        .map(|(_, function)| (&function.params[..], function.results));
+    let imported = comp.iter_imports().filter_map(|(_, import)| match import {
+        Import::Plain(import) => match &import.external_type {
+            ast::ExternalType::Function(fn_type) => Some((&fn_type.params[..], fn_type.results)),
+            ast::ExternalType::Value(type_id) => Some((&[][..], Some(*type_id))),
+        },
+        Import::ImportFrom(_) => None,
+    });
+    for (params, results) in exported.chain(imported) {
+        let types = params.iter().map(|(_, type_id)| *type_id);
+        if let Some(type_id) = types.chain(results).find(|id| is_vector(comp, *id)) {
+            return Err(Error::VectorAtBoundary {
+                src: comp.source(),
+                span: comp.type_span(type_id),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Finds the first use of a `v128` type or a SIMD builtin.
+struct SimdFinder<'a> {
+    rcomp: &'a ResolvedComponent,
+    /// The function being visited
+    function: Option<FunctionId>,
+    found: Option<Span>,
+}
+
+impl SimdFinder<'_> {
+    fn found(&mut self, span: Span) {
+        self.found.get_or_insert(span);
+    }
+}
+
+impl<'ast> Visitor<'ast> for SimdFinder<'_> {
+    fn visit_function(
+        &mut self,
+        comp: &'ast ast::Component,
+        id: FunctionId,
+        function: &'ast ast::Function,
+    ) {
+        self.function = Some(id);
+        walk_function(self, comp, function);
+        self.function = None;
+    }
+
+    fn visit_type(&mut self, comp: &'ast ast::Component, type_id: TypeId) {
+        if is_vector(comp, type_id) {
+            self.found(comp.type_span(type_id));
+        }
+        walk_type(self, comp, type_id);
+    }
+
+    fn visit_expression(&mut self, comp: &'ast ast::Component, expression: ExpressionId) {
+        if let ast::Expression::Call(call) = comp.get_expression(expression) {
+            let binding = self
+                .function
+                .and_then(|id| self.rcomp.funcs.get(&id))
+                .and_then(|function| function.bindings.get(&call.ident));
+            if let Some(ItemId::Builtin(Builtin::Simd(..))) = binding {
+                self.found(comp.expression_span(expression));
+            }
+        }
+        walk_expression(self, comp, expression);
+    }
+}
+
+fn is_vector(comp: &ast::Component, type_id: TypeId) -> bool {
+    matches!(
+        comp.get_type(type_id),
+        ValType::Primitive(PrimitiveType::V128)
+    )
+}
@@ -0,0 +1,209 @@
+use ast::{NameId, PrimitiveType, Span, TypeId, ValType};
+use claw_resolver::{AccessorKind, ResolvedComponent};
+use wit_parser::{Resolve, Type, TypeDefKind, WorldId, WorldItem};
+
+use crate::{ast, Error};
+
+/// A WIT world that a component's exports must conform to.
+#[derive(Debug, Clone)]
+pub struct TargetWorld {
+    /// The WIT the world was loaded from
+    pub wit: Resolve,
+    pub world: WorldId,
+}
+
+impl TargetWorld {
+    /// The name of the world.
+    pub fn name(&self) -> &str {
+        &self.wit.worlds[self.world].name
+    }
+}
+
+/// A function the component exports, as it's declared in the source.
+struct Export {
+    name: String,
+    /// Where the export is named
+    span: Span,
+    params: Vec<(Option<NameId>, TypeId)>,
+    results: Option<TypeId>,
+}
+
+/// Check that the component exports every function of `target` with a matching signature.
+///
+/// Signatures are compared structurally, so parameter names don't have to match.
+/// Exporting functions that the world doesn't have is allowed.
+pub(crate) fn check_target_world(
+    comp: &ast::Component,
+    rcomp: &ResolvedComponent,
+    target: &TargetWorld,
+) -> Result<(), Error> {
+    let exports = exports(comp, rcomp);
+    let world = &target.wit.worlds[target.world];
+    for (key, item) in world.exports.iter() {
+        let name = target.wit.name_world_key(key);
+        let func = match item {
+            WorldItem::Function(func) => func,
+            // Claw can't export interfaces
+            WorldItem::Interface(_) => return Err(missing_export(target, name)),
+            WorldItem::Type(_) => continue,
+        };
+        let export = match exports.iter().find(|export| export.name == name) {
+            Some(export) => export,
+            None => return Err(missing_export(target, name)),
+        };
+        let mismatch = |span: Span, reason: String| Error::ExportMismatch {
+            src: comp.source(),
+            span,
+            name: name.clone(),
+            world: target.name().to_owned(),
+            reason,
+        };
+
+        if export.params.len() != func.params.len() {
+            let reason = format!("The world expects {} parameter(s)", func.params.len());
+            return Err(mismatch(export.span, reason));
+        }
+        for ((_, type_id), (_, wit_type)) in export.params.iter().zip(func.params.iter()) {
+            if !conforms(comp, *type_id, &target.wit, wit_type) {
+                let reason = format!("Expected `{}`", wit_type_name(&target.wit, wit_type));
+                return Err(mismatch(comp.type_span(*type_id), reason));
+            }
+        }
+
+        let wit_results: Vec<&Type> = func.results.iter_types().collect();
+        match (export.results, wit_results.as_slice()) {
+            (None, []) => {}
+            (Some(type_id), [wit_type]) if conforms(comp, type_id, &target.wit, wit_type) => {}
+            (Some(type_id), []) => {
+                let reason = "The world expects no result".to_owned();
+                return Err(mismatch(comp.type_span(type_id), reason));
+            }
+            (results, wit_results) => {
+                let names: Vec<String> = wit_results
+                    .iter()
+                    .map(|wit_type| wit_type_name(&target.wit, wit_type))
+                    .collect();
+                let reason = format!("Expected a result of `{}`", names.join(", "));
+                let span = results.map_or(export.span, |type_id| comp.type_span(type_id));
+                return Err(mismatch(span, reason));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn missing_export(target: &TargetWorld, name: String) -> Error {
+    Error::MissingExport {
+        name,
+        world: target.name().to_owned(),
+    }
+}
+
+/// The exported functions and global accessors of the component.
+fn exports(comp: &ast::Component, rcomp: &ResolvedComponent) -> Vec<Export> {
+    let functions = rcomp.exported_functions(comp).map(|(name, id)| {
+        let function = comp.get_function(id);
+        Export {
+            name: name.to_owned(),
+            span: comp.name_span(function.ident),
+            params: function
+                .params
+                .iter()
+                .map(|(name, type_id)| (Some(*name), *type_id))
+                .collect(),
+            results: function.results,
+        }
+    });
+    let accessors = rcomp.global_accessors(comp).into_iter().map(|accessor| {
+        let global = comp.get_global(accessor.global);
+        let (params, results) = match accessor.kind {
+            AccessorKind::Get => (vec![], Some(global.type_id)),
+            AccessorKind::Set => (vec![(None, global.type_id)], None),
+        };
+        Export {
+            name: accessor.name,
+            span: comp.name_span(global.ident),
+            params,
+            results,
+        }
+    });
+    functions.chain(accessors).collect()
+}
+
+/// Whether a Claw type has the same structure as a WIT type.
+fn conforms(comp: &ast::Component, type_id: TypeId, wit: &Resolve, wit_type: &Type) -> bool {
+    let wit_type = match wit_type {
+        Type::Id(id) => match &wit.types[*id].kind {
+            TypeDefKind::Type(inner) => return conforms(comp, type_id, wit, inner),
+            TypeDefKind::Result(result) => {
+                let result_type = match comp.get_type(type_id) {
+                    ValType::Result(result_type) => result_type,
+                    ValType::Primitive(_) => return false,
+                };
+                let part = |type_id, wit_type: &Option<Type>| match wit_type {
+                    Some(wit_type) => conforms(comp, type_id, wit, wit_type),
+                    None => false,
+                };
+                return part(result_type.ok, &result.ok) && part(result_type.err, &result.err);
+            }
+            _ => return false,
+        },
+        wit_type => wit_type,
+    };
+    let primitive = match comp.get_type(type_id) {
+        ValType::Primitive(primitive) => primitive,
+        ValType::Result(_) => return false,
+    };
+    matches!(
+        (primitive, wit_type),
+        (PrimitiveType::Bool, Type::Bool)
+            | (PrimitiveType::U8, Type::U8)
+            | (PrimitiveType::S8, Type::S8)
+            | (PrimitiveType::U16, Type::U16)
+            | (PrimitiveType::S16, Type::S16)
+            | (PrimitiveType::U32, Type::U32)
+            | (PrimitiveType::S32, Type::S32)
+            | (PrimitiveType::U64, Type::U64)
+            | (PrimitiveType::S64, Type::S64)
+            | (PrimitiveType::F32, Type::F32)
+            | (PrimitiveType::F64, Type::F64)
+            | (PrimitiveType::String, Type::String)
+    )
+}
+
+/// The name of a WIT type as it's written in WIT.
+fn wit_type_name(wit: &Resolve, wit_type: &Type) -> String {
+    let name = match wit_type {
+        Type::Bool => "bool",
+        Type::U8 => "u8",
+        Type::U16 => "u16",
+        Type::U32 => "u32",
+        Type::U64 => "u64",
+        Type::S8 => "s8",
+        Type::S16 => "s16",
+        Type::S32 => "s32",
+        Type::S64 => "s64",
+        Type::F32 => "f32",
+        Type::F64 => "f64",
+        Type::Char => "char",
+        Type::String => "string",
+        Type::Id(id) => {
+            let type_def = &wit.types[*id];
+            if let Some(name) = &type_def.name {
+                return name.clone();
+            }
+            return match &type_def.kind {
+                TypeDefKind::Type(inner) => wit_type_name(wit, inner),
+                TypeDefKind::Result(result) => {
+                    let part = |wit_type: &Option<Type>| match wit_type {
+                        Some(wit_type) => wit_type_name(wit, wit_type),
+                        None => "_".to_owned(),
+                    };
+                    format!("result<{}, {}>", part(&result.ok), part(&result.err))
+                }
+                _ => "an anonymous type".to_owned(),
+            };
+        }
+    };
+    name.to_owned()
+}
@@ -0,0 +1,59 @@
+use std::fmt;
+use std::time::Duration;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::Instant;
+
+/// How long each phase of a compilation took.
+///
+/// Phases that didn't run, because an earlier one failed, take no time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompileTimings {
+    pub lexing: Duration,
+    pub parsing: Duration,
+    pub resolution: Duration,
+    pub optimization: Duration,
+    pub codegen: Duration,
+}
+
+impl CompileTimings {
+    /// The time taken by all of the phases together.
+    pub fn total(&self) -> Duration {
+        self.lexing + self.parsing + self.resolution + self.optimization + self.codegen
+    }
+}
+
+impl fmt::Display for CompileTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.total();
+        let phases = [
+            ("lexing", self.lexing),
+            ("parsing", self.parsing),
+            ("resolution", self.resolution),
+            ("optimization", self.optimization),
+            ("codegen", self.codegen),
+        ];
+        for (name, duration) in phases.iter() {
+            let percent = match total.is_zero() {
+                true => 0.0,
+                false => 100.0 * duration.as_secs_f64() / total.as_secs_f64(),
+            };
+            writeln!(f, "{:<12} {:>10.3?} {:>5.1}%", name, duration, percent)?;
+        }
+        write!(f, "{:<12} {:>10.3?}", "total", total)
+    }
+}
+
+/// Time how long `phase` takes, adding it to `duration`.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) fn time<T>(duration: &mut Duration, phase: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = phase();
+    *duration += start.elapsed();
+    result
+}
+
+/// Run `phase` without timing it, since `Instant::now` panics without a clock to read.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub(crate) fn time<T>(_duration: &mut Duration, phase: impl FnOnce() -> T) -> T {
+    phase()
+}
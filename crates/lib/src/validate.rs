@@ -0,0 +1,81 @@
+use claw_resolver::ResolvedComponent;
+use wasmparser::{BinaryReaderError, Parser, Payload, Validator};
+
+use crate::{ast, Error, WasmFeatures};
+
+/// Check that a generated component is valid and only uses the enabled features.
+///
+/// Invalid output is a bug in code generation, so it's reported as an internal
+/// compiler error pointing at the function whose code is invalid, if it's known,
+/// instead of producing a component that fails to load.
+pub fn validate_output(
+    component: &[u8],
+    comp: &ast::Component,
+    rcomp: &ResolvedComponent,
+    features: &WasmFeatures,
+) -> Result<(), Error> {
+    let mut validator = Validator::new_with_features(wasmparser_features(features));
+    let error = match validator.validate_all(component) {
+        Ok(_) => return Ok(()),
+        Err(error) => error,
+    };
+    let function = invalid_function(component, &error, comp, rcomp);
+    Err(Error::InvalidOutput {
+        message: error.message().to_owned(),
+        function: function.map(|(name, _)| name.to_owned()),
+        src: comp.source(),
+        span: function.map(|(_, span)| span),
+    })
+}
+
+/// The features the validator accepts, which are its defaults
+/// with the ones that code generation knows about replaced.
+fn wasmparser_features(features: &WasmFeatures) -> wasmparser::WasmFeatures {
+    use wasmparser::WasmFeatures as Features;
+
+    let mut result = Features::default();
+    result.set(Features::MUTABLE_GLOBAL, features.mutable_globals);
+    result.set(Features::SIGN_EXTENSION, features.sign_ext);
+    result.set(Features::BULK_MEMORY, features.bulk_memory);
+    result.set(Features::SIMD, features.simd);
+    if !features.simd {
+        // Relaxed SIMD builds on SIMD
+        result.remove(Features::RELAXED_SIMD);
+    }
+    result.set(Features::TAIL_CALL, features.tail_call);
+    result.set(Features::MULTI_VALUE, features.multi_value);
+    result
+}
+
+/// The name and span of the function whose code contains the error, if any.
+fn invalid_function<'a>(
+    component: &[u8],
+    error: &BinaryReaderError,
+    comp: &'a ast::Component,
+    rcomp: &ResolvedComponent,
+) -> Option<(&'a str, ast::Span)> {
+    // The code module is the last module in the component, after the allocator's.
+    // Its bodies are the functions that weren't eliminated in order, then their post returns.
+    let mut bodies = Vec::new();
+    for payload in Parser::new(0).parse_all(component) {
+        match payload.ok()? {
+            Payload::ModuleSection { .. } => bodies.clear(),
+            Payload::CodeSectionEntry(body) => bodies.push(body.range()),
+            _ => {}
+        }
+    }
+    let index = bodies
+        .iter()
+        .position(|range| range.contains(&error.offset()))?;
+
+    let functions: Vec<&ast::Function> = comp
+        .iter_functions()
+        .filter(|(id, _)| rcomp.funcs.contains_key(id))
+        .map(|(_, function)| function)
+        .collect();
+    let function = functions.get(index.checked_rem(functions.len())?)?;
+    Some((
+        comp.get_name(function.ident),
+        comp.name_span(function.ident),
+    ))
+}
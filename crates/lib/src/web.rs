@@ -0,0 +1,42 @@
+//! JavaScript bindings for running the compiler in a browser, e.g. for a playground.
+//!
+//! Build with `--target wasm32-unknown-unknown --features web`
+//! and generate the JavaScript glue with `wasm-bindgen`.
+
+use wasm_bindgen::prelude::*;
+
+use crate::embed;
+
+/// The component compiled by [compile], along with its diagnostics.
+#[wasm_bindgen]
+pub struct CompileResult {
+    output: Option<Vec<u8>>,
+    diagnostics: Vec<String>,
+}
+
+#[wasm_bindgen]
+impl CompileResult {
+    /// The binary encoding of the component, or `undefined` if compilation failed
+    #[wasm_bindgen(getter)]
+    pub fn output(&self) -> Option<Vec<u8>> {
+        self.output.clone()
+    }
+
+    /// The warnings, followed by the error if compilation failed, rendered as text
+    #[wasm_bindgen(getter)]
+    pub fn diagnostics(&self) -> Vec<String> {
+        self.diagnostics.clone()
+    }
+}
+
+/// Compile a Claw source file into a component with the default options.
+///
+/// There's no filesystem to load WIT from, so the source can't import anything.
+#[wasm_bindgen]
+pub fn compile(name: &str, source: &str) -> CompileResult {
+    let compiled = embed::compile(name, source);
+    CompileResult {
+        output: compiled.output,
+        diagnostics: compiled.diagnostics,
+    }
+}
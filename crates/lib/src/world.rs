@@ -0,0 +1,153 @@
+use std::fmt::Write;
+
+use claw_resolver::{AccessorKind, ResolvedComponent};
+
+use crate::ast;
+
+/// Words that can't be used as names in WIT without escaping them with `%`.
+const WIT_KEYWORDS: &[&str] = &[
+    "as",
+    "bool",
+    "borrow",
+    "char",
+    "constructor",
+    "enum",
+    "export",
+    "f32",
+    "f64",
+    "flags",
+    "float32",
+    "float64",
+    "from",
+    "func",
+    "future",
+    "import",
+    "include",
+    "interface",
+    "list",
+    "option",
+    "own",
+    "package",
+    "record",
+    "resource",
+    "result",
+    "s16",
+    "s32",
+    "s64",
+    "s8",
+    "static",
+    "stream",
+    "string",
+    "tuple",
+    "type",
+    "u16",
+    "u32",
+    "u64",
+    "u8",
+    "use",
+    "variant",
+    "with",
+    "world",
+];
+
+/// Describe the imports and exports of a component as a WIT world.
+///
+/// The world is named after `world`, which may be a source file name like
+/// `my_component.claw` that is turned into an identifier like `my-component`.
+/// It is in a package of its own, `claw:<world>`. Interfaces imported
+/// from other packages are referred to by their ids, so the WIT they come from
+/// must be available to tools that read the world.
+pub fn generate_wit(comp: &ast::Component, rcomp: &ResolvedComponent, world: &str) -> String {
+    let imports = &rcomp.imports;
+    let world = world_name(world);
+
+    let mut lines = Vec::new();
+    for interface in imports.interfaces.iter() {
+        let line = format!("import {};", interface.name);
+        if !lines.contains(&line) {
+            lines.push(line);
+        }
+    }
+    for func in imports.loose_funcs.iter() {
+        let func = &imports.funcs[*func];
+        let params: Vec<String> = func
+            .params
+            .iter()
+            .map(|(name, rtype)| format!("{}: {}", escape(name), rtype.name(comp, imports)))
+            .collect();
+        let mut line = format!("import {}: func({})", escape(&func.name), params.join(", "));
+        if let Some(results) = func.results {
+            write!(line, " -> {}", results.name(comp, imports)).unwrap();
+        }
+        line.push(';');
+        lines.push(line);
+    }
+
+    let import_count = lines.len();
+    for (name, function) in rcomp.exported_functions(comp) {
+        let function = comp.get_function(function);
+        let params: Vec<String> = function
+            .params
+            .iter()
+            .map(|(name, type_id)| {
+                let name = escape(comp.get_name(*name));
+                format!("{}: {}", name, comp.get_type(*type_id).name(comp))
+            })
+            .collect();
+        let mut line = format!("export {}: func({})", escape(name), params.join(", "));
+        if let Some(results) = function.results {
+            write!(line, " -> {}", comp.get_type(results).name(comp)).unwrap();
+        }
+        line.push(';');
+        lines.push(line);
+    }
+    for accessor in rcomp.global_accessors(comp) {
+        let global = comp.get_global(accessor.global);
+        let type_name = comp.get_type(global.type_id).name(comp);
+        let signature = match accessor.kind {
+            AccessorKind::Get => format!("func() -> {}", type_name),
+            AccessorKind::Set => format!("func(value: {})", type_name),
+        };
+        lines.push(format!("export {}: {};", escape(&accessor.name), signature));
+    }
+
+    let mut wit = format!("package claw:{};\n\nworld {} {{\n", world, world);
+    for (index, line) in lines.iter().enumerate() {
+        if index == import_count && index > 0 {
+            wit.push('\n');
+        }
+        writeln!(wit, "    {}", line).unwrap();
+    }
+    wit.push_str("}\n");
+    wit
+}
+
+/// Turn a source file name into a WIT identifier.
+fn world_name(source_name: &str) -> String {
+    let stem = source_name.split('.').next().unwrap_or_default();
+    let mut name = String::new();
+    for c in stem.chars() {
+        match c {
+            'a'..='z' | '0'..='9' => name.push(c),
+            'A'..='Z' => name.push(c.to_ascii_lowercase()),
+            _ if !name.is_empty() && !name.ends_with('-') => name.push('-'),
+            _ => {}
+        }
+    }
+    let name = name.trim_end_matches('-');
+    match name.chars().next() {
+        Some('a'..='z') => name.to_owned(),
+        _ => format!(
+            "component{}{}",
+            if name.is_empty() { "" } else { "-" },
+            name
+        ),
+    }
+}
+
+fn escape(name: &str) -> String {
+    match WIT_KEYWORDS.contains(&name) {
+        true => format!("%{}", name),
+        false => name.to_owned(),
+    }
+}
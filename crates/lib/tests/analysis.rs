@@ -0,0 +1,365 @@
+use claw_common::{Diagnostics, UnwrapPretty};
+use compile_claw::ast::visit::{walk_component, walk_expression, Visitor};
+use compile_claw::{
+    analyze, ast, dump_ast, dump_tokens, parse_component, semantic_tokens, Builtin, ConstValue,
+    ItemId, SemanticKind,
+};
+
+use wit_parser::Resolve;
+
+const SOURCE: &str = "
+let offset: u32 = 2 + 3;
+
+func add-offset(x: u32) -> u32 {
+    return x + offset;
+}
+
+export func answer(y: u32) -> u32 {
+    return add-offset(y);
+}
+";
+
+#[test]
+fn test_resolved_component_queries() {
+    let mut diagnostics = Diagnostics::new();
+    let (comp, rcomp) = analyze(
+        "analysis.claw".to_owned(),
+        SOURCE,
+        Resolve::new(),
+        &mut diagnostics,
+    )
+    .unwrap_pretty();
+
+    let exports: Vec<_> = rcomp.exported_functions(&comp).collect();
+    let answer = rcomp.export(&comp, "answer").unwrap();
+    assert_eq!(exports, [("answer", answer)]);
+    assert!(rcomp.export(&comp, "add-offset").is_none());
+
+    let (add_offset, _) = comp
+        .iter_functions()
+        .find(|(_, function)| comp.get_name(function.ident) == "add-offset")
+        .unwrap();
+    let (offset, _) = comp.iter_globals().next().unwrap();
+    assert_eq!(rcomp.global_value(offset), Some(ConstValue::Integer(5)));
+
+    // `answer` calls `add-offset`
+    let body = &comp.get_function(answer).body;
+    let call = match comp.get_statement(body[0]) {
+        ast::Statement::Return(ast::Return {
+            expression: Some(expression),
+        }) => *expression,
+        statement => panic!("Expected a return statement, found {:?}", statement),
+    };
+    let ident = match comp.get_expression(call) {
+        ast::Expression::Call(call) => call.ident,
+        expression => panic!("Expected a call, found {:?}", expression),
+    };
+    assert!(matches!(
+        rcomp.binding(answer, ident),
+        Some(ItemId::Function(id)) if id == add_offset
+    ));
+    let call_type = rcomp.expression_type(answer, call).unwrap();
+    assert_eq!(call_type.primitive(&comp), Some(ast::PrimitiveType::U32));
+
+    // Names are only bound in the function that uses them
+    assert!(rcomp.binding(add_offset, ident).is_none());
+}
+
+#[test]
+fn test_builtin_bindings() {
+    let source = "
+    func max(a: u32) -> u32 {
+        return a;
+    }
+
+    export func larger(a: u32, b: u32) -> u32 {
+        return max(min(a, b));
+    }
+    ";
+    let mut diagnostics = Diagnostics::new();
+    let (comp, rcomp) = analyze(
+        "builtins.claw".to_owned(),
+        source,
+        Resolve::new(),
+        &mut diagnostics,
+    )
+    .unwrap_pretty();
+
+    let larger = rcomp.export(&comp, "larger").unwrap();
+    let call = |expression: ast::ExpressionId| match comp.get_expression(expression) {
+        ast::Expression::Call(call) => call,
+        expression => panic!("Expected a call, found {:?}", expression),
+    };
+    let outer = match comp.get_statement(comp.get_function(larger).body[0]) {
+        ast::Statement::Return(ast::Return {
+            expression: Some(expression),
+        }) => call(*expression),
+        statement => panic!("Expected a return statement, found {:?}", statement),
+    };
+    let inner = call(outer.args[0]);
+    let binding = |call: &ast::Call| rcomp.binding(larger, call.ident).unwrap();
+
+    // Builtins can be called without importing them, but other items shadow them
+    assert!(matches!(binding(inner), ItemId::Builtin(Builtin::Min)));
+    assert!(matches!(binding(outer), ItemId::Function(_)));
+}
+
+#[test]
+fn test_visitor() {
+    #[derive(Default)]
+    struct Counter<'ast> {
+        names: Vec<&'ast str>,
+        calls: usize,
+    }
+
+    impl<'ast> Visitor<'ast> for Counter<'ast> {
+        fn visit_expression(&mut self, comp: &'ast ast::Component, expression: ast::ExpressionId) {
+            if let ast::Expression::Call(_) = comp.get_expression(expression) {
+                self.calls += 1;
+            }
+            walk_expression(self, comp, expression);
+        }
+
+        fn visit_name(&mut self, comp: &'ast ast::Component, name: ast::NameId) {
+            self.names.push(comp.get_name(name));
+        }
+    }
+
+    let mut diagnostics = Diagnostics::new();
+    let (comp, _) = analyze(
+        "analysis.claw".to_owned(),
+        SOURCE,
+        Resolve::new(),
+        &mut diagnostics,
+    )
+    .unwrap_pretty();
+
+    let mut counter = Counter::default();
+    walk_component(&mut counter, &comp);
+    assert_eq!(
+        counter.names,
+        [
+            "offset",
+            "add-offset",
+            "x",
+            "x",
+            "offset",
+            "answer",
+            "y",
+            "add-offset",
+            "y"
+        ]
+    );
+    assert_eq!(counter.calls, 1);
+}
+
+#[test]
+fn test_serialize_ast() {
+    let source = "export func id(x: u32) -> u32 {\n    return x;\n}\n";
+    let mut diagnostics = Diagnostics::new();
+    let comp = parse_component("id.claw".to_owned(), source, &mut diagnostics).unwrap_pretty();
+
+    let json = serde_json::to_value(&comp).unwrap();
+    let function = &json["functions"][0]["node"];
+    assert_eq!(function["exported"], true);
+    assert_eq!(
+        json["names"][function["ident"].as_u64().unwrap() as usize]["node"],
+        "id"
+    );
+
+    let statement = &json["statements"][0];
+    assert_eq!(
+        statement["span"],
+        serde_json::json!({ "offset": 36, "len": 9 })
+    );
+    let expression = &json["expressions"][0];
+    assert_eq!(expression["node"]["Identifier"]["ident"], 2);
+    assert_eq!(json["names"][2]["node"], "x");
+}
+
+#[test]
+fn test_node_at() {
+    let mut diagnostics = Diagnostics::new();
+    let comp =
+        parse_component("analysis.claw".to_owned(), SOURCE, &mut diagnostics).unwrap_pretty();
+    let offset_of = |text: &str| SOURCE.find(text).unwrap();
+
+    // The name is preferred over the identifier expression with the same span
+    let x_use = offset_of("x + offset");
+    let node = comp.node_at(x_use).unwrap();
+    assert!(matches!(node, ast::NodeId::Name(name) if comp.get_name(name) == "x"));
+    assert_eq!(comp.node_span(node).offset(), x_use);
+
+    // Between the operands is the binary expression
+    let plus = offset_of("+ offset");
+    let node = comp.node_at(plus).unwrap();
+    assert!(matches!(
+        node,
+        ast::NodeId::Expression(expression)
+            if matches!(comp.get_expression(expression), ast::Expression::Binary(_))
+    ));
+
+    let u32_type = offset_of("u32 = 2");
+    assert!(matches!(comp.node_at(u32_type), Some(ast::NodeId::Type(_))));
+
+    let return_keyword = offset_of("return add-offset");
+    assert!(matches!(
+        comp.node_at(return_keyword),
+        Some(ast::NodeId::Statement(_))
+    ));
+
+    assert_eq!(comp.node_at(0), None);
+}
+
+#[test]
+fn test_definition() {
+    let mut diagnostics = Diagnostics::new();
+    let (comp, rcomp) = analyze(
+        "analysis.claw".to_owned(),
+        SOURCE,
+        Resolve::new(),
+        &mut diagnostics,
+    )
+    .unwrap_pretty();
+    let offset_of = |text: &str| SOURCE.find(text).unwrap();
+    let definition = |offset: usize| rcomp.definition(&comp, offset).map(|span| span.offset());
+
+    // Globals, parameters, and functions used in bodies
+    assert_eq!(
+        definition(offset_of("offset;")),
+        Some(offset_of("offset: u32"))
+    );
+    assert_eq!(
+        definition(offset_of("x + offset")),
+        Some(offset_of("x: u32"))
+    );
+    assert_eq!(
+        definition(offset_of("add-offset(y)")),
+        Some(offset_of("add-offset(x"))
+    );
+
+    // Declarations are their own definitions
+    assert_eq!(definition(offset_of("y: u32")), Some(offset_of("y: u32")));
+    assert_eq!(definition(offset_of("answer")), Some(offset_of("answer")));
+
+    // Not a name
+    assert_eq!(definition(offset_of("return x")), None);
+}
+
+#[test]
+fn test_document_symbols() {
+    let source = "import log: func(message: string);\nexport let mut count: u32 = 0;\n\nfunc double(x: u32) -> u32 {\n    return x * 2;\n}\n";
+    let mut diagnostics = Diagnostics::new();
+    let comp = parse_component("symbols.claw".to_owned(), source, &mut diagnostics).unwrap_pretty();
+
+    let symbols: Vec<_> = comp
+        .document_symbols()
+        .into_iter()
+        .map(|symbol| (symbol.name, symbol.kind, symbol.detail, symbol.exported))
+        .collect();
+    assert_eq!(
+        symbols,
+        [
+            (
+                "log".to_owned(),
+                ast::SymbolKind::Import,
+                "func(message: string)".to_owned(),
+                false
+            ),
+            (
+                "count".to_owned(),
+                ast::SymbolKind::Global,
+                "mut u32".to_owned(),
+                true
+            ),
+            (
+                "double".to_owned(),
+                ast::SymbolKind::Function,
+                "func(x: u32) -> u32".to_owned(),
+                false
+            ),
+        ]
+    );
+    let double = &comp.document_symbols()[2];
+    assert_eq!(double.span.offset(), source.find("double").unwrap());
+}
+
+#[test]
+fn test_semantic_tokens() {
+    let source =
+        "// Shadows the function\nfunc f(f: u32) -> u32 {\n    let y = f + 1;\n    return y;\n}\n";
+    let mut diagnostics = Diagnostics::new();
+    let (comp, rcomp) = analyze(
+        "semantic.claw".to_owned(),
+        source,
+        Resolve::new(),
+        &mut diagnostics,
+    )
+    .unwrap_pretty();
+
+    let tokens: Vec<_> = semantic_tokens(&comp, &rcomp)
+        .into_iter()
+        .map(|token| {
+            let start = token.span.offset();
+            (&source[start..start + token.span.len()], token.kind)
+        })
+        .collect();
+    assert_eq!(
+        tokens,
+        [
+            ("// Shadows the function", SemanticKind::Comment),
+            ("func", SemanticKind::Keyword),
+            ("f", SemanticKind::Function),
+            ("f", SemanticKind::Parameter),
+            ("u32", SemanticKind::Type),
+            ("u32", SemanticKind::Type),
+            ("let", SemanticKind::Keyword),
+            ("y", SemanticKind::Variable),
+            ("=", SemanticKind::Operator),
+            ("f", SemanticKind::Parameter),
+            ("+", SemanticKind::Operator),
+            ("1", SemanticKind::Number),
+            ("return", SemanticKind::Keyword),
+            ("y", SemanticKind::Variable),
+        ]
+    );
+}
+
+#[test]
+fn test_dump_tokens() {
+    let dump = dump_tokens("dump.claw".to_owned(), "let x: u32 = 0x10;\n$");
+    let expected = "\
+1:1     0..3        keyword     let
+1:5     4..5        identifier  x
+1:6     5..6        punctuation :
+1:8     7..10       type        u32
+1:12    11..12      operator    =
+1:14    13..17      number      0x10 = 16
+1:18    17..18      punctuation ;
+2:1     19..20      error       $
+";
+    assert_eq!(dump, expected);
+}
+
+#[test]
+fn test_dump_ast() {
+    let comp = parse_component("analysis.claw".to_owned(), SOURCE, &mut Diagnostics::new())
+        .unwrap_pretty();
+    let expected = "\
+Component analysis.claw
+  Global offset: u32 @ 2:5
+    Binary Add @ 2:19
+      Integer 2 @ 2:19
+      Integer 3 @ 2:23
+  Function add-offset(x: u32) -> u32 @ 4:6
+    Return @ 5:5
+      Binary Add @ 5:12
+        Identifier x @ 5:12
+        Identifier offset @ 5:16
+  Function export answer(y: u32) -> u32 @ 8:13
+    Return @ 9:5
+      Call add-offset @ 9:12
+        Identifier y @ 9:23
+";
+    assert_eq!(dump_ast(&comp), expected);
+}
@@ -0,0 +1,52 @@
+#![cfg(feature = "capi")]
+
+use compile_claw::capi::*;
+
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::ptr;
+
+#[test]
+fn test_capi_compile() {
+    let name = CString::new("factorial.claw").unwrap();
+    let source =
+        CString::new(fs::read_to_string("./tests/programs/factorial.claw").unwrap()).unwrap();
+
+    unsafe {
+        let result = wrought_compile(name.as_ptr(), source.as_ptr());
+        assert!(wrought_succeeded(result));
+        let mut len = 0;
+        let output = wrought_output(result, &mut len);
+        assert!(!output.is_null());
+        let bytes = std::slice::from_raw_parts(output, len);
+        assert_eq!(&bytes[..4], b"\0asm");
+        assert_eq!(wrought_diagnostic_count(result), 0);
+        assert!(wrought_diagnostic(result, 0).is_null());
+        wrought_free(result);
+    }
+}
+
+#[test]
+fn test_capi_diagnostics() {
+    let name = CString::new("bad.claw").unwrap();
+    let source = CString::new("func foo() -> u32 { return true; }").unwrap();
+
+    unsafe {
+        let result = wrought_compile(name.as_ptr(), source.as_ptr());
+        assert!(!wrought_succeeded(result));
+        assert!(wrought_output(result, ptr::null_mut()).is_null());
+        assert_eq!(wrought_diagnostic_count(result), 1);
+        let diagnostic = CStr::from_ptr(wrought_diagnostic(result, 0))
+            .to_str()
+            .unwrap();
+        assert!(diagnostic.contains("bad.claw"), "{}", diagnostic);
+        wrought_free(result);
+
+        let result = wrought_compile(ptr::null(), source.as_ptr());
+        assert!(!wrought_succeeded(result));
+        assert_eq!(wrought_diagnostic_count(result), 1);
+        wrought_free(result);
+
+        wrought_free(ptr::null_mut());
+    }
+}
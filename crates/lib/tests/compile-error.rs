@@ -1,12 +1,22 @@
-use compile_claw::compile;
-use miette::{GraphicalReportHandler, GraphicalTheme};
+#![cfg(feature = "codegen")]
+
+use claw_ast as ast;
+use claw_common::{make_source, Diagnostics};
+use claw_resolver::{resolve, wit::ResolvedWit, ResolverError};
+use compile_claw::{
+    compile_all, compile_with_options, explain, render_diagnostic, CompileInput, CompileOptions,
+    Error, RenderOptions, TargetWorld, WasmFeatures,
+};
+use miette::Diagnostic;
 
 use std::fs;
+use std::path::Path;
 
-use wit_parser::Resolve;
+use wit_parser::{Resolve, UnresolvedPackage};
 
 #[test]
 fn test_bad_programs() {
+    let mut inputs = Vec::new();
     for f in fs::read_dir("./tests/bad-programs").unwrap() {
         let f = f.unwrap();
         let source_name = f.file_name().into_string().unwrap();
@@ -19,14 +29,21 @@ fn test_bad_programs() {
 
         let source_code = fs::read_to_string(f.path()).unwrap();
 
-        let mut error_file_path = f.path();
-        error_file_path.set_extension("error.txt");
-        let error_file_contents = fs::read_to_string(error_file_path).unwrap();
+        inputs.push(CompileInput {
+            source_name,
+            source_code,
+            wit: Resolve::new(),
+        });
+    }
 
-        let wit = Resolve::new();
+    for output in compile_all(inputs, &CompileOptions::default()) {
+        let source_name = output.source_name;
+        let error_file_path = Path::new("./tests/bad-programs")
+            .join(&source_name)
+            .with_extension("error.txt");
+        let error_file_contents = fs::read_to_string(&error_file_path).unwrap();
 
-        let result = compile(source_name.clone(), &source_code, wit);
-        match result {
+        match output.result {
             Ok(_) => {
                 eprintln!(
                     "File '{}' compiled without error when the following error was expected:",
@@ -36,12 +53,238 @@ fn test_bad_programs() {
                 panic!()
             }
             Err(error) => {
-                let mut error_string = String::new();
-                GraphicalReportHandler::new_themed(GraphicalTheme::none())
-                    .render_report(&mut error_string, &error)
-                    .unwrap();
+                let options = RenderOptions {
+                    unicode: false,
+                    ..Default::default()
+                };
+                let error_string = render_diagnostic(&error, options);
                 assert_eq!(error_string, error_file_contents);
+
+                let code = error.code().expect("Every error must have a code");
+                assert!(explain(&code.to_string()).is_some());
             }
         }
     }
 }
+
+#[test]
+fn test_no_alloc() {
+    let options = CompileOptions {
+        no_alloc: true,
+        ..Default::default()
+    };
+    let compile = |source_code: &str| {
+        compile_with_options(
+            "no-alloc.claw".to_owned(),
+            source_code,
+            Resolve::new(),
+            &options,
+            &mut Diagnostics::new(),
+        )
+        .map_err(Box::new)
+    };
+
+    let result = compile("export func answer() -> u32 { return 42; }");
+    assert!(result.is_ok());
+
+    let source_code = "export func greet() -> string { return \"hello\"; }";
+    match compile(source_code).map_err(|error| *error) {
+        Err(Error::HeapAllocation { span, .. }) => {
+            let start = span.offset();
+            assert_eq!(&source_code[start..start + span.len()], "string");
+        }
+        Err(other) => panic!("expected a heap allocation error, got {:?}", other),
+        Ok(_) => panic!("expected a heap allocation error"),
+    }
+}
+
+#[test]
+fn test_simd_boundary() {
+    let options = CompileOptions {
+        features: WasmFeatures {
+            simd: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let compile = |source_code: &str| {
+        compile_with_options(
+            "simd.claw".to_owned(),
+            source_code,
+            Resolve::new(),
+            &options,
+            &mut Diagnostics::new(),
+        )
+        .map_err(Box::new)
+    };
+
+    let result = compile(
+        "export func twice(a: s32) -> s32 { return i32x4-extract-lane(i32x4-splat(a), 1); }",
+    );
+    assert!(result.is_ok());
+
+    let source_code = "export func twice(v: v128) -> v128 { return i32x4-add(v, v); }";
+    match compile(source_code).map_err(|error| *error) {
+        Err(Error::VectorAtBoundary { span, .. }) => {
+            let start = span.offset();
+            assert_eq!(&source_code[start..start + span.len()], "v128");
+        }
+        Err(other) => panic!("expected a vector at the boundary error, got {:?}", other),
+        Ok(_) => panic!("expected a vector at the boundary error"),
+    }
+}
+
+#[test]
+fn test_immutable_assignment_suggestion() {
+    let compile = |source_code: &str| {
+        compile_with_options(
+            "immutable.claw".to_owned(),
+            source_code,
+            Resolve::new(),
+            &CompileOptions::default(),
+            &mut Diagnostics::new(),
+        )
+        .map_err(Box::new)
+    };
+
+    let source_code = "export func count() -> u32 { let a: u32 = 1; a = a + 1; return a; }";
+    let suggestion = match compile(source_code).map_err(|error| *error) {
+        Err(Error::Resolver(ResolverError::AssignedToImmutable {
+            suggestion: Some(suggestion),
+            ..
+        })) => suggestion,
+        Err(other) => panic!("expected an immutable assignment error, got {:?}", other),
+        Ok(_) => panic!("expected an immutable assignment error"),
+    };
+    let fixed = suggestion.apply(source_code);
+    assert!(fixed.contains("let mut a: u32"));
+    assert!(compile(&fixed).is_ok());
+}
+
+/// The parser can't produce result types yet, so the component is built directly.
+#[test]
+fn test_unexportable_global() {
+    let source_code = "export let pair: result<u32, u32> = 1;";
+    let mut comp = ast::Component::new(make_source("result-global.claw", source_code));
+    let ident = comp.new_name("pair", (11, 4).into());
+    let ok = comp.new_type(
+        ast::ValType::Primitive(ast::PrimitiveType::U32),
+        (24, 3).into(),
+    );
+    let err = comp.new_type(
+        ast::ValType::Primitive(ast::PrimitiveType::U32),
+        (29, 3).into(),
+    );
+    let type_id = comp.new_type(
+        ast::ValType::Result(ast::ResultType { ok, err }),
+        (17, 16).into(),
+    );
+    let init_value = comp.new_expression(
+        ast::Expression::Literal(ast::Literal::Integer(1)),
+        (36, 1).into(),
+    );
+    comp.push_global(ast::Global {
+        exported: true,
+        mutable: false,
+        ident,
+        type_id,
+        init_value,
+    });
+
+    let result = resolve(
+        &comp,
+        ResolvedWit::new(Resolve::new()),
+        &mut Diagnostics::new(),
+    );
+    match result {
+        Err(error @ ResolverError::UnexportableGlobal { .. }) => {
+            assert_eq!(error.code().unwrap().to_string(), "E0228");
+            assert!(explain("E0228").is_some());
+        }
+        Err(other) => panic!("expected an unexportable global error, got {:?}", other),
+        Ok(_) => panic!("expected an unexportable global error"),
+    }
+}
+
+#[test]
+fn test_target_world() {
+    let world_wit = "
+        package example:calculator;
+
+        world calculator {
+            export add: func(a: u32, b: u32) -> u32;
+            export get-total: func() -> u64;
+        }
+    ";
+    let mut wit = Resolve::new();
+    let package = UnresolvedPackage::parse(Path::new("calculator.wit"), world_wit).unwrap();
+    let package = wit.push(package).unwrap();
+    let world = wit.select_world(package, Some("calculator")).unwrap();
+    let options = CompileOptions {
+        world: Some(TargetWorld { wit, world }),
+        ..Default::default()
+    };
+    let compile = |source_code: &str| {
+        compile_with_options(
+            "calculator.claw".to_owned(),
+            source_code,
+            Resolve::new(),
+            &options,
+            &mut Diagnostics::new(),
+        )
+        .map_err(Box::new)
+    };
+
+    let result = compile(
+        "export let total: u64 = 0;
+        export func add(x: u32, y: u32) -> u32 { return x + y; }
+        export func unused() {}",
+    );
+    assert!(result.is_ok());
+
+    let source_code = "export let total: u64 = 0;
+        export func add(x: u32, y: u64) -> u32 { return x; }";
+    match compile(source_code).map_err(|error| *error) {
+        Err(Error::ExportMismatch { span, name, .. }) => {
+            assert_eq!(name, "add");
+            let start = span.offset();
+            assert_eq!(&source_code[start..start + span.len()], "u64");
+        }
+        Err(other) => panic!("expected an export mismatch error, got {:?}", other),
+        Ok(_) => panic!("expected an export mismatch error"),
+    }
+
+    match compile("export func add(x: u32, y: u32) -> u32 { return x; }").map_err(|error| *error) {
+        Err(Error::MissingExport { name, .. }) => assert_eq!(name, "get-total"),
+        Err(other) => panic!("expected a missing export error, got {:?}", other),
+        Ok(_) => panic!("expected a missing export error"),
+    }
+}
+
+#[test]
+fn test_errors_are_cloneable_and_serializable() {
+    let source_code = "export func f() -> u32 { let a: u32; return a; }";
+    let error = compile_with_options(
+        "unassigned.claw".to_owned(),
+        source_code,
+        Resolve::new(),
+        &CompileOptions::default(),
+        &mut Diagnostics::new(),
+    )
+    .map_err(Box::new)
+    .unwrap_err();
+
+    let resolver_error = match *error.clone() {
+        Error::Resolver(resolver_error) => resolver_error,
+        other => panic!("expected a resolver error, got {:?}", other),
+    };
+    drop(error);
+
+    let json = serde_json::to_value(&resolver_error).unwrap();
+    let unassigned = &json["UnassignedRead"];
+    assert_eq!(unassigned["src"], "unassigned.claw");
+    assert_eq!(unassigned["ident"], "a");
+    let start = unassigned["span"]["offset"].as_u64().unwrap() as usize;
+    let len = unassigned["span"]["length"].as_u64().unwrap() as usize;
+    assert_eq!(&source_code[start..start + len], "a");
+}
@@ -0,0 +1,50 @@
+#![cfg(feature = "codegen")]
+
+use claw_common::{Diagnostics, UnwrapPretty};
+use compile_claw::{compile_with_options, format_source, CompileOptions, WasmFeatures};
+
+use std::fs;
+
+use wit_parser::Resolve;
+
+#[test]
+fn test_format_programs() {
+    for f in fs::read_dir("./tests/programs").unwrap() {
+        let f = f.unwrap();
+        let source_name = f.file_name().into_string().unwrap();
+        if !source_name.ends_with(".claw") {
+            continue; // skip the wit directory
+        }
+
+        let source_code = fs::read_to_string(f.path()).unwrap();
+        let formatted = format_source(source_name.clone(), &source_code).unwrap_pretty();
+        let reformatted = format_source(source_name.clone(), &formatted).unwrap_pretty();
+        assert_eq!(
+            formatted, reformatted,
+            "Formatting '{}' isn't idempotent",
+            source_name
+        );
+
+        // Formatting must not change what the program means.
+        // SIMD is allowed so that every program compiles.
+        let options = CompileOptions {
+            features: WasmFeatures {
+                simd: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let compile = |source: &str| {
+            let mut wit = Resolve::new();
+            wit.push_path("./tests/programs/wit").unwrap();
+            let name = source_name.trim_end_matches(".claw").to_owned();
+            compile_with_options(name, source, wit, &options, &mut Diagnostics::new())
+                .unwrap_pretty()
+        };
+        assert!(
+            compile(&source_code) == compile(&formatted),
+            "Formatting '{}' changed its output",
+            source_name
+        );
+    }
+}
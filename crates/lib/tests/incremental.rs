@@ -0,0 +1,92 @@
+#![cfg(feature = "codegen")]
+
+use claw_common::{Diagnostics, UnwrapPretty};
+use compile_claw::{compile_with_options, CompileOptions, IncrementalCompiler, OptLevel};
+
+use wit_parser::Resolve;
+
+const SOURCE: &str = "
+let offset: u32 = 2;
+
+func double(x: u32) -> u32 {
+    return x * 2;
+}
+
+export func greeting() -> string {
+    return \"hello\";
+}
+
+export func answer(y: u32) -> u32 {
+    return double(y) + offset;
+}
+
+export func farewell() -> string {
+    return \"goodbye\";
+}
+";
+
+fn compile_fresh(source: &str, options: &CompileOptions) -> Vec<u8> {
+    compile_with_options(
+        "incremental.claw".to_owned(),
+        source,
+        Resolve::new(),
+        options,
+        &mut Diagnostics::new(),
+    )
+    .unwrap_pretty()
+}
+
+fn compile_incremental(compiler: &mut IncrementalCompiler, source: &str) -> Vec<u8> {
+    compiler
+        .compile(
+            "incremental.claw".to_owned(),
+            source,
+            Resolve::new(),
+            &mut Diagnostics::new(),
+        )
+        .unwrap_pretty()
+}
+
+#[test]
+fn test_unchanged_functions_are_reused() {
+    let options = CompileOptions::default();
+    let mut compiler = IncrementalCompiler::new(options.clone());
+
+    let output = compile_incremental(&mut compiler, SOURCE);
+    assert_eq!(output, compile_fresh(SOURCE, &options));
+    assert_eq!((compiler.reused(), compiler.generated()), (0, 4));
+
+    // Whitespace and comments don't matter
+    let source = SOURCE.replace("x * 2;", "x  *  2; // twice");
+    let output = compile_incremental(&mut compiler, &source);
+    assert_eq!(output, compile_fresh(&source, &options));
+    assert_eq!((compiler.reused(), compiler.generated()), (4, 0));
+
+    // The string in `greeting` changes length without moving the one in `farewell`
+    let source = SOURCE.replace("\"hello\"", "\"hello there\"");
+    let output = compile_incremental(&mut compiler, &source);
+    assert_eq!(output, compile_fresh(&source, &options));
+    assert_eq!((compiler.reused(), compiler.generated()), (3, 1));
+
+    // Changing a global changes everything
+    let source = source.replace("offset: u32 = 2", "offset: u32 = 3");
+    let output = compile_incremental(&mut compiler, &source);
+    assert_eq!(output, compile_fresh(&source, &options));
+    assert_eq!((compiler.reused(), compiler.generated()), (0, 4));
+}
+
+#[test]
+fn test_inlined_callees_invalidate_callers() {
+    let options = CompileOptions {
+        opt_level: OptLevel::O1,
+        ..Default::default()
+    };
+    let mut compiler = IncrementalCompiler::new(options.clone());
+    compile_incremental(&mut compiler, SOURCE);
+
+    // `double` is inlined into `answer`, and then removed
+    let source = SOURCE.replace("x * 2;", "x * 4;");
+    let output = compile_incremental(&mut compiler, &source);
+    assert_eq!(output, compile_fresh(&source, &options));
+    assert_eq!((compiler.reused(), compiler.generated()), (2, 1));
+}
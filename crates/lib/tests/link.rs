@@ -0,0 +1,89 @@
+#![cfg(feature = "codegen")]
+
+use claw_common::{Diagnostics, UnwrapPretty};
+use compile_claw::{compile_with_options, compose, link, CompileOptions, Dependency, LinkError};
+
+use wasmtime::component::{Component, Linker};
+use wasmtime::{Config, Engine, Store};
+use wit_parser::Resolve;
+
+const MAIN: &str = "
+import double: func(a: u32) -> u32;
+
+export func quadruple(a: u32) -> u32 {
+    return double(double(a));
+}
+";
+
+const MATH: &str = "
+export func double(a: u32) -> u32 {
+    return a * 2;
+}
+";
+
+fn compile(name: &str, source: &str) -> Vec<u8> {
+    compile_with_options(
+        name.to_owned(),
+        source,
+        Resolve::new(),
+        &CompileOptions::default(),
+        &mut Diagnostics::new(),
+    )
+    .unwrap_pretty()
+}
+
+fn dependency(name: &str, source: &str) -> Dependency {
+    Dependency {
+        name: name.to_owned(),
+        component: compile(name, source),
+    }
+}
+
+#[test]
+fn test_link() {
+    let main = compile("main", MAIN);
+
+    let math = dependency("math", MATH);
+    assert!(link(&main, &[math]).is_ok());
+
+    let empty = dependency("empty", "export func other() -> u32 { return 1; }");
+    let result = link(&main, &[empty]);
+    assert!(
+        matches!(result, Err(LinkError::UnsatisfiedImport { import, .. }) if import == "double")
+    );
+
+    // A dependency with imports can be linked against but not composed
+    let proxy = dependency(
+        "proxy",
+        "import triple: func(a: u32) -> u32;
+        export func double(a: u32) -> u32 { return triple(a); }",
+    );
+    assert!(link(&main, std::slice::from_ref(&proxy)).is_ok());
+    let result = compose(&main, &[proxy]);
+    assert!(matches!(
+        result,
+        Err(LinkError::DependencyHasImports { .. })
+    ));
+}
+
+#[test]
+fn test_compose() {
+    let main = compile("main", MAIN);
+    let unused = dependency("unused", "export func other() -> u32 { return 1; }");
+    let math = dependency("math", MATH);
+    let composed = compose(&main, &[unused, math]).unwrap();
+
+    let mut config = Config::new();
+    config.wasm_component_model(true);
+    let engine = Engine::new(&config).unwrap();
+    let component = Component::new(&engine, &composed).unwrap();
+    let linker = Linker::new(&engine);
+    let mut store = Store::new(&engine, ());
+    let instance = linker.instantiate(&mut store, &component).unwrap();
+
+    let quadruple = instance
+        .get_typed_func::<(u32,), (u32,)>(&mut store, "quadruple")
+        .unwrap();
+    let (result,) = quadruple.call(&mut store, (5,)).unwrap();
+    assert_eq!(result, 20);
+}
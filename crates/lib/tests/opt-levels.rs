@@ -0,0 +1,53 @@
+#![cfg(feature = "codegen")]
+
+use claw_common::{Diagnostics, UnwrapPretty};
+use compile_claw::{compile_with_options, CompileOptions, OptLevel};
+
+use std::fs;
+
+use wit_parser::Resolve;
+
+#[test]
+fn test_opt_level_snapshots() {
+    for f in fs::read_dir("./tests/opt-programs").unwrap() {
+        let f = f.unwrap();
+        let source_name = f.file_name().into_string().unwrap();
+
+        if source_name.ends_with(".wat") {
+            continue; // skip snapshot files
+        }
+
+        assert!(source_name.ends_with(".claw"));
+
+        let source_code = fs::read_to_string(f.path()).unwrap();
+
+        for (opt_level, extension) in [
+            (OptLevel::O0, "O0.wat"),
+            (OptLevel::O1, "O1.wat"),
+            (OptLevel::O2, "O2.wat"),
+        ] {
+            let options = CompileOptions {
+                opt_level,
+                ..Default::default()
+            };
+            let mut diagnostics = Diagnostics::new();
+            let component_bytes = compile_with_options(
+                source_name.clone(),
+                &source_code,
+                Resolve::new(),
+                &options,
+                &mut diagnostics,
+            )
+            .unwrap_pretty();
+            let wat = wasmprinter::print_bytes(&component_bytes).unwrap();
+
+            let snapshot_path = f.path().with_extension(extension);
+            let snapshot = fs::read_to_string(snapshot_path).unwrap();
+            assert_eq!(
+                wat, snapshot,
+                "Output of '{}' at {:?} doesn't match its snapshot",
+                source_name, opt_level
+            );
+        }
+    }
+}
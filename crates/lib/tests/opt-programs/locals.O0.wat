@@ -0,0 +1,203 @@
+(component
+  (core module (;0;)
+    (memory (;0;) 1)
+    (global (;0;) i32 i32.const 8)
+    (export "memory" (memory 0))
+    (export "heap-base" (global 0))
+    (@producers
+      (language "Claw" "")
+      (processed-by "claw" "0.2.6")
+    )
+  )
+  (core instance (;0;) (instantiate 0))
+  (core module (;1;)
+    (type (;0;) (func (param i32 i32 i32 i32) (result i32)))
+    (type (;1;) (func (param i32 i32 i32)))
+    (type (;2;) (func))
+    (import "data" "memory" (memory $memory (;0;) 1))
+    (import "data" "heap-base" (global $heap_base (;0;) i32))
+    (func $realloc (;0;) (type 0) (param $old_ptr i32) (param $old_size i32) (param $align i32) (param $new_size i32) (result i32)
+      (local $ret i32)
+      local.get $old_ptr
+      if ;; label = @1
+        local.get $old_size
+        local.get $new_size
+        i32.gt_u
+        if ;; label = @2
+          local.get $old_ptr
+          return
+        end
+      end
+      global.get $last
+      local.get $align
+      i32.const -1
+      i32.add
+      i32.add
+      local.get $align
+      i32.const -1
+      i32.add
+      i32.const -1
+      i32.xor
+      i32.and
+      global.set $last
+      global.get $last
+      local.set $ret
+      global.get $last
+      local.get $new_size
+      i32.add
+      global.set $last
+      loop $loop
+        memory.size
+        i32.const 65536
+        i32.mul
+        global.get $last
+        i32.lt_u
+        if ;; label = @2
+          i32.const 1
+          memory.grow
+          i32.const -1
+          i32.eq
+          if ;; label = @3
+            unreachable
+          end
+          br $loop
+        end
+      end
+      local.get $ret
+      i32.const 222
+      local.get $new_size
+      call $fill
+      local.get $old_ptr
+      if ;; label = @1
+        local.get $ret
+        local.get $old_ptr
+        local.get $old_size
+        call $copy
+      end
+      local.get $ret
+    )
+    (func $fill (;1;) (type 1) (param $dest i32) (param $value i32) (param $size i32)
+      local.get $dest
+      local.get $value
+      local.get $size
+      memory.fill
+    )
+    (func $copy (;2;) (type 1) (param $dest i32) (param $src i32) (param $size i32)
+      local.get $dest
+      local.get $src
+      local.get $size
+      memory.copy
+    )
+    (func $clear (;3;) (type 2)
+      global.get $heap_base
+      global.set $last
+    )
+    (global $last (;1;) (mut i32) global.get $heap_base)
+    (export "memory" (memory $memory))
+    (export "cabi_realloc" (func $realloc))
+    (export "copy" (func $copy))
+    (export "clear" (func $clear))
+  )
+  (core instance (;1;) (instantiate 1
+      (with "data" (instance 0))
+    )
+  )
+  (alias core export 1 "memory" (core memory (;0;)))
+  (alias core export 1 "cabi_realloc" (core func (;0;)))
+  (core instance (;2;))
+  (core module (;2;)
+    (type (;0;) (func (param i32 i32 i32 i32) (result i32)))
+    (type (;1;) (func))
+    (type (;2;) (func (param i32) (result i32)))
+    (type (;3;) (func (param i32 i32) (result i32)))
+    (type (;4;) (func (param i32)))
+    (type (;5;) (func (param i32)))
+    (import "alloc" "memory" (memory (;0;) 1))
+    (import "alloc" "cabi_realloc" (func (;0;) (type 0)))
+    (import "alloc" "clear" (func (;1;) (type 1)))
+    (func (;2;) (type 2) (param i32) (result i32)
+      (local i32 i32 i32 i32 i32)
+      local.get 0
+      local.set 4
+      i32.const 2
+      local.set 5
+      local.get 4
+      local.get 5
+      i32.mul
+      local.set 3
+      local.get 3
+      return
+    )
+    (func (;3;) (type 3) (param i32 i32) (result i32)
+      (local i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32)
+      local.get 0
+      local.set 7
+      local.get 1
+      local.set 8
+      local.get 7
+      local.get 8
+      i32.add
+      local.set 6
+      local.get 6
+      local.set 4
+      local.get 4
+      local.set 10
+      global.get 0
+      local.set 11
+      local.get 10
+      local.get 11
+      i32.mul
+      local.set 9
+      local.get 9
+      local.set 5
+      local.get 5
+      local.set 14
+      local.get 14
+      call 2
+      local.set 13
+      i32.const 1
+      local.set 16
+      i32.const 2
+      local.set 17
+      local.get 16
+      local.get 17
+      i32.add
+      local.set 15
+      local.get 13
+      local.get 15
+      i32.add
+      local.set 12
+      local.get 12
+      return
+    )
+    (func (;4;) (type 4) (param i32)
+      call 1
+    )
+    (func (;5;) (type 5) (param i32)
+      call 1
+    )
+    (global (;0;) i32 i32.const 3)
+    (global (;1;) i32 i32.const 7)
+    (export "compute" (func 3))
+    (export "double_post_return" (func 4))
+    (export "compute_post_return" (func 5))
+    (@producers
+      (language "Claw" "")
+      (processed-by "claw" "0.2.6")
+    )
+  )
+  (core instance (;3;) (instantiate 2
+      (with "alloc" (instance 1))
+      (with "claw" (instance 2))
+    )
+  )
+  (alias core export 3 "compute" (core func (;1;)))
+  (alias core export 3 "compute_post_return" (core func (;2;)))
+  (type (;0;) (func (param "a" u32) (param "b" u32) (result u32)))
+  (func (;0;) (type 0) (canon lift (core func 1) string-encoding=utf8 (memory 0) (realloc 0) (post-return 2)))
+  (export (;1;) "compute" (func 0) (func (type 0)))
+  (@producers
+    (language "Claw" "")
+    (processed-by "claw" "0.2.6")
+  )
+)
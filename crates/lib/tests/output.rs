@@ -0,0 +1,711 @@
+#![cfg(feature = "codegen")]
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use claw_common::{Diagnostics, UnwrapPretty};
+use compile_claw::{
+    analyze, compile, compile_artifact, compile_with_options, generate_wit, print_wat,
+    validate_output, CompileOptions, Error, OptLevel, OutputFormat, PostProcess,
+    WasmFeatures as TargetFeatures, METADATA_SECTION,
+};
+
+use wasmparser::{
+    BinaryReader, CanonicalFunction, CanonicalOption, ConstExpr, DataKind, Encoding, Operator,
+    Parser, Payload, Validator, WasmFeatures,
+};
+use wit_parser::{Resolve, UnresolvedPackage};
+
+const SOURCE: &str = "
+export func answer() -> u32 {
+    return 42;
+}
+";
+
+#[test]
+fn test_output_format() {
+    let wasm = compile("output.claw".to_owned(), SOURCE, Resolve::new()).unwrap();
+    assert!(wasm.starts_with(b"\0asm"));
+
+    let binary = OutputFormat::Wasm.encode(wasm.clone()).unwrap();
+    assert_eq!(binary, wasm);
+
+    let text = OutputFormat::Wat.encode(wasm.clone()).unwrap();
+    let text = String::from_utf8(text).unwrap();
+    assert_eq!(text, print_wat(&wasm).unwrap());
+    assert!(text.starts_with("(component"));
+    assert!(text.contains("(export \"answer\""));
+
+    assert!(print_wat(b"not wasm").is_err());
+}
+
+#[test]
+fn test_output_is_component() {
+    let source = "
+    import double: func(a: u32) -> u32;
+
+    export func quadruple(a: u32) -> u32 {
+        return double(double(a));
+    }
+    ";
+    let wasm = compile("component.claw".to_owned(), source, Resolve::new()).unwrap();
+    Validator::new().validate_all(&wasm).unwrap();
+
+    // The core module is wrapped in a component that lowers
+    // the imports into it and lifts its exports out of it
+    let mut depth = 0;
+    let (mut lifts, mut lowers, mut core_modules) = (0, 0, 0);
+    let mut core_exports = Vec::new();
+    for payload in Parser::new(0).parse_all(&wasm) {
+        match payload.unwrap() {
+            Payload::Version { encoding, .. } => {
+                if depth == 0 {
+                    assert_eq!(encoding, Encoding::Component);
+                }
+                depth += 1;
+            }
+            Payload::End(_) => depth -= 1,
+            Payload::ModuleSection { .. } => core_modules += 1,
+            Payload::ExportSection(reader) => {
+                for export in reader {
+                    core_exports.push(export.unwrap().name.to_owned());
+                }
+            }
+            Payload::ComponentCanonicalSection(reader) if depth == 1 => {
+                for function in reader {
+                    let options = match function.unwrap() {
+                        CanonicalFunction::Lift { options, .. } => {
+                            lifts += 1;
+                            options
+                        }
+                        CanonicalFunction::Lower { options, .. } => {
+                            lowers += 1;
+                            options
+                        }
+                        _ => continue,
+                    };
+                    // Strings are passed the way the canonical ABI expects
+                    assert!(options.contains(&CanonicalOption::UTF8));
+                    assert!(options
+                        .iter()
+                        .any(|option| matches!(option, CanonicalOption::Memory(_))));
+                    assert!(options
+                        .iter()
+                        .any(|option| matches!(option, CanonicalOption::Realloc(_))));
+                }
+            }
+            _ => {}
+        }
+    }
+    assert!(core_modules >= 1);
+    assert_eq!(lifts, 1);
+    assert_eq!(lowers, 1);
+    assert!(core_exports.iter().any(|name| name == "cabi_realloc"));
+}
+
+#[test]
+fn test_generate_wit() {
+    let source = "
+    import log: func(message: string);
+
+    export func greet(name: string, type: u8) -> u32 {
+        log(name);
+        return len(name);
+    }
+
+    export func count() -> u64 {
+        return 0;
+    }
+    ";
+    let (comp, rcomp) = analyze(
+        "my_component.claw".to_owned(),
+        source,
+        Resolve::new(),
+        &mut Diagnostics::new(),
+    )
+    .unwrap_pretty();
+    let wit = generate_wit(&comp, &rcomp, "my_component.claw");
+    assert_eq!(
+        wit,
+        "package claw:my-component;
+
+world my-component {
+    import log: func(message: string);
+
+    export greet: func(name: string, %type: u8) -> u32;
+    export count: func() -> u64;
+}
+"
+    );
+
+    let mut resolve = Resolve::new();
+    let package = UnresolvedPackage::parse(Path::new("my-component.wit"), &wit).unwrap();
+    let package = resolve.push(package).unwrap();
+    let world = resolve.select_world(package, None).unwrap();
+    assert_eq!(resolve.worlds[world].imports.len(), 1);
+    assert_eq!(resolve.worlds[world].exports.len(), 2);
+}
+
+#[test]
+fn test_compile_artifact() {
+    let options = CompileOptions::default();
+    let artifact = compile_artifact("answer.claw".to_owned(), SOURCE, Resolve::new(), &options)
+        .unwrap_pretty();
+
+    let wasm = compile_with_options(
+        "answer.claw".to_owned(),
+        SOURCE,
+        Resolve::new(),
+        &options,
+        &mut Diagnostics::new(),
+    )
+    .unwrap_pretty();
+    assert_eq!(artifact.source_name(), "answer.claw");
+    assert_eq!(artifact.wasm_bytes(), wasm.as_slice());
+    assert_eq!(artifact.wat().unwrap(), print_wat(&wasm).unwrap());
+    assert!(artifact.wit().contains("export answer: func() -> u32;"));
+    assert!(artifact.diagnostics().is_empty());
+    assert!(!artifact.timings().codegen.is_zero());
+    assert_eq!(artifact.into_wasm_bytes(), wasm);
+}
+
+#[test]
+fn test_generate_wit_for_globals() {
+    let source = "
+    export let limit: u32 = 10;
+    export let mut count: u32 = 0;
+    ";
+    let (comp, rcomp) = analyze(
+        "globals.claw".to_owned(),
+        source,
+        Resolve::new(),
+        &mut Diagnostics::new(),
+    )
+    .unwrap_pretty();
+    let wit = generate_wit(&comp, &rcomp, "globals.claw");
+    assert_eq!(
+        wit,
+        "package claw:globals;
+
+world globals {
+    export get-limit: func() -> u32;
+    export get-count: func() -> u32;
+    export set-count: func(value: u32);
+}
+"
+    );
+}
+
+#[test]
+fn test_debug_names() {
+    let source = "
+    import double: func(a: u32) -> u32;
+
+    let mut total: u32 = 0;
+
+    export func add-twice(amount: u32) -> u32 {
+        let doubled = double(amount);
+        total = total + doubled;
+        return total;
+    }
+    ";
+    let compile_wat = |debug_names| {
+        let options = CompileOptions {
+            debug_names,
+            ..Default::default()
+        };
+        let wasm = compile_with_options(
+            "names.claw".to_owned(),
+            source,
+            Resolve::new(),
+            &options,
+            &mut Diagnostics::new(),
+        )
+        .unwrap_pretty();
+        print_wat(&wasm).unwrap()
+    };
+
+    let named = compile_wat(true);
+    assert!(named.contains("(import \"claw\" \"double\" (func $double"));
+    assert!(named.contains("(func $add-twice"));
+    assert!(named.contains("(param $amount i32)"));
+    assert!(named.contains("(local $doubled i32)"));
+    assert!(named.contains("(global $total"));
+
+    let unnamed = compile_wat(false);
+    assert!(!unnamed.contains("$add-twice"));
+    assert!(!unnamed.contains("$total"));
+}
+
+#[test]
+fn test_debug_info() {
+    let source = "export func sum-to(n: u32) -> u32 {
+    let mut total: u32 = 0;
+    let mut i: u32 = 0;
+    if i < n {
+        i = i + 1;
+        total = total + i;
+    }
+    return total;
+}
+";
+    let options = CompileOptions {
+        debug_info: true,
+        ..Default::default()
+    };
+    let wasm = compile_with_options(
+        "debug.claw".to_owned(),
+        source,
+        Resolve::new(),
+        &options,
+        &mut Diagnostics::new(),
+    )
+    .unwrap_pretty();
+
+    // Find the core module with the source map
+    let mut found = None;
+    for payload in Parser::new(0).parse_all(&wasm) {
+        if let Payload::ModuleSection {
+            unchecked_range, ..
+        } = payload.unwrap()
+        {
+            let module = &wasm[unchecked_range];
+            let mut code_range = None;
+            let mut url = None;
+            for payload in Parser::new(0).parse_all(module) {
+                match payload.unwrap() {
+                    Payload::CodeSectionStart { range, .. } => code_range = Some(range),
+                    Payload::CustomSection(reader) if reader.name() == "sourceMappingURL" => {
+                        let mut data = BinaryReader::new(reader.data());
+                        url = Some(data.read_string().unwrap().to_owned());
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(url) = url {
+                found = Some((code_range.unwrap(), url));
+            }
+        }
+    }
+    let (code_range, url) = found.expect("no module has a source map");
+
+    let map = url.strip_prefix("data:application/json;base64,").unwrap();
+    let map: serde_json::Value = serde_json::from_slice(&decode_base64(map)).unwrap();
+    assert_eq!(map["version"], 3);
+    assert_eq!(map["sources"], serde_json::json!(["debug.claw"]));
+    assert_eq!(map["sourcesContent"], serde_json::json!([source]));
+
+    // Every statement is mapped from an instruction in the code section
+    let mut lines = Vec::new();
+    let (mut offset, mut line) = (0, 0);
+    for segment in map["mappings"].as_str().unwrap().split(',') {
+        let fields = decode_vlq(segment);
+        assert_eq!(fields.len(), 4);
+        assert_eq!(fields[1], 0);
+        offset += fields[0];
+        line += fields[2];
+        assert!(code_range.contains(&(offset as usize)));
+        lines.push(line);
+    }
+    assert_eq!(lines, vec![1, 2, 3, 4, 5, 7]);
+}
+
+#[test]
+fn test_producers_and_metadata() {
+    // The custom sections at the top level of the component, by name
+    let custom_sections = |options: &CompileOptions| {
+        let wasm = compile_with_options(
+            "meta.claw".to_owned(),
+            SOURCE,
+            Resolve::new(),
+            options,
+            &mut Diagnostics::new(),
+        )
+        .unwrap_pretty();
+        Validator::new().validate_all(&wasm).unwrap();
+
+        let mut depth = 0;
+        let mut sections = Vec::new();
+        for payload in Parser::new(0).parse_all(&wasm) {
+            match payload.unwrap() {
+                Payload::Version { .. } => depth += 1,
+                Payload::End(_) => depth -= 1,
+                Payload::CustomSection(reader) if depth == 1 => {
+                    sections.push((reader.name().to_owned(), reader.data().to_vec()));
+                }
+                _ => {}
+            }
+        }
+        sections
+    };
+
+    let sections = custom_sections(&CompileOptions::default());
+    let names: Vec<&str> = sections.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, vec!["producers"]);
+
+    let options = CompileOptions {
+        metadata: true,
+        ..Default::default()
+    };
+    let sections = custom_sections(&options);
+    let (_, data) = sections
+        .iter()
+        .find(|(name, _)| name == METADATA_SECTION)
+        .unwrap();
+    let metadata: serde_json::Value = serde_json::from_slice(data).unwrap();
+    assert!(metadata["compiler"]
+        .as_str()
+        .unwrap()
+        .starts_with("compile-claw "));
+    assert_eq!(metadata["options"].as_str().unwrap().len(), 16);
+    assert_eq!(metadata["sources"], serde_json::json!(["meta.claw"]));
+
+    // Different options are told apart
+    let other = custom_sections(&CompileOptions {
+        overflow_checks: true,
+        ..options
+    });
+    let (_, other_data) = other
+        .iter()
+        .find(|(name, _)| name == METADATA_SECTION)
+        .unwrap();
+    let other: serde_json::Value = serde_json::from_slice(other_data).unwrap();
+    assert_ne!(metadata["options"], other["options"]);
+}
+
+#[test]
+fn test_constant_data_layout() {
+    let source = "
+export func first() -> string {
+    return \"hello\";
+}
+
+export func second() -> string {
+    return \"world\";
+}
+
+export func third() -> string {
+    return \"hello\";
+}
+";
+    let wasm = compile("strings.claw".to_owned(), source, Resolve::new()).unwrap();
+    Validator::new().validate_all(&wasm).unwrap();
+
+    let i32_const = |expr: ConstExpr| match expr.get_operators_reader().read().unwrap() {
+        Operator::I32Const { value } => value,
+        other => panic!("expected an i32.const, got {:?}", other),
+    };
+
+    // The data module comes first, with the heap base and the deduplicated strings
+    let mut heap_base = None;
+    let mut segments = Vec::new();
+    for payload in Parser::new(0).parse_all(&wasm) {
+        match payload.unwrap() {
+            Payload::GlobalSection(reader) if heap_base.is_none() => {
+                let global = reader.into_iter().next().unwrap().unwrap();
+                heap_base = Some(i32_const(global.init_expr));
+            }
+            Payload::DataSection(reader) => {
+                for data in reader {
+                    let data = data.unwrap();
+                    match data.kind {
+                        DataKind::Active { offset_expr, .. } => {
+                            segments.push((i32_const(offset_expr), data.data.to_vec()));
+                        }
+                        DataKind::Passive => panic!("expected only active data segments"),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(
+        segments,
+        vec![(8, b"hello".to_vec()), (13, b"world".to_vec())]
+    );
+    assert_eq!(heap_base, Some(24));
+}
+
+#[test]
+fn test_without_bulk_memory() {
+    let source = "
+export func concat(a: string, b: string) -> string {
+    return a + b;
+}
+";
+    let compile = |bulk_memory| {
+        let options = CompileOptions {
+            features: TargetFeatures {
+                bulk_memory,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        compile_with_options(
+            "concat.claw".to_owned(),
+            source,
+            Resolve::new(),
+            &options,
+            &mut Diagnostics::new(),
+        )
+        .unwrap_pretty()
+    };
+    let validator =
+        || Validator::new_with_features(WasmFeatures::all() - WasmFeatures::BULK_MEMORY);
+
+    // Copies and the allocator use `memory.copy` and `memory.fill` by default
+    assert!(validator().validate_all(&compile(true)).is_err());
+    // and byte loops without bulk memory
+    validator().validate_all(&compile(false)).unwrap();
+}
+
+#[test]
+fn test_mvp_features() {
+    let source = "
+func both(a: string, b: string) -> string {
+    return a + b;
+}
+
+export func smaller(a: s8, b: s8) -> s8 {
+    return min(a, b);
+}
+
+export func twice(s: string) -> string {
+    return both(s, s);
+}
+";
+    let compile = |features| {
+        let options = CompileOptions {
+            features,
+            ..Default::default()
+        };
+        compile_with_options(
+            "mvp.claw".to_owned(),
+            source,
+            Resolve::new(),
+            &options,
+            &mut Diagnostics::new(),
+        )
+        .unwrap_pretty()
+    };
+    let mvp = WasmFeatures::all()
+        - WasmFeatures::SIGN_EXTENSION
+        - WasmFeatures::MULTI_VALUE
+        - WasmFeatures::BULK_MEMORY;
+    let validator = || Validator::new_with_features(mvp);
+
+    // Comparing `s8`s sign extends them and `both` returns a pointer and a length
+    assert!(validator()
+        .validate_all(&compile(TargetFeatures::default()))
+        .is_err());
+    let wasm = compile(TargetFeatures::mvp());
+    validator().validate_all(&wasm).unwrap();
+    assert!(!print_wat(&wasm).unwrap().contains("extend8_s"));
+}
+
+#[test]
+fn test_unknown_feature() {
+    let mut features = TargetFeatures::default();
+    features.set("sign-ext", false).unwrap();
+    assert!(!features.sign_ext);
+    assert!(features.set("threads", true).is_err());
+}
+
+#[test]
+fn test_reproducible_output() {
+    let options = CompileOptions {
+        opt_level: OptLevel::O2,
+        debug_names: true,
+        debug_info: true,
+        metadata: true,
+        features: TargetFeatures {
+            tail_call: true,
+            simd: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let compile = |name: &str, source: &str| {
+        let mut wit = Resolve::new();
+        wit.push_path("./tests/programs/wit").unwrap();
+        compile_with_options(
+            name.to_owned(),
+            source,
+            wit,
+            &options,
+            &mut Diagnostics::new(),
+        )
+        .unwrap_pretty()
+    };
+
+    // Hash maps are seeded differently every time they're created,
+    // so compiling twice catches output that depends on their order
+    for entry in std::fs::read_dir("./tests/programs").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension() != Some("claw".as_ref()) {
+            continue; // skip the wit directory
+        }
+        let name = path.file_stem().unwrap().to_str().unwrap();
+        let source = std::fs::read_to_string(&path).unwrap();
+        assert!(
+            compile(name, &source) == compile(name, &source),
+            "Compiling '{}' twice gave different output",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_validate_output() {
+    let (comp, rcomp) = analyze(
+        "invalid.claw".to_owned(),
+        SOURCE,
+        Resolve::new(),
+        &mut Diagnostics::new(),
+    )
+    .unwrap_pretty();
+    let mut wasm = compile("invalid.claw".to_owned(), SOURCE, Resolve::new()).unwrap();
+    let features = TargetFeatures::default();
+    assert!(validate_output(&wasm, &comp, &rcomp, &features).is_ok());
+
+    // Turn the `i32.const 42` in the last module's first body into an `i64.const 42`
+    let mut bodies = Vec::new();
+    for payload in Parser::new(0).parse_all(&wasm) {
+        match payload.unwrap() {
+            Payload::ModuleSection { .. } => bodies.clear(),
+            Payload::CodeSectionEntry(body) => bodies.push(body.range()),
+            _ => {}
+        }
+    }
+    let body = bodies[0].clone();
+    let position = wasm[body.clone()]
+        .windows(2)
+        .position(|bytes| bytes == [0x41, 42])
+        .unwrap();
+    wasm[body.start + position] = 0x42;
+
+    let result = validate_output(&wasm, &comp, &rcomp, &features);
+    match result {
+        Err(Error::InvalidOutput { function, span, .. }) => {
+            assert_eq!(function.as_deref(), Some("answer"));
+            let function = comp.iter_functions().next().unwrap().1;
+            assert_eq!(span, Some(comp.name_span(function.ident)));
+        }
+        other => panic!("expected invalid output, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_post_process() {
+    let compile = |post_process| {
+        let options = CompileOptions {
+            post_process,
+            ..Default::default()
+        };
+        compile_with_options(
+            "post.claw".to_owned(),
+            SOURCE,
+            Resolve::new(),
+            &options,
+            &mut Diagnostics::new(),
+        )
+        .map_err(Box::new)
+    };
+    let wasm = compile(Vec::new()).unwrap();
+
+    // Stages see the output of the ones before them
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let record = seen.clone();
+    let stages = vec![
+        PostProcess::Callback(Arc::new(|wasm: &[u8]| Ok([wasm, b"!"].concat()))),
+        PostProcess::Callback(Arc::new(move |wasm: &[u8]| {
+            *record.lock().unwrap() = wasm.to_vec();
+            Ok(b"done".to_vec())
+        })),
+    ];
+    assert_eq!(compile(stages).unwrap(), b"done");
+    assert_eq!(*seen.lock().unwrap(), [&wasm[..], b"!"].concat());
+
+    let failing = PostProcess::Callback(Arc::new(|_: &[u8]| Err("broken".to_owned())));
+    let result = compile(vec![failing]);
+    assert!(
+        matches!(result.map_err(|err| *err), Err(Error::PostProcess { message, .. }) if message == "broken")
+    );
+
+    let missing = PostProcess::WasmOpt {
+        program: "claw-test-missing-wasm-opt".into(),
+        args: Vec::new(),
+    };
+    let result = compile(vec![missing]).map_err(|err| *err);
+    assert!(matches!(result, Err(Error::PostProcess { .. })));
+}
+
+/// Stand in for `wasm-opt` with a script that copies its input to its output.
+#[cfg(unix)]
+#[test]
+fn test_wasm_opt_replaces_code_module() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir().join(format!("claw-fake-wasm-opt-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let script = dir.join("wasm-opt");
+    // Called as `wasm-opt <input> -O2 -o <output>`
+    std::fs::write(&script, "#!/bin/sh\ncp \"$1\" \"$4\"\n").unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let compile = |post_process| {
+        let options = CompileOptions {
+            post_process,
+            ..Default::default()
+        };
+        compile_with_options(
+            "wasm-opt.claw".to_owned(),
+            SOURCE,
+            Resolve::new(),
+            &options,
+            &mut Diagnostics::new(),
+        )
+        .unwrap_pretty()
+    };
+    let wasm_opt = PostProcess::WasmOpt {
+        program: script,
+        args: vec!["-O2".to_owned()],
+    };
+    // Putting the unchanged code module back gives the same component
+    assert_eq!(compile(vec![wasm_opt]), compile(Vec::new()));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+const BASE64: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn decode_base64(text: &str) -> Vec<u8> {
+    let mut bits = 0u32;
+    let mut count = 0;
+    let mut bytes = Vec::new();
+    for c in text.chars().filter(|c| *c != '=') {
+        bits = (bits << 6) | BASE64.find(c).unwrap() as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((bits >> count) as u8);
+        }
+    }
+    bytes
+}
+
+fn decode_vlq(segment: &str) -> Vec<i64> {
+    let mut values = Vec::new();
+    let (mut value, mut shift) = (0, 0);
+    for c in segment.chars() {
+        let digit = BASE64.find(c).unwrap() as i64;
+        value |= (digit & 0b11111) << shift;
+        shift += 5;
+        if digit & 0b100000 == 0 {
+            let sign = if value & 1 == 1 { -1 } else { 1 };
+            values.push(sign * (value >> 1));
+            value = 0;
+            shift = 0;
+        }
+    }
+    values
+}
@@ -0,0 +1,135 @@
+#![cfg(feature = "codegen")]
+
+use claw_common::{Diagnostics, UnwrapPretty};
+use compile_claw::{
+    compile_project, compile_with_options, load_sources, load_wit, CompileOptions, Error,
+    FsProvider, MemoryProvider,
+};
+use miette::Diagnostic;
+
+use std::path::PathBuf;
+
+use wit_parser::Resolve;
+
+const MATH: &str = "
+let offset: u32 = 2;
+
+func add-offset(x: u32) -> u32 {
+    return x + offset;
+}
+";
+
+const MAIN: &str = "// Uses items from math.claw
+export func answer(y: u32) -> u32 {
+    return add-offset(y);
+}";
+
+fn sources(files: &[(&str, &str)]) -> Vec<(String, String)> {
+    files
+        .iter()
+        .map(|(name, code)| (name.to_string(), code.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_compile_project() {
+    let options = CompileOptions::default();
+    let output = compile_project(
+        "project".to_owned(),
+        &sources(&[("main.claw", MAIN), ("math.claw", MATH)]),
+        Resolve::new(),
+        &options,
+        &mut Diagnostics::new(),
+    )
+    .unwrap_pretty();
+
+    let combined = format!("{}\n{}\n", MAIN, MATH);
+    let expected = compile_with_options(
+        "project".to_owned(),
+        &combined,
+        Resolve::new(),
+        &options,
+        &mut Diagnostics::new(),
+    )
+    .unwrap_pretty();
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn test_project_syntax_errors_name_their_file() {
+    let broken = "func broken( {}";
+    let error = compile_project(
+        "project".to_owned(),
+        &sources(&[("main.claw", MAIN), ("broken.claw", broken)]),
+        Resolve::new(),
+        &CompileOptions::default(),
+        &mut Diagnostics::new(),
+    )
+    .unwrap_err();
+
+    let source = error.source_code().unwrap();
+    let span = error.labels().unwrap().next().unwrap();
+    let contents = source.read_span(span.inner(), 0, 0).unwrap();
+    assert_eq!(contents.name(), Some("broken.claw"));
+}
+
+#[test]
+fn test_project_errors_point_into_their_file() {
+    let broken = "func add-offset(x: u32) -> u32 {\n    return x + missing;\n}";
+    let error = compile_project(
+        "project".to_owned(),
+        &sources(&[("main.claw", MAIN), ("math.claw", broken)]),
+        Resolve::new(),
+        &CompileOptions::default(),
+        &mut Diagnostics::new(),
+    )
+    .unwrap_err();
+
+    let source = error.source_code().unwrap();
+    let span = error.labels().unwrap().next().unwrap();
+    let contents = source.read_span(span.inner(), 0, 0).unwrap();
+    assert_eq!(contents.name(), Some("math.claw"));
+    assert_eq!((contents.line(), contents.column()), (1, 15));
+}
+
+#[test]
+fn test_memory_provider() {
+    let mut provider = MemoryProvider::new();
+    provider.insert("src/main.claw", MAIN);
+    provider.insert("src/math.claw", MATH);
+    provider.insert(
+        "wit/host.wit",
+        "package claw:host;\n\nworld host {\n    import log: func(message: string);\n}\n",
+    );
+
+    let paths = [
+        PathBuf::from("src/main.claw"),
+        PathBuf::from("src/math.claw"),
+    ];
+    let loaded = load_sources(&provider, &paths).unwrap_pretty();
+    assert_eq!(loaded, sources(&[("main.claw", MAIN), ("math.claw", MATH)]));
+
+    let wit = load_wit(&provider, &[PathBuf::from("wit/host.wit")]).unwrap_pretty();
+    assert_eq!(wit.worlds.len(), 1);
+    compile_project(
+        "project".to_owned(),
+        &loaded,
+        wit,
+        &CompileOptions::default(),
+        &mut Diagnostics::new(),
+    )
+    .unwrap_pretty();
+
+    let error = load_sources(&provider, &[PathBuf::from("src/missing.claw")]).unwrap_err();
+    assert!(matches!(error, Error::ReadSource { .. }));
+    let error = load_wit(&provider, &[PathBuf::from("wit/missing.wit")]).unwrap_err();
+    assert!(matches!(error, Error::LoadWit { .. }));
+}
+
+#[test]
+fn test_fs_provider() {
+    let provider = FsProvider::new("./tests/programs");
+    let loaded = load_sources(&provider, &[PathBuf::from("factorial.claw")]).unwrap_pretty();
+    assert_eq!(loaded[0].0, "factorial.claw");
+    assert!(loaded[0].1.contains("factorial"));
+}
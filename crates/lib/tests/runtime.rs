@@ -1,7 +1,10 @@
-use claw_common::UnwrapPretty;
-use compile_claw::compile;
+#![cfg(feature = "codegen")]
+
+use claw_common::{Diagnostics, UnwrapPretty};
+use compile_claw::{compile_with_options, CompileOptions, OptLevel, PassKind, WasmFeatures};
 
 use std::fs;
+use std::sync::atomic::{AtomicU32, Ordering};
 
 use wasmtime::component::{bindgen, Component, Linker};
 use wasmtime::{Config, Engine, Store};
@@ -17,16 +20,24 @@ struct Runtime {
 
 impl Runtime {
     pub fn new(name: &str) -> Self {
+        Self::new_with_options(name, &CompileOptions::default())
+    }
+
+    pub fn new_with_options(name: &str, options: &CompileOptions) -> Self {
         let path = format!("./tests/programs/{}.claw", name);
         let input = fs::read_to_string(path).unwrap();
         let mut wit = Resolve::new();
         wit.push_path("./tests/programs/wit").unwrap();
-        let component_bytes = compile(name.to_owned(), &input, wit).unwrap_pretty();
+        let mut diagnostics = Diagnostics::new();
+        let component_bytes =
+            compile_with_options(name.to_owned(), &input, wit, options, &mut diagnostics)
+                .unwrap_pretty();
 
         println!("{}", wasmprinter::print_bytes(&component_bytes).unwrap());
 
         let mut config = Config::new();
         config.wasm_component_model(true);
+        config.wasm_tail_call(true);
         let engine = Engine::new(&config).unwrap();
 
         let component = Component::new(&engine, &component_bytes).unwrap();
@@ -54,6 +65,114 @@ fn test_arithmetic() {
     assert!(arithmetic.call_test_u8_masking(&mut runtime.store).unwrap());
 }
 
+#[test]
+fn test_builtins() {
+    bindgen!("builtins" in "tests/programs/wit");
+
+    let mut runtime = Runtime::new("builtins");
+
+    let (builtins, _) =
+        Builtins::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+    let store = &mut runtime.store;
+
+    assert_eq!(builtins.call_min_s8(&mut *store, -5, 3).unwrap(), -5);
+    assert_eq!(builtins.call_min_s8(&mut *store, 7, -128).unwrap(), -128);
+    assert_eq!(
+        builtins.call_max_u64(&mut *store, u64::MAX, 1).unwrap(),
+        u64::MAX
+    );
+    assert_eq!(builtins.call_max_f64(&mut *store, -0.5, 2.5).unwrap(), 2.5);
+    assert_eq!(builtins.call_abs_s16(&mut *store, -300).unwrap(), 300);
+    assert_eq!(builtins.call_abs_s16(&mut *store, 42).unwrap(), 42);
+    assert_eq!(builtins.call_abs_f32(&mut *store, -1.5).unwrap(), 1.5);
+    assert_eq!(
+        builtins.call_clamp_s32(&mut *store, -10, -3, 3).unwrap(),
+        -3
+    );
+    assert_eq!(builtins.call_clamp_s32(&mut *store, 2, -3, 3).unwrap(), 2);
+    assert_eq!(builtins.call_clamp_s32(&mut *store, 10, -3, 3).unwrap(), 3);
+    assert_eq!(builtins.call_percent(&mut *store, 150).unwrap(), 100);
+    assert_eq!(builtins.call_percent(&mut *store, -1).unwrap(), 0);
+    assert_eq!(builtins.call_length(&mut *store, "héllo").unwrap(), 6);
+    assert!(builtins.call_empty(&mut *store, "").unwrap());
+    assert!(!builtins.call_empty(&mut *store, "claw").unwrap());
+}
+
+#[test]
+fn test_builtins_for_mvp() {
+    bindgen!("builtins" in "tests/programs/wit");
+
+    // Sign extension is done with shifts
+    let options = CompileOptions {
+        features: WasmFeatures::mvp(),
+        ..Default::default()
+    };
+    let mut runtime = Runtime::new_with_options("builtins", &options);
+
+    let (builtins, _) =
+        Builtins::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+    let store = &mut runtime.store;
+
+    assert_eq!(builtins.call_min_s8(&mut *store, -5, 3).unwrap(), -5);
+    assert_eq!(builtins.call_min_s8(&mut *store, 7, -128).unwrap(), -128);
+    assert_eq!(builtins.call_abs_s16(&mut *store, -300).unwrap(), 300);
+    assert_eq!(builtins.call_length(&mut *store, "héllo").unwrap(), 6);
+}
+
+#[test]
+fn test_const_globals() {
+    bindgen!("const-globals" in "tests/programs/wit");
+
+    let mut runtime = Runtime::new("const-globals");
+
+    let (const_globals, _) =
+        ConstGlobals::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+    assert_eq!(
+        const_globals.call_get_answer(&mut runtime.store).unwrap(),
+        42
+    );
+    assert_eq!(
+        const_globals.call_get_offset(&mut runtime.store).unwrap(),
+        -8
+    );
+    assert_eq!(
+        const_globals.call_get_mask(&mut runtime.store).unwrap(),
+        (1 << 40) - 1
+    );
+    assert_eq!(
+        const_globals.call_get_ratio(&mut runtime.store).unwrap(),
+        5.5
+    );
+    assert!(const_globals.call_get_is_big(&mut runtime.store).unwrap());
+}
+
+#[test]
+fn test_const_funcs() {
+    bindgen!("const-funcs" in "tests/programs/wit");
+
+    let mut runtime = Runtime::new("const-funcs");
+
+    let (const_funcs, _) =
+        ConstFuncs::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+    let store = &mut runtime.store;
+
+    assert_eq!(
+        const_funcs.call_get_buffer_size(&mut *store).unwrap(),
+        65536
+    );
+    assert_eq!(const_funcs.call_get_level(&mut *store).unwrap(), -10);
+    assert_eq!(
+        const_funcs.call_get_factorial(&mut *store).unwrap(),
+        2432902008176640000
+    );
+    assert_eq!(const_funcs.call_get_squares(&mut *store).unwrap(), 25);
+    assert_eq!(
+        const_funcs.call_double_kibibytes(&mut *store, 3).unwrap(),
+        6144
+    );
+}
+
 #[test]
 fn test_counter() {
     bindgen!("counter" in "tests/programs/wit");
@@ -104,6 +223,96 @@ fn test_counter() {
     }
 }
 
+#[test]
+fn test_dead_code() {
+    bindgen!("dead-code" in "tests/programs/wit");
+
+    let options = CompileOptions {
+        opt_level: OptLevel::O1,
+        verbose: true,
+        ..Default::default()
+    };
+
+    let input = fs::read_to_string("./tests/programs/dead-code.claw").unwrap();
+    let mut wit = Resolve::new();
+    wit.push_path("./tests/programs/wit").unwrap();
+    let mut diagnostics = Diagnostics::new();
+    compile_with_options(
+        "dead-code".to_owned(),
+        &input,
+        wit,
+        &options,
+        &mut diagnostics,
+    )
+    .unwrap_pretty();
+    let notes: Vec<String> = diagnostics.iter().map(|note| note.to_string()).collect();
+    assert_eq!(
+        notes,
+        [
+            // Only called from `answer`, where it's inlined
+            "Removed unused function `helper`",
+            "Removed unused function `dead-helper`",
+            "Removed unused global `unused`",
+            "Removed unused global `only-dead`",
+        ]
+    );
+
+    let mut runtime = Runtime::new_with_options("dead-code", &options);
+
+    let (dead_code, _) =
+        DeadCode::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+    assert_eq!(dead_code.call_answer(&mut runtime.store).unwrap(), 42);
+
+    // Without inlining first, `helper` is still called
+    let options = CompileOptions {
+        passes: Some(vec![PassKind::DeadCode]),
+        verbose: true,
+        ..Default::default()
+    };
+    let input = fs::read_to_string("./tests/programs/dead-code.claw").unwrap();
+    let mut wit = Resolve::new();
+    wit.push_path("./tests/programs/wit").unwrap();
+    let mut diagnostics = Diagnostics::new();
+    compile_with_options(
+        "dead-code".to_owned(),
+        &input,
+        wit,
+        &options,
+        &mut diagnostics,
+    )
+    .unwrap_pretty();
+    let notes: Vec<String> = diagnostics.iter().map(|note| note.to_string()).collect();
+    assert_eq!(
+        notes,
+        [
+            "Removed unused function `dead-helper`",
+            "Removed unused global `unused`",
+            "Removed unused global `only-dead`",
+        ]
+    );
+}
+
+#[test]
+fn test_exported_globals() {
+    bindgen!("exported-globals" in "tests/programs/wit");
+
+    let mut runtime = Runtime::new("exported-globals");
+
+    let (globals, _) =
+        ExportedGlobals::instantiate(&mut runtime.store, &runtime.component, &runtime.linker)
+            .unwrap();
+
+    assert_eq!(globals.call_get_step(&mut runtime.store).unwrap(), 3);
+    assert_eq!(globals.call_get_total(&mut runtime.store).unwrap(), 0);
+    assert_eq!(globals.call_advance(&mut runtime.store).unwrap(), 3);
+    assert_eq!(globals.call_get_total(&mut runtime.store).unwrap(), 3);
+
+    globals.call_set_total(&mut runtime.store, -10).unwrap();
+    assert_eq!(globals.call_get_total(&mut runtime.store).unwrap(), -10);
+    assert_eq!(globals.call_advance(&mut runtime.store).unwrap(), -7);
+}
+
 #[test]
 fn test_factorial() {
     bindgen!("factorial" in "tests/programs/wit");
@@ -125,6 +334,48 @@ fn test_factorial() {
     }
 }
 
+#[test]
+fn test_folding() {
+    bindgen!("folding" in "tests/programs/wit");
+
+    for opt_level in [OptLevel::O0, OptLevel::O1, OptLevel::O2] {
+        let options = CompileOptions {
+            opt_level,
+            ..Default::default()
+        };
+        let mut runtime = Runtime::new_with_options("folding", &options);
+
+        let (folding, _) =
+            Folding::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+        assert_eq!(folding.call_folded(&mut runtime.store).unwrap(), -42);
+        assert_eq!(folding.call_wrapped(&mut runtime.store).unwrap(), 0);
+        assert!(folding.call_compare(&mut runtime.store).unwrap());
+        assert!(folding.call_compare_wrapped(&mut runtime.store).unwrap());
+        assert_eq!(folding.call_partial(&mut runtime.store, 5).unwrap(), 22);
+    }
+}
+
+#[test]
+fn test_folding_overflow_checks() {
+    bindgen!("folding" in "tests/programs/wit");
+
+    // Folding mustn't remove a trap that happens without it
+    for opt_level in [OptLevel::O0, OptLevel::O1, OptLevel::O2] {
+        let options = CompileOptions {
+            opt_level,
+            overflow_checks: true,
+            ..Default::default()
+        };
+        let mut runtime = Runtime::new_with_options("folding", &options);
+
+        let (folding, _) =
+            Folding::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+        assert!(folding.call_compare_wrapped(&mut runtime.store).is_err());
+    }
+}
+
 #[test]
 fn test_identity() {
     bindgen!("identity" in "tests/programs/wit");
@@ -185,6 +436,226 @@ fn test_compare() {
     }
 }
 
+#[test]
+fn test_inference() {
+    bindgen!("inference" in "tests/programs/wit");
+
+    let mut runtime = Runtime::new("inference");
+
+    let (inference, _) =
+        Inference::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+    for a in 0..10 {
+        let sum = inference.call_scaled_sum(&mut runtime.store, a, 3).unwrap();
+        assert_eq!(a * 4 + 3, sum);
+        let clamped = inference.call_clamp_to_ten(&mut runtime.store, a).unwrap();
+        assert_eq!(std::cmp::min(a * 2, 10), clamped);
+    }
+
+    // Unconstrained literals default to s32, so the division is signed
+    let negative = inference
+        .call_defaulted_division(&mut runtime.store)
+        .unwrap();
+    assert!(negative);
+
+    // The local is assigned once on each path
+    for a in [-7, 0, 12] {
+        let sign = inference.call_sign_of(&mut runtime.store, a).unwrap();
+        assert_eq!(a.signum(), sign);
+    }
+}
+
+#[test]
+fn test_inlining() {
+    bindgen!("inlining" in "tests/programs/wit");
+
+    for opt_level in [OptLevel::O0, OptLevel::O1, OptLevel::O2] {
+        let options = CompileOptions {
+            opt_level,
+            verbose: true,
+            ..Default::default()
+        };
+
+        let input = fs::read_to_string("./tests/programs/inlining.claw").unwrap();
+        let mut wit = Resolve::new();
+        wit.push_path("./tests/programs/wit").unwrap();
+        let mut diagnostics = Diagnostics::new();
+        compile_with_options(
+            "inlining".to_owned(),
+            &input,
+            wit,
+            &options,
+            &mut diagnostics,
+        )
+        .unwrap_pretty();
+        let notes: Vec<String> = diagnostics.iter().map(|note| note.to_string()).collect();
+        if opt_level >= OptLevel::O1 {
+            // `square` is still called with a non-trivial argument
+            assert_eq!(notes, ["Removed unused function `add-offset`"]);
+        } else {
+            assert!(notes.is_empty());
+        }
+
+        let mut runtime = Runtime::new_with_options("inlining", &options);
+
+        let (inlining, _) =
+            Inlining::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+        assert_eq!(inlining.call_compute(&mut runtime.store, 5).unwrap(), 58);
+        assert_eq!(
+            inlining.call_not_inlined(&mut runtime.store, 10).unwrap(),
+            25
+        );
+    }
+}
+
+#[test]
+fn test_import_values() {
+    bindgen!("import-values" in "tests/programs/wit");
+
+    impl ImportValuesImports for () {
+        fn get_limit(&mut self) -> Result<u32, wasmtime::Error> {
+            Ok(10)
+        }
+
+        fn get_scale(&mut self) -> Result<f64, wasmtime::Error> {
+            Ok(2.5)
+        }
+
+        fn get_greeting(&mut self) -> Result<String, wasmtime::Error> {
+            Ok("hello, ".to_owned())
+        }
+    }
+
+    let mut runtime = Runtime::new("import-values");
+    ImportValues::add_to_linker(&mut runtime.linker, |s| s).unwrap();
+
+    let (values, _) =
+        ImportValues::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+    assert_eq!(values.call_clamp(&mut runtime.store, 3).unwrap(), 3);
+    assert_eq!(values.call_clamp(&mut runtime.store, 30).unwrap(), 10);
+    assert_eq!(values.call_scaled(&mut runtime.store, 4.0).unwrap(), 10.0);
+    assert_eq!(
+        values.call_greet(&mut runtime.store, "claw").unwrap(),
+        "hello, claw"
+    );
+}
+
+#[test]
+fn test_init() {
+    bindgen!("init" in "tests/programs/wit");
+
+    static SEEDS: AtomicU32 = AtomicU32::new(0);
+
+    impl InitImports for () {
+        fn seed(&mut self) -> Result<u32, wasmtime::Error> {
+            SEEDS.fetch_add(1, Ordering::Relaxed);
+            Ok(21)
+        }
+    }
+
+    let mut runtime = Runtime::new("init");
+    Init::add_to_linker(&mut runtime.linker, |s| s).unwrap();
+
+    let (init, _) =
+        Init::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+    // The init block already ran during instantiation
+    assert_eq!(SEEDS.load(Ordering::Relaxed), 1);
+
+    for _ in 0..3 {
+        assert_eq!(init.call_get_base(&mut runtime.store).unwrap(), 21);
+        assert_eq!(init.call_get_table_size(&mut runtime.store).unwrap(), 43);
+    }
+    assert_eq!(SEEDS.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn test_overflow_checks() {
+    bindgen!("overflow" in "tests/programs/wit");
+
+    let options = CompileOptions {
+        overflow_checks: true,
+        ..Default::default()
+    };
+    let mut runtime = Runtime::new_with_options("overflow", &options);
+
+    let (overflow, _) =
+        Overflow::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+    // In range arithmetic is unaffected
+    assert_eq!(
+        overflow
+            .call_add_u16(&mut runtime.store, 65000, 535)
+            .unwrap(),
+        u16::MAX
+    );
+    assert_eq!(
+        overflow
+            .call_mul_s16(&mut runtime.store, -128, 256)
+            .unwrap(),
+        i16::MIN
+    );
+    assert_eq!(overflow.call_sub_u32(&mut runtime.store, 5, 5).unwrap(), 0);
+    assert_eq!(
+        overflow
+            .call_add_s32(&mut runtime.store, -1, i32::MIN + 1)
+            .unwrap(),
+        i32::MIN
+    );
+    assert_eq!(
+        overflow
+            .call_negate_s32(&mut runtime.store, i32::MAX)
+            .unwrap(),
+        -i32::MAX
+    );
+    assert_eq!(overflow.call_abs_s8(&mut runtime.store, -127).unwrap(), 127);
+    assert_eq!(
+        overflow
+            .call_sub_s64(&mut runtime.store, -1, i64::MAX)
+            .unwrap(),
+        i64::MIN
+    );
+    assert_eq!(
+        overflow
+            .call_add_u64(&mut runtime.store, u64::MAX - 1, 1)
+            .unwrap(),
+        u64::MAX
+    );
+    assert_eq!(
+        overflow
+            .call_mul_s64(&mut runtime.store, -2, 1 << 62)
+            .unwrap(),
+        i64::MIN
+    );
+
+    // Overflowing arithmetic traps
+    assert!(overflow
+        .call_add_u16(&mut runtime.store, 65000, 536)
+        .is_err());
+    assert!(overflow.call_mul_s16(&mut runtime.store, 128, 256).is_err());
+    assert!(overflow.call_sub_u32(&mut runtime.store, 5, 6).is_err());
+    assert!(overflow
+        .call_add_s32(&mut runtime.store, -2, i32::MIN + 1)
+        .is_err());
+    assert!(overflow
+        .call_negate_s32(&mut runtime.store, i32::MIN)
+        .is_err());
+    assert!(overflow.call_abs_s8(&mut runtime.store, i8::MIN).is_err());
+    assert!(overflow
+        .call_sub_s64(&mut runtime.store, -2, i64::MAX)
+        .is_err());
+    assert!(overflow
+        .call_add_u64(&mut runtime.store, u64::MAX, 1)
+        .is_err());
+    assert!(overflow
+        .call_mul_s64(&mut runtime.store, 2, 1 << 62)
+        .is_err());
+    assert!(overflow
+        .call_mul_s64(&mut runtime.store, -1, i64::MIN)
+        .is_err());
+}
+
 #[test]
 fn test_proxy_call() {
     bindgen!("proxy-call" in "tests/programs/wit");
@@ -239,6 +710,67 @@ fn test_quadratic() {
     }
 }
 
+#[test]
+fn test_short_circuit() {
+    bindgen!("short-circuit" in "tests/programs/wit");
+
+    static VISITS: AtomicU32 = AtomicU32::new(0);
+
+    impl ShortCircuitImports for () {
+        fn visit(&mut self, value: bool) -> Result<bool, wasmtime::Error> {
+            VISITS.fetch_add(1, Ordering::Relaxed);
+            Ok(value)
+        }
+    }
+
+    let mut runtime = Runtime::new("short-circuit");
+    ShortCircuit::add_to_linker(&mut runtime.linker, |s| s).unwrap();
+    let (short_circuit, _) =
+        ShortCircuit::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+    let store = &mut runtime.store;
+
+    // The right side is only called when the left side doesn't decide the result
+    let cases = [
+        (false, false, false, 1, false, 2),
+        (false, true, false, 1, true, 2),
+        (true, false, false, 2, true, 1),
+        (true, true, true, 2, true, 1),
+    ];
+    for (left, right, and, and_visits, or, or_visits) in cases {
+        VISITS.store(0, Ordering::Relaxed);
+        assert_eq!(
+            short_circuit
+                .call_visit_and(&mut *store, left, right)
+                .unwrap(),
+            and
+        );
+        assert_eq!(VISITS.load(Ordering::Relaxed), and_visits);
+
+        VISITS.store(0, Ordering::Relaxed);
+        assert_eq!(
+            short_circuit
+                .call_visit_or(&mut *store, left, right)
+                .unwrap(),
+            or
+        );
+        assert_eq!(VISITS.load(Ordering::Relaxed), or_visits);
+    }
+
+    // Dividing by zero would trap if the right side were evaluated
+    assert!(short_circuit.call_divides(&mut *store, 6, 3).unwrap());
+    assert!(!short_circuit.call_divides(&mut *store, 6, 4).unwrap());
+    assert!(!short_circuit.call_divides(&mut *store, 6, 0).unwrap());
+    assert!(short_circuit
+        .call_zero_or_divides(&mut *store, 6, 0)
+        .unwrap());
+    assert!(short_circuit
+        .call_zero_or_divides(&mut *store, 6, 2)
+        .unwrap());
+    assert!(!short_circuit
+        .call_zero_or_divides(&mut *store, 6, 5)
+        .unwrap());
+}
+
 #[test]
 fn test_strings() {
     bindgen!("strings" in "tests/programs/wit");
@@ -277,6 +809,104 @@ fn test_strings() {
                 .unwrap()
         );
     }
+
+    // `greet` isn't exported, so it returns the string's pointer and length with multi-value
+    for case in cases {
+        assert_eq!(
+            format!("hello, {}", case).as_str(),
+            strings.call_greeting(&mut runtime.store, case).unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_strings_without_bulk_memory() {
+    bindgen!("strings" in "tests/programs/wit");
+
+    let options = CompileOptions {
+        features: WasmFeatures {
+            bulk_memory: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut runtime = Runtime::new_with_options("strings", &options);
+
+    let (strings, _) =
+        Strings::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+    let long_string = "Z".repeat(1000);
+    for case in ["", "asdf", long_string.as_str()] {
+        assert_eq!(
+            format!("{}Lorem Ipsum", case).as_str(),
+            strings
+                .call_concat(&mut runtime.store, case, "Lorem Ipsum")
+                .unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_simd() {
+    bindgen!("simd" in "tests/programs/wit");
+
+    let options = CompileOptions {
+        features: WasmFeatures {
+            simd: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut runtime = Runtime::new_with_options("simd", &options);
+
+    let (simd, _) =
+        Simd::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+    assert_eq!(
+        simd.call_sum_products(&mut runtime.store, 3, -5).unwrap(),
+        -60
+    );
+    assert_eq!(
+        simd.call_double_difference(&mut runtime.store, 10, 25)
+            .unwrap(),
+        -30
+    );
+    assert_eq!(
+        simd.call_scale_f32(&mut runtime.store, 1.5, 4.0).unwrap(),
+        6.0
+    );
+    assert_eq!(
+        simd.call_offset_f64(&mut runtime.store, 0.25, 2.0).unwrap(),
+        2.25
+    );
+}
+
+#[test]
+fn test_tail_calls() {
+    bindgen!("tail-calls" in "tests/programs/wit");
+
+    let options = CompileOptions {
+        features: WasmFeatures {
+            tail_call: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut runtime = Runtime::new_with_options("tail-calls", &options);
+
+    let (tail_calls, _) =
+        TailCalls::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+    // Deep enough to overflow the stack if each call took a new frame
+    let depth = 1_000_000;
+    assert_eq!(
+        tail_calls.call_sum_to(&mut runtime.store, depth).unwrap(),
+        depth * (depth + 1) / 2
+    );
+    assert!(tail_calls
+        .call_is_even(&mut runtime.store, 1_000_000)
+        .unwrap());
+    assert!(tail_calls.call_is_odd(&mut runtime.store, 999_999).unwrap());
 }
 
 #[test]
@@ -335,3 +965,34 @@ fn test_unary() {
         assert_eq!(-x, inverse);
     }
 }
+
+#[test]
+fn test_widening() {
+    bindgen!("widening" in "tests/programs/wit");
+
+    let mut runtime = Runtime::new("widening");
+
+    let (widening, _) =
+        Widening::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+    for a in [0, 1, 127, 200, u8::MAX] {
+        let wide = widening.call_widen_unsigned(&mut runtime.store, a).unwrap();
+        assert_eq!(a as u64, wide);
+    }
+    for (a, b) in [
+        (0, 0),
+        (-1, -1),
+        (i8::MIN, i16::MIN),
+        (i8::MAX, i16::MAX),
+        (-5, 300),
+    ] {
+        let total = widening
+            .call_widen_signed(&mut runtime.store, a, b)
+            .unwrap();
+        assert_eq!(a as i64 + b as i64, total);
+    }
+    for a in [0.0, 1.5, -0.1, f32::MAX] {
+        let wide = widening.call_widen_float(&mut runtime.store, a).unwrap();
+        assert_eq!(a as f64 * 2.0, wide);
+    }
+}
@@ -0,0 +1,59 @@
+#![cfg(feature = "codegen")]
+
+use claw_common::{Diagnostics, UnwrapPretty};
+use compile_claw::{compile_with_options, compile_with_timings, CompileOptions, CompileTimings};
+
+use std::fs;
+
+use wit_parser::Resolve;
+
+#[test]
+fn test_timings() {
+    let source_code = fs::read_to_string("./tests/programs/factorial.claw").unwrap();
+    let options = CompileOptions::default();
+
+    let mut timings = CompileTimings::default();
+    let output = compile_with_timings(
+        "factorial.claw".to_owned(),
+        &source_code,
+        Resolve::new(),
+        &options,
+        &mut Diagnostics::new(),
+        &mut timings,
+    )
+    .unwrap_pretty();
+
+    let expected = compile_with_options(
+        "factorial.claw".to_owned(),
+        &source_code,
+        Resolve::new(),
+        &options,
+        &mut Diagnostics::new(),
+    )
+    .unwrap_pretty();
+    assert_eq!(output, expected);
+
+    assert!(!timings.codegen.is_zero());
+    assert_eq!(
+        timings.total(),
+        timings.lexing
+            + timings.parsing
+            + timings.resolution
+            + timings.optimization
+            + timings.codegen
+    );
+
+    let report = timings.to_string();
+    for phase in [
+        "lexing",
+        "parsing",
+        "resolution",
+        "optimization",
+        "codegen",
+        "total",
+    ]
+    .iter()
+    {
+        assert!(report.contains(phase), "Missing {} in\n{}", phase, report);
+    }
+}
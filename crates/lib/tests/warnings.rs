@@ -0,0 +1,163 @@
+#![cfg(feature = "codegen")]
+
+use claw_common::Diagnostics;
+use compile_claw::{
+    check_with_options, compile_with_options, explain, render_diagnostics, CompileOptions, Error,
+    RenderOptions, WarningLevel,
+};
+
+use std::fs;
+
+use wit_parser::Resolve;
+
+#[test]
+fn test_warning_programs() {
+    for f in fs::read_dir("./tests/warning-programs").unwrap() {
+        let f = f.unwrap();
+        let source_name = f.file_name().into_string().unwrap();
+
+        if source_name.ends_with(".warnings.txt") {
+            continue; // skip expected warning files
+        }
+
+        assert!(source_name.ends_with(".claw"));
+
+        let source_code = fs::read_to_string(f.path()).unwrap();
+
+        let mut warnings_file_path = f.path();
+        warnings_file_path.set_extension("warnings.txt");
+        let warnings_file_contents = fs::read_to_string(warnings_file_path).unwrap();
+
+        let options = CompileOptions::default();
+        let mut diagnostics = Diagnostics::new();
+        let result = compile_with_options(
+            source_name.clone(),
+            &source_code,
+            Resolve::new(),
+            &options,
+            &mut diagnostics,
+        );
+        assert!(result.is_ok(), "File '{}' failed to compile", source_name);
+
+        for diagnostic in diagnostics.iter() {
+            let code = diagnostic.code().expect("Every warning must have a code");
+            assert!(explain(&code.to_string()).is_some());
+        }
+        let render_options = RenderOptions {
+            unicode: false,
+            ..Default::default()
+        };
+        let warnings_string = render_diagnostics(&diagnostics, render_options);
+        assert_eq!(warnings_string, warnings_file_contents);
+    }
+}
+
+#[test]
+fn test_render_options() {
+    let source_code = fs::read_to_string("./tests/warning-programs/unused-local.claw").unwrap();
+    let mut diagnostics = Diagnostics::new();
+    compile_with_options(
+        "unused-local.claw".to_owned(),
+        &source_code,
+        Resolve::new(),
+        &CompileOptions::default(),
+        &mut diagnostics,
+    )
+    .unwrap();
+
+    let render = |color, unicode| {
+        let options = RenderOptions {
+            color,
+            unicode,
+            ..Default::default()
+        };
+        render_diagnostics(&diagnostics, options)
+    };
+    let plain = render(false, false);
+    assert!(plain.contains("unused-local.claw"));
+    assert!(plain.is_ascii());
+    assert!(!render(false, true).is_ascii());
+    assert!(!render(false, true).contains('\u{1b}'));
+    assert!(render(true, false).contains('\u{1b}'));
+}
+
+#[test]
+fn test_warning_levels() {
+    let source_code = fs::read_to_string("./tests/warning-programs/unused-local.claw").unwrap();
+
+    let compile_at = |warnings: WarningLevel| {
+        let options = CompileOptions {
+            warnings,
+            ..Default::default()
+        };
+        let mut diagnostics = Diagnostics::new();
+        let result = compile_with_options(
+            "unused-local.claw".to_owned(),
+            &source_code,
+            Resolve::new(),
+            &options,
+            &mut diagnostics,
+        );
+        (result, diagnostics.len())
+    };
+
+    let (result, count) = compile_at(WarningLevel::Allow);
+    assert!(result.is_ok());
+    assert_eq!(count, 0);
+
+    let (result, count) = compile_at(WarningLevel::Warn);
+    assert!(result.is_ok());
+    assert_eq!(count, 1);
+
+    let (result, count) = compile_at(WarningLevel::Deny);
+    assert!(matches!(result, Err(Error::DeniedWarnings { count: 1 })));
+    assert_eq!(count, 1);
+}
+
+#[test]
+fn test_check() {
+    let source_code = fs::read_to_string("./tests/warning-programs/unused-local.claw").unwrap();
+
+    let check_at = |warnings: WarningLevel| {
+        let options = CompileOptions {
+            warnings,
+            ..Default::default()
+        };
+        let mut diagnostics = Diagnostics::new();
+        let result = check_with_options(
+            "unused-local.claw".to_owned(),
+            &source_code,
+            Resolve::new(),
+            &options,
+            &mut diagnostics,
+        );
+        (result, diagnostics.len())
+    };
+
+    let (result, count) = check_at(WarningLevel::Warn);
+    assert!(result.is_ok());
+    assert_eq!(count, 1);
+
+    let (result, count) = check_at(WarningLevel::Deny);
+    assert!(matches!(result, Err(Error::DeniedWarnings { count: 1 })));
+    assert_eq!(count, 1);
+
+    let mut diagnostics = Diagnostics::new();
+    let result = check_with_options(
+        "bad.claw".to_owned(),
+        "func f() -> u32 { return missing; }",
+        Resolve::new(),
+        &CompileOptions::default(),
+        &mut diagnostics,
+    );
+    assert!(matches!(result, Err(Error::Resolver(_))));
+
+    let result = check_with_options(
+        "mismatched.claw".to_owned(),
+        "export func f() -> u32 { let x = 1; return x < 2; }",
+        Resolve::new(),
+        &CompileOptions::default(),
+        &mut diagnostics,
+    );
+    assert!(matches!(result, Err(Error::Resolver(_))));
+}
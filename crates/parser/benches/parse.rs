@@ -0,0 +1,49 @@
+use claw_common::{make_source, Diagnostics, Interner};
+use claw_parser::{parse, parse_streaming, tokenize};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// A large source file made of many small functions.
+fn large_source() -> String {
+    let mut source = String::from("let mut counter: u32 = 0;\n\n");
+    for i in 0..2000 {
+        source.push_str(&format!(
+            "// Function number {i}\n\
+             export func step{i}(a: u32, b: u32) -> u32 {{\n    \
+                 let sum = a * {i} + b - counter;\n    \
+                 if sum > 100 and b != 0 {{\n        \
+                     counter = counter + 1;\n    \
+                 }}\n    \
+                 return step-helper(sum, \"label {i}\") % 7;\n\
+             }}\n\n"
+        ));
+    }
+    source
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let source = large_source();
+    let src = make_source("large.claw", &source);
+
+    c.bench_function("tokenize", |b| {
+        b.iter(|| tokenize(src.clone(), black_box(&source), &mut Interner::new()).unwrap())
+    });
+    c.bench_function("parse", |b| {
+        let mut interner = Interner::new();
+        let tokens = tokenize(src.clone(), &source, &mut interner).unwrap();
+        b.iter(|| {
+            parse(
+                src.clone(),
+                black_box(tokens.clone()),
+                interner.clone(),
+                &mut Diagnostics::new(),
+            )
+            .unwrap()
+        })
+    });
+    c.bench_function("parse_streaming", |b| {
+        b.iter(|| parse_streaming(black_box(src.clone()), &mut Diagnostics::new()).unwrap())
+    });
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
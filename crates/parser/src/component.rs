@@ -1,7 +1,7 @@
 use crate::lexer::Token;
 use crate::{
     expressions::parse_expression, statements::parse_block, types::parse_valtype, ParseInput,
-    ParserError,
+    ParserError, ParserWarning,
 };
 use ast::{FunctionId, GlobalId, Import, ImportFrom, ImportId, NameId, PlainImport, TypeId};
 use claw_ast as ast;
@@ -15,19 +15,29 @@ pub fn parse_component(src: Source, input: &mut ParseInput) -> Result<ast::Compo
 
     while !input.done() {
         // Check for the export keyword
-        let exported = input.next_if(Token::Export).is_some();
+        let export_span = input.next_if(Token::Export);
+        let exported = export_span.is_some();
 
         // Determine the kind of item and parse it
         match input.peek()?.token {
             Token::Import => {
+                if let Some(span) = export_span {
+                    input.warn(ParserWarning::ExportedImport {
+                        src: input.get_source(),
+                        span,
+                    });
+                }
                 parse_import(input, &mut component)?;
             }
             Token::Let => {
                 parse_global(input, &mut component, exported)?;
             }
-            Token::Func => {
+            Token::Func | Token::Const => {
                 parse_func(input, &mut component, exported)?;
             }
+            Token::Init if !exported => {
+                parse_init(input, &mut component)?;
+            }
             _ => {
                 return Err(input.unexpected_token("Top level item (e.g. import, global, function"))
             }
@@ -41,7 +51,7 @@ fn parse_import(
     input: &mut ParseInput,
     comp: &mut ast::Component,
 ) -> Result<ImportId, ParserError> {
-    let token = input.peekn(1).unwrap();
+    let token = input.peekn(1).ok_or(ParserError::EndOfInput)?;
     let import = match token {
         Token::LBrace => Import::ImportFrom(parse_import_from(input, comp)?),
         Token::Identifier(_) => Import::Plain(parse_plain_import(input, comp)?),
@@ -170,23 +180,50 @@ fn parse_func(
     comp: &mut ast::Component,
     exported: bool,
 ) -> Result<FunctionId, ParserError> {
+    let constant = input.next_if(Token::Const).is_some();
     input.assert_next(Token::Func, "Function signature")?;
     let ident = parse_ident(input, comp)?;
     let params = parse_params(input, comp)?;
     let results = parse_results(input, comp)?;
-    let (body, _) = parse_block(input, comp)?;
+    let (body, body_span) = parse_block(input, comp)?;
 
     let function = ast::Function {
         exported,
+        constant,
         ident,
         params,
         results,
         body,
+        body_span,
     };
 
     Ok(comp.push_function(function))
 }
 
+fn parse_init(
+    input: &mut ParseInput,
+    comp: &mut ast::Component,
+) -> Result<FunctionId, ParserError> {
+    let span = input.assert_next(Token::Init, "Init block")?;
+    if comp.init().is_some() {
+        return Err(input.unexpected_token("A component can only have one init block"));
+    }
+    let ident = comp.new_name("init", span);
+    let (body, body_span) = parse_block(input, comp)?;
+
+    let function = ast::Function {
+        exported: false,
+        constant: false,
+        ident,
+        params: Vec::new(),
+        results: None,
+        body,
+        body_span,
+    };
+
+    Ok(comp.push_init(function))
+}
+
 fn parse_params(
     input: &mut ParseInput,
     comp: &mut ast::Component,
@@ -237,7 +274,10 @@ fn parse_external_type(
     input: &mut ParseInput,
     comp: &mut ast::Component,
 ) -> Result<ast::ExternalType, ParserError> {
-    Ok(ast::ExternalType::Function(parse_fn_type(input, comp)?))
+    match input.peek()?.token {
+        Token::Func => Ok(ast::ExternalType::Function(parse_fn_type(input, comp)?)),
+        _ => Ok(ast::ExternalType::Value(parse_valtype(input, comp)?)),
+    }
 }
 
 fn parse_fn_type(
@@ -288,6 +328,15 @@ mod tests {
         parse_component(src, &mut input).unwrap_pretty();
     }
 
+    #[test]
+    fn test_const_function() {
+        let source = "export const func double(a: u32) -> u32 { return a * 2; }\nfunc f() {}";
+        let (src, mut input) = make_input(source);
+        let comp = parse_component(src, &mut input).unwrap_pretty();
+        let constant: Vec<_> = comp.iter_functions().map(|(_, f)| f.constant).collect();
+        assert_eq!(constant, [true, false]);
+    }
+
     #[test]
     fn test_parse_global() {
         let source = "let mut counter: u32 = 0;";
@@ -295,4 +344,33 @@ mod tests {
         let mut comp = ast::Component::new(src);
         parse_global(&mut input, &mut comp, false).unwrap_pretty();
     }
+
+    #[test]
+    fn test_parse_init() {
+        let source = "
+        let mut counter: u32 = 0;
+
+        init {
+            counter = 1;
+        }";
+        let (src, mut input) = make_input(source);
+        let comp = parse_component(src, &mut input).unwrap_pretty();
+        let init = comp.init().unwrap();
+        assert_eq!(comp.get_function(init).body.len(), 1);
+    }
+
+    #[test]
+    fn test_truncated_import() {
+        for source in ["import", "export import"] {
+            let (src, mut input) = make_input(source);
+            assert!(parse_component(src, &mut input).is_err());
+        }
+    }
+
+    #[test]
+    fn test_duplicate_init() {
+        let source = "init {} init {}";
+        let (src, mut input) = make_input(source);
+        assert!(parse_component(src, &mut input).is_err());
+    }
 }
@@ -58,9 +58,7 @@ fn pratt_parse(
 }
 
 fn parse_leaf(input: &mut ParseInput, comp: &mut Component) -> Result<ExpressionId, ParserError> {
-    let peek0 = &input.peek()?.token;
-    let peek1 = input.peekn(1);
-    match (peek0, peek1) {
+    match input.peek_pair()? {
         (Token::LParen, _) => parse_parenthetical(input, comp),
         (Token::Identifier(_), Some(Token::LParen)) => parse_call(input, comp),
         (Token::Identifier(_), Some(Token::Colon)) => parse_enum(input, comp),
@@ -86,9 +84,9 @@ pub fn parse_ident_expr(
 ) -> Result<ExpressionId, ParserError> {
     match &input.peek()?.token {
         Token::Identifier(ident) => {
-            let ident = ident.clone();
+            let ident = *ident;
             let span = input.next().unwrap().span;
-            let ident = comp.new_name(ident, span);
+            let ident = comp.new_name(input.resolve(ident), span);
             Ok(comp.new_expression(Identifier { ident }.into(), span))
         }
         _ => Err(input.unexpected_token("Parsing identifier expression")),
@@ -102,7 +100,7 @@ fn parse_literal(
     let next = input.next()?;
     let span = next.span;
     let literal = match &next.token {
-        Token::StringLiteral(value) => ast::Literal::String(value.to_owned()),
+        Token::StringLiteral(value) => ast::Literal::String(input.resolve(*value).to_owned()),
         Token::IntLiteral(value) => ast::Literal::Integer(*value),
         Token::FloatLiteral(value) => ast::Literal::Float(*value),
         _ => return Err(input.unexpected_token("Parse Literal")),
@@ -158,80 +156,94 @@ fn parse_enum(input: &mut ParseInput, comp: &mut Component) -> Result<Expression
     Ok(comp.new_expression(enum_lit.into(), span))
 }
 
+/// How operators of the same precedence group together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Assoc {
+    /// `a - b - c` is `(a - b) - c`
+    Left,
+    /// `a or b or c` is `a or (b or c)`
+    Right,
+}
+
+/// A binary operator in the [INFIX_OPERATORS] table.
+struct InfixOperator {
+    token: Token,
+    op: BinaryOp,
+    /// Operators with higher precedence bind more tightly
+    precedence: u8,
+    assoc: Assoc,
+}
+
+const fn infix(token: Token, op: BinaryOp, precedence: u8, assoc: Assoc) -> InfixOperator {
+    InfixOperator {
+        token,
+        op,
+        precedence,
+        assoc,
+    }
+}
+
+/// Every binary operator, from the loosest to the tightest binding.
+///
+/// New operators only need a row here.
+#[rustfmt::skip]
+const INFIX_OPERATORS: &[InfixOperator] = &[
+    infix(Token::LogicalOr, BinaryOp::LogicalOr, 1, Assoc::Right),
+    infix(Token::LogicalAnd, BinaryOp::LogicalAnd, 2, Assoc::Left),
+    infix(Token::BitOr, BinaryOp::BitOr, 3, Assoc::Left),
+    infix(Token::BitXor, BinaryOp::BitXor, 4, Assoc::Left),
+    infix(Token::BitAnd, BinaryOp::BitAnd, 5, Assoc::Left),
+    infix(Token::EQ, BinaryOp::Equals, 6, Assoc::Left),
+    infix(Token::NEQ, BinaryOp::NotEquals, 6, Assoc::Left),
+    infix(Token::LT, BinaryOp::LessThan, 7, Assoc::Left),
+    infix(Token::LTE, BinaryOp::LessThanEqual, 7, Assoc::Left),
+    infix(Token::GT, BinaryOp::GreaterThan, 7, Assoc::Left),
+    infix(Token::GTE, BinaryOp::GreaterThanEqual, 7, Assoc::Left),
+    infix(Token::BitShiftL, BinaryOp::BitShiftL, 8, Assoc::Left),
+    infix(Token::BitShiftR, BinaryOp::BitShiftR, 8, Assoc::Left),
+    infix(Token::ArithShiftR, BinaryOp::ArithShiftR, 8, Assoc::Left),
+    infix(Token::Add, BinaryOp::Add, 9, Assoc::Left),
+    infix(Token::Sub, BinaryOp::Subtract, 9, Assoc::Left),
+    infix(Token::Mult, BinaryOp::Multiply, 10, Assoc::Left),
+    infix(Token::Div, BinaryOp::Divide, 10, Assoc::Left),
+    infix(Token::Mod, BinaryOp::Modulo, 10, Assoc::Left),
+];
+
+/// Every unary prefix operator, which all bind more tightly than any binary operator.
+const PREFIX_OPERATORS: &[(Token, UnaryOp)] = &[(Token::Sub, UnaryOp::Negate)];
+
 fn peek_unary_op(input: &mut ParseInput) -> Option<UnaryOp> {
     let next = input.peek().ok()?;
-    let op = match &next.token {
-        Token::Sub => UnaryOp::Negate,
-        _ => return None,
-    };
-    Some(op)
+    PREFIX_OPERATORS
+        .iter()
+        .find(|(token, _)| *token == next.token)
+        .map(|(_, op)| *op)
 }
 
-fn prefix_binding_power(op: UnaryOp) -> ((), u8) {
+pub(crate) fn prefix_binding_power(op: UnaryOp) -> ((), u8) {
     match op {
-        UnaryOp::Negate => ((), 200),
+        UnaryOp::Negate => ((), u8::MAX),
     }
 }
 
 fn peek_bin_op(input: &mut ParseInput) -> Option<BinaryOp> {
     let next = input.peek().ok()?;
-    let op = match &next.token {
-        Token::LogicalOr => BinaryOp::LogicalOr,
-        Token::LogicalAnd => BinaryOp::LogicalAnd,
-
-        Token::BitOr => BinaryOp::BitOr,
-
-        Token::BitXor => BinaryOp::BitXor,
-
-        Token::BitAnd => BinaryOp::BitAnd,
-
-        Token::EQ => BinaryOp::Equals,
-        Token::NEQ => BinaryOp::NotEquals,
-
-        Token::LT => BinaryOp::LessThan,
-        Token::LTE => BinaryOp::LessThanEqual,
-        Token::GT => BinaryOp::GreaterThan,
-        Token::GTE => BinaryOp::GreaterThanEqual,
-
-        Token::BitShiftL => BinaryOp::BitShiftL,
-        Token::BitShiftR => BinaryOp::BitShiftR,
-        Token::ArithShiftR => BinaryOp::ArithShiftR,
-
-        Token::Add => BinaryOp::Add,
-        Token::Sub => BinaryOp::Subtract,
-
-        Token::Mult => BinaryOp::Multiply,
-        Token::Div => BinaryOp::Divide,
-        Token::Mod => BinaryOp::Modulo,
-
-        _ => return None,
-    };
-    Some(op)
+    INFIX_OPERATORS
+        .iter()
+        .find(|operator| operator.token == next.token)
+        .map(|operator| operator.op)
 }
 
-fn infix_binding_power(op: BinaryOp) -> (u8, u8) {
-    match op {
-        BinaryOp::LogicalOr => (10, 1),
-        BinaryOp::LogicalAnd => (20, 21),
-
-        BinaryOp::BitOr => (30, 31),
-
-        BinaryOp::BitXor => (40, 41),
-
-        BinaryOp::BitAnd => (50, 51),
-
-        BinaryOp::Equals | BinaryOp::NotEquals => (60, 61),
-
-        BinaryOp::LessThan
-        | BinaryOp::LessThanEqual
-        | BinaryOp::GreaterThan
-        | BinaryOp::GreaterThanEqual => (70, 71),
-
-        BinaryOp::BitShiftL | BinaryOp::BitShiftR | BinaryOp::ArithShiftR => (80, 81),
-
-        BinaryOp::Add | BinaryOp::Subtract => (90, 91),
-
-        BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Modulo => (100, 101),
+/// The left and right binding power of a binary operator, derived from the [INFIX_OPERATORS] table.
+pub(crate) fn infix_binding_power(op: BinaryOp) -> (u8, u8) {
+    let operator = INFIX_OPERATORS
+        .iter()
+        .find(|operator| operator.op == op)
+        .expect("Every binary operator is in the table");
+    let power = operator.precedence * 2;
+    match operator.assoc {
+        Assoc::Left => (power, power + 1),
+        Assoc::Right => (power + 1, power),
     }
 }
 
@@ -287,7 +299,7 @@ mod tests {
         for (source, span) in cases {
             let (src, mut input) = make_input(source);
             let mut comp = Component::new(src);
-            let ident = comp.new_name(source.to_owned(), span);
+            let ident = comp.new_name(source, span);
             let expected_expression = comp.new_expression(ast::Identifier { ident }.into(), span);
             let found_ident = parse_ident_expr(&mut input.clone(), &mut comp).unwrap();
             assert!(found_ident.context_eq(&expected_expression, &comp));
@@ -312,7 +324,7 @@ mod tests {
         for (source, ident, span) in cases {
             let (src, mut input) = make_input(source);
             let mut comp = Component::new(src);
-            let ident = comp.new_name(ident.to_owned(), span);
+            let ident = comp.new_name(ident, span);
             let expected_expression = comp.new_expression(ast::Identifier { ident }.into(), span);
             let found_expression = parse_parenthetical(&mut input.clone(), &mut comp).unwrap();
             assert!(found_expression.context_eq(&expected_expression, &comp));
@@ -336,7 +348,7 @@ mod tests {
             // Construct ast
             let (src, input) = make_input(source.as_str());
             let mut comp = Component::new(src);
-            let ident = comp.new_name(ident.to_owned(), ident_span);
+            let ident = comp.new_name(ident, ident_span);
             let expected_expression = comp.new_expression(
                 ast::Expression::Call(ast::Call {
                     ident,
@@ -448,4 +460,19 @@ mod tests {
             assert!(expression.context_eq(&expected, &comp));
         }
     }
+
+    #[test]
+    fn operator_table_is_consistent() {
+        for (i, operator) in INFIX_OPERATORS.iter().enumerate() {
+            let later = &INFIX_OPERATORS[i + 1..];
+            assert!(later.iter().all(|other| other.token != operator.token));
+            assert!(later.iter().all(|other| other.op != operator.op));
+            assert!(later
+                .iter()
+                .all(|other| other.precedence >= operator.precedence));
+        }
+        let (_, negate) = prefix_binding_power(UnaryOp::Negate);
+        let (_, tightest) = infix_binding_power(BinaryOp::Multiply);
+        assert!(negate > tightest);
+    }
 }
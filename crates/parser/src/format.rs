@@ -0,0 +1,609 @@
+//! Pretty printing of a parsed [Component] back into canonically formatted source code.
+//!
+//! The AST doesn't contain comments, so they are taken from the token trivia
+//! and re-attached to the item or statement that follows them.
+//! Single blank lines between items and statements are kept.
+//!
+//! Without tokens, the component is printed from the AST alone: items are grouped
+//! by kind, literals are written from their values, and there are no comments.
+
+use ast::{BinaryOp, Component, Expression, ExpressionId, Import, Span, Statement, StatementId};
+use claw_ast as ast;
+use claw_common::Source;
+
+use crate::expressions::{infix_binding_power, prefix_binding_power};
+use crate::lexer::Token;
+use crate::trivia::{SyntaxTokens, Trivia, TriviaKind};
+
+const INDENT: &str = "    ";
+
+/// Format a component that was parsed from `tokens`.
+pub fn format_component(comp: &Component, tokens: &SyntaxTokens) -> String {
+    let mut formatter = Formatter {
+        comp,
+        src: comp.source(),
+        tokens: Some(tokens),
+        comments: find_comments(tokens),
+        next_comment: 0,
+        out: String::new(),
+        indent: 0,
+        last_end: None,
+    };
+    formatter.component();
+    formatter.out
+}
+
+/// Print a component in canonical form using only its syntax tree.
+///
+/// Parsing the output gives back a component with the [same structure](Component::same_structure).
+/// Floats that aren't finite can't be written in Claw, so they don't round trip.
+pub fn print_component(comp: &Component) -> String {
+    let mut formatter = Formatter {
+        comp,
+        src: comp.source(),
+        tokens: None,
+        comments: Vec::new(),
+        next_comment: 0,
+        out: String::new(),
+        indent: 0,
+        last_end: None,
+    };
+    formatter.component();
+    formatter.out
+}
+
+/// A `//` comment in the source.
+struct Comment {
+    start: usize,
+    end: usize,
+    /// Whether the comment is on its own line instead of following code.
+    own_line: bool,
+}
+
+/// Find the comments in the trivia, in source order.
+fn find_comments(tokens: &SyntaxTokens) -> Vec<Comment> {
+    let mut comments = Vec::new();
+    for token in tokens.iter() {
+        push_comments(&token.leading, true, &mut comments);
+        push_comments(&token.trailing, false, &mut comments);
+    }
+    push_comments(tokens.end_trivia(), true, &mut comments);
+    comments
+}
+
+fn push_comments(trivia: &[Trivia], own_line: bool, comments: &mut Vec<Comment>) {
+    for trivia in trivia {
+        if trivia.kind == TriviaKind::Comment {
+            comments.push(Comment {
+                start: trivia.span.offset(),
+                end: span_end(&trivia.span),
+                own_line,
+            });
+        }
+    }
+}
+
+fn span_end(span: &Span) -> usize {
+    span.offset() + span.len()
+}
+
+/// A top-level item, ordered by where it appears in the source.
+enum Item<'a> {
+    Import(&'a Import),
+    Global(&'a ast::Global),
+    Function(&'a ast::Function),
+    Init(&'a ast::Function),
+}
+
+struct Formatter<'a> {
+    comp: &'a Component,
+    src: Source,
+    /// What the component was parsed from, or [None] to print it from the AST alone
+    tokens: Option<&'a SyntaxTokens>,
+    comments: Vec<Comment>,
+    next_comment: usize,
+    out: String,
+    indent: usize,
+    /// Where the last thing written ended in the source,
+    /// or [None] at the start of the file or a block.
+    last_end: Option<usize>,
+}
+
+impl<'a> Formatter<'a> {
+    fn component(&mut self) {
+        let comp = self.comp;
+        let items = match self.tokens {
+            Some(tokens) => source_order(comp, tokens),
+            None => kind_order(comp),
+        };
+
+        // Whether the previous item was a function, if there was one
+        let mut previous_was_function = None;
+        for (start, item) in items {
+            let is_function = matches!(item, Item::Function(_) | Item::Init(_));
+            let force_blank = previous_was_function.is_some_and(|previous| previous || is_function);
+            self.leading(start, force_blank);
+            match item {
+                Item::Import(import) => self.import(import, start),
+                Item::Global(global) => self.global(global),
+                Item::Function(function) => self.function(function),
+                Item::Init(init) => {
+                    let start = span_end(&comp.name_span(init.ident));
+                    self.block("init".to_owned(), &init.body, start);
+                }
+            }
+            previous_was_function = Some(is_function);
+        }
+        self.flush_comments(usize::MAX, false);
+    }
+
+    fn import(&mut self, import: &Import, start: usize) {
+        let comp = self.comp;
+        let line = match import {
+            Import::Plain(import) => {
+                let mut line = format!("import {}", comp.get_name(import.ident));
+                if let Some(alias) = import.alias {
+                    line.push_str(&format!(" as {}", comp.get_name(alias)));
+                }
+                match &import.external_type {
+                    ast::ExternalType::Function(fn_type) => line.push_str(&format!(
+                        ": func{}",
+                        self.signature(&fn_type.params, fn_type.results)
+                    )),
+                    ast::ExternalType::Value(type_id) => {
+                        line.push_str(&format!(": {}", comp.get_type(*type_id).name(comp)))
+                    }
+                }
+                line
+            }
+            Import::ImportFrom(import) => {
+                let items: Vec<String> = import
+                    .items
+                    .iter()
+                    .map(|(name, alias)| match alias {
+                        Some(alias) => {
+                            format!("{} as {}", comp.get_name(*name), comp.get_name(*alias))
+                        }
+                        None => comp.get_name(*name).to_owned(),
+                    })
+                    .collect();
+                let items = match items.is_empty() {
+                    true => "{}".to_owned(),
+                    false => format!("{{ {} }}", items.join(", ")),
+                };
+                format!(
+                    "import {} from {}:{}/{}",
+                    items, import.package.namespace, import.package.name, import.interface
+                )
+            }
+        };
+        self.line(&format!("{};", line));
+        self.last_end = self.token_after(start, Token::Semicolon);
+    }
+
+    fn global(&mut self, global: &ast::Global) {
+        let comp = self.comp;
+        let mut line = String::new();
+        if global.exported {
+            line.push_str("export ");
+        }
+        line.push_str("let ");
+        if global.mutable {
+            line.push_str("mut ");
+        }
+        line.push_str(&format!(
+            "{}: {} = {};",
+            comp.get_name(global.ident),
+            comp.get_type(global.type_id).name(comp),
+            self.expression(global.init_value)
+        ));
+        self.line(&line);
+        let init_end = span_end(&comp.expression_span(global.init_value));
+        self.last_end = self.token_after(init_end, Token::Semicolon);
+    }
+
+    fn function(&mut self, function: &ast::Function) {
+        let comp = self.comp;
+        let mut line = String::new();
+        if function.exported {
+            line.push_str("export ");
+        }
+        if function.constant {
+            line.push_str("const ");
+        }
+        line.push_str(&format!(
+            "func {}{}",
+            comp.get_name(function.ident),
+            self.signature(&function.params, function.results)
+        ));
+        let start = span_end(&comp.name_span(function.ident));
+        self.block(line, &function.body, start);
+    }
+
+    fn signature(
+        &self,
+        params: &[(ast::NameId, ast::TypeId)],
+        results: Option<ast::TypeId>,
+    ) -> String {
+        ast::signature(self.comp, params, results)
+    }
+
+    /// Write `header` followed by a braced block of statements.
+    ///
+    /// The block's closing brace is the first one after `start` or the last statement.
+    fn block(&mut self, header: String, body: &[StatementId], start: usize) {
+        let comp = self.comp;
+        let start = body
+            .last()
+            .map_or(start, |last| span_end(&comp.statement_span(*last)));
+        let close = self.token_after_start(start, Token::RBrace);
+
+        let has_comments = matches!(
+            (self.comments.get(self.next_comment), close),
+            (Some(comment), Some(close)) if comment.start < close
+        );
+        if body.is_empty() && !has_comments {
+            self.line(&format!("{} {{}}", header));
+        } else {
+            self.line(&format!("{} {{", header));
+            self.indent += 1;
+            self.last_end = None;
+            for statement in body {
+                self.statement(*statement);
+            }
+            if let Some(close) = close {
+                self.flush_comments(close, false);
+            }
+            self.indent -= 1;
+            self.line("}");
+        }
+        self.last_end = close.map(|close| close + 1);
+    }
+
+    fn statement(&mut self, statement: StatementId) {
+        let comp = self.comp;
+        let span = comp.statement_span(statement);
+        self.leading(span.offset(), false);
+        match comp.get_statement(statement) {
+            Statement::Let(let_) => {
+                let mut line = String::from("let ");
+                if let_.mutable {
+                    line.push_str("mut ");
+                }
+                line.push_str(comp.get_name(let_.ident));
+                if let Some(annotation) = let_.annotation {
+                    line.push_str(&format!(": {}", comp.get_type(annotation).name(comp)));
+                }
+                match let_.expression {
+                    Some(expression) => {
+                        line.push_str(&format!(" = {};", self.expression(expression)))
+                    }
+                    None => line.push(';'),
+                }
+                self.line(&line);
+            }
+            Statement::Assign(assign) => {
+                let line = format!(
+                    "{} = {};",
+                    comp.get_name(assign.ident),
+                    self.expression(assign.expression)
+                );
+                self.line(&line);
+            }
+            Statement::Call(call) => {
+                let line = format!("{};", self.call(call));
+                self.line(&line);
+            }
+            Statement::If(if_) => {
+                let header = format!("if {}", self.expression(if_.condition));
+                let start = span_end(&comp.expression_span(if_.condition));
+                self.block(header, &if_.block, start);
+                return;
+            }
+            Statement::Return(return_) => match return_.expression {
+                Some(expression) => {
+                    let line = format!("return {};", self.expression(expression));
+                    self.line(&line);
+                }
+                None => self.line("return;"),
+            },
+        }
+        self.last_end = Some(span_end(&span));
+    }
+
+    fn expression(&self, expression: ExpressionId) -> String {
+        let comp = self.comp;
+        match comp.get_expression(expression) {
+            Expression::Identifier(identifier) => comp.get_name(identifier.ident).to_owned(),
+            Expression::Enum(enum_literal) => format!(
+                "{}::{}",
+                comp.get_name(enum_literal.enum_name),
+                comp.get_name(enum_literal.case_name)
+            ),
+            // Keep literals as written, e.g. in hexadecimal or with escapes
+            Expression::Literal(_) if self.tokens.is_some() => {
+                let span = comp.expression_span(expression);
+                self.src.inner()[span.offset()..span_end(&span)].to_owned()
+            }
+            Expression::Literal(literal) => literal_text(literal),
+            Expression::Call(call) => self.call(call),
+            Expression::Unary(unary) => {
+                let ((), r_bp) = prefix_binding_power(unary.op);
+                let inner = self.operand(unary.inner, |op| infix_binding_power(op).0 >= r_bp);
+                match unary.op {
+                    ast::UnaryOp::Negate => format!("-{}", inner),
+                }
+            }
+            Expression::Binary(binary) => {
+                let (l_bp, r_bp) = infix_binding_power(binary.op);
+                // The left operand would absorb this operator if it binds tighter
+                let left = self.operand(binary.left, |op| infix_binding_power(op).1 > l_bp);
+                let right = self.operand(binary.right, |op| infix_binding_power(op).0 >= r_bp);
+                format!("{} {} {}", left, binary_op_symbol(binary.op), right)
+            }
+        }
+    }
+
+    /// Format an operand, parenthesizing it if it's a binary expression that wouldn't parse back as-is.
+    fn operand(&self, expression: ExpressionId, fits: impl Fn(BinaryOp) -> bool) -> String {
+        let formatted = self.expression(expression);
+        match self.comp.get_expression(expression) {
+            Expression::Binary(binary) if !fits(binary.op) => format!("({})", formatted),
+            _ => formatted,
+        }
+    }
+
+    fn call(&self, call: &ast::Call) -> String {
+        let args: Vec<String> = call.args.iter().map(|arg| self.expression(*arg)).collect();
+        format!("{}({})", self.comp.get_name(call.ident), args.join(", "))
+    }
+
+    /// Write any comments before `start`, then a blank line if the source had one.
+    fn leading(&mut self, start: usize, force_blank: bool) {
+        let force_blank = self.flush_comments(start, force_blank);
+        self.separate(start, force_blank);
+    }
+
+    /// Write the comments before `end`, returning whether a forced blank line is still pending.
+    fn flush_comments(&mut self, end: usize, mut force_blank: bool) -> bool {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.start >= end {
+                break;
+            }
+            let (start, comment_end, own_line) = (comment.start, comment.end, comment.own_line);
+            self.next_comment += 1;
+            let text = self.src.inner()[start..comment_end].trim_end().to_owned();
+            if !own_line && self.out.ends_with('\n') {
+                // Keep trailing comments on the line they follow
+                self.out.pop();
+                self.out.push(' ');
+                self.out.push_str(&text);
+                self.out.push('\n');
+            } else {
+                self.separate(start, force_blank);
+                force_blank = false;
+                self.line(&text);
+            }
+            self.last_end = Some(comment_end);
+        }
+        force_blank
+    }
+
+    fn separate(&mut self, start: usize, force_blank: bool) {
+        if self.tokens.is_none() {
+            if force_blank {
+                self.out.push('\n');
+            }
+            return;
+        }
+        if let Some(last_end) = self.last_end {
+            let gap = self.src.inner().get(last_end..start).unwrap_or("");
+            if force_blank || gap.matches('\n').count() >= 2 {
+                self.out.push('\n');
+            }
+        }
+    }
+
+    fn line(&mut self, line: &str) {
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    /// The end of the first `token` at or after `offset`.
+    fn token_after(&self, offset: usize, token: Token) -> Option<usize> {
+        self.token_after_start(offset, token).map(|start| start + 1)
+    }
+
+    /// The start of the first `token` at or after `offset`.
+    fn token_after_start(&self, offset: usize, token: Token) -> Option<usize> {
+        self.tokens?
+            .iter()
+            .map(|syntax_token| &syntax_token.data)
+            .find(|data| data.span.offset() >= offset && data.token == token)
+            .map(|data| data.span.offset())
+    }
+}
+
+/// The top-level items in the order they appear in the source.
+///
+/// Each is paired with where it starts, so comments and blank lines before it can be kept.
+fn source_order<'a>(comp: &'a Component, tokens: &SyntaxTokens) -> Vec<(usize, Item<'a>)> {
+    let mut items: Vec<(usize, Item)> = Vec::new();
+    // Imports start at their `import` keyword, since imports of no items have no other spans
+    let import_starts = tokens
+        .iter()
+        .filter(|token| token.data.token == Token::Import)
+        .map(|token| token.data.span.offset());
+    for ((_, import), start) in comp.iter_imports().zip(import_starts) {
+        items.push((start, Item::Import(import)));
+    }
+    for (_, global) in comp.iter_globals() {
+        let start = comp.name_span(global.ident).offset();
+        items.push((start, Item::Global(global)));
+    }
+    for (id, function) in comp.iter_functions() {
+        let start = comp.name_span(function.ident).offset();
+        match comp.is_init(id) {
+            true => items.push((start, Item::Init(function))),
+            false => items.push((start, Item::Function(function))),
+        }
+    }
+    items.sort_by_key(|(start, _)| *start);
+    items
+}
+
+/// The top-level items grouped by kind: imports, globals, the `init` block, then functions.
+///
+/// They're numbered in order instead of having source positions.
+fn kind_order(comp: &Component) -> Vec<(usize, Item<'_>)> {
+    let imports = comp.iter_imports().map(|(_, import)| Item::Import(import));
+    let globals = comp.iter_globals().map(|(_, global)| Item::Global(global));
+    let init = comp.init().map(|id| Item::Init(comp.get_function(id)));
+    let functions = comp
+        .iter_functions()
+        .filter(|(id, _)| !comp.is_init(*id))
+        .map(|(_, function)| Item::Function(function));
+    imports
+        .chain(globals)
+        .chain(init)
+        .chain(functions)
+        .enumerate()
+        .collect()
+}
+
+/// Write a literal from its value, in the canonical way it's spelled.
+fn literal_text(literal: &ast::Literal) -> String {
+    match literal {
+        ast::Literal::Integer(value) => value.to_string(),
+        ast::Literal::Float(value) => {
+            let text = value.to_string();
+            match text.contains('.') {
+                true => text,
+                false => format!("{}.0", text),
+            }
+        }
+        ast::Literal::String(value) => {
+            let mut text = String::from("\"");
+            for c in value.chars() {
+                match c {
+                    '"' => text.push_str("\\\""),
+                    '\\' => text.push_str("\\\\"),
+                    '\n' => text.push_str("\\n"),
+                    '\r' => text.push_str("\\r"),
+                    '\t' => text.push_str("\\t"),
+                    c if c.is_control() => text.push_str(&format!("\\u{:04x}", c as u32)),
+                    c => text.push(c),
+                }
+            }
+            text.push('"');
+            text
+        }
+    }
+}
+
+fn binary_op_symbol(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Multiply => "*",
+        BinaryOp::Divide => "/",
+        BinaryOp::Modulo => "%",
+        BinaryOp::Add => "+",
+        BinaryOp::Subtract => "-",
+        BinaryOp::BitShiftL => "<<",
+        BinaryOp::BitShiftR => ">>",
+        BinaryOp::ArithShiftR => ">>>",
+        BinaryOp::LessThan => "<",
+        BinaryOp::LessThanEqual => "<=",
+        BinaryOp::GreaterThan => ">",
+        BinaryOp::GreaterThanEqual => ">=",
+        BinaryOp::Equals => "==",
+        BinaryOp::NotEquals => "!=",
+        BinaryOp::BitOr => "|",
+        BinaryOp::BitXor => "^",
+        BinaryOp::BitAnd => "&",
+        BinaryOp::LogicalOr => "or",
+        BinaryOp::LogicalAnd => "and",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trivia::tokenize_lossless;
+    use crate::{parse_component, ParseInput};
+    use claw_common::make_source;
+
+    fn format_source(source: &str) -> String {
+        let src = make_source("test", source);
+        let tokens = tokenize_lossless(src.clone(), source).unwrap();
+        let interner = tokens.interner().clone();
+        let mut input = ParseInput::new(src.clone(), tokens.to_tokens(), interner);
+        let comp = parse_component(src, &mut input).unwrap();
+        format_component(&comp, &tokens)
+    }
+
+    #[test]
+    fn test_format_spacing() {
+        let source = "export   func add(a:u32,b : u32)->u32{return a+b;}\nlet  x:u32=0x10;";
+        let expected = "\
+export func add(a: u32, b: u32) -> u32 {
+    return a + b;
+}
+
+let x: u32 = 0x10;
+";
+        assert_eq!(format_source(source), expected);
+        assert_eq!(format_source(expected), expected);
+    }
+
+    #[test]
+    fn test_format_parentheses() {
+        let cases = [
+            ("(a + b) * c", "(a + b) * c"),
+            ("a + (b * c)", "a + b * c"),
+            ("(a - b) - c", "a - b - c"),
+            ("a - (b - c)", "a - (b - c)"),
+            ("-(a + b)", "-(a + b)"),
+            ("(a or b) and c", "(a or b) and c"),
+        ];
+        for (expression, expected) in cases {
+            let source = format!("let x: u32 = {};", expression);
+            let expected = format!("let x: u32 = {};\n", expected);
+            assert_eq!(format_source(&source), expected);
+        }
+    }
+
+    #[test]
+    fn test_format_comments() {
+        let source = "\
+// The answer
+let x: u32 = 42; // Trailing
+
+func f() {
+    // Leading
+
+    let y = 1;
+    if y > 0 {
+        // Only a comment
+    }
+    // At the end
+}
+// The end
+";
+        assert_eq!(format_source(source), source);
+    }
+
+    #[test]
+    fn test_format_empty_import() {
+        let source = "import {  } from a:b/c;\nimport {} from d:e/f;\nlet x: u32 = 0;";
+        let expected = "\
+import {} from a:b/c;
+import {} from d:e/f;
+let x: u32 = 0;
+";
+        assert_eq!(format_source(source), expected);
+        assert_eq!(format_source(expected), expected);
+    }
+}
@@ -1,58 +1,172 @@
 #![allow(clippy::upper_case_acronyms)]
 
 use logos::Logos;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
 
 use miette::{Diagnostic, SourceSpan};
 use thiserror::Error;
 
-use claw_common::Source;
+use claw_common::{Interner, Source, Symbol};
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub struct TokenData {
     pub token: Token,
     pub span: SourceSpan,
 }
 
-#[derive(Error, Debug, Diagnostic)]
+#[derive(Error, Debug, Diagnostic, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[error("Unable to tokenize input")]
-#[diagnostic()]
+#[diagnostic(code(E0001))]
 pub struct LexerError {
     #[source_code]
     src: Source,
     #[label("Here")]
     span: SourceSpan,
+    /// The invalid tokens after this one
+    #[related]
+    others: Vec<LexerError>,
 }
 
-pub fn tokenize(src: Source, contents: &str) -> Result<Vec<TokenData>, LexerError> {
-    let lexer = Token::lexer(contents);
-
-    lexer
-        .spanned()
-        .map(|(token, span)| match token {
-            Ok(token) => Ok(TokenData {
-                token,
-                span: SourceSpan::from(span),
-            }),
-            Err(_error) => Err(LexerError {
-                src: src.clone(),
-                span: span.into(),
-            }),
+impl LexerError {
+    /// Combine the errors into one that reports all of them, if there are any.
+    pub(crate) fn combine(mut errors: Vec<LexerError>) -> Option<LexerError> {
+        if errors.is_empty() {
+            return None;
+        }
+        let mut first = errors.remove(0);
+        first.others = errors;
+        Some(first)
+    }
+}
+
+/// Tokenize `contents`, failing with every invalid token if there are any.
+///
+/// The text of identifiers and strings is interned into `interner`.
+pub fn tokenize(
+    src: Source,
+    contents: &str,
+    interner: &mut Interner,
+) -> Result<Vec<TokenData>, LexerError> {
+    let (tokens, errors) = tokenize_with_errors(src, contents, interner);
+    match LexerError::combine(errors) {
+        Some(error) => Err(error),
+        None => Ok(tokens),
+    }
+}
+
+/// Tokenize `contents`, producing a [Token::Error] and a [LexerError]
+/// for each run of unrecognized characters instead of stopping at the first.
+pub fn tokenize_with_errors(
+    src: Source,
+    contents: &str,
+    interner: &mut Interner,
+) -> (Vec<TokenData>, Vec<LexerError>) {
+    debug_assert_eq!(src.inner(), contents);
+    let mut stream = TokenStream::new(src);
+    stream.interner = std::mem::take(interner);
+    let tokens = stream.by_ref().collect();
+    *interner = stream.interner;
+    (tokens, stream.errors)
+}
+
+/// Lexes the tokens of a source file one at a time, as they're needed.
+///
+/// Unrecognized characters produce a [Token::Error] and are recorded as a [LexerError].
+#[derive(Debug, Clone)]
+pub struct TokenStream {
+    src: Source,
+    offset: usize,
+    errors: Vec<LexerError>,
+    /// The text of the identifiers and strings lexed so far
+    interner: Interner,
+}
+
+impl TokenStream {
+    pub fn new(src: Source) -> Self {
+        Self {
+            src,
+            offset: 0,
+            errors: Vec::new(),
+            interner: Interner::new(),
+        }
+    }
+
+    /// The interner that the symbols in the tokens lexed so far belong to.
+    pub fn interner(&self) -> &Interner {
+        &self.interner
+    }
+
+    /// The invalid tokens found so far.
+    pub fn errors(&self) -> &[LexerError] {
+        &self.errors
+    }
+
+    /// Take the invalid tokens found so far.
+    pub fn take_errors(&mut self) -> Vec<LexerError> {
+        std::mem::take(&mut self.errors)
+    }
+}
+
+impl Iterator for TokenStream {
+    type Item = TokenData;
+
+    fn next(&mut self) -> Option<TokenData> {
+        let base = self.offset;
+        let interner = std::mem::take(&mut self.interner);
+        let mut lexer = Token::lexer_with_extras(&self.src.inner()[base..], interner);
+        let token = lexer.next();
+        self.interner = std::mem::take(&mut lexer.extras);
+        let token = token?;
+        let start = base + lexer.span().start;
+        let mut end = base + lexer.span().end;
+
+        let token = match token {
+            Ok(token) => token,
+            Err(()) => {
+                // Merge adjacent unrecognized characters into one error,
+                // anything else after them is lexed again by the next call
+                loop {
+                    let mut ahead = Token::lexer(&self.src.inner()[end..]);
+                    match ahead.next() {
+                        Some(Err(())) if ahead.span().start == 0 => {
+                            end += ahead.span().end;
+                        }
+                        _ => break,
+                    }
+                }
+                self.errors.push(LexerError {
+                    src: self.src.clone(),
+                    span: SourceSpan::from(start..end),
+                    others: Vec::new(),
+                });
+                Token::Error
+            }
+        };
+
+        self.offset = end;
+        Some(TokenData {
+            token,
+            span: SourceSpan::from(start..end),
         })
-        .collect()
+    }
 }
 
 /// The Token type for the language.
-#[derive(Logos, Debug, PartialEq, Clone)]
+#[derive(Logos, Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[logos(error = ())]
+#[logos(extras = Interner)]
 #[logos(skip r"[ \t\r\n\f]+")]
 #[logos(skip r"//[^\n]*")]
-#[logos(subpattern word = r"[a-z][a-z0-9]*|[A-Z][A-Z0-9]*")]
+#[logos(subpattern lower = r"[\p{XID_Start}--[A-Z_]]")]
+#[logos(subpattern word = r"(?&lower)[\p{XID_Continue}--[A-Z_]]*|[A-Z][A-Z0-9]*")]
 #[logos(subpattern id = r"%?(?&word)(-(?&word))*")]
 pub enum Token {
     /// Double-quoted string literal
-    #[token("\"", parse_string_literal)]
-    #[token("r", parse_raw_string_literal)]
-    StringLiteral(String),
+    #[token("\"", |lex| parse_string_literal(lex).map(|s| lex.extras.intern(&s)))]
+    #[token("r", |lex| parse_raw_string_literal(lex).map(|s| lex.extras.intern(&s)))]
+    StringLiteral(Symbol),
 
     /// A Decimal number literal
     #[regex(r"[0-9][_0-9]*", |lex| parse_decint_literal(lex.slice()))]
@@ -64,9 +178,9 @@ pub enum Token {
     #[regex(r"[0-9][_0-9]*\.[0-9][_0-9]*", |lex| parse_decfloat_literal(lex.slice()))]
     FloatLiteral(f64),
 
-    /// An Identifier
-    #[regex(r"(?&id)", |lex| lex.slice().to_string())]
-    Identifier(String),
+    /// An Identifier, normalized to NFC
+    #[regex(r"(?&id)", intern_identifier)]
+    Identifier(Symbol),
 
     // Keywords -----------------------------------------
     /// The Export Keyword
@@ -85,6 +199,14 @@ pub enum Token {
     #[token("func")]
     Func,
 
+    /// The Const Keyword
+    #[token("const")]
+    Const,
+
+    /// The Init Keyword
+    #[token("init")]
+    Init,
+
     /// The If Keyword
     #[token("if")]
     If,
@@ -121,6 +243,10 @@ pub enum Token {
     #[token("string")]
     String,
 
+    /// The 128-bit Vector Type Keyword
+    #[token("v128")]
+    V128,
+
     /// The Unsigned 8-bit Integer Type Keyword
     #[token("u8")]
     U8,
@@ -349,19 +475,25 @@ pub enum Token {
     // Not Equals Operator "!="
     #[token("!=")]
     NEQ,
+
+    /// Characters that aren't part of any token
+    Error,
 }
 
 impl std::fmt::Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Token::StringLiteral(s) => write!(f, "\"{}\"", s),
+            // The text of strings and identifiers is only known to their interner
+            Token::StringLiteral(_) => write!(f, "string literal"),
             Token::IntLiteral(i) => write!(f, "{}", i),
             Token::FloatLiteral(float) => write!(f, "{:?}", float),
-            Token::Identifier(ident) => write!(f, "{}", ident),
+            Token::Identifier(_) => write!(f, "identifier"),
             Token::Export => write!(f, "export"),
             Token::Import => write!(f, "import"),
             Token::From => write!(f, "from"),
             Token::Func => write!(f, "func"),
+            Token::Const => write!(f, "const"),
+            Token::Init => write!(f, "init"),
             Token::If => write!(f, "if"),
             Token::For => write!(f, "for"),
             Token::In => write!(f, "in"),
@@ -371,6 +503,7 @@ impl std::fmt::Display for Token {
             Token::Return => write!(f, "return"),
             Token::Result => write!(f, "result"),
             Token::String => write!(f, "string"),
+            Token::V128 => write!(f, "v128"),
             Token::U8 => write!(f, "u8"),
             Token::U16 => write!(f, "u16"),
             Token::U32 => write!(f, "u32"),
@@ -428,10 +561,120 @@ impl std::fmt::Display for Token {
             Token::GTE => write!(f, ">="),
             Token::EQ => write!(f, "=="),
             Token::NEQ => write!(f, "!="),
+            Token::Error => write!(f, "<error>"),
         }
     }
 }
 
+/// The lexical category of a token, e.g. for syntax highlighting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenClass {
+    Keyword,
+    /// The names of built-in types
+    Type,
+    Identifier,
+    Number,
+    String,
+    Operator,
+    Punctuation,
+    Error,
+}
+
+impl Token {
+    pub fn class(&self) -> TokenClass {
+        match self {
+            Token::StringLiteral(_) => TokenClass::String,
+            Token::IntLiteral(_) | Token::FloatLiteral(_) => TokenClass::Number,
+            Token::Identifier(_) => TokenClass::Identifier,
+            Token::Export
+            | Token::Import
+            | Token::From
+            | Token::Func
+            | Token::Const
+            | Token::Init
+            | Token::If
+            | Token::For
+            | Token::In
+            | Token::Loop
+            | Token::Break
+            | Token::Continue
+            | Token::Return
+            | Token::As
+            | Token::At
+            | Token::Let
+            | Token::Mut
+            | Token::True
+            | Token::False => TokenClass::Keyword,
+            Token::Result
+            | Token::String
+            | Token::V128
+            | Token::U8
+            | Token::U16
+            | Token::U32
+            | Token::U64
+            | Token::S8
+            | Token::S16
+            | Token::S32
+            | Token::S64
+            | Token::F32
+            | Token::F64
+            | Token::Bool => TokenClass::Type,
+            Token::LParen
+            | Token::RParen
+            | Token::LBrace
+            | Token::RBrace
+            | Token::LBracket
+            | Token::RBracket
+            | Token::Comma
+            | Token::Dot
+            | Token::Colon
+            | Token::Semicolon
+            | Token::Arrow => TokenClass::Punctuation,
+            Token::Range
+            | Token::Assign
+            | Token::Add
+            | Token::Sub
+            | Token::Mult
+            | Token::Div
+            | Token::Mod
+            | Token::Invert
+            | Token::LogicalAnd
+            | Token::LogicalOr
+            | Token::BitOr
+            | Token::BitAnd
+            | Token::BitXor
+            | Token::BitShiftL
+            | Token::BitShiftR
+            | Token::ArithShiftR
+            | Token::BitOrAssign
+            | Token::BitAndAssign
+            | Token::BitXorAssign
+            | Token::AddAssign
+            | Token::SubAssign
+            | Token::StarAssign
+            | Token::DivAssign
+            | Token::LT
+            | Token::LTE
+            | Token::GT
+            | Token::GTE
+            | Token::EQ
+            | Token::NEQ => TokenClass::Operator,
+            Token::Error => TokenClass::Error,
+        }
+    }
+}
+
+/// Intern an identifier, normalizing it to NFC first if it isn't already.
+fn intern_identifier(lex: &mut logos::Lexer<'_, Token>) -> Symbol {
+    let ident = lex.slice();
+    if is_nfc(ident) {
+        lex.extras.intern(ident)
+    } else {
+        let normalized: String = ident.nfc().collect();
+        lex.extras.intern(&normalized)
+    }
+}
+
 /// Parses a string according to the JSON string format in ECMA-404.
 fn parse_string_literal(lex: &mut logos::Lexer<'_, Token>) -> Option<String> {
     let mut c_iter = lex.remainder().chars();
@@ -579,8 +822,9 @@ mod test {
     fn tokenize_func_declaration() {
         let contents = "func test(a: u32) -> u32";
         let src = make_source("test", contents);
-        let ident_test = Token::Identifier("test".to_owned());
-        let ident_a = Token::Identifier("a".to_owned());
+        let mut interner = Interner::new();
+        let ident_test = Token::Identifier(interner.intern("test"));
+        let ident_a = Token::Identifier(interner.intern("a"));
         let output = vec![
             (Token::Func, SourceSpan::from(0..4)),
             (ident_test, SourceSpan::from(5..9)),
@@ -596,7 +840,7 @@ mod test {
         .map(to_token_data)
         .collect::<Vec<TokenData>>();
 
-        match tokenize(src, contents) {
+        match tokenize(src, contents, &mut interner) {
             Ok(tokens) => assert_eq!(output, tokens),
             Err(_) => panic!("Should not have failed"),
         }
@@ -606,8 +850,9 @@ mod test {
     fn tokenize_let() {
         let contents = r#"let a = "asdf\"";"#;
         let src = make_source("test", contents);
-        let ident_a = Token::Identifier("a".to_owned());
-        let string_asdf = Token::StringLiteral(String::from(r#"asdf""#));
+        let mut interner = Interner::new();
+        let ident_a = Token::Identifier(interner.intern("a"));
+        let string_asdf = Token::StringLiteral(interner.intern(r#"asdf""#));
         let output = vec![
             (Token::Let, SourceSpan::from(0..3)),
             (ident_a, SourceSpan::from(4..5)),
@@ -619,12 +864,62 @@ mod test {
         .map(to_token_data)
         .collect::<Vec<TokenData>>();
 
-        match tokenize(src, contents) {
+        match tokenize(src, contents, &mut interner) {
             Ok(tokens) => assert_eq!(output, tokens),
             Err(_) => panic!("Should not have failed"),
         }
     }
 
+    #[test]
+    fn tokenize_unicode_identifiers() {
+        // The second `café` is written with a combining accent
+        let contents = "größe-λ café cafe\u{301} 変数";
+        let src = make_source("test", contents);
+        let mut interner = Interner::new();
+        let tokens: Vec<Token> = tokenize(src, contents, &mut interner)
+            .unwrap()
+            .into_iter()
+            .map(|data| data.token)
+            .collect();
+        let mut ident = |name: &str| Token::Identifier(interner.intern(name));
+        assert_eq!(
+            tokens,
+            vec![
+                ident("größe-λ"),
+                ident("café"),
+                ident("café"),
+                ident("変数"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_reports_every_error() {
+        let contents = "let $a = 1 @@ 2;";
+        let src = make_source("test", contents);
+        let mut interner = Interner::new();
+        let (tokens, errors) = tokenize_with_errors(src.clone(), contents, &mut interner);
+
+        let error_spans: Vec<SourceSpan> = errors.iter().map(|error| error.span).collect();
+        assert_eq!(
+            error_spans,
+            vec![SourceSpan::from(4..5), SourceSpan::from(11..13)]
+        );
+        assert_eq!(
+            tokens[1],
+            to_token_data((Token::Error, SourceSpan::from(4..5)))
+        );
+        assert_eq!(tokens[2].token, Token::Identifier(interner.intern("a")));
+        assert_eq!(
+            tokens[5],
+            to_token_data((Token::Error, SourceSpan::from(11..13)))
+        );
+
+        let error = tokenize(src, contents, &mut Interner::new()).unwrap_err();
+        assert_eq!(error.span, SourceSpan::from(4..5));
+        assert_eq!(error.others.len(), 1);
+    }
+
     fn to_token_data(d: (Token, SourceSpan)) -> TokenData {
         TokenData {
             token: d.0,
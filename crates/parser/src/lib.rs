@@ -4,28 +4,40 @@
 
 mod component;
 mod expressions;
+mod format;
 mod lexer;
 mod names;
+mod roundtrip;
 mod statements;
+mod trivia;
 mod types;
 
-use std::sync::Arc;
+use std::collections::VecDeque;
 
-use crate::lexer::{Token, TokenData};
+use crate::names::Confusables;
 use ast::{component::Component, Span};
 use claw_ast as ast;
-use claw_common::Source;
+use claw_common::{make_source, Diagnostics, Interner, Source, Suggestion, Symbol};
 
-use miette::{Diagnostic, NamedSource, SourceSpan};
+use miette::{Diagnostic, SourceSpan};
 use thiserror::Error;
 
 use component::parse_component;
 
-pub use lexer::{tokenize, LexerError};
+pub use format::{format_component, print_component};
+pub use lexer::{
+    tokenize, tokenize_with_errors, LexerError, Token, TokenClass, TokenData, TokenStream,
+};
+#[cfg(feature = "arbitrary")]
+pub use roundtrip::check_arbitrary;
+pub use roundtrip::{check_round_trip, RoundTripError};
+pub use trivia::{tokenize_lossless, SyntaxToken, SyntaxTokens, Trivia, TriviaKind};
 
-#[derive(Error, Debug, Diagnostic)]
+#[derive(Error, Debug, Diagnostic, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ParserError {
     #[error("Failed to parse")]
+    #[diagnostic(code(E0101))]
     Base {
         #[source_code]
         src: Source,
@@ -33,87 +45,276 @@ pub enum ParserError {
         span: SourceSpan,
     },
     #[error("{description}")]
+    #[diagnostic(code(E0102))]
     UnexpectedToken {
         #[source_code]
         src: Source,
-        #[label("Found {token:?}")]
+        #[label("Found {token}")]
         span: SourceSpan,
         description: String,
-        token: Token,
+        token: String,
+        #[help]
+        suggestion: Option<Box<Suggestion>>,
     },
     #[error("End of input reached")]
+    #[diagnostic(code(E0103))]
     EndOfInput,
-    #[error("Feature {feature} not supported yet at {token:?}")]
-    NotYetSupported { feature: String, token: Token },
+    #[error("Feature {feature} not supported yet at {token}")]
+    #[diagnostic(code(E0104))]
+    NotYetSupported { feature: String, token: String },
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Lexer(#[from] LexerError),
 }
 
-pub fn parse(src: Source, tokens: Vec<TokenData>) -> Result<Component, ParserError> {
-    let mut input = ParseInput::new(src.clone(), tokens);
-    parse_component(src, &mut input)
+#[derive(Error, Debug, Diagnostic, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[diagnostic(severity(Warning))]
+pub enum ParserWarning {
+    #[error("`export` has no effect on imports")]
+    #[diagnostic(code(W0101))]
+    ExportedImport {
+        #[source_code]
+        src: Source,
+        #[label("This `export` is ignored")]
+        span: SourceSpan,
+    },
+    #[error("`{name}` looks like `{other}`")]
+    #[diagnostic(code(W0102))]
+    ConfusableIdentifier {
+        #[source_code]
+        src: Source,
+        #[label("This is easily confused")]
+        span: SourceSpan,
+        #[label("With this")]
+        other_span: SourceSpan,
+        name: String,
+        other: String,
+    },
+}
+
+/// Parse tokens whose symbols belong to `interner` into a component.
+pub fn parse(
+    src: Source,
+    tokens: Vec<TokenData>,
+    interner: Interner,
+    diagnostics: &mut Diagnostics,
+) -> Result<Component, ParserError> {
+    let mut input = ParseInput::new(src.clone(), tokens, interner);
+    let component = parse_component(src, &mut input)?;
+    for warning in input.warnings {
+        diagnostics.push(warning);
+    }
+    Ok(component)
+}
+
+/// Parse a component, lexing its tokens as the parser needs them
+/// instead of materializing them all first.
+pub fn parse_streaming(
+    src: Source,
+    diagnostics: &mut Diagnostics,
+) -> Result<Component, ParserError> {
+    let mut input = ParseInput::streaming(src.clone());
+    let result = parse_component(src, &mut input);
+    // Invalid tokens are the root cause of any parser error after them
+    if let Some(error) = LexerError::combine(input.take_lexer_errors()) {
+        return Err(error.into());
+    }
+    let component = result?;
+    for warning in input.warnings {
+        diagnostics.push(warning);
+    }
+    Ok(component)
 }
 
 #[derive(Debug, Clone)]
 pub struct ParseInput {
     src: Source,
-    tokens: Vec<TokenData>,
-    index: usize,
+    tokens: Tokens,
+    /// Tokens that have been lexed but not consumed yet
+    lookahead: VecDeque<TokenData>,
+    /// The last two tokens consumed, oldest first
+    previous: VecDeque<TokenData>,
+    confusables: Confusables,
+    warnings: Vec<ParserWarning>,
+}
+
+/// Where the parser gets its tokens from.
+#[derive(Debug, Clone)]
+enum Tokens {
+    Lexed(std::vec::IntoIter<TokenData>, Interner),
+    Streaming(TokenStream),
+}
+
+impl Tokens {
+    fn interner(&self) -> &Interner {
+        match self {
+            Tokens::Lexed(_, interner) => interner,
+            Tokens::Streaming(stream) => stream.interner(),
+        }
+    }
+}
+
+impl Iterator for Tokens {
+    type Item = TokenData;
+
+    fn next(&mut self) -> Option<TokenData> {
+        match self {
+            Tokens::Lexed(tokens, _) => tokens.next(),
+            Tokens::Streaming(stream) => stream.next(),
+        }
+    }
 }
 
 impl ParseInput {
-    pub fn new(src: Source, tokens: Vec<TokenData>) -> Self {
+    pub fn new(src: Source, tokens: Vec<TokenData>, interner: Interner) -> Self {
+        Self::with_tokens(src, Tokens::Lexed(tokens.into_iter(), interner))
+    }
+
+    /// Create an input that lexes `src` lazily while it's parsed.
+    pub fn streaming(src: Source) -> Self {
+        let stream = TokenStream::new(src.clone());
+        Self::with_tokens(src, Tokens::Streaming(stream))
+    }
+
+    fn with_tokens(src: Source, tokens: Tokens) -> Self {
         ParseInput {
             src,
             tokens,
-            index: 0,
+            lookahead: VecDeque::new(),
+            previous: VecDeque::new(),
+            confusables: Confusables::default(),
+            warnings: Vec::new(),
         }
     }
 
-    pub fn unsupported_error(&self, feature: &str) -> ParserError {
-        ParserError::NotYetSupported {
-            feature: feature.to_string(),
-            token: self.tokens[self.index].token.clone(),
+    /// Lex the rest of a streaming input and take every invalid token found in it.
+    pub fn take_lexer_errors(&mut self) -> Vec<LexerError> {
+        match &mut self.tokens {
+            Tokens::Lexed(..) => Vec::new(),
+            Tokens::Streaming(stream) => {
+                stream.for_each(drop);
+                stream.take_errors()
+            }
+        }
+    }
+
+    /// Buffer up to `num` tokens of lookahead, returning whether there were enough.
+    fn fill(&mut self, num: usize) -> bool {
+        while self.lookahead.len() < num {
+            match self.tokens.next() {
+                Some(data) => {
+                    let interner = self.tokens.interner();
+                    if let Some(warning) = self.confusables.check(&self.src, &data, interner) {
+                        self.warnings.push(warning);
+                    }
+                    self.lookahead.push_back(data);
+                }
+                None => return false,
+            }
+        }
+        true
+    }
+
+    /// The text of an identifier or string from this input's tokens.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        self.tokens.interner().resolve(symbol)
+    }
+
+    /// How a token is shown in errors.
+    fn describe(&self, token: Token) -> String {
+        match token {
+            Token::StringLiteral(value) => format!("StringLiteral({:?})", self.resolve(value)),
+            Token::Identifier(ident) => format!("Identifier({:?})", self.resolve(ident)),
+            token => format!("{:?}", token),
+        }
+    }
+
+    pub fn warn(&mut self, warning: ParserWarning) {
+        self.warnings.push(warning);
+    }
+
+    pub fn unsupported_error(&mut self, feature: &str) -> ParserError {
+        match self.peek() {
+            Ok(data) => ParserError::NotYetSupported {
+                feature: feature.to_string(),
+                token: self.describe(data.token),
+            },
+            Err(error) => error,
         }
     }
 
     pub fn unexpected_token(&self, description: &str) -> ParserError {
-        let index = if self.index == 0 {
-            self.index
-        } else {
-            self.index - 1
+        let data = match self.previous.back().or_else(|| self.lookahead.front()) {
+            Some(data) => data,
+            None => return ParserError::EndOfInput,
         };
-        let data = &self.tokens[index];
         ParserError::UnexpectedToken {
             src: self.src.clone(),
             span: data.span,
             description: description.to_string(),
-            token: data.token.clone(),
+            token: self.describe(data.token),
+            suggestion: None,
         }
     }
 
+    /// Suggest inserting an expected token which appears to be missing
+    /// between the previous token and the current one.
+    fn missing_token_suggestion(&self, token: &Token) -> Option<Box<Suggestion>> {
+        let text = match token {
+            Token::Semicolon => ";",
+            _ => return None,
+        };
+        if self.previous.len() < 2 {
+            return None;
+        }
+        let previous = &self.previous[0];
+        let start = previous.span.offset();
+        let offset = start + previous.span.len();
+        let previous_text = &self.src.inner()[start..offset];
+        let message = format!("Add the missing `{}` after `{}`", text, previous_text);
+        Some(Box::new(Suggestion::insert(offset, text, &message)))
+    }
+
     pub fn get_source(&self) -> Source {
         self.src.clone()
     }
 
-    pub fn has(&self, num: usize) -> bool {
-        self.index + num <= self.tokens.len()
+    pub fn has(&mut self, num: usize) -> bool {
+        self.fill(num)
+    }
+
+    pub fn done(&mut self) -> bool {
+        !self.fill(1)
     }
 
-    pub fn done(&self) -> bool {
-        self.index >= self.tokens.len()
+    pub fn peek(&mut self) -> Result<TokenData, ParserError> {
+        self.fill(1);
+        self.lookahead
+            .front()
+            .copied()
+            .ok_or(ParserError::EndOfInput)
     }
 
-    pub fn peek(&self) -> Result<&TokenData, ParserError> {
-        self.tokens.get(self.index).ok_or(ParserError::EndOfInput)
+    pub fn peekn(&mut self, n: usize) -> Option<Token> {
+        self.fill(n + 1);
+        self.lookahead.get(n).map(|t| t.token)
     }
 
-    pub fn peekn(&self, n: usize) -> Option<&Token> {
-        self.tokens.get(self.index + n).map(|t| &t.token)
+    /// Peek at the next token and the one after it, if there is one.
+    pub fn peek_pair(&mut self) -> Result<(Token, Option<Token>), ParserError> {
+        let first = self.peek()?.token;
+        Ok((first, self.peekn(1)))
     }
 
-    pub fn next(&mut self) -> Result<&TokenData, ParserError> {
-        let result = self.tokens.get(self.index);
-        self.index += 1;
-        result.ok_or(ParserError::EndOfInput)
+    pub fn next(&mut self) -> Result<TokenData, ParserError> {
+        self.fill(1);
+        let data = self.lookahead.pop_front().ok_or(ParserError::EndOfInput)?;
+        if self.previous.len() == 2 {
+            self.previous.pop_front();
+        }
+        self.previous.push_back(data);
+        Ok(data)
     }
 
     pub fn assert_next(&mut self, token: Token, description: &str) -> Result<Span, ParserError> {
@@ -121,7 +322,11 @@ impl ParseInput {
         if next.token == token {
             Ok(next.span)
         } else {
-            Err(self.unexpected_token(description))
+            let mut error = self.unexpected_token(description);
+            if let ParserError::UnexpectedToken { suggestion, .. } = &mut error {
+                *suggestion = self.missing_token_suggestion(&token);
+            }
+            Err(error)
         }
     }
 
@@ -134,22 +339,13 @@ impl ParseInput {
         }
         Some(self.next().ok()?.span)
     }
-
-    pub fn slice_next(&mut self, num: usize) -> Result<&[TokenData], ParserError> {
-        if self.has(num) {
-            let result = &self.tokens[self.index..self.index + num];
-            self.index += num;
-            Ok(result)
-        } else {
-            Err(ParserError::EndOfInput)
-        }
-    }
 }
 
 pub fn make_input(source: &str) -> (Source, ParseInput) {
-    let src = Arc::new(NamedSource::new("test", source.to_string()));
-    let tokens = crate::lexer::tokenize(src.clone(), source).unwrap();
-    (src.clone(), ParseInput::new(src, tokens))
+    let src = make_source("test", source);
+    let mut interner = Interner::new();
+    let tokens = crate::lexer::tokenize(src.clone(), source, &mut interner).unwrap();
+    (src.clone(), ParseInput::new(src, tokens, interner))
 }
 
 pub fn make_span(start: usize, len: usize) -> Span {
@@ -159,6 +355,7 @@ pub fn make_span(start: usize, len: usize) -> Span {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use claw_common::make_source;
 
     #[test]
     fn test_peek() {
@@ -175,12 +372,50 @@ mod tests {
     #[test]
     fn test_peekn() {
         let (_src, mut input) = make_input("export func () -> {}");
-        assert_eq!(input.peekn(0).unwrap(), &Token::Export);
-        assert_eq!(input.peekn(1).unwrap(), &Token::Func);
-        assert_eq!(input.peekn(2).unwrap(), &Token::LParen);
+        assert_eq!(input.peekn(0).unwrap(), Token::Export);
+        assert_eq!(input.peekn(1).unwrap(), Token::Func);
+        assert_eq!(input.peekn(2).unwrap(), Token::LParen);
+        input.next().unwrap();
+        assert_eq!(input.peekn(0).unwrap(), Token::Func);
+        assert_eq!(input.peekn(1).unwrap(), Token::LParen);
+        assert_eq!(input.peekn(2).unwrap(), Token::RParen);
+    }
+
+    #[test]
+    fn test_streaming_lookahead() {
+        let src = make_source("test", "export func () -> {}");
+        let mut input = ParseInput::streaming(src);
+        assert_eq!(input.peekn(1).unwrap(), Token::Func);
+        assert_eq!(input.lookahead.len(), 2);
+        input.next().unwrap();
         input.next().unwrap();
-        assert_eq!(input.peekn(0).unwrap(), &Token::Func);
-        assert_eq!(input.peekn(1).unwrap(), &Token::LParen);
-        assert_eq!(input.peekn(2).unwrap(), &Token::RParen);
+        assert_eq!(input.peek().unwrap().token, Token::LParen);
+        assert_eq!(input.lookahead.len(), 1);
+    }
+
+    #[test]
+    fn test_streaming_reports_lexer_errors() {
+        let src = make_source("test", "func $f() {}\nlet # = 1;");
+        let error = parse_streaming(src, &mut Diagnostics::new()).unwrap_err();
+        match error {
+            ParserError::Lexer(error) => assert_eq!(error.related().unwrap().count(), 1),
+            error => panic!("Expected a lexer error, found {:?}", error),
+        }
+    }
+
+    #[test]
+    fn test_missing_semicolon_suggestion() {
+        let source = "let a: u32 = 1\nfunc foo() {}";
+        let (src, mut input) = make_input(source);
+        match parse_component(src, &mut input) {
+            Err(ParserError::UnexpectedToken {
+                suggestion: Some(suggestion),
+                ..
+            }) => {
+                assert_eq!(suggestion.span, make_span(14, 0));
+                assert_eq!(suggestion.apply(source), "let a: u32 = 1;\nfunc foo() {}");
+            }
+            _ => panic!("Expected a missing semicolon suggestion"),
+        }
     }
 }
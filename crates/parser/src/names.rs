@@ -1,14 +1,20 @@
+use std::collections::{HashMap, HashSet};
+
+use claw_common::{Interner, Source, Symbol};
+use miette::SourceSpan;
+use unicode_security::skeleton;
+
 use crate::ast::{Component, NameId, PackageName};
-use crate::lexer::Token;
-use crate::{ParseInput, ParserError};
+use crate::lexer::{Token, TokenData};
+use crate::{ParseInput, ParserError, ParserWarning};
 
 /// Parse an identifier
 pub fn parse_ident(input: &mut ParseInput, comp: &mut Component) -> Result<NameId, ParserError> {
     match &input.peek()?.token {
         Token::Identifier(ident) => {
-            let ident = ident.clone();
+            let ident = *ident;
             let span = input.next().unwrap().span;
-            Ok(comp.new_name(ident, span))
+            Ok(comp.new_name(input.resolve(ident), span))
         }
         _ => {
             input.next().unwrap();
@@ -37,8 +43,52 @@ pub fn parse_interface_name(input: &mut ParseInput) -> Result<(PackageName, Stri
 }
 
 fn parse_identifier(input: &mut ParseInput) -> Result<String, ParserError> {
-    match &input.next()?.token {
-        Token::Identifier(ident) => Ok(ident.clone()),
+    match input.next()?.token {
+        Token::Identifier(ident) => Ok(input.resolve(ident).to_owned()),
         _ => Err(input.unexpected_token("Identifier part of interface name")),
     }
 }
+
+/// Finds non-ASCII identifiers that look like a different identifier
+/// used earlier, using the confusable skeletons from Unicode TR39.
+#[derive(Debug, Clone, Default)]
+pub struct Confusables {
+    /// The first identifier seen with each skeleton
+    seen: HashMap<String, (Symbol, SourceSpan)>,
+    /// The identifiers that have already been checked
+    checked: HashSet<Symbol>,
+}
+
+impl Confusables {
+    /// Check the next token, returning a warning if it's confusable with an earlier identifier.
+    pub fn check(
+        &mut self,
+        src: &Source,
+        data: &TokenData,
+        interner: &Interner,
+    ) -> Option<ParserWarning> {
+        let ident = match data.token {
+            Token::Identifier(ident) => ident,
+            _ => return None,
+        };
+        if !self.checked.insert(ident) {
+            return None;
+        }
+        let name = interner.resolve(ident);
+        let (other, other_span) = *self
+            .seen
+            .entry(skeleton(name).collect())
+            .or_insert((ident, data.span));
+        let other = interner.resolve(other);
+        if other == name || (name.is_ascii() && other.is_ascii()) {
+            return None;
+        }
+        Some(ParserWarning::ConfusableIdentifier {
+            src: src.clone(),
+            span: data.span,
+            other_span,
+            name: name.to_owned(),
+            other: other.to_owned(),
+        })
+    }
+}
@@ -0,0 +1,119 @@
+//! Checking that printing a syntax tree and parsing it again gives back the same tree.
+//!
+//! With the `arbitrary` feature, [check_arbitrary] runs the check on a component
+//! generated from random bytes, for use in fuzz targets and property tests.
+
+use ast::Component;
+use claw_ast as ast;
+use claw_common::{make_source, Diagnostics};
+use thiserror::Error;
+
+use crate::{parse_streaming, print_component, ParserError};
+
+#[derive(Error, Debug, Clone)]
+pub enum RoundTripError {
+    #[error("The printed component doesn't parse: {error}\n{printed}")]
+    Unparsable { printed: String, error: ParserError },
+    #[error("Parsing the printed component gave a different structure\n{printed}")]
+    Changed { printed: String },
+    #[error("Printing the component again gave different code\n{printed}\n{reprinted}")]
+    NotIdempotent { printed: String, reprinted: String },
+}
+
+/// Print `comp` with [print_component], parse the result,
+/// and check it has the same structure and prints the same way.
+///
+/// Returns the printed code.
+pub fn check_round_trip(comp: &Component) -> Result<String, RoundTripError> {
+    let printed = print_component(comp);
+    let src = make_source("printed.claw", &printed);
+    let reparsed = match parse_streaming(src, &mut Diagnostics::new()) {
+        Ok(reparsed) => reparsed,
+        Err(error) => return Err(RoundTripError::Unparsable { printed, error }),
+    };
+    if !comp.same_structure(&reparsed) {
+        return Err(RoundTripError::Changed { printed });
+    }
+    let reprinted = print_component(&reparsed);
+    if reprinted != printed {
+        return Err(RoundTripError::NotIdempotent { printed, reprinted });
+    }
+    Ok(printed)
+}
+
+/// Generate a component from `data` and check that it round trips.
+///
+/// Returns [None] if there wasn't enough data to generate a component.
+#[cfg(feature = "arbitrary")]
+pub fn check_arbitrary(data: &[u8]) -> Option<Result<String, RoundTripError>> {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    let comp = Component::arbitrary(&mut Unstructured::new(data)).ok()?;
+    Some(check_round_trip(&comp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arbitrary::{Arbitrary, Unstructured};
+
+    fn parse_source(source: &str) -> Component {
+        let src = make_source("test", source);
+        parse_streaming(src, &mut Diagnostics::new()).unwrap()
+    }
+
+    #[test]
+    fn test_print_literals() {
+        let comp = parse_source(
+            "let a: u32 = 0x10;\nlet b: f64 = 3.0;\nlet c: f64 = 2.50;\nlet d: string = \"tab\\tquote\\\"\\u0001\";",
+        );
+        let expected = "\
+let a: u32 = 16;
+let b: f64 = 3.0;
+let c: f64 = 2.5;
+let d: string = \"tab\\tquote\\\"\\u0001\";
+";
+        assert_eq!(check_round_trip(&comp).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_print_groups_items() {
+        let comp = parse_source(
+            "func f() {}\n// A comment\nlet x: u32 = 1;\n\n\nimport g: func();\nexport func h() {}",
+        );
+        let expected = "\
+import g: func();
+let x: u32 = 1;
+
+func f() {}
+
+export func h() {}
+";
+        assert_eq!(check_round_trip(&comp).unwrap(), expected);
+    }
+
+    /// A property test over components generated from pseudo-random bytes.
+    #[test]
+    fn test_arbitrary_round_trips() {
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut checked = 0;
+        for _ in 0..200 {
+            let data: Vec<u8> = (0..512)
+                .map(|_| {
+                    // xorshift64
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect();
+            if let Ok(comp) = Component::arbitrary(&mut Unstructured::new(&data)) {
+                if let Err(error) = check_round_trip(&comp) {
+                    panic!("{}", error);
+                }
+                checked += 1;
+            }
+        }
+        assert!(checked > 0);
+    }
+}
@@ -26,9 +26,7 @@ pub fn parse_statement(
     input: &mut ParseInput,
     comp: &mut Component,
 ) -> Result<StatementId, ParserError> {
-    let peek0 = &input.peek()?.token;
-    let peek1 = input.peekn(1);
-    match (peek0, peek1) {
+    match input.peek_pair()? {
         (Token::Return, _) => parse_return(input, comp),
         (Token::Let, _) => parse_let(input, comp),
         (Token::If, _) => parse_if(input, comp),
@@ -53,10 +51,16 @@ fn parse_let(input: &mut ParseInput, comp: &mut Component) -> Result<StatementId
         None => None,
     };
 
-    // Suffix
-    input.assert_next(Token::Assign, "Assignment '='")?;
-    let expression = parse_expression(input, comp)?;
-    let end_span = input.assert_next(Token::Semicolon, "Semicolon ';'")?;
+    // Initial value, which can be left for a later assignment
+    let (expression, end_span) = match input.next_if(Token::Semicolon) {
+        Some(end_span) => (None, end_span),
+        None => {
+            input.assert_next(Token::Assign, "Assignment '=' or semicolon ';'")?;
+            let expression = parse_expression(input, comp)?;
+            let end_span = input.assert_next(Token::Semicolon, "Semicolon ';'")?;
+            (Some(expression), end_span)
+        }
+    };
 
     let statement = ast::Let {
         mutable,
@@ -188,4 +192,17 @@ mod tests {
         let _let_stmt = parse_let(&mut input, &mut comp).unwrap_pretty();
         assert!(input.done());
     }
+
+    #[test]
+    fn test_parse_let_without_value() {
+        let source = "let mut total: u32;";
+        let (src, mut input) = make_input(source);
+        let mut comp = Component::new(src);
+        let let_stmt = parse_let(&mut input, &mut comp).unwrap_pretty();
+        assert!(input.done());
+        match comp.get_statement(let_stmt) {
+            ast::Statement::Let(let_) => assert!(let_.expression.is_none()),
+            _ => panic!("expected a let statement"),
+        }
+    }
 }
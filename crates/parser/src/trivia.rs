@@ -0,0 +1,214 @@
+//! A lossless view of the token stream that keeps whitespace and comments.
+//!
+//! Every token carries the trivia around it, so concatenating the
+//! trivia and tokens in order reproduces the source byte-for-byte.
+//! Trivia on the same line after a token is its trailing trivia,
+//! everything else belongs to the leading trivia of the next token.
+
+use miette::SourceSpan;
+
+use claw_common::{Interner, Source};
+
+use crate::lexer::{tokenize, LexerError, TokenData};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TriviaKind {
+    /// A run of spaces, tabs, and line breaks
+    Whitespace,
+    /// A `//` comment, not including the line break after it
+    Comment,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub span: SourceSpan,
+}
+
+/// A token along with the trivia around it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SyntaxToken {
+    pub data: TokenData,
+    pub leading: Vec<Trivia>,
+    pub trailing: Vec<Trivia>,
+}
+
+/// The tokens of a source file along with all of its trivia.
+#[derive(Debug, Clone)]
+pub struct SyntaxTokens {
+    src: Source,
+    tokens: Vec<SyntaxToken>,
+    /// The trivia after the last token that isn't trailing it
+    end: Vec<Trivia>,
+    /// The text of the identifiers and strings in the tokens
+    interner: Interner,
+}
+
+/// Tokenize `contents`, keeping the whitespace and comments between the tokens.
+pub fn tokenize_lossless(src: Source, contents: &str) -> Result<SyntaxTokens, LexerError> {
+    let mut interner = Interner::new();
+    let tokens = tokenize(src.clone(), contents, &mut interner)?;
+
+    let mut syntax_tokens: Vec<SyntaxToken> = Vec::with_capacity(tokens.len());
+    let mut gap_start = 0;
+    for data in tokens {
+        let trivia = split_trivia(contents, gap_start, data.span.offset());
+        let leading = match syntax_tokens.last_mut() {
+            Some(previous) => attach_trailing(contents, previous, trivia),
+            None => trivia,
+        };
+        gap_start = data.span.offset() + data.span.len();
+        syntax_tokens.push(SyntaxToken {
+            data,
+            leading,
+            trailing: Vec::new(),
+        });
+    }
+    let trivia = split_trivia(contents, gap_start, contents.len());
+    let end = match syntax_tokens.last_mut() {
+        Some(last) => attach_trailing(contents, last, trivia),
+        None => trivia,
+    };
+
+    Ok(SyntaxTokens {
+        src,
+        tokens: syntax_tokens,
+        end,
+        interner,
+    })
+}
+
+/// Split the whitespace and comments between `start` and `end` into trivia.
+fn split_trivia(contents: &str, start: usize, end: usize) -> Vec<Trivia> {
+    let mut trivia = Vec::new();
+    let mut offset = start;
+    while offset < end {
+        let rest = &contents[offset..end];
+        let (kind, len) = if rest.starts_with("//") {
+            (TriviaKind::Comment, rest.find('\n').unwrap_or(rest.len()))
+        } else {
+            (
+                TriviaKind::Whitespace,
+                rest.find("//").unwrap_or(rest.len()),
+            )
+        };
+        trivia.push(Trivia {
+            kind,
+            span: SourceSpan::from(offset..offset + len),
+        });
+        offset += len;
+    }
+    trivia
+}
+
+/// Attach the trivia before the next line break to `token`, returning the rest.
+fn attach_trailing(
+    contents: &str,
+    token: &mut SyntaxToken,
+    mut trivia: Vec<Trivia>,
+) -> Vec<Trivia> {
+    let split = trivia
+        .iter()
+        .position(|trivia| {
+            let (start, len) = (trivia.span.offset(), trivia.span.len());
+            contents[start..start + len].contains('\n')
+        })
+        .unwrap_or(trivia.len());
+    let leading = trivia.split_off(split);
+    token.trailing = trivia;
+    leading
+}
+
+impl SyntaxTokens {
+    pub fn source(&self) -> &Source {
+        &self.src
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SyntaxToken> {
+        self.tokens.iter()
+    }
+
+    /// The trivia after the last token that isn't trailing it.
+    pub fn end_trivia(&self) -> &[Trivia] {
+        &self.end
+    }
+
+    /// The tokens without their trivia, as expected by [crate::parse].
+    pub fn to_tokens(&self) -> Vec<TokenData> {
+        self.tokens.iter().map(|token| token.data).collect()
+    }
+
+    /// The interner that the symbols in the tokens belong to.
+    pub fn interner(&self) -> &Interner {
+        &self.interner
+    }
+
+    /// The text of a token or trivia span.
+    pub fn text(&self, span: SourceSpan) -> &str {
+        &self.src.inner()[span.offset()..span.offset() + span.len()]
+    }
+
+    /// Rebuild the source text from the tokens and trivia.
+    pub fn to_source(&self) -> String {
+        let mut out = String::with_capacity(self.src.inner().len());
+        for token in self.tokens.iter() {
+            for trivia in token.leading.iter() {
+                out.push_str(self.text(trivia.span));
+            }
+            out.push_str(self.text(token.data.span));
+            for trivia in token.trailing.iter() {
+                out.push_str(self.text(trivia.span));
+            }
+        }
+        for trivia in self.end.iter() {
+            out.push_str(self.text(trivia.span));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claw_common::make_source;
+    use pretty_assertions::assert_eq;
+
+    fn lossless(contents: &str) -> SyntaxTokens {
+        let src = make_source("test", contents);
+        tokenize_lossless(src, contents).unwrap()
+    }
+
+    fn texts<'a>(tokens: &'a SyntaxTokens, trivia: &[Trivia]) -> Vec<&'a str> {
+        trivia
+            .iter()
+            .map(|trivia| tokens.text(trivia.span))
+            .collect()
+    }
+
+    #[test]
+    fn test_trivia_attachment() {
+        let contents = "// header\nlet x = 1; // one\n\n  // two\nlet y = 2;\t\n// end";
+        let tokens = lossless(contents);
+        let all: Vec<&SyntaxToken> = tokens.iter().collect();
+
+        assert_eq!(texts(&tokens, &all[0].leading), ["// header", "\n"]);
+        assert_eq!(texts(&tokens, &all[4].trailing), [" ", "// one"]);
+        assert_eq!(texts(&tokens, &all[5].leading), ["\n\n  ", "// two", "\n"]);
+        assert_eq!(texts(&tokens, &all[9].trailing), Vec::<&str>::new());
+        assert_eq!(texts(&tokens, tokens.end_trivia()), ["\t\n", "// end"]);
+        assert_eq!(tokens.to_tokens().len(), 10);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let sources = [
+            "",
+            "   \n// only a comment",
+            "func f() {}\r\n  // crlf\r\n",
+            "let x: u32 = 1;   // trailing   \n\n\n\tlet y: u32 = x;\n\n",
+        ];
+        for contents in sources {
+            assert_eq!(lossless(contents).to_source(), contents);
+        }
+    }
+}
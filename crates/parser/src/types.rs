@@ -23,6 +23,8 @@ pub fn parse_valtype(input: &mut ParseInput, comp: &mut Component) -> Result<Typ
         Token::F64 => ValType::Primitive(PrimitiveType::F64),
         // String
         Token::String => ValType::Primitive(PrimitiveType::String),
+        // Vectors
+        Token::V128 => ValType::Primitive(PrimitiveType::V128),
         _ => return Err(input.unexpected_token("Not a legal type")),
     };
     let name_id = comp.new_type(valtype, span);
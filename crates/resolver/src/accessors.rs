@@ -0,0 +1,82 @@
+use ast::GlobalId;
+use claw_ast as ast;
+
+use crate::{ResolvedComponent, ResolverError};
+
+/// A function exported in place of an exported global.
+///
+/// Components can't export core globals, so each exported global is read
+/// with a `get-<name>` function and, when it's mutable, written with a
+/// `set-<name>` function taking the new value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlobalAccessor {
+    /// The global being accessed
+    pub global: GlobalId,
+    /// Whether the accessor reads or writes the global
+    pub kind: AccessorKind,
+    /// The name the accessor is exported as
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessorKind {
+    Get,
+    Set,
+}
+
+impl ResolvedComponent {
+    /// The accessors of the exported globals, in the order the globals were defined.
+    pub fn global_accessors(&self, comp: &ast::Component) -> Vec<GlobalAccessor> {
+        global_accessors(comp)
+            .filter(|accessor| self.global_vals.contains_key(&accessor.global))
+            .collect()
+    }
+}
+
+fn global_accessors(comp: &ast::Component) -> impl Iterator<Item = GlobalAccessor> + '_ {
+    comp.iter_globals()
+        .filter(|(_, global)| global.exported)
+        .flat_map(move |(id, global)| {
+            let name = comp.get_name(global.ident);
+            let getter = GlobalAccessor {
+                global: id,
+                kind: AccessorKind::Get,
+                name: format!("get-{}", name),
+            };
+            let setter = global.mutable.then(|| GlobalAccessor {
+                global: id,
+                kind: AccessorKind::Set,
+                name: format!("set-{}", name),
+            });
+            std::iter::once(getter).chain(setter)
+        })
+}
+
+/// Check that each accessor can pass its global's value,
+/// and that no function has the name of an accessor.
+pub(crate) fn check_accessors(comp: &ast::Component) -> Result<(), ResolverError> {
+    for accessor in global_accessors(comp) {
+        let global = comp.get_global(accessor.global);
+        // Accessors can only pass primitive values so far
+        if let ast::ValType::Result(_) = comp.get_type(global.type_id) {
+            return Err(ResolverError::UnexportableGlobal {
+                src: comp.source(),
+                span: comp.type_span(global.type_id),
+                name: comp.get_name(global.ident).to_owned(),
+                type_name: comp.get_type(global.type_id).name(comp),
+            });
+        }
+        let function = comp
+            .iter_functions()
+            .find(|(_, function)| comp.get_name(function.ident) == accessor.name);
+        if let Some((_, function)) = function {
+            return Err(ResolverError::AccessorConflict {
+                src: comp.source(),
+                span: comp.name_span(function.ident),
+                global_span: comp.name_span(global.ident),
+                name: accessor.name,
+            });
+        }
+    }
+    Ok(())
+}
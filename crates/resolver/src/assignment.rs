@@ -0,0 +1,132 @@
+use std::collections::{HashMap, HashSet};
+
+use ast::{ExpressionId, NameId, Span, StatementId};
+use claw_ast as ast;
+
+use crate::{FunctionResolver, ItemId, LocalId, ResolverError};
+
+/// Which locals have been assigned a value at some point in a function body.
+#[derive(Clone, Default)]
+struct Assigned {
+    /// Locals assigned on every path to this point
+    definitely: HashSet<LocalId>,
+    /// Locals assigned on at least one path to this point
+    maybe: HashSet<LocalId>,
+    /// Locals that were only assigned inside an `if`, and the condition that skips it
+    conditionally: HashMap<LocalId, Span>,
+}
+
+impl<'ctx> FunctionResolver<'ctx> {
+    /// Check that every local is assigned a value on every path before it's read,
+    /// and that immutable locals are assigned at most once.
+    pub(crate) fn check_assignments(&self) -> Result<(), ResolverError> {
+        let mut assigned = Assigned::default();
+        self.check_block_assignments(&self.function.body, &mut assigned)?;
+        Ok(())
+    }
+
+    /// Update `assigned` with the assignments of a block,
+    /// returning whether the block always returns.
+    fn check_block_assignments(
+        &self,
+        statements: &[StatementId],
+        assigned: &mut Assigned,
+    ) -> Result<bool, ResolverError> {
+        for statement in statements {
+            match self.component.get_statement(*statement) {
+                ast::Statement::Let(let_) => {
+                    if let Some(expression) = let_.expression {
+                        self.check_reads(expression, assigned)?;
+                        self.assign(let_.ident, assigned)?;
+                    }
+                }
+                ast::Statement::Assign(assign) => {
+                    self.check_reads(assign.expression, assigned)?;
+                    self.assign(assign.ident, assigned)?;
+                }
+                ast::Statement::Call(call) => {
+                    for arg in call.args.iter() {
+                        self.check_reads(*arg, assigned)?;
+                    }
+                }
+                ast::Statement::If(if_) => {
+                    self.check_reads(if_.condition, assigned)?;
+                    let mut inner = assigned.clone();
+                    let returns = self.check_block_assignments(&if_.block, &mut inner)?;
+                    // Assignments in a block that always returns never reach the code after it
+                    if !returns {
+                        let condition_span = self.component.expression_span(if_.condition);
+                        for local in inner.definitely.difference(&assigned.definitely) {
+                            assigned
+                                .conditionally
+                                .entry(*local)
+                                .or_insert(condition_span);
+                        }
+                        assigned.maybe.extend(inner.maybe);
+                    }
+                }
+                ast::Statement::Return(return_) => {
+                    if let Some(expression) = return_.expression {
+                        self.check_reads(expression, assigned)?;
+                    }
+                    // The rest of the block is unreachable
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Record an assignment to `ident`, if it's a local.
+    fn assign(&self, ident: NameId, assigned: &mut Assigned) -> Result<(), ResolverError> {
+        let local = match self.bindings.get(&ident) {
+            Some(ItemId::Local(local)) => *local,
+            _ => return Ok(()),
+        };
+        let info = &self.locals[local];
+        if !info.mutable && assigned.maybe.contains(&local) {
+            return Err(self.assigned_to_immutable(ident, info.ident));
+        }
+        assigned.definitely.insert(local);
+        assigned.maybe.insert(local);
+        Ok(())
+    }
+
+    /// Check that the locals read by an expression have been assigned.
+    fn check_reads(
+        &self,
+        expression: ExpressionId,
+        assigned: &Assigned,
+    ) -> Result<(), ResolverError> {
+        match self.component.get_expression(expression) {
+            ast::Expression::Identifier(identifier) => {
+                if let Some(ItemId::Local(local)) = self.bindings.get(&identifier.ident) {
+                    if !assigned.definitely.contains(local) {
+                        let comp = self.component;
+                        let info = &self.locals[*local];
+                        return Err(ResolverError::UnassignedRead {
+                            src: comp.source(),
+                            span: comp.expression_span(expression),
+                            declared_span: comp.name_span(info.ident),
+                            skipped_span: assigned.conditionally.get(local).copied(),
+                            ident: comp.get_name(info.ident).to_owned(),
+                        });
+                    }
+                }
+                Ok(())
+            }
+            ast::Expression::Enum(_) | ast::Expression::Literal(_) => Ok(()),
+            ast::Expression::Call(call) => {
+                for arg in call.args.iter() {
+                    self.check_reads(*arg, assigned)?;
+                }
+                Ok(())
+            }
+            ast::Expression::Unary(unary) => self.check_reads(unary.inner, assigned),
+            ast::Expression::Binary(binary) => {
+                self.check_reads(binary.left, assigned)?;
+                self.check_reads(binary.right, assigned)
+            }
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use claw_ast as ast;
+
+use crate::types::ResolvedType;
+
+/// A function provided by the compiler that every component can call without importing it.
+///
+/// A function, global, or import with the same name shadows a builtin.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Builtin {
+    /// The smaller of two numbers
+    Min,
+    /// The larger of two numbers
+    Max,
+    /// The absolute value of a number
+    Abs,
+    /// A number limited to the range between two others
+    Clamp,
+    /// The length of a string in bytes
+    Len,
+    /// Whether a string is empty
+    IsEmpty,
+    /// A lane-wise operation on a `v128`, which needs the `simd` target feature
+    Simd(SimdOp, Shape),
+}
+
+/// An operation on the lanes of a `v128`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SimdOp {
+    /// A vector with every lane set to the same value
+    Splat,
+    /// The lane-wise sum of two vectors
+    Add,
+    /// The lane-wise difference of two vectors
+    Sub,
+    /// The lane-wise product of two vectors
+    Mul,
+    /// The value of one lane, chosen by an integer literal
+    ExtractLane,
+}
+
+/// How the 128 bits of a `v128` are split into lanes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Shape {
+    I32x4,
+    I64x2,
+    F32x4,
+    F64x2,
+}
+
+impl SimdOp {
+    pub const ALL: [SimdOp; 5] = [
+        SimdOp::Splat,
+        SimdOp::Add,
+        SimdOp::Sub,
+        SimdOp::Mul,
+        SimdOp::ExtractLane,
+    ];
+}
+
+impl Shape {
+    pub const ALL: [Shape; 4] = [Shape::I32x4, Shape::I64x2, Shape::F32x4, Shape::F64x2];
+
+    /// The type of each lane.
+    pub fn lane_type(&self) -> ast::PrimitiveType {
+        match self {
+            Shape::I32x4 => ast::PrimitiveType::S32,
+            Shape::I64x2 => ast::PrimitiveType::S64,
+            Shape::F32x4 => ast::PrimitiveType::F32,
+            Shape::F64x2 => ast::PrimitiveType::F64,
+        }
+    }
+
+    /// The lane type as the parameters of a splat.
+    fn lane_params(&self) -> &'static [ast::PrimitiveType] {
+        match self {
+            Shape::I32x4 => &[ast::PrimitiveType::S32],
+            Shape::I64x2 => &[ast::PrimitiveType::S64],
+            Shape::F32x4 => &[ast::PrimitiveType::F32],
+            Shape::F64x2 => &[ast::PrimitiveType::F64],
+        }
+    }
+
+    /// The number of lanes.
+    pub fn lanes(&self) -> u32 {
+        match self {
+            Shape::I32x4 | Shape::F32x4 => 4,
+            Shape::I64x2 | Shape::F64x2 => 2,
+        }
+    }
+}
+
+/// The types a builtin accepts and returns.
+pub enum BuiltinSignature {
+    /// Every argument and the result have the same numeric type
+    Numeric { params: usize },
+    /// The arguments and result have fixed types
+    Fixed {
+        params: &'static [ast::PrimitiveType],
+        result: ast::PrimitiveType,
+    },
+}
+
+impl Builtin {
+    /// The builtins that work on scalar values.
+    pub const SCALAR: [Builtin; 6] = [
+        Builtin::Min,
+        Builtin::Max,
+        Builtin::Abs,
+        Builtin::Clamp,
+        Builtin::Len,
+        Builtin::IsEmpty,
+    ];
+
+    /// Every builtin, scalar ones first.
+    pub fn all() -> impl Iterator<Item = Builtin> {
+        let simd = Shape::ALL
+            .iter()
+            .flat_map(|shape| SimdOp::ALL.iter().map(move |op| Builtin::Simd(*op, *shape)));
+        Builtin::SCALAR.iter().copied().chain(simd)
+    }
+
+    /// The name the builtin is called by.
+    pub fn name(&self) -> &'static str {
+        use {Shape::*, SimdOp::*};
+        match self {
+            Builtin::Min => "min",
+            Builtin::Max => "max",
+            Builtin::Abs => "abs",
+            Builtin::Clamp => "clamp",
+            Builtin::Len => "len",
+            Builtin::IsEmpty => "is-empty",
+            Builtin::Simd(op, shape) => match (shape, op) {
+                (I32x4, Splat) => "i32x4-splat",
+                (I32x4, Add) => "i32x4-add",
+                (I32x4, Sub) => "i32x4-sub",
+                (I32x4, Mul) => "i32x4-mul",
+                (I32x4, ExtractLane) => "i32x4-extract-lane",
+                (I64x2, Splat) => "i64x2-splat",
+                (I64x2, Add) => "i64x2-add",
+                (I64x2, Sub) => "i64x2-sub",
+                (I64x2, Mul) => "i64x2-mul",
+                (I64x2, ExtractLane) => "i64x2-extract-lane",
+                (F32x4, Splat) => "f32x4-splat",
+                (F32x4, Add) => "f32x4-add",
+                (F32x4, Sub) => "f32x4-sub",
+                (F32x4, Mul) => "f32x4-mul",
+                (F32x4, ExtractLane) => "f32x4-extract-lane",
+                (F64x2, Splat) => "f64x2-splat",
+                (F64x2, Add) => "f64x2-add",
+                (F64x2, Sub) => "f64x2-sub",
+                (F64x2, Mul) => "f64x2-mul",
+                (F64x2, ExtractLane) => "f64x2-extract-lane",
+            },
+        }
+    }
+
+    pub fn signature(&self) -> BuiltinSignature {
+        use ast::PrimitiveType::{Bool, String, U32, V128};
+        match self {
+            Builtin::Min | Builtin::Max => BuiltinSignature::Numeric { params: 2 },
+            Builtin::Abs => BuiltinSignature::Numeric { params: 1 },
+            Builtin::Clamp => BuiltinSignature::Numeric { params: 3 },
+            Builtin::Len => BuiltinSignature::Fixed {
+                params: &[String],
+                result: U32,
+            },
+            Builtin::IsEmpty => BuiltinSignature::Fixed {
+                params: &[String],
+                result: Bool,
+            },
+            Builtin::Simd(op, shape) => {
+                let params: &'static [ast::PrimitiveType] = match op {
+                    SimdOp::Splat => shape.lane_params(),
+                    SimdOp::Add | SimdOp::Sub | SimdOp::Mul => &[V128, V128],
+                    SimdOp::ExtractLane => &[V128, U32],
+                };
+                let result = match op {
+                    SimdOp::ExtractLane => shape.lane_type(),
+                    _ => V128,
+                };
+                BuiltinSignature::Fixed { params, result }
+            }
+        }
+    }
+
+    /// The number of arguments the builtin takes.
+    pub fn arity(&self) -> usize {
+        match self.signature() {
+            BuiltinSignature::Numeric { params } => params,
+            BuiltinSignature::Fixed { params, .. } => params.len(),
+        }
+    }
+
+    /// The signature of the builtin as it would be documented, with `T` for any numeric type.
+    pub fn describe(&self) -> String {
+        let description = match self {
+            Builtin::Min => "func min(a: T, b: T) -> T",
+            Builtin::Max => "func max(a: T, b: T) -> T",
+            Builtin::Abs => "func abs(value: T) -> T",
+            Builtin::Clamp => "func clamp(value: T, min: T, max: T) -> T",
+            Builtin::Len => "func len(s: string) -> u32",
+            Builtin::IsEmpty => "func is-empty(s: string) -> bool",
+            Builtin::Simd(op, shape) => {
+                let lane = shape.lane_type();
+                let (params, result) = match op {
+                    SimdOp::Splat => (format!("value: {}", lane), "v128".to_owned()),
+                    SimdOp::Add | SimdOp::Sub | SimdOp::Mul => {
+                        ("a: v128, b: v128".to_owned(), "v128".to_owned())
+                    }
+                    SimdOp::ExtractLane => ("v: v128, lane: u32".to_owned(), lane.to_string()),
+                };
+                return format!("func {}({}) -> {}", self.name(), params, result);
+            }
+        };
+        description.to_owned()
+    }
+}
+
+/// Whether numeric builtins accept values of this type.
+pub(crate) fn is_numeric(rtype: ResolvedType, comp: &ast::Component) -> bool {
+    match rtype.primitive(comp) {
+        Some(ast::PrimitiveType::Bool | ast::PrimitiveType::String | ast::PrimitiveType::V128)
+        | None => false,
+        Some(_) => true,
+    }
+}
@@ -0,0 +1,725 @@
+use std::collections::HashMap;
+
+use ast::{ExpressionId, GlobalId, NameId, PrimitiveType, Span, StatementId};
+use claw_ast as ast;
+use claw_common::Symbol;
+use miette::LabeledSpan;
+
+use crate::expression::check_integer_literal;
+use crate::function::ResolvedFunction;
+use crate::types::ResolvedType;
+use crate::{ItemId, ResolverError};
+
+/// A value computed at compile time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConstValue {
+    Integer(i128),
+    Float(f64),
+    Bool(bool),
+}
+
+/// How deeply calls to `const` functions can nest before evaluation gives up.
+pub const MAX_CONST_CALL_DEPTH: usize = 64;
+
+/// How many calls to `const` functions evaluating one expression can make before giving up.
+pub const MAX_CONST_CALLS: usize = 10_000;
+
+/// Evaluates constant expressions, following references to immutable globals
+/// and calls to `const` functions.
+pub(crate) struct ConstEvaluator<'ctx> {
+    comp: &'ctx ast::Component,
+    names: Names<'ctx>,
+    /// The types the resolver gave expressions, when evaluating in a resolved function
+    types: Option<&'ctx HashMap<ExpressionId, ResolvedType>>,
+
+    values: HashMap<GlobalId, ConstValue>,
+    /// The globals being evaluated, outermost first,
+    /// with the global read their initializer is currently following
+    in_progress: Vec<(GlobalId, Option<ExpressionId>)>,
+    /// The calls to `const` functions being evaluated, innermost last
+    frames: Vec<Frame>,
+    /// How many calls the outermost call being evaluated has made
+    calls: usize,
+}
+
+/// A call to a `const` function being evaluated.
+struct Frame {
+    /// The parameters and the locals in scope, in the order they were declared
+    locals: Vec<Local>,
+    /// The function's result type
+    result: PrimitiveType,
+}
+
+struct Local {
+    name: Symbol,
+    /// The declared type, if it's known without inference
+    primitive: Option<PrimitiveType>,
+    /// The current value, if it's been assigned one
+    value: Option<ConstValue>,
+}
+
+/// How identifiers are looked up by the evaluator
+enum Names<'ctx> {
+    /// Top-level names, used while evaluating global initializers
+    Component(&'ctx HashMap<String, ItemId>),
+    /// The bindings of a resolved function, whose globals are already evaluated
+    Function(&'ctx HashMap<NameId, ItemId>),
+}
+
+impl<'ctx> ConstEvaluator<'ctx> {
+    pub fn new(comp: &'ctx ast::Component, mappings: &'ctx HashMap<String, ItemId>) -> Self {
+        Self {
+            comp,
+            names: Names::Component(mappings),
+            types: None,
+            values: Default::default(),
+            in_progress: Default::default(),
+            frames: Default::default(),
+            calls: 0,
+        }
+    }
+
+    /// Create an evaluator for expressions in a function body.
+    pub fn for_function(
+        comp: &'ctx ast::Component,
+        function: &'ctx ResolvedFunction,
+        global_vals: &HashMap<GlobalId, ConstValue>,
+    ) -> Self {
+        Self {
+            comp,
+            names: Names::Function(&function.bindings),
+            types: Some(&function.expression_types),
+            values: global_vals.clone(),
+            in_progress: Default::default(),
+            frames: Default::default(),
+            calls: 0,
+        }
+    }
+
+    pub fn finish(self) -> HashMap<GlobalId, ConstValue> {
+        self.values
+    }
+
+    /// Evaluate the initial value of a global.
+    pub fn eval_global(&mut self, id: GlobalId) -> Result<ConstValue, ResolverError> {
+        if let Some(value) = self.values.get(&id) {
+            return Ok(*value);
+        }
+        let global = self.comp.get_global(id);
+        let primitive = match ResolvedType::Defined(global.type_id).primitive(self.comp) {
+            Some(PrimitiveType::V128) => {
+                return Err(ResolverError::NotYetSupported(
+                    "Globals of type `v128`".to_owned(),
+                ))
+            }
+            Some(primitive) => primitive,
+            None => {
+                return Err(ResolverError::NotYetSupported(
+                    "Globals of non-primitive types".to_owned(),
+                ))
+            }
+        };
+
+        self.in_progress.push((id, None));
+        let value = self.eval(global.init_value, Some(primitive))?;
+        self.in_progress.pop();
+
+        self.values.insert(id, value);
+        Ok(value)
+    }
+
+    /// Evaluate an expression whose result is `primitive`, if known.
+    pub fn eval(
+        &mut self,
+        expression: ExpressionId,
+        primitive: Option<PrimitiveType>,
+    ) -> Result<ConstValue, ResolverError> {
+        let value = match self.comp.get_expression(expression) {
+            ast::Expression::Literal(literal) => {
+                self.eval_literal(literal, expression, primitive)?
+            }
+            ast::Expression::Identifier(identifier) => {
+                self.eval_identifier(identifier, expression, primitive)?
+            }
+            ast::Expression::Unary(unary) => self.eval_unary(unary, expression, primitive)?,
+            ast::Expression::Binary(binary) => self.eval_binary(binary, expression, primitive)?,
+            ast::Expression::Call(call) => self.eval_call(call, expression, primitive)?,
+            ast::Expression::Enum(_) => return Err(self.not_const(expression)),
+        };
+        Ok(value)
+    }
+
+    fn eval_literal(
+        &mut self,
+        literal: &ast::Literal,
+        expression: ExpressionId,
+        primitive: Option<PrimitiveType>,
+    ) -> Result<ConstValue, ResolverError> {
+        match (literal, primitive) {
+            (ast::Literal::Integer(value), Some(PrimitiveType::F32 | PrimitiveType::F64)) => {
+                Ok(ConstValue::Float(*value as f64))
+            }
+            (
+                ast::Literal::Integer(_),
+                Some(
+                    expected @ (PrimitiveType::Bool | PrimitiveType::String | PrimitiveType::V128),
+                ),
+            ) => Err(self.type_conflict(expected, "integer", expression)),
+            (ast::Literal::Integer(value), _) => {
+                let value = *value as i128;
+                if let Some(primitive) = primitive {
+                    let span = self.comp.expression_span(expression);
+                    let rtype = ResolvedType::Primitive(primitive);
+                    check_integer_literal(self.comp, value, rtype, span)?;
+                }
+                Ok(ConstValue::Integer(value))
+            }
+            (ast::Literal::Float(value), None | Some(PrimitiveType::F32 | PrimitiveType::F64)) => {
+                Ok(ConstValue::Float(*value))
+            }
+            (ast::Literal::Float(_), Some(expected)) => {
+                Err(self.type_conflict(expected, "float", expression))
+            }
+            (ast::Literal::String(_), _) => Err(self.not_const(expression)),
+        }
+    }
+
+    fn eval_identifier(
+        &mut self,
+        identifier: &ast::Identifier,
+        expression: ExpressionId,
+        primitive: Option<PrimitiveType>,
+    ) -> Result<ConstValue, ResolverError> {
+        let name = self.comp.get_name(identifier.ident);
+        if let Some(frame) = self.frames.last() {
+            let symbol = self.comp.get_symbol(identifier.ident);
+            if let Some(local) = frame.locals.iter().rev().find(|local| local.name == symbol) {
+                return match local.value {
+                    Some(value) => self.convert(value, primitive.or(local.primitive), expression),
+                    None => Err(self.not_const(expression)),
+                };
+            }
+        }
+        let global_id = match self.item(identifier.ident) {
+            Some(ItemId::Global(global_id)) => global_id,
+            _ => return Err(self.not_const(expression)),
+        };
+        let global = self.comp.get_global(global_id);
+        if global.mutable {
+            return Err(self.not_const(expression));
+        }
+        if let Some(start) = self.in_progress.iter().position(|(id, _)| *id == global_id) {
+            return Err(self.global_cycle(start, expression, name));
+        }
+        if let Some(primitive) = primitive {
+            let global_type = ResolvedType::Defined(global.type_id);
+            if global_type.primitive(self.comp) != Some(primitive) {
+                let found = self.comp.get_type(global.type_id).name(self.comp);
+                return Err(self.type_conflict(primitive, &found, expression));
+            }
+        }
+        if let Some((_, read)) = self.in_progress.last_mut() {
+            *read = Some(expression);
+        }
+        self.eval_global(global_id)
+    }
+
+    /// Report a read of the global at `start` in the stack of globals being evaluated,
+    /// labeling the read that each initializer in the cycle follows to the next.
+    fn global_cycle(&self, start: usize, expression: ExpressionId, ident: &str) -> ResolverError {
+        let cycle = &self.in_progress[start..];
+        let steps = cycle.iter().zip(cycle.iter().skip(1));
+        let labels = steps
+            .filter_map(|((from, read), (to, _))| {
+                let from = self.comp.get_name(self.comp.get_global(*from).ident);
+                let to = self.comp.get_name(self.comp.get_global(*to).ident);
+                let label = format!("`{}` is initialized using `{}`", from, to);
+                read.map(|read| LabeledSpan::at(self.comp.expression_span(read), label))
+            })
+            .collect();
+        ResolverError::GlobalCycle {
+            src: self.comp.source(),
+            span: self.comp.expression_span(expression),
+            cycle: labels,
+            ident: ident.to_owned(),
+        }
+    }
+
+    fn eval_unary(
+        &mut self,
+        unary: &ast::UnaryExpression,
+        expression: ExpressionId,
+        primitive: Option<PrimitiveType>,
+    ) -> Result<ConstValue, ResolverError> {
+        let ast::UnaryOp::Negate = unary.op;
+        // Check negated literals as a whole so that e.g. `-128` fits in `s8`
+        if let ast::Expression::Literal(ast::Literal::Integer(value)) =
+            self.comp.get_expression(unary.inner)
+        {
+            if let Some(PrimitiveType::F32 | PrimitiveType::F64) = primitive {
+                return Ok(ConstValue::Float(-(*value as f64)));
+            }
+            if let Some(
+                expected @ (PrimitiveType::Bool | PrimitiveType::String | PrimitiveType::V128),
+            ) = primitive
+            {
+                return Err(self.type_conflict(expected, "integer", unary.inner));
+            }
+            let value = -(*value as i128);
+            if let Some(primitive) = primitive {
+                let span = self.comp.expression_span(expression);
+                let rtype = ResolvedType::Primitive(primitive);
+                check_integer_literal(self.comp, value, rtype, span)?;
+            }
+            return Ok(ConstValue::Integer(value));
+        }
+        match self.eval(unary.inner, primitive)? {
+            ConstValue::Integer(value) => self.integer(-value, primitive, expression),
+            ConstValue::Float(value) => Ok(self.float(-value, primitive)),
+            ConstValue::Bool(_) => Err(self.not_const(expression)),
+        }
+    }
+
+    fn eval_binary(
+        &mut self,
+        binary: &ast::BinaryExpression,
+        expression: ExpressionId,
+        primitive: Option<PrimitiveType>,
+    ) -> Result<ConstValue, ResolverError> {
+        use ast::BinaryOp as Op;
+
+        if binary.is_relation() {
+            if let Some(expected) = primitive.filter(|p| *p != PrimitiveType::Bool) {
+                return Err(self.type_conflict(expected, "bool", expression));
+            }
+            let (left, right) = self.operand_types(binary);
+            let left = self.eval(binary.left, left)?;
+            let right = self.eval(binary.right, right)?;
+            let result = match (left, right) {
+                (ConstValue::Integer(l), ConstValue::Integer(r)) => compare(binary.op, l, r),
+                (ConstValue::Float(l), ConstValue::Float(r)) => compare(binary.op, l, r),
+                (ConstValue::Integer(l), ConstValue::Float(r)) => compare(binary.op, l as f64, r),
+                (ConstValue::Float(l), ConstValue::Integer(r)) => compare(binary.op, l, r as f64),
+                (ConstValue::Bool(l), ConstValue::Bool(r)) => compare(binary.op, l, r),
+                _ => return Err(self.not_const(expression)),
+            };
+            return Ok(ConstValue::Bool(result));
+        }
+
+        let left = self.eval(binary.left, primitive)?;
+        // Like at runtime, the right operand isn't evaluated if the left one decides the result
+        if let (Op::LogicalAnd, ConstValue::Bool(false)) | (Op::LogicalOr, ConstValue::Bool(true)) =
+            (binary.op, left)
+        {
+            return Ok(left);
+        }
+        let right = self.eval(binary.right, primitive)?;
+        match (left, right) {
+            (ConstValue::Integer(l), ConstValue::Integer(r)) => {
+                let result = match binary.op {
+                    Op::Add => l.checked_add(r),
+                    Op::Subtract => l.checked_sub(r),
+                    Op::Multiply => l.checked_mul(r),
+                    Op::Divide | Op::Modulo if r == 0 => {
+                        return Err(ResolverError::DivisionByZero {
+                            src: self.comp.source(),
+                            span: self.comp.expression_span(binary.right),
+                            operation: match binary.op {
+                                Op::Divide => "divide",
+                                _ => "calculate the remainder",
+                            },
+                        })
+                    }
+                    Op::Divide => l.checked_div(r),
+                    Op::Modulo => l.checked_rem(r),
+                    Op::BitAnd => Some(l & r),
+                    Op::BitOr => Some(l | r),
+                    Op::BitXor => Some(l ^ r),
+                    Op::BitShiftL | Op::BitShiftR | Op::ArithShiftR => {
+                        return self.eval_shift(binary.op, l, r, primitive, expression)
+                    }
+                    _ => return Err(self.not_const(expression)),
+                };
+                match result {
+                    Some(result) => self.integer(result, primitive, expression),
+                    None => Err(self.overflow(primitive, expression)),
+                }
+            }
+            (ConstValue::Float(l), ConstValue::Float(r)) => {
+                let result = match binary.op {
+                    Op::Add => l + r,
+                    Op::Subtract => l - r,
+                    Op::Multiply => l * r,
+                    Op::Divide => l / r,
+                    _ => return Err(self.not_const(expression)),
+                };
+                Ok(self.float(result, primitive))
+            }
+            (ConstValue::Bool(l), ConstValue::Bool(r)) => {
+                let result = match binary.op {
+                    Op::LogicalAnd | Op::BitAnd => l & r,
+                    Op::LogicalOr | Op::BitOr => l | r,
+                    Op::BitXor => l ^ r,
+                    _ => return Err(self.not_const(expression)),
+                };
+                Ok(ConstValue::Bool(result))
+            }
+            _ => Err(self.not_const(expression)),
+        }
+    }
+
+    /// Evaluate a call to a `const` function by running its body.
+    fn eval_call(
+        &mut self,
+        call: &ast::Call,
+        expression: ExpressionId,
+        primitive: Option<PrimitiveType>,
+    ) -> Result<ConstValue, ResolverError> {
+        let function = match self.item(call.ident) {
+            Some(ItemId::Function(function)) => self.comp.get_function(function),
+            _ => return Err(self.not_const(expression)),
+        };
+        let result = function
+            .results
+            .and_then(|results| ResolvedType::Defined(results).primitive(self.comp));
+        let result = match result {
+            Some(result) if function.constant && function.params.len() == call.args.len() => result,
+            _ => return Err(self.not_const(expression)),
+        };
+        if let Some(expected) = primitive {
+            if result != expected && !result.widens_to(expected) {
+                return Err(self.type_conflict(expected, &result.to_string(), expression));
+            }
+        }
+
+        if self.frames.is_empty() {
+            self.calls = 0;
+        }
+        self.calls += 1;
+        if self.frames.len() >= MAX_CONST_CALL_DEPTH || self.calls > MAX_CONST_CALLS {
+            return Err(ResolverError::ConstEvalLimit {
+                src: self.comp.source(),
+                span: self.comp.expression_span(expression),
+                max_depth: MAX_CONST_CALL_DEPTH,
+                max_calls: MAX_CONST_CALLS,
+            });
+        }
+
+        let mut locals = Vec::with_capacity(call.args.len());
+        for ((name, type_id), arg) in function.params.iter().zip(call.args.iter()) {
+            let param = ResolvedType::Defined(*type_id).primitive(self.comp);
+            locals.push(Local {
+                name: self.comp.get_symbol(*name),
+                primitive: param,
+                value: Some(self.eval(*arg, param)?),
+            });
+        }
+        self.frames.push(Frame { locals, result });
+        let returned = self.exec_block(&function.body);
+        self.frames.pop();
+        match returned? {
+            Some(value) => self.convert(value, primitive, expression),
+            None => Err(self.not_const(expression)),
+        }
+    }
+
+    /// Run the statements of a `const` function body,
+    /// returning the returned value if a `return` is reached.
+    fn exec_block(&mut self, block: &[StatementId]) -> Result<Option<ConstValue>, ResolverError> {
+        let scope = self.frame().locals.len();
+        let mut returned = None;
+        for statement in block {
+            returned = self.exec(*statement)?;
+            if returned.is_some() {
+                break;
+            }
+        }
+        self.frame().locals.truncate(scope);
+        Ok(returned)
+    }
+
+    fn exec(&mut self, statement: StatementId) -> Result<Option<ConstValue>, ResolverError> {
+        let span = self.comp.statement_span(statement);
+        match self.comp.get_statement(statement) {
+            ast::Statement::Let(let_) => {
+                let primitive = let_
+                    .annotation
+                    .and_then(|annotation| ResolvedType::Defined(annotation).primitive(self.comp));
+                let value = match let_.expression {
+                    Some(expression) => Some(self.eval(expression, primitive)?),
+                    None => None,
+                };
+                let name = self.comp.get_symbol(let_.ident);
+                self.frame().locals.push(Local {
+                    name,
+                    primitive,
+                    value,
+                });
+            }
+            ast::Statement::Assign(assign) => {
+                let name = self.comp.get_symbol(assign.ident);
+                let index = self
+                    .frame()
+                    .locals
+                    .iter()
+                    .rposition(|local| local.name == name);
+                // Assigning to a global would have an effect outside the call
+                let index = index.ok_or_else(|| self.not_const_at(span))?;
+                let primitive = self.frame().locals[index].primitive;
+                let value = self.eval(assign.expression, primitive)?;
+                self.frame().locals[index].value = Some(value);
+            }
+            ast::Statement::If(if_) => match self.eval(if_.condition, Some(PrimitiveType::Bool))? {
+                ConstValue::Bool(true) => return self.exec_block(&if_.block),
+                ConstValue::Bool(false) => {}
+                _ => return Err(self.not_const(if_.condition)),
+            },
+            ast::Statement::Return(ast::Return {
+                expression: Some(expression),
+            }) => {
+                let result = self.frame().result;
+                return Ok(Some(self.eval(*expression, Some(result))?));
+            }
+            // Calls made for their effects can't be made at compile time
+            ast::Statement::Call(_) | ast::Statement::Return(_) => {
+                return Err(self.not_const_at(span))
+            }
+        }
+        Ok(None)
+    }
+
+    /// The innermost call being evaluated.
+    fn frame(&mut self) -> &mut Frame {
+        self.frames
+            .last_mut()
+            .expect("Statements are only run in calls")
+    }
+
+    /// The item a name outside of a local refers to.
+    fn item(&self, ident: NameId) -> Option<ItemId> {
+        let name = self.comp.get_name(ident);
+        // Names in `const` function bodies refer to top-level items, which only
+        // the component's mappings, and not the bindings of the function where
+        // evaluation started, know about
+        if !self.frames.is_empty() {
+            let global = self
+                .comp
+                .iter_globals()
+                .find(|(_, global)| self.comp.get_name(global.ident) == name)
+                .map(|(id, _)| ItemId::Global(id));
+            let function = || {
+                self.comp
+                    .iter_functions()
+                    .find(|(_, function)| self.comp.get_name(function.ident) == name)
+                    .map(|(id, _)| ItemId::Function(id))
+            };
+            return global.or_else(function);
+        }
+        match self.names {
+            Names::Component(mappings) => mappings.get(name).copied(),
+            Names::Function(bindings) => bindings.get(&ident).copied(),
+        }
+    }
+
+    /// The types the operands of a comparison are evaluated as.
+    ///
+    /// In a resolved function these are the types the resolver gave them. Otherwise,
+    /// like in the resolver, both take the type of whichever operand has a known type,
+    /// or else the type that the first literal in the operands defaults to.
+    fn operand_types(
+        &self,
+        binary: &ast::BinaryExpression,
+    ) -> (Option<PrimitiveType>, Option<PrimitiveType>) {
+        // Only the function where evaluation started was resolved, not the `const` functions it calls
+        if let (Some(types), true) = (self.types, self.frames.is_empty()) {
+            let resolved = |expression| {
+                let rtype: &ResolvedType = types.get(&expression)?;
+                rtype.primitive(self.comp)
+            };
+            return (resolved(binary.left), resolved(binary.right));
+        }
+        let primitive = self
+            .known_type(binary.left)
+            .or_else(|| self.known_type(binary.right))
+            .or_else(|| self.literal_type(binary.left))
+            .or_else(|| self.literal_type(binary.right));
+        (primitive, primitive)
+    }
+
+    /// The type of an expression, if it's known without inference.
+    fn known_type(&self, expression: ExpressionId) -> Option<PrimitiveType> {
+        match self.comp.get_expression(expression) {
+            ast::Expression::Identifier(identifier) => {
+                if let Some(frame) = self.frames.last() {
+                    let symbol = self.comp.get_symbol(identifier.ident);
+                    if let Some(local) =
+                        frame.locals.iter().rev().find(|local| local.name == symbol)
+                    {
+                        return local.primitive;
+                    }
+                }
+                match self.item(identifier.ident)? {
+                    ItemId::Global(global) => {
+                        let global = self.comp.get_global(global);
+                        ResolvedType::Defined(global.type_id).primitive(self.comp)
+                    }
+                    _ => None,
+                }
+            }
+            ast::Expression::Call(call) => match self.item(call.ident)? {
+                ItemId::Function(function) => {
+                    let results = self.comp.get_function(function).results?;
+                    ResolvedType::Defined(results).primitive(self.comp)
+                }
+                _ => None,
+            },
+            ast::Expression::Unary(unary) => self.known_type(unary.inner),
+            ast::Expression::Binary(binary) if binary.is_relation() => Some(PrimitiveType::Bool),
+            ast::Expression::Binary(binary) => self
+                .known_type(binary.left)
+                .or_else(|| self.known_type(binary.right)),
+            ast::Expression::Literal(_) | ast::Expression::Enum(_) => None,
+        }
+    }
+
+    /// The type the first numeric literal in an expression defaults to.
+    ///
+    /// Integers default to `s32` and floats to `f64`.
+    fn literal_type(&self, expression: ExpressionId) -> Option<PrimitiveType> {
+        match self.comp.get_expression(expression) {
+            ast::Expression::Literal(ast::Literal::Integer(_)) => Some(PrimitiveType::S32),
+            ast::Expression::Literal(ast::Literal::Float(_)) => Some(PrimitiveType::F64),
+            ast::Expression::Unary(unary) => self.literal_type(unary.inner),
+            ast::Expression::Binary(binary) if !binary.is_relation() => self
+                .literal_type(binary.left)
+                .or_else(|| self.literal_type(binary.right)),
+            _ => None,
+        }
+    }
+
+    /// Convert a value computed without knowing its type to the type it's used as,
+    /// the same way literals are.
+    fn convert(
+        &self,
+        value: ConstValue,
+        primitive: Option<PrimitiveType>,
+        expression: ExpressionId,
+    ) -> Result<ConstValue, ResolverError> {
+        match (value, primitive) {
+            (ConstValue::Integer(value), Some(PrimitiveType::F32 | PrimitiveType::F64)) => {
+                Ok(self.float(value as f64, primitive))
+            }
+            (ConstValue::Integer(value), _) => self.integer(value, primitive, expression),
+            (ConstValue::Float(value), _) => Ok(self.float(value, primitive)),
+            (ConstValue::Bool(_), _) => Ok(value),
+        }
+    }
+
+    fn eval_shift(
+        &mut self,
+        op: ast::BinaryOp,
+        value: i128,
+        amount: i128,
+        primitive: Option<PrimitiveType>,
+        expression: ExpressionId,
+    ) -> Result<ConstValue, ResolverError> {
+        // Shifts depend on the bit width so the type must be known
+        let (min, max) = match primitive.and_then(|p| p.integer_range()) {
+            Some(range) => range,
+            None => return Err(self.not_const(expression)),
+        };
+        let modulus = max - min + 1;
+        let bits = modulus.trailing_zeros() as i128;
+        if !(0..bits).contains(&amount) {
+            return Err(self.overflow(primitive, expression));
+        }
+        let result = match op {
+            ast::BinaryOp::BitShiftL => value << amount,
+            ast::BinaryOp::BitShiftR => value.rem_euclid(modulus) >> amount,
+            _ => value >> amount,
+        };
+        // Reinterpret the shifted bits as the original type
+        let mut result = result.rem_euclid(modulus);
+        if result > max {
+            result -= modulus;
+        }
+        Ok(ConstValue::Integer(result))
+    }
+
+    /// Check that an intermediate integer result fits in its type.
+    fn integer(
+        &self,
+        value: i128,
+        primitive: Option<PrimitiveType>,
+        expression: ExpressionId,
+    ) -> Result<ConstValue, ResolverError> {
+        if let Some((min, max)) = primitive.and_then(|p| p.integer_range()) {
+            if value < min || value > max {
+                return Err(self.overflow(primitive, expression));
+            }
+        }
+        Ok(ConstValue::Integer(value))
+    }
+
+    /// Round an intermediate float result to the precision of its type.
+    fn float(&self, value: f64, primitive: Option<PrimitiveType>) -> ConstValue {
+        match primitive {
+            Some(PrimitiveType::F32) => ConstValue::Float(value as f32 as f64),
+            _ => ConstValue::Float(value),
+        }
+    }
+
+    fn not_const(&self, expression: ExpressionId) -> ResolverError {
+        self.not_const_at(self.comp.expression_span(expression))
+    }
+
+    fn not_const_at(&self, span: Span) -> ResolverError {
+        ResolverError::NotConst {
+            src: self.comp.source(),
+            span,
+        }
+    }
+
+    fn overflow(
+        &self,
+        primitive: Option<PrimitiveType>,
+        expression: ExpressionId,
+    ) -> ResolverError {
+        match primitive {
+            Some(primitive) => ResolverError::ConstOverflow {
+                src: self.comp.source(),
+                span: self.comp.expression_span(expression),
+                primitive,
+            },
+            // Without a type there's no range to report, only that the value is too large to compute
+            None => self.not_const(expression),
+        }
+    }
+
+    fn type_conflict(
+        &self,
+        expected: PrimitiveType,
+        found: &str,
+        expression: ExpressionId,
+    ) -> ResolverError {
+        ResolverError::TypeConflict {
+            src: self.comp.source(),
+            expected_span: None,
+            found_span: self.comp.expression_span(expression),
+            expected: expected.to_string(),
+            found: found.to_owned(),
+        }
+    }
+}
+
+fn compare<T: PartialOrd>(op: ast::BinaryOp, left: T, right: T) -> bool {
+    match op {
+        ast::BinaryOp::LessThan => left < right,
+        ast::BinaryOp::LessThanEqual => left <= right,
+        ast::BinaryOp::GreaterThan => left > right,
+        ast::BinaryOp::GreaterThanEqual => left >= right,
+        ast::BinaryOp::Equals => left == right,
+        ast::BinaryOp::NotEquals => left != right,
+        _ => unreachable!("Not a relation"),
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use ast::{FunctionId, GlobalId};
+use claw_ast as ast;
+
+use crate::{ItemId, ResolvedComponent};
+
+/// A function or global removed by [eliminate_dead_items].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum DeadItem {
+    Function(String),
+    Global(String),
+}
+
+impl fmt::Display for DeadItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeadItem::Function(name) => write!(f, "function `{}`", name),
+            DeadItem::Global(name) => write!(f, "global `{}`", name),
+        }
+    }
+}
+
+/// Remove the functions and globals that can't be reached from any export
+/// or from the `init` block.
+///
+/// Removed items are dropped from the [ResolvedComponent] so that no code is
+/// generated for them, and are returned in the order they were defined.
+pub fn eliminate_dead_items(comp: &ast::Component, rcomp: &mut ResolvedComponent) -> Vec<DeadItem> {
+    let mut live_funcs: HashSet<FunctionId> = HashSet::new();
+    let mut live_globals: HashSet<GlobalId> = HashSet::new();
+
+    let mut queue: Vec<FunctionId> = Vec::new();
+    for (id, function) in comp.iter_functions() {
+        if function.exported || comp.is_init(id) {
+            queue.push(id);
+        }
+    }
+    for (id, global) in comp.iter_globals() {
+        if global.exported {
+            live_globals.insert(id);
+        }
+    }
+
+    while let Some(id) = queue.pop() {
+        if !live_funcs.insert(id) {
+            continue;
+        }
+        for item in rcomp.funcs[&id].bindings.values() {
+            match item {
+                ItemId::Function(function) => queue.push(*function),
+                ItemId::Global(global) => {
+                    live_globals.insert(*global);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut dead = Vec::new();
+    for (id, function) in comp.iter_functions() {
+        if !live_funcs.contains(&id) {
+            rcomp.funcs.remove(&id);
+            let name = comp.get_name(function.ident).to_owned();
+            dead.push(DeadItem::Function(name));
+        }
+    }
+    for (id, global) in comp.iter_globals() {
+        if !live_globals.contains(&id) {
+            rcomp.global_vals.remove(&id);
+            let name = comp.get_name(global.ident).to_owned();
+            dead.push(DeadItem::Global(name));
+        }
+    }
+    dead
+}
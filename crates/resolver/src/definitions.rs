@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+
+use ast::NameId;
+use claw_ast as ast;
+
+use crate::ResolverError;
+
+/// Check that no two imports, globals, or functions define the same name.
+///
+/// The definition that comes second in the source is reported.
+pub(crate) fn check_duplicate_definitions(comp: &ast::Component) -> Result<(), ResolverError> {
+    let mut definitions = defined_names(comp);
+    definitions.sort_by_key(|ident| comp.name_span(*ident).offset());
+
+    let mut first_definitions: HashMap<&str, NameId> = HashMap::new();
+    for ident in definitions {
+        let name = comp.get_name(ident);
+        if let Some(first) = first_definitions.insert(name, ident) {
+            return Err(ResolverError::DuplicateDefinition {
+                src: comp.source(),
+                first_span: comp.name_span(first),
+                span: comp.name_span(ident),
+                ident: name.to_owned(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The names defined by the top level items of a component.
+fn defined_names(comp: &ast::Component) -> Vec<NameId> {
+    let mut names = Vec::new();
+    for (_, import) in comp.iter_imports() {
+        match import {
+            ast::Import::Plain(import) => names.push(import.alias.unwrap_or(import.ident)),
+            ast::Import::ImportFrom(import) => names.extend(
+                import
+                    .items
+                    .iter()
+                    .map(|(ident, alias)| alias.unwrap_or(*ident)),
+            ),
+        }
+    }
+    names.extend(comp.iter_globals().map(|(_, global)| global.ident));
+    names.extend(comp.iter_functions().map(|(_, function)| function.ident));
+    names
+}
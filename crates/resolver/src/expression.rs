@@ -1,6 +1,9 @@
-use ast::ExpressionId;
+use ast::{ExpressionId, Span};
 use claw_ast as ast;
+use claw_common::{closest_match, Suggestion};
 
+use crate::builtins::{is_numeric, Builtin, BuiltinSignature, Shape, SimdOp};
+use crate::imports::{ImportResolver, ImportType};
 use crate::types::{ResolvedType, RESOLVED_BOOL};
 use crate::{FunctionResolver, ItemId, ResolverError};
 
@@ -14,6 +17,7 @@ pub(crate) trait ResolveExpression {
     ///
     /// Implementations may
     /// * Call [FunctionResolver::set_expr_type] if the type of an expression is known.
+    /// * Call [FunctionResolver::require_type] if where an expression is used requires a type.
     fn setup_resolve(
         &self,
         expression: ExpressionId,
@@ -101,12 +105,22 @@ impl ResolveExpression for ast::Identifier {
                 let global = resolver.component.get_global(global);
                 resolver.set_expr_type(expression, ResolvedType::Defined(global.type_id));
             }
+            ItemId::ImportValue(import_func) => {
+                let rtype = resolver.imports.funcs[import_func].results.unwrap();
+                resolver.set_expr_type(expression, rtype);
+            }
             ItemId::Param(param) => {
                 let param_type = *resolver.params.get(param).unwrap();
                 resolver.set_expr_type(expression, ResolvedType::Defined(param_type));
             }
-            ItemId::Local(local) => resolver.use_local(local, expression),
-            _ => {}
+            ItemId::Local(local) => {
+                resolver.read_locals.insert(local);
+                resolver.use_local(local, expression);
+            }
+            ItemId::Function(_) | ItemId::ImportFunc(_) | ItemId::Builtin(_) | ItemId::Type(_) => {
+                let span = resolver.component.expression_span(expression);
+                return Err(resolver.not_a_value(self.ident, span));
+            }
         }
         Ok(())
     }
@@ -143,6 +157,97 @@ impl ResolveExpression for ast::Literal {
         }
         Ok(())
     }
+
+    fn on_resolved(
+        &self,
+        rtype: ResolvedType,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        reject_vector(rtype, expression, resolver)?;
+        check_literal_kind(self, rtype, expression, resolver)?;
+        if let ast::Literal::Integer(value) = self {
+            let comp = resolver.component;
+            let mut value = *value as i128;
+            let mut span = comp.expression_span(expression);
+            // Check negated literals as a whole so that e.g. `-128` fits in `s8`
+            if let Some(parent) = resolver.expr_parent_map.get(&expression) {
+                if let ast::Expression::Unary(ast::UnaryExpression {
+                    op: ast::UnaryOp::Negate,
+                    ..
+                }) = comp.get_expression(*parent)
+                {
+                    value = -value;
+                    span = comp.expression_span(*parent);
+                }
+            }
+            check_integer_literal(comp, value, rtype, span)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check that an integer or float literal was resolved to an integer or float type.
+fn check_literal_kind(
+    literal: &ast::Literal,
+    rtype: ResolvedType,
+    expression: ExpressionId,
+    resolver: &FunctionResolver,
+) -> Result<(), ResolverError> {
+    let comp = resolver.component;
+    let primitive = rtype.primitive(comp);
+    let (matches, found) = match literal {
+        ast::Literal::Integer(_) => (
+            primitive.and_then(|p| p.integer_range()).is_some(),
+            "integer",
+        ),
+        ast::Literal::Float(_) => (
+            matches!(
+                primitive,
+                Some(ast::PrimitiveType::F32 | ast::PrimitiveType::F64)
+            ),
+            "float",
+        ),
+        ast::Literal::String(_) => return Ok(()),
+    };
+    if matches {
+        return Ok(());
+    }
+    Err(ResolverError::TypeConflict {
+        src: comp.source(),
+        expected_span: rtype.span(comp),
+        found_span: comp.expression_span(expression),
+        expected: rtype.name(comp, resolver.imports),
+        found: found.to_owned(),
+    })
+}
+
+/// Check that the value of an integer literal fits in the type it was resolved to.
+pub(crate) fn check_integer_literal(
+    comp: &ast::Component,
+    value: i128,
+    rtype: ResolvedType,
+    span: Span,
+) -> Result<(), ResolverError> {
+    let primitive = match rtype.primitive(comp) {
+        Some(primitive) => primitive,
+        None => return Ok(()),
+    };
+    let (min, max) = match primitive.integer_range() {
+        Some(range) => range,
+        None => return Ok(()),
+    };
+    if value < min || value > max {
+        return Err(ResolverError::LiteralOutOfRange {
+            src: comp.source(),
+            span,
+            type_span: rtype.span(comp),
+            value,
+            primitive,
+            range: format!("{} to {}", min, max),
+        });
+    }
+    Ok(())
 }
 
 impl ResolveExpression for ast::EnumLiteral {
@@ -154,14 +259,48 @@ impl ResolveExpression for ast::EnumLiteral {
         let item = resolver.use_name(self.enum_name)?;
         match item {
             ItemId::Type(rtype) => {
+                enum_case(self, rtype, resolver.component, resolver.imports)?;
                 resolver.set_expr_type(expression, rtype);
             }
-            _ => panic!("Can only use literals for enums"),
+            _ => return Err(ResolverError::NotYetSupported("Enum literals".to_owned())),
         };
         Ok(())
     }
 }
 
+/// The index of the case an enum literal of type `rtype` refers to.
+pub(crate) fn enum_case(
+    literal: &ast::EnumLiteral,
+    rtype: ResolvedType,
+    comp: &ast::Component,
+    imports: &ImportResolver,
+) -> Result<u32, ResolverError> {
+    let enum_type = match rtype {
+        ResolvedType::Import(import_type) => match &imports.types[import_type] {
+            ImportType::Enum(enum_type) => enum_type,
+        },
+        _ => return Err(ResolverError::NotYetSupported("Enum literals".to_owned())),
+    };
+    let case_name = comp.get_name(literal.case_name);
+    if let Some(index) = enum_type.cases.iter().position(|case| case == case_name) {
+        return Ok(index as u32);
+    }
+
+    let span = comp.name_span(literal.case_name);
+    let cases = enum_type.cases.iter().map(String::as_str);
+    let suggestion = closest_match(case_name, cases).map(|similar| {
+        let message = format!("Did you mean `{}`?", similar);
+        Box::new(Suggestion::replace(span, similar, &message))
+    });
+    Err(ResolverError::NameError {
+        src: comp.source(),
+        span,
+        ident: format!("{}::{}", enum_type.name, case_name),
+        similar_span: None,
+        suggestion,
+    })
+}
+
 impl ResolveExpression for ast::Call {
     fn setup_resolve(
         &self,
@@ -169,34 +308,201 @@ impl ResolveExpression for ast::Call {
         resolver: &mut FunctionResolver,
     ) -> Result<(), ResolverError> {
         let item = resolver.use_name(self.ident)?;
-        let (params, results): (Vec<_>, _) = match item {
-            ItemId::ImportFunc(import_func) => {
-                let import_func = &resolver.imports.funcs[import_func];
-                let params = import_func.params.iter().map(|(_name, rtype)| *rtype);
-                let results = import_func.results.unwrap();
-                (params.collect(), results)
-            }
-            ItemId::Function(func) => {
-                let func = &resolver.component.get_function(func);
-                let params = func
-                    .params
-                    .iter()
-                    .map(|(_name, type_id)| ResolvedType::Defined(*type_id));
-                let results = ResolvedType::Defined(*func.results.as_ref().unwrap());
-                (params.collect(), results)
+        let span = resolver.component.expression_span(expression);
+        let CallSignature { params, results } = match call_signature(self, item, span, resolver)? {
+            Some(signature) => signature,
+            None => return setup_builtin_call(self, item, expression, resolver),
+        };
+        let results = match results {
+            Some(results) => results,
+            None => {
+                return Err(ResolverError::NoResult {
+                    src: resolver.component.source(),
+                    span,
+                    ident: resolver.component.get_name(self.ident).to_owned(),
+                })
             }
-            _ => panic!("Can only call functions"),
         };
-        assert_eq!(params.len(), self.args.len());
         for (arg, rtype) in self.args.iter().copied().zip(params.into_iter()) {
             resolver.setup_child_expression(expression, arg)?;
-            resolver.set_expr_type(arg, rtype);
+            resolver.expect_type(arg, rtype);
         }
 
         resolver.set_expr_type(expression, results);
 
         Ok(())
     }
+
+    fn on_resolved(
+        &self,
+        rtype: ResolvedType,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        if let Some(builtin) = numeric_builtin(self, resolver) {
+            if !is_numeric(rtype, resolver.component) {
+                return Err(ResolverError::BuiltinTypeMismatch {
+                    src: resolver.component.source(),
+                    span: resolver.component.expression_span(expression),
+                    builtin: builtin.name(),
+                    found: rtype.name(resolver.component, resolver.imports),
+                });
+            }
+            for arg in self.args.iter() {
+                resolver.set_expr_type(*arg, rtype);
+            }
+        }
+        Ok(())
+    }
+
+    fn on_child_resolved(
+        &self,
+        rtype: ResolvedType,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        // The arguments of numeric builtins have the same type as their result
+        if numeric_builtin(self, resolver).is_some() {
+            resolver.set_expr_type(expression, rtype);
+        }
+        Ok(())
+    }
+}
+
+/// The parameter and result types of a function being called.
+pub(crate) struct CallSignature {
+    pub params: Vec<ResolvedType>,
+    pub results: Option<ResolvedType>,
+}
+
+/// The signature of the function that a call refers to,
+/// or [None] for builtins, which only have their number of arguments checked.
+pub(crate) fn call_signature(
+    call: &ast::Call,
+    item: ItemId,
+    span: Span,
+    resolver: &FunctionResolver,
+) -> Result<Option<CallSignature>, ResolverError> {
+    let (params, results): (Vec<_>, _) = match item {
+        ItemId::ImportFunc(import_func) => {
+            let import_func = &resolver.imports.funcs[import_func];
+            let params = import_func.params.iter().map(|(_name, rtype)| *rtype);
+            (params.collect(), import_func.results)
+        }
+        ItemId::Function(func) => {
+            let func = &resolver.component.get_function(func);
+            let params = func
+                .params
+                .iter()
+                .map(|(_name, type_id)| ResolvedType::Defined(*type_id));
+            let results = func.results.map(ResolvedType::Defined);
+            (params.collect(), results)
+        }
+        ItemId::Builtin(builtin) => {
+            check_argument_count(call, builtin.arity(), span, resolver)?;
+            return Ok(None);
+        }
+        _ => {
+            return Err(ResolverError::NotCallable {
+                src: resolver.component.source(),
+                span,
+                defined_span: resolver.item_span(item),
+                ident: resolver.component.get_name(call.ident).to_owned(),
+            })
+        }
+    };
+    check_argument_count(call, params.len(), span, resolver)?;
+    Ok(Some(CallSignature { params, results }))
+}
+
+fn check_argument_count(
+    call: &ast::Call,
+    count: usize,
+    span: Span,
+    resolver: &FunctionResolver,
+) -> Result<(), ResolverError> {
+    if call.args.len() != count {
+        return Err(ResolverError::CallArgumentsMismatch {
+            src: resolver.component.source(),
+            span,
+            ident: resolver.component.get_name(call.ident).to_owned(),
+        });
+    }
+    Ok(())
+}
+
+fn setup_builtin_call(
+    call: &ast::Call,
+    item: ItemId,
+    expression: ExpressionId,
+    resolver: &mut FunctionResolver,
+) -> Result<(), ResolverError> {
+    let builtin = match item {
+        ItemId::Builtin(builtin) => builtin,
+        _ => unreachable!("Only builtins have no signature"),
+    };
+    for arg in call.args.iter() {
+        resolver.setup_child_expression(expression, *arg)?;
+    }
+    if let Builtin::Simd(SimdOp::ExtractLane, shape) = builtin {
+        check_lane(call.args[1], builtin, shape, resolver)?;
+    }
+    if let BuiltinSignature::Fixed { params, result } = builtin.signature() {
+        for (arg, ptype) in call.args.iter().zip(params.iter()) {
+            resolver.require_type(*arg, ResolvedType::Primitive(*ptype));
+        }
+        resolver.set_expr_type(expression, ResolvedType::Primitive(result));
+    }
+    Ok(())
+}
+
+/// Check that the lane of a SIMD builtin is an integer literal the instruction can encode.
+fn check_lane(
+    lane: ExpressionId,
+    builtin: Builtin,
+    shape: Shape,
+    resolver: &FunctionResolver,
+) -> Result<(), ResolverError> {
+    let comp = resolver.component;
+    match comp.get_expression(lane) {
+        ast::Expression::Literal(ast::Literal::Integer(value)) if *value < shape.lanes() as u64 => {
+            Ok(())
+        }
+        _ => Err(ResolverError::InvalidLane {
+            src: comp.source(),
+            span: comp.expression_span(lane),
+            builtin: builtin.name(),
+            lanes: shape.lanes(),
+            max: shape.lanes() - 1,
+        }),
+    }
+}
+
+/// Reject `v128` values where an operator or literal would need them to be a single number.
+fn reject_vector(
+    rtype: ResolvedType,
+    expression: ExpressionId,
+    resolver: &FunctionResolver,
+) -> Result<(), ResolverError> {
+    let comp = resolver.component;
+    match rtype.primitive(comp) {
+        Some(ast::PrimitiveType::V128) => Err(ResolverError::VectorOperator {
+            src: comp.source(),
+            span: comp.expression_span(expression),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// The builtin a call is to, if its arguments and result share a numeric type.
+fn numeric_builtin(call: &ast::Call, resolver: &FunctionResolver) -> Option<Builtin> {
+    match resolver.lookup_name(call.ident) {
+        Ok(ItemId::Builtin(builtin)) => match builtin.signature() {
+            BuiltinSignature::Numeric { .. } => Some(builtin),
+            BuiltinSignature::Fixed { .. } => None,
+        },
+        _ => None,
+    }
 }
 
 impl ResolveExpression for ast::UnaryExpression {
@@ -211,9 +517,10 @@ impl ResolveExpression for ast::UnaryExpression {
     fn on_resolved(
         &self,
         rtype: ResolvedType,
-        _expression: ExpressionId,
+        expression: ExpressionId,
         resolver: &mut FunctionResolver,
     ) -> Result<(), ResolverError> {
+        reject_vector(rtype, expression, resolver)?;
         resolver.set_expr_type(self.inner, rtype);
         Ok(())
     }
@@ -224,6 +531,7 @@ impl ResolveExpression for ast::UnaryExpression {
         expression: ExpressionId,
         resolver: &mut FunctionResolver,
     ) -> Result<(), ResolverError> {
+        reject_vector(rtype, self.inner, resolver)?;
         resolver.set_expr_type(expression, rtype);
         Ok(())
     }
@@ -248,13 +556,31 @@ impl ResolveExpression for ast::BinaryExpression {
     fn on_resolved(
         &self,
         rtype: ResolvedType,
-        _expression: ExpressionId,
+        expression: ExpressionId,
         resolver: &mut FunctionResolver,
     ) -> Result<(), ResolverError> {
         if !self.is_relation() {
+            reject_vector(rtype, expression, resolver)?;
             resolver.set_expr_type(self.left, rtype);
             resolver.set_expr_type(self.right, rtype);
         }
+        let operation = match self.op {
+            ast::BinaryOp::Divide => "divide",
+            ast::BinaryOp::Modulo => "calculate the remainder",
+            _ => return Ok(()),
+        };
+        let comp = resolver.component;
+        let is_integer = rtype
+            .primitive(comp)
+            .and_then(|primitive| primitive.integer_range())
+            .is_some();
+        if is_integer && is_zero_literal(comp, self.right) {
+            return Err(ResolverError::DivisionByZero {
+                src: comp.source(),
+                span: comp.expression_span(self.right),
+                operation,
+            });
+        }
         Ok(())
     }
 
@@ -270,6 +596,13 @@ impl ResolveExpression for ast::BinaryExpression {
 
         let left = resolver.expression_types.get(&self.left).copied();
         let right = resolver.expression_types.get(&self.right).copied();
+        if let Some(left) = left {
+            reject_vector(left, self.left, resolver)?;
+        }
+        if let Some(right) = right {
+            reject_vector(right, self.right, resolver)?;
+        }
+        check_operator(self.op, rtype, expression, resolver)?;
 
         match (left, right) {
             (Some(_left), Some(_right)) => {
@@ -290,3 +623,60 @@ impl ResolveExpression for ast::BinaryExpression {
         Ok(())
     }
 }
+
+/// Check that a binary operator can be used with the type of its operands.
+///
+/// Strings can only be concatenated, floats don't support remainders or bitwise
+/// operators, and `and` and `or` only work with 32-bit values.
+fn check_operator(
+    op: ast::BinaryOp,
+    rtype: ResolvedType,
+    expression: ExpressionId,
+    resolver: &FunctionResolver,
+) -> Result<(), ResolverError> {
+    use ast::BinaryOp as Op;
+    use ast::PrimitiveType as P;
+
+    let comp = resolver.component;
+    let primitive = match rtype.primitive(comp) {
+        Some(primitive) => primitive,
+        None => return Ok(()),
+    };
+    let supported = match primitive {
+        P::String => op == Op::Add,
+        P::F32 | P::F64 => !matches!(
+            op,
+            Op::Modulo
+                | Op::BitShiftL
+                | Op::BitShiftR
+                | Op::ArithShiftR
+                | Op::BitOr
+                | Op::BitXor
+                | Op::BitAnd
+                | Op::LogicalOr
+                | Op::LogicalAnd
+        ),
+        P::U64 | P::S64 => !matches!(op, Op::LogicalOr | Op::LogicalAnd),
+        _ => true,
+    };
+    if supported {
+        return Ok(());
+    }
+    Err(ResolverError::UnsupportedOperator {
+        src: comp.source(),
+        span: comp.expression_span(expression),
+        found: primitive,
+    })
+}
+
+/// Whether an expression is the integer literal zero, possibly negated.
+fn is_zero_literal(comp: &ast::Component, expression: ExpressionId) -> bool {
+    match comp.get_expression(expression) {
+        ast::Expression::Literal(ast::Literal::Integer(0)) => true,
+        ast::Expression::Unary(ast::UnaryExpression {
+            op: ast::UnaryOp::Negate,
+            inner,
+        }) => is_zero_literal(comp, *inner),
+        _ => false,
+    }
+}
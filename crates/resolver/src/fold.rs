@@ -0,0 +1,52 @@
+use ast::ExpressionId;
+use claw_ast as ast;
+
+use crate::const_eval::{ConstEvaluator, ConstValue};
+use crate::ResolvedComponent;
+
+/// Replace each constant subexpression in a function body with a literal of its value.
+///
+/// Expressions are evaluated at the types the resolver gave them, including the
+/// operands of comparisons. Expressions which can't be evaluated without an error
+/// (e.g. ones that overflow) are left alone so that they behave the same at runtime,
+/// whether that's wrapping or trapping depends on the configured overflow checks.
+pub fn fold_constants(comp: &mut ast::Component, rcomp: &ResolvedComponent) {
+    let mut folded: Vec<(ExpressionId, ast::Literal)> = Vec::new();
+
+    // Functions and their expressions are folded in declaration order so the output is reproducible
+    for (id, _) in comp.iter_functions() {
+        let function = match rcomp.funcs.get(&id) {
+            Some(function) => function,
+            None => continue,
+        };
+        let mut evaluator = ConstEvaluator::for_function(comp, function, &rcomp.global_vals);
+        let mut expressions: Vec<_> = function.expression_types.iter().collect();
+        expressions.sort_by_key(|(expression, _)| **expression);
+        for (expression, rtype) in expressions {
+            if let ast::Expression::Literal(_) = comp.get_expression(*expression) {
+                continue;
+            }
+            let primitive = match rtype.primitive(comp) {
+                Some(primitive) => primitive,
+                None => continue,
+            };
+            if let Ok(value) = evaluator.eval(*expression, Some(primitive)) {
+                folded.push((*expression, to_literal(value)));
+            }
+        }
+    }
+
+    for (expression, literal) in folded {
+        comp.replace_expression(expression, ast::Expression::Literal(literal));
+    }
+}
+
+fn to_literal(value: ConstValue) -> ast::Literal {
+    match value {
+        // Negative values use their two's complement representation
+        ConstValue::Integer(value) => ast::Literal::Integer(value as u64),
+        ConstValue::Float(value) => ast::Literal::Float(value),
+        // Booleans are represented as the integers 0 and 1
+        ConstValue::Bool(value) => ast::Literal::Integer(value as u64),
+    }
+}
@@ -1,9 +1,9 @@
 use ast::{ExpressionId, NameId, Span, StatementId, TypeId};
 use claw_ast as ast;
-use claw_common::StackMap;
+use claw_common::{closest_match, Diagnostics, StackMap, Suggestion};
 
-use cranelift_entity::{entity_impl, EntityList, ListPool, PrimaryMap};
-use std::collections::{HashMap, VecDeque};
+use cranelift_entity::{entity_impl, EntityList, EntityRef, ListPool, PrimaryMap};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 #[cfg(test)]
 use miette::{miette, LabeledSpan};
@@ -12,7 +12,7 @@ use crate::expression::*;
 use crate::imports::ImportResolver;
 use crate::statement::*;
 use crate::types::ResolvedType;
-use crate::{ItemId, ResolverError};
+use crate::{ItemId, ResolverError, ResolverWarning};
 
 pub(crate) struct FunctionResolver<'ctx> {
     pub(crate) component: &'ctx ast::Component,
@@ -30,14 +30,29 @@ pub(crate) struct FunctionResolver<'ctx> {
     pub(crate) mapping: StackMap<String, ItemId>,
     /// The resolved bindings of expressions to subjects
     pub(crate) bindings: HashMap<NameId, ItemId>,
+    /// The locals whose value is read at least once
+    pub(crate) read_locals: HashSet<LocalId>,
 
     // Type Resolution
-    resolver_queue: VecDeque<(ResolvedType, ResolverItem)>,
-
+    resolver_queue: VecDeque<(ResolvedType, TypeOrigin, ResolverItem)>,
+    /// Where the type of each resolved local and expression came from
+    type_origins: HashMap<ResolverItem, TypeOrigin>,
+    /// The origin of the type being propagated, which the types it implies share
+    origin: TypeOrigin,
+
+    /// Every expression in the function, in the order they were set up
+    expressions: Vec<ExpressionId>,
     // The parent expression (if there is one) for each expression
     pub(crate) expr_parent_map: HashMap<ExpressionId, ExpressionId>,
+    /// The local each let initializer or assigned expression is stored in
+    pub(crate) stored_in: HashMap<ExpressionId, LocalId>,
     /// The type of each expression
     pub(crate) expression_types: HashMap<ExpressionId, ResolvedType>,
+    /// The type expected of each call argument and assigned value,
+    /// which the value's own type may implicitly widen to
+    expected_types: HashMap<ExpressionId, ResolvedType>,
+    /// The literals that nothing constrained, and the type each defaulted to
+    defaulted_literals: Vec<(ExpressionId, ast::PrimitiveType)>,
 
     local_uses_list_pool: ListPool<ExpressionId>,
     // The expressions which use a given local
@@ -53,6 +68,17 @@ enum ResolverItem {
     Expression(ExpressionId),
 }
 
+/// Where a type constraint came from, which decides which of two
+/// conflicting types is reported as the expected one.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+enum TypeOrigin {
+    /// Required by where the value is used, like a signature or an annotation
+    Context,
+    /// Implied by the value itself, like the type of a parameter it reads
+    #[default]
+    Value,
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct LocalInfo {
@@ -94,18 +120,35 @@ impl<'ctx> FunctionResolver<'ctx> {
             locals: Default::default(),
             local_spans: Default::default(),
             bindings: Default::default(),
+            read_locals: Default::default(),
             resolver_queue: Default::default(),
+            type_origins: Default::default(),
+            origin: Default::default(),
+            expressions: Default::default(),
             expr_parent_map: Default::default(),
+            stored_in: Default::default(),
             expression_types: Default::default(),
+            expected_types: Default::default(),
+            defaulted_literals: Default::default(),
             local_uses_list_pool: Default::default(),
             local_uses: Default::default(),
             local_types: Default::default(),
         }
     }
 
-    pub(crate) fn resolve(mut self) -> Result<ResolvedFunction, ResolverError> {
+    pub(crate) fn resolve(
+        mut self,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<ResolvedFunction, ResolverError> {
         self.setup_block(&self.function.body)?;
+        self.check_assignments()?;
+        self.check_returns()?;
         self.resolve_types()?;
+        self.check_inferred()?;
+        let coercions = self.check_coercions()?;
+        self.check_defaulted_literals(diagnostics);
+        self.check_unused_locals(diagnostics);
+        self.check_unreachable(&self.function.body, diagnostics);
 
         Ok(ResolvedFunction {
             params: self.params,
@@ -114,6 +157,34 @@ impl<'ctx> FunctionResolver<'ctx> {
             local_types: self.local_types,
             bindings: self.bindings,
             expression_types: self.expression_types,
+            coercions,
+        })
+    }
+
+    /// Check that a function with a result can't reach the end of its body.
+    fn check_returns(&self) -> Result<(), ResolverError> {
+        let results = match self.function.results {
+            Some(results) => results,
+            None => return Ok(()),
+        };
+        // An `if` might not run, so only a `return` directly in the body
+        // guarantees that the end isn't reached
+        let returns = self.function.body.iter().any(|statement| {
+            matches!(
+                self.component.get_statement(*statement),
+                ast::Statement::Return(_)
+            )
+        });
+        if returns {
+            return Ok(());
+        }
+        let body_span = self.function.body_span;
+        let closing_brace = body_span.offset() + body_span.len() - 1;
+        Err(ResolverError::MissingReturn {
+            src: self.component.source(),
+            result_span: self.component.type_span(results),
+            end_span: Span::new(closing_brace.into(), 1),
+            ident: self.component.get_name(self.function.ident).to_owned(),
         })
     }
 
@@ -130,13 +201,16 @@ impl<'ctx> FunctionResolver<'ctx> {
     }
 
     pub(crate) fn setup_statement(&mut self, statement: StatementId) -> Result<(), ResolverError> {
-        self.component.get_statement(statement).setup_resolve(self)
+        self.component
+            .get_statement(statement)
+            .setup_resolve(statement, self)
     }
 
     pub(crate) fn setup_expression(
         &mut self,
         expression: ExpressionId,
     ) -> Result<(), ResolverError> {
+        self.expressions.push(expression);
         self.component
             .get_expression(expression)
             .setup_resolve(expression, self)
@@ -179,13 +253,59 @@ impl<'ctx> FunctionResolver<'ctx> {
     fn name_error<T>(&self, ident: NameId) -> Result<T, ResolverError> {
         let span = self.component.name_span(ident);
         let ident = self.component.get_name(ident).to_owned();
+
+        let in_scope = self.mapping.iter().map(|(name, _)| name.as_str());
+        let (similar_span, suggestion) = match closest_match(&ident, in_scope) {
+            Some(similar) => {
+                let item = *self.mapping.lookup(&similar.to_owned()).unwrap();
+                let message = format!("Did you mean `{}`?", similar);
+                let suggestion = Suggestion::replace(span, similar, &message);
+                (self.item_span(item), Some(Box::new(suggestion)))
+            }
+            None => (None, None),
+        };
+
         Err(ResolverError::NameError {
             src: self.component.source(),
             span,
             ident,
+            similar_span,
+            suggestion,
         })
     }
 
+    /// The span of the name an item was declared with, if it has one.
+    pub(crate) fn item_span(&self, item: ItemId) -> Option<Span> {
+        let ident = match item {
+            ItemId::Global(global) => self.component.get_global(global).ident,
+            ItemId::Function(function) => self.component.get_function(function).ident,
+            ItemId::Param(param) => self.function.params[param.index()].0,
+            ItemId::Local(local) => self.locals[local].ident,
+            ItemId::ImportValue(import_func) => {
+                let alias = &self.imports.funcs[import_func].alias;
+                crate::import_names(self.component)
+                    .find(|ident| self.component.get_name(*ident) == alias)?
+            }
+            ItemId::ImportFunc(_) | ItemId::Type(_) | ItemId::Builtin(_) => return None,
+        };
+        Some(self.component.name_span(ident))
+    }
+
+    /// The error for using a function or type where a value is expected.
+    pub(crate) fn not_a_value(&self, ident: NameId, span: Span) -> ResolverError {
+        let name = self.component.get_name(ident);
+        let help = match self.lookup_name(ident) {
+            Ok(ItemId::Type(_)) => None,
+            _ => Some(format!("Call it to use its result, like `{}(...)`", name)),
+        };
+        ResolverError::NotAValue {
+            src: self.component.source(),
+            span,
+            ident: name.to_owned(),
+            help,
+        }
+    }
+
     pub(crate) fn use_local(&mut self, local: LocalId, expression: ExpressionId) {
         let existing_uses = self.local_uses.get_mut(&local);
         if let Some(uses) = existing_uses {
@@ -197,30 +317,168 @@ impl<'ctx> FunctionResolver<'ctx> {
         }
     }
 
+    /// Warn about literals that defaulted to a type in an exported function,
+    /// where a type picked by default is easy to miss.
+    fn check_defaulted_literals(&self, diagnostics: &mut Diagnostics) {
+        if !self.function.exported {
+            return;
+        }
+        for (expression, primitive) in self.defaulted_literals.iter() {
+            diagnostics.push(ResolverWarning::DefaultedLiteral {
+                src: self.component.source(),
+                span: self.component.expression_span(*expression),
+                primitive: *primitive,
+            });
+        }
+    }
+
+    fn check_unused_locals(&self, diagnostics: &mut Diagnostics) {
+        for (local, info) in self.locals.iter() {
+            if !self.read_locals.contains(&local) {
+                diagnostics.push(ResolverWarning::UnusedLocal {
+                    src: self.component.source(),
+                    span: self.component.name_span(info.ident),
+                    ident: self.component.get_name(info.ident).to_owned(),
+                });
+            }
+        }
+    }
+
+    fn check_unreachable(&self, statements: &[StatementId], diagnostics: &mut Diagnostics) {
+        for (index, statement) in statements.iter().enumerate() {
+            match self.component.get_statement(*statement) {
+                ast::Statement::Return(_) => {
+                    let rest = &statements[index + 1..];
+                    if let (Some(first), Some(last)) = (rest.first(), rest.last()) {
+                        let first_span = self.component.statement_span(*first);
+                        let last_span = self.component.statement_span(*last);
+                        diagnostics.push(ResolverWarning::UnreachableCode {
+                            src: self.component.source(),
+                            terminator_span: self.component.statement_span(*statement),
+                            unreachable_span: ast::merge(&first_span, &last_span),
+                        });
+                    }
+                    // Blocks nested in the dead code are already covered
+                    return;
+                }
+                ast::Statement::If(if_statement) => {
+                    self.check_unreachable(&if_statement.block, diagnostics);
+                }
+                _ => {}
+            }
+        }
+    }
+
     pub(crate) fn set_expr_type(&mut self, id: ExpressionId, rtype: ResolvedType) {
-        self.resolver_queue
-            .push_back((rtype, ResolverItem::Expression(id)));
+        self.constrain(ResolverItem::Expression(id), rtype, self.origin);
+    }
+
+    /// Require `id` to have exactly the type `rtype` because of where it is used.
+    pub(crate) fn require_type(&mut self, id: ExpressionId, rtype: ResolvedType) {
+        self.constrain(ResolverItem::Expression(id), rtype, TypeOrigin::Context);
+    }
+
+    /// Expect `id` to have the type `rtype`, or a type that implicitly widens to it.
+    pub(crate) fn expect_type(&mut self, id: ExpressionId, rtype: ResolvedType) {
+        self.expected_types.insert(id, rtype);
     }
 
     pub(crate) fn set_local_type(&mut self, id: LocalId, rtype: ResolvedType) {
-        self.resolver_queue
-            .push_back((rtype, ResolverItem::Local(id)));
+        self.constrain(ResolverItem::Local(id), rtype, self.origin);
+    }
+
+    /// Require the local `id` to have the type it's annotated with.
+    pub(crate) fn require_local_type(&mut self, id: LocalId, rtype: ResolvedType) {
+        self.constrain(ResolverItem::Local(id), rtype, TypeOrigin::Context);
+    }
+
+    fn constrain(&mut self, item: ResolverItem, rtype: ResolvedType, origin: TypeOrigin) {
+        self.resolver_queue.push_back((rtype, origin, item));
     }
 
     fn resolve_types(&mut self) -> Result<(), ResolverError> {
-        while let Some((next_type, next_item)) = self.resolver_queue.pop_front() {
+        loop {
+            self.propagate_types()?;
+            // Once nothing else constrains a local, it takes the type of a value stored in it
+            if let Some((local, rtype)) = self.next_stored_type() {
+                self.set_local_type(local, rtype);
+                continue;
+            }
+            // Then a value without a type takes the type expected of it
+            if let Some((expression, rtype)) = self.next_expected_type() {
+                self.require_type(expression, rtype);
+                continue;
+            }
+            // Once nothing at all constrains a literal, it takes a default type
+            match self.next_defaulted_literal() {
+                Some((expression, primitive)) => {
+                    self.defaulted_literals.push((expression, primitive));
+                    self.set_expr_type(expression, ResolvedType::Primitive(primitive));
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+
+    /// The first expression without a type that is expected to have one.
+    fn next_expected_type(&self) -> Option<(ExpressionId, ResolvedType)> {
+        self.expressions.iter().find_map(|expression| {
+            if self.expression_types.contains_key(expression) {
+                return None;
+            }
+            Some((*expression, *self.expected_types.get(expression)?))
+        })
+    }
+
+    /// The first numeric literal without a type, and the type it defaults to.
+    ///
+    /// Integers default to `s32` and floats to `f64`.
+    fn next_defaulted_literal(&self) -> Option<(ExpressionId, ast::PrimitiveType)> {
+        self.expressions.iter().find_map(|expression| {
+            if self.expression_types.contains_key(expression) {
+                return None;
+            }
+            match self.component.get_expression(*expression) {
+                ast::Expression::Literal(ast::Literal::Integer(_)) => {
+                    Some((*expression, ast::PrimitiveType::S32))
+                }
+                ast::Expression::Literal(ast::Literal::Float(_)) => {
+                    Some((*expression, ast::PrimitiveType::F64))
+                }
+                _ => None,
+            }
+        })
+    }
+
+    /// The first local without a type that a value with a known type is stored in.
+    fn next_stored_type(&self) -> Option<(LocalId, ResolvedType)> {
+        self.expressions.iter().find_map(|expression| {
+            let local = *self.stored_in.get(expression)?;
+            if self.local_types.contains_key(&local) {
+                return None;
+            }
+            Some((local, *self.expression_types.get(expression)?))
+        })
+    }
+
+    fn propagate_types(&mut self) -> Result<(), ResolverError> {
+        let result = self.propagate_queued_types();
+        self.origin = TypeOrigin::Value;
+        result
+    }
+
+    fn propagate_queued_types(&mut self) -> Result<(), ResolverError> {
+        while let Some((next_type, next_origin, next_item)) = self.resolver_queue.pop_front() {
+            self.origin = next_origin;
             match next_item {
                 ResolverItem::Expression(expression) => {
                     // Apply the inferred type and detect conflicts
                     if let Some(existing_type) = self.expression_types.get(&expression) {
                         if !next_type.type_eq(existing_type, self.component) {
                             let span = self.component.expression_span(expression);
-                            return Err(ResolverError::TypeConflict {
-                                src: self.component.source(),
-                                span,
-                                type_a: *existing_type,
-                                type_b: next_type,
-                            });
+                            let (expected, found) =
+                                self.conflicting_types(next_type, *existing_type, next_item);
+                            return Err(self.type_conflict(expected, found, span));
                         } else {
                             #[cfg(test)]
                             self.notify_skipped_expression(expression);
@@ -228,6 +486,7 @@ impl<'ctx> FunctionResolver<'ctx> {
                         }
                     } else {
                         self.expression_types.insert(expression, next_type);
+                        self.type_origins.insert(next_item, next_origin);
                     }
 
                     #[cfg(test)]
@@ -247,7 +506,10 @@ impl<'ctx> FunctionResolver<'ctx> {
                 ResolverItem::Local(local) => {
                     if let Some(existing_type) = self.local_types.get(&local) {
                         if !next_type.type_eq(existing_type, self.component) {
-                            panic!("Local type error!!!");
+                            let span = *self.local_spans.get(&local).unwrap();
+                            let (expected, found) =
+                                self.conflicting_types(next_type, *existing_type, next_item);
+                            return Err(self.type_conflict(expected, found, span));
                         } else {
                             #[cfg(test)]
                             self.notify_skipped_local(local);
@@ -255,6 +517,7 @@ impl<'ctx> FunctionResolver<'ctx> {
                         }
                     } else {
                         self.local_types.insert(local, next_type);
+                        self.type_origins.insert(next_item, next_origin);
                     }
 
                     #[cfg(test)]
@@ -270,7 +533,12 @@ impl<'ctx> FunctionResolver<'ctx> {
                                 let uses = self.local_uses.get(&local).unwrap();
                                 uses.get(i, &self.local_uses_list_pool).unwrap()
                             };
-                            self.set_expr_type(local_use, next_type);
+                            // Reading a local gives a value of the local's type
+                            self.constrain(
+                                ResolverItem::Expression(local_use),
+                                next_type,
+                                TypeOrigin::Value,
+                            );
                         }
                     }
                 }
@@ -280,6 +548,109 @@ impl<'ctx> FunctionResolver<'ctx> {
         Ok(())
     }
 
+    /// Order the type `item` already has and the conflicting `next` type
+    /// as the expected type and the found type.
+    ///
+    /// A type required by the context is the expected one.
+    fn conflicting_types(
+        &self,
+        next: ResolvedType,
+        existing: ResolvedType,
+        item: ResolverItem,
+    ) -> (ResolvedType, ResolvedType) {
+        match (self.origin, self.type_origins[&item]) {
+            (TypeOrigin::Value, TypeOrigin::Context) => (existing, next),
+            _ => (next, existing),
+        }
+    }
+
+    /// Check that the type of every local and expression was inferred.
+    ///
+    /// Unresolved locals are reported first, since annotating them is the usual fix.
+    fn check_inferred(&self) -> Result<(), ResolverError> {
+        let comp = self.component;
+        for (local, info) in self.locals.iter() {
+            if !self.local_types.contains_key(&local) {
+                let name = comp.get_name(info.ident);
+                let mutable = if info.mutable { "mut " } else { "" };
+                return Err(ResolverError::AmbiguousType {
+                    src: comp.source(),
+                    span: comp.name_span(info.ident),
+                    subject: format!("`{}`", name),
+                    help: format!(
+                        "Add a type annotation, like `let {}{}: u32 = ...`",
+                        mutable, name
+                    ),
+                });
+            }
+        }
+        for expression in self.expressions.iter() {
+            if !self.expression_types.contains_key(expression) {
+                return Err(ResolverError::AmbiguousType {
+                    src: comp.source(),
+                    span: comp.expression_span(*expression),
+                    subject: "this expression".to_owned(),
+                    help: "Use it where a value of a known type is expected, \
+                        or store it in a variable with a type annotation"
+                        .to_owned(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that each value has the type expected of it, or one that implicitly widens to it.
+    ///
+    /// Returns the values that are widened, and the type each is widened to.
+    fn check_coercions(&self) -> Result<HashMap<ExpressionId, ResolvedType>, ResolverError> {
+        let comp = self.component;
+        let mut coercions = HashMap::new();
+        for expression in self.expressions.iter() {
+            let expected = match self.expected_types.get(expression) {
+                Some(expected) => *expected,
+                None => continue,
+            };
+            let found = self.expression_types[expression];
+            if found.type_eq(&expected, comp) {
+                continue;
+            }
+            let found_span = comp.expression_span(*expression);
+            match found.primitive(comp).zip(expected.primitive(comp)) {
+                Some((from, to)) if from.widens_to(to) => {
+                    coercions.insert(*expression, expected);
+                }
+                Some((from, to)) if is_narrowing(from, to) => {
+                    return Err(ResolverError::NarrowingConversion {
+                        src: comp.source(),
+                        expected_span: expected.span(comp),
+                        found_span,
+                        expected: expected.name(comp, self.imports),
+                        found: found.name(comp, self.imports),
+                    });
+                }
+                _ => return Err(self.type_conflict(expected, found, found_span)),
+            }
+        }
+        Ok(coercions)
+    }
+
+    /// The item at `found_span` was already inferred to have the `found` type
+    /// when it was also required to have the `expected` type.
+    fn type_conflict(
+        &self,
+        expected: ResolvedType,
+        found: ResolvedType,
+        found_span: Span,
+    ) -> ResolverError {
+        ResolverError::TypeConflict {
+            src: self.component.source(),
+            expected_span: expected.span(self.component),
+            found_span,
+            expected: expected.name(self.component, self.imports),
+            found: found.name(self.component, self.imports),
+        }
+    }
+
     #[cfg(test)]
     fn notify_skipped_expression(&self, expression: ExpressionId) {
         let span = self.component.expression_span(expression);
@@ -345,6 +716,8 @@ pub struct ResolvedFunction {
     pub bindings: HashMap<NameId, ItemId>,
     /// The type of each expression
     pub expression_types: HashMap<ExpressionId, ResolvedType>,
+    /// The values that are implicitly widened, and the type each is widened to
+    pub coercions: HashMap<ExpressionId, ResolvedType>,
 }
 
 impl ResolvedFunction {
@@ -384,3 +757,12 @@ impl ResolvedFunction {
         }
     }
 }
+
+/// Whether converting `from` to `to` could lose information,
+/// because both are integers or both are floats but `from` doesn't widen to `to`.
+fn is_narrowing(from: ast::PrimitiveType, to: ast::PrimitiveType) -> bool {
+    use ast::PrimitiveType::{F32, F64};
+    let both_integers = from.integer_range().is_some() && to.integer_range().is_some();
+    let both_floats = matches!(from, F32 | F64) && matches!(to, F32 | F64);
+    (both_integers || both_floats) && !from.widens_to(to)
+}
@@ -22,6 +22,8 @@ pub struct ImportResolver {
 pub enum ImportItemId {
     Type(ResolvedType),
     Func(ImportFuncId),
+    /// A value read through an imported accessor function
+    Value(ImportFuncId),
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -73,7 +75,37 @@ impl ImportResolver {
             ast::ExternalType::Function(fn_type) => {
                 self.resolve_plain_import_func(import.ident, import.alias, fn_type, comp);
             }
+            ast::ExternalType::Value(type_id) => {
+                self.resolve_plain_import_value(import.ident, import.alias, *type_id, comp);
+            }
+        };
+    }
+
+    /// Values can't be imported into core modules, so they're read by
+    /// calling an imported `get-<name>` function whenever they're used.
+    fn resolve_plain_import_value(
+        &mut self,
+        name: NameId,
+        alias: Option<NameId>,
+        type_id: ast::TypeId,
+        comp: &ast::Component,
+    ) {
+        let name = comp.get_name(name);
+        let alias = match alias {
+            Some(alias) => comp.get_name(alias),
+            None => name,
+        };
+        let import_func = ImportFunction {
+            alias: alias.to_owned(),
+            name: format!("get-{}", name),
+            params: Vec::new(),
+            results: Some(ResolvedType::Defined(type_id)),
         };
+
+        let import_func_id = self.funcs.push(import_func);
+        let import_item_id = ImportItemId::Value(import_func_id);
+        self.mapping.insert(alias.to_owned(), import_item_id);
+        self.loose_funcs.push(import_func_id);
     }
 
     fn resolve_plain_import_func(
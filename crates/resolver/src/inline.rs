@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use ast::{ExpressionId, FunctionId};
+use claw_ast as ast;
+use cranelift_entity::EntityRef;
+
+use crate::{ItemId, ResolvedComponent, ResolvedFunction};
+
+/// Replace calls to small functions with the expression they return.
+///
+/// Only functions whose body is a single `return` of an expression with at most
+/// `threshold` nodes and no calls are inlined, so recursive functions never are.
+/// Arguments may end up duplicated or dropped, so calls are only inlined when
+/// every argument is a literal or a name. Arguments that are implicitly widened
+/// would lose their conversion when substituted, so those calls aren't inlined either.
+pub fn inline_functions(
+    comp: &mut ast::Component,
+    rcomp: &mut ResolvedComponent,
+    threshold: usize,
+) {
+    let mut candidates: HashMap<FunctionId, ExpressionId> = HashMap::new();
+    for (id, function) in comp.iter_functions() {
+        if let [statement] = function.body.as_slice() {
+            if let ast::Statement::Return(ast::Return {
+                expression: Some(expression),
+            }) = comp.get_statement(*statement)
+            {
+                if matches!(expression_size(comp, *expression), Some(size) if size <= threshold) {
+                    candidates.insert(id, *expression);
+                }
+            }
+        }
+    }
+    if candidates.is_empty() {
+        return;
+    }
+
+    let mut callers: Vec<FunctionId> = rcomp.funcs.keys().copied().collect();
+    callers.sort();
+    for caller_id in callers {
+        let mut caller = rcomp.funcs.remove(&caller_id).unwrap();
+
+        let mut calls: Vec<ExpressionId> = caller.expression_types.keys().copied().collect();
+        calls.sort();
+        for call in calls {
+            let (ident, args) = match comp.get_expression(call) {
+                ast::Expression::Call(call) => (call.ident, call.args.clone()),
+                _ => continue,
+            };
+            let callee_id = match caller.bindings.get(&ident) {
+                Some(ItemId::Function(callee_id)) => *callee_id,
+                _ => continue,
+            };
+            let body = match candidates.get(&callee_id) {
+                Some(body) => *body,
+                None => continue,
+            };
+            if !args.iter().all(|arg| is_atom(comp, *arg)) {
+                continue;
+            }
+            if args.iter().any(|arg| caller.coercions.contains_key(arg)) {
+                continue;
+            }
+
+            let mut inliner = Inliner {
+                callee: &rcomp.funcs[&callee_id],
+                caller: &mut caller,
+                args,
+            };
+            let inlined = inliner.copy(comp, body, true);
+            let inlined = comp.get_expression(inlined).clone();
+            comp.replace_expression(call, inlined);
+            caller.bindings.remove(&ident);
+        }
+
+        rcomp.funcs.insert(caller_id, caller);
+    }
+}
+
+/// Copies expressions from a callee into a caller, substituting arguments for parameters.
+struct Inliner<'a> {
+    callee: &'a ResolvedFunction,
+    caller: &'a mut ResolvedFunction,
+    args: Vec<ExpressionId>,
+}
+
+impl<'a> Inliner<'a> {
+    /// Deep copy an expression from the callee (or caller) into the caller.
+    fn copy(
+        &mut self,
+        comp: &mut ast::Component,
+        expression: ExpressionId,
+        from_callee: bool,
+    ) -> ExpressionId {
+        let source = if from_callee {
+            self.callee
+        } else {
+            &*self.caller
+        };
+        let rtype = source.expression_types[&expression];
+        let node = match comp.get_expression(expression).clone() {
+            ast::Expression::Identifier(identifier) => {
+                let item = source.bindings[&identifier.ident];
+                if let (true, ItemId::Param(param)) = (from_callee, item) {
+                    let arg = self.args[param.index()];
+                    return self.copy(comp, arg, false);
+                }
+                self.caller.bindings.insert(identifier.ident, item);
+                ast::Expression::Identifier(identifier)
+            }
+            ast::Expression::Enum(enum_literal) => {
+                let item = source.bindings[&enum_literal.enum_name];
+                self.caller.bindings.insert(enum_literal.enum_name, item);
+                ast::Expression::Enum(enum_literal)
+            }
+            ast::Expression::Literal(literal) => ast::Expression::Literal(literal),
+            ast::Expression::Unary(unary) => ast::Expression::Unary(ast::UnaryExpression {
+                op: unary.op,
+                inner: self.copy(comp, unary.inner, from_callee),
+            }),
+            ast::Expression::Binary(binary) => ast::Expression::Binary(ast::BinaryExpression {
+                op: binary.op,
+                left: self.copy(comp, binary.left, from_callee),
+                right: self.copy(comp, binary.right, from_callee),
+            }),
+            ast::Expression::Call(_) => unreachable!("Calls are never inlined or copied"),
+        };
+        let span = comp.expression_span(expression);
+        let copy = comp.new_expression(node, span);
+        self.caller.expression_types.insert(copy, rtype);
+        copy
+    }
+}
+
+/// The number of nodes in an expression, or [None] if it contains a call.
+fn expression_size(comp: &ast::Component, expression: ExpressionId) -> Option<usize> {
+    match comp.get_expression(expression) {
+        ast::Expression::Identifier(_) | ast::Expression::Enum(_) | ast::Expression::Literal(_) => {
+            Some(1)
+        }
+        ast::Expression::Call(_) => None,
+        ast::Expression::Unary(unary) => Some(expression_size(comp, unary.inner)? + 1),
+        ast::Expression::Binary(binary) => {
+            Some(expression_size(comp, binary.left)? + expression_size(comp, binary.right)? + 1)
+        }
+    }
+}
+
+/// Whether an expression is a literal or a name, which are cheap to copy and can't trap.
+fn is_atom(comp: &ast::Component, expression: ExpressionId) -> bool {
+    matches!(
+        comp.get_expression(expression),
+        ast::Expression::Identifier(_) | ast::Expression::Enum(_) | ast::Expression::Literal(_)
+    )
+}
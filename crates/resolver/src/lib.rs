@@ -1,71 +1,245 @@
 #![allow(clippy::single_match)]
 
+mod accessors;
+mod assignment;
+mod builtins;
+mod const_eval;
+mod dead_code;
+mod definitions;
 mod expression;
+mod fold;
 mod function;
 mod imports;
+mod inline;
+mod lower;
+pub mod mir;
+pub mod passes;
 mod statement;
+mod tail_calls;
 pub mod types;
 pub mod wit;
 
-use ast::{FunctionId, GlobalId};
+use ast::{ExpressionId, FunctionId, GlobalId, NameId, Span};
 use claw_ast as ast;
-use claw_common::Source;
+use claw_common::{Diagnostics, Source, Suggestion};
+use cranelift_entity::EntityRef;
 
 use std::collections::HashMap;
 use wit::{ResolvedWit, WitError};
 
-use miette::{Diagnostic, SourceSpan};
+use miette::{Diagnostic, LabeledSpan, SourceSpan};
 use thiserror::Error;
 
+use const_eval::ConstEvaluator;
+
+pub use accessors::{AccessorKind, GlobalAccessor};
+pub use builtins::{Builtin, BuiltinSignature, Shape, SimdOp};
+pub use const_eval::ConstValue;
+pub use dead_code::{eliminate_dead_items, DeadItem};
+pub use fold::fold_constants;
 pub use function::*;
 pub use imports::*;
+pub use inline::inline_functions;
+pub use lower::lower_function;
+pub use tail_calls::{recursive_tail_calls, TailCall};
 pub use types::*;
 
+/// The result of resolving a [ast::Component].
+///
+/// Besides being the input to code generation, this can be queried by tools
+/// that need to know what names refer to and what type each expression has.
 pub struct ResolvedComponent {
+    /// The WIT the component was resolved against
     pub wit: ResolvedWit,
-    pub global_vals: HashMap<GlobalId, ast::Literal>,
+    /// The value each global is initialized to
+    pub global_vals: HashMap<GlobalId, ConstValue>,
+    /// The imported functions and types
     pub imports: ImportResolver,
+    /// The resolved body of each function that is part of the component
     pub funcs: HashMap<FunctionId, ResolvedFunction>,
 }
 
+impl ResolvedComponent {
+    /// The resolved body of a function, or [None] if it was removed as dead code.
+    pub fn function(&self, function: FunctionId) -> Option<&ResolvedFunction> {
+        self.funcs.get(&function)
+    }
+
+    /// The item a name used in the body of `function` refers to.
+    pub fn binding(&self, function: FunctionId, name: NameId) -> Option<ItemId> {
+        self.funcs.get(&function)?.bindings.get(&name).copied()
+    }
+
+    /// The type inferred for an expression in the body of `function`.
+    pub fn expression_type(
+        &self,
+        function: FunctionId,
+        expression: ExpressionId,
+    ) -> Option<ResolvedType> {
+        self.funcs
+            .get(&function)?
+            .expression_types
+            .get(&expression)
+            .copied()
+    }
+
+    /// The value a global is initialized to.
+    pub fn global_value(&self, global: GlobalId) -> Option<ConstValue> {
+        self.global_vals.get(&global).copied()
+    }
+
+    /// The exported functions and the names they are exported as.
+    pub fn exported_functions<'a>(
+        &'a self,
+        comp: &'a ast::Component,
+    ) -> impl Iterator<Item = (&'a str, FunctionId)> + 'a {
+        comp.iter_functions()
+            .filter(move |(id, function)| function.exported && self.funcs.contains_key(id))
+            .map(move |(id, function)| (comp.get_name(function.ident), id))
+    }
+
+    /// The function exported as `name`.
+    pub fn export(&self, comp: &ast::Component, name: &str) -> Option<FunctionId> {
+        self.exported_functions(comp)
+            .find(|(export_name, _)| *export_name == name)
+            .map(|(_, id)| id)
+    }
+
+    /// The span of the declaration of the name at a byte offset.
+    ///
+    /// A name that is itself being declared is its own definition.
+    /// Names in global initializers are looked up among the top-level items.
+    pub fn definition(&self, comp: &ast::Component, offset: usize) -> Option<Span> {
+        let name = match comp.node_at(offset)? {
+            ast::NodeId::Name(name) => name,
+            _ => return None,
+        };
+        for (function, resolved) in self.funcs.iter() {
+            if let Some(item) = resolved.bindings.get(&name) {
+                return self.item_span(comp, *function, *item);
+            }
+        }
+
+        let text = comp.get_name(name);
+        let top_level = comp
+            .iter_globals()
+            .map(|(_, global)| global.ident)
+            .chain(comp.iter_functions().map(|(_, function)| function.ident))
+            .chain(import_names(comp))
+            .find(|ident| comp.get_name(*ident) == text);
+        if let Some(ident) = top_level {
+            return Some(comp.name_span(ident));
+        }
+
+        let is_declaration = comp.iter_functions().any(|(id, function)| {
+            function.params.iter().any(|(param, _)| *param == name)
+                || self.funcs.get(&id).is_some_and(|resolved| {
+                    resolved.locals.values().any(|local| local.ident == name)
+                })
+        });
+        is_declaration.then(|| comp.name_span(name))
+    }
+
+    /// The span of the name an item used in `function` was declared with.
+    pub fn item_span(
+        &self,
+        comp: &ast::Component,
+        function: FunctionId,
+        item: ItemId,
+    ) -> Option<Span> {
+        let ident = match item {
+            ItemId::Global(global) => comp.get_global(global).ident,
+            ItemId::Function(function) => comp.get_function(function).ident,
+            ItemId::Param(param) => comp.get_function(function).params[param.index()].0,
+            ItemId::Local(local) => self.funcs.get(&function)?.locals[local].ident,
+            ItemId::Builtin(_) => return None,
+            ItemId::ImportFunc(_) | ItemId::ImportValue(_) | ItemId::Type(_) => {
+                let (text, _) =
+                    self.imports
+                        .mapping
+                        .iter()
+                        .find(|(_, import)| match (import, item) {
+                            (ImportItemId::Func(import), ItemId::ImportFunc(func))
+                            | (ImportItemId::Value(import), ItemId::ImportValue(func)) => {
+                                *import == func
+                            }
+                            (
+                                ImportItemId::Type(ResolvedType::Import(import)),
+                                ItemId::Type(ResolvedType::Import(import_type)),
+                            ) => *import == import_type,
+                            _ => false,
+                        })?;
+                import_names(comp).find(|ident| comp.get_name(*ident) == text)?
+            }
+        };
+        Some(comp.name_span(ident))
+    }
+}
+
+/// The names that imports are available as.
+fn import_names(comp: &ast::Component) -> impl Iterator<Item = NameId> + '_ {
+    comp.iter_imports().flat_map(|(_, import)| match import {
+        ast::Import::Plain(import) => vec![import.alias.unwrap_or(import.ident)],
+        ast::Import::ImportFrom(import) => import
+            .items
+            .iter()
+            .map(|(name, alias)| alias.unwrap_or(*name))
+            .collect(),
+    })
+}
+
+/// An item that a name can refer to.
 #[derive(Clone, Copy, Debug)]
 pub enum ItemId {
     ImportFunc(ImportFuncId),
+    ImportValue(ImportFuncId),
     Type(ResolvedType),
     Global(GlobalId),
     Param(ParamId),
     Local(LocalId),
     Function(FunctionId),
+    Builtin(Builtin),
 }
 
-#[derive(Error, Debug, Diagnostic)]
+#[derive(Error, Debug, Diagnostic, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ResolverError {
     #[error("Failed to resolve")]
+    #[diagnostic(code(E0201))]
     Base {
         #[source_code]
         src: Source,
         #[label("This bit")]
         span: SourceSpan,
     },
-    #[error("Conflicting types inferred for expression {type_a} != {type_b}")]
+    #[error("Mismatched types, expected `{expected}` but found `{found}`")]
+    #[diagnostic(code(E0202))]
     TypeConflict {
         #[source_code]
         src: Source,
-        #[label("This bit")]
-        span: SourceSpan,
+        #[label("Expected `{expected}` because of this")]
+        expected_span: Option<SourceSpan>,
+        #[label("Found `{found}`")]
+        found_span: SourceSpan,
 
-        type_a: ResolvedType,
-        type_b: ResolvedType,
+        expected: String,
+        found: String,
     },
     #[error("Failed to resolve name \"{ident}\"")]
+    #[diagnostic(code(E0203))]
     NameError {
         #[source_code]
         src: Source,
         #[label("Name referenced here")]
         span: SourceSpan,
         ident: String,
+        #[label("A similar name is defined here")]
+        similar_span: Option<SourceSpan>,
+        #[help]
+        suggestion: Option<Box<Suggestion>>,
     },
     #[error("Assigned to immutable variable \"{ident}\"")]
+    #[diagnostic(code(E0204))]
     AssignedToImmutable {
         #[source_code]
         src: Source,
@@ -74,8 +248,11 @@ pub enum ResolverError {
         #[label("Assigned here")]
         assigned_span: SourceSpan,
         ident: String,
+        #[help]
+        suggestion: Option<Box<Suggestion>>,
     },
     #[error("Function call with wrong number of arguments \"{ident}\"")]
+    #[diagnostic(code(E0205))]
     CallArgumentsMismatch {
         #[source_code]
         src: Source,
@@ -83,7 +260,278 @@ pub enum ResolverError {
         span: SourceSpan,
         ident: String,
     },
+    #[error("Literal `{value}` is out of range for `{primitive}`")]
+    #[diagnostic(code(E0207), help("`{primitive}` values range from {range}"))]
+    LiteralOutOfRange {
+        #[source_code]
+        src: Source,
+        #[label("This literal doesn't fit in `{primitive}`")]
+        span: SourceSpan,
+        #[label("`{primitive}` declared here")]
+        type_span: Option<SourceSpan>,
+
+        value: i128,
+        primitive: ast::PrimitiveType,
+        range: String,
+    },
+    #[error("Attempt to {operation} with a divisor of zero")]
+    #[diagnostic(code(E0208), help("This operation would always trap at runtime"))]
+    DivisionByZero {
+        #[source_code]
+        src: Source,
+        #[label("This divisor is always zero")]
+        span: SourceSpan,
+
+        operation: &'static str,
+    },
+    #[error("Expression can't be evaluated at compile time")]
+    #[diagnostic(
+        code(E0209),
+        help(
+            "Global initializers may only use literals, operators, immutable globals, \
+            and calls to `const` functions"
+        )
+    )]
+    NotConst {
+        #[source_code]
+        src: Source,
+        #[label("Not a constant expression")]
+        span: SourceSpan,
+    },
+    #[error("Constant expression overflows `{primitive}`")]
+    #[diagnostic(code(E0210))]
+    ConstOverflow {
+        #[source_code]
+        src: Source,
+        #[label("This overflows `{primitive}`")]
+        span: SourceSpan,
+
+        primitive: ast::PrimitiveType,
+    },
+    #[error("Global `{ident}` depends on its own value")]
+    #[diagnostic(code(E0211))]
+    GlobalCycle {
+        #[source_code]
+        src: Source,
+        #[label("`{ident}` is used here while it is being initialized")]
+        span: SourceSpan,
+        #[label(collection)]
+        cycle: Vec<LabeledSpan>,
+
+        ident: String,
+    },
+    #[error("`{builtin}` can't be used with `{found}`")]
+    #[diagnostic(code(E0212), help("`{builtin}` only accepts numeric types"))]
+    BuiltinTypeMismatch {
+        #[source_code]
+        src: Source,
+        #[label("This has type `{found}`")]
+        span: SourceSpan,
+
+        builtin: &'static str,
+        found: String,
+    },
+    #[error("The lane of `{builtin}` must be an integer literal from 0 to {max}")]
+    #[diagnostic(code(E0213), help("`v128` values of this shape have {lanes} lanes"))]
+    InvalidLane {
+        #[source_code]
+        src: Source,
+        #[label("Not a valid lane")]
+        span: SourceSpan,
+
+        builtin: &'static str,
+        lanes: u32,
+        max: u32,
+    },
+    #[error("Operators can't be used with `v128` values")]
+    #[diagnostic(code(E0214), help("Use the SIMD builtins instead, like `i32x4-add`"))]
+    VectorOperator {
+        #[source_code]
+        src: Source,
+        #[label("This has type `v128`")]
+        span: SourceSpan,
+    },
+    #[error("Function `{name}` conflicts with an accessor of an exported global")]
+    #[diagnostic(
+        code(E0215),
+        help("Exported globals are exported as `get-<name>` and `set-<name>` functions")
+    )]
+    AccessorConflict {
+        #[source_code]
+        src: Source,
+        #[label("This function has the same name")]
+        span: SourceSpan,
+        #[label("As an accessor of this global")]
+        global_span: SourceSpan,
+
+        name: String,
+    },
+    #[error("Exported global `{name}` has a type that can't be exported yet")]
+    #[diagnostic(
+        code(E0228),
+        help("Only globals of primitive types like `u32` or `string` can be exported")
+    )]
+    UnexportableGlobal {
+        #[source_code]
+        src: Source,
+        #[label("`{type_name}` can't be passed by its accessors")]
+        span: SourceSpan,
+
+        name: String,
+        type_name: String,
+    },
+    #[error("`{ident}` is not a function")]
+    #[diagnostic(code(E0216))]
+    NotCallable {
+        #[source_code]
+        src: Source,
+        #[label("Called here")]
+        span: SourceSpan,
+        #[label("Defined here")]
+        defined_span: Option<SourceSpan>,
+        ident: String,
+    },
+    #[error("`{ident}` can't be used as a value")]
+    #[diagnostic(code(E0217))]
+    NotAValue {
+        #[source_code]
+        src: Source,
+        #[label("Used as a value here")]
+        span: SourceSpan,
+        ident: String,
+        #[help]
+        help: Option<String>,
+    },
+    #[error("Function `{ident}` has no result to use")]
+    #[diagnostic(code(E0218))]
+    NoResult {
+        #[source_code]
+        src: Source,
+        #[label("The result of this call is used")]
+        span: SourceSpan,
+        ident: String,
+    },
+    #[error("Return statement is missing a `{expected}` value")]
+    #[diagnostic(code(E0219))]
+    MissingReturnValue {
+        #[source_code]
+        src: Source,
+        #[label("Return a value here")]
+        span: SourceSpan,
+        #[label("Because the function returns `{expected}`")]
+        result_span: SourceSpan,
+        expected: String,
+    },
+    #[error("Function without a result can't return a value")]
+    #[diagnostic(
+        code(E0220),
+        help("Add a result type to the function, or remove the value")
+    )]
+    UnexpectedReturnValue {
+        #[source_code]
+        src: Source,
+        #[label("This value is returned")]
+        span: SourceSpan,
+    },
+    #[error("Operator can't be used with `{found}` values")]
+    #[diagnostic(code(E0221))]
+    UnsupportedOperator {
+        #[source_code]
+        src: Source,
+        #[label("Used with `{found}` here")]
+        span: SourceSpan,
+        found: ast::PrimitiveType,
+    },
+    #[error("Can't infer the type of {subject}")]
+    #[diagnostic(code(E0222))]
+    AmbiguousType {
+        #[source_code]
+        src: Source,
+        #[label("Nothing here determines its type")]
+        span: SourceSpan,
+        subject: String,
+        #[help]
+        help: String,
+    },
+    #[error("Mismatched types, expected `{expected}` but found `{found}`")]
+    #[diagnostic(
+        code(E0223),
+        help("Converting `{found}` to `{expected}` could lose information, so it isn't implicit")
+    )]
+    NarrowingConversion {
+        #[source_code]
+        src: Source,
+        #[label("Expected `{expected}` because of this")]
+        expected_span: Option<SourceSpan>,
+        #[label("Found `{found}`")]
+        found_span: SourceSpan,
+
+        expected: String,
+        found: String,
+    },
+    #[error("`{ident}` is defined more than once")]
+    #[diagnostic(
+        code(E0224),
+        help("Imports, globals, and functions share one namespace, rename one of them")
+    )]
+    DuplicateDefinition {
+        #[source_code]
+        src: Source,
+        #[label("First defined here")]
+        first_span: SourceSpan,
+        #[label("Redefined here")]
+        span: SourceSpan,
+
+        ident: String,
+    },
+    #[error("`{ident}` is read before it's assigned a value")]
+    #[diagnostic(
+        code(E0225),
+        help("Assign `{ident}` a value on every path before reading it")
+    )]
+    UnassignedRead {
+        #[source_code]
+        src: Source,
+        #[label("Read here")]
+        span: SourceSpan,
+        #[label("Declared here without a value")]
+        declared_span: SourceSpan,
+        #[label("Only assigned when this condition is true")]
+        skipped_span: Option<SourceSpan>,
+
+        ident: String,
+    },
+    #[error("Function `{ident}` doesn't return a value on every path")]
+    #[diagnostic(code(E0226), help("Add a `return` at the end of the function body"))]
+    MissingReturn {
+        #[source_code]
+        src: Source,
+        #[label("Declared to return a value")]
+        result_span: SourceSpan,
+        #[label("Falls through here without returning")]
+        end_span: SourceSpan,
+
+        ident: String,
+    },
+    #[error("Evaluating this call at compile time takes too long")]
+    #[diagnostic(
+        code(E0227),
+        help(
+            "Calls to `const` functions can nest {max_depth} deep, \
+            and make {max_calls} calls in total, at compile time"
+        )
+    )]
+    ConstEvalLimit {
+        #[source_code]
+        src: Source,
+        #[label("This call doesn't finish")]
+        span: SourceSpan,
+
+        max_depth: usize,
+        max_calls: usize,
+    },
     #[error("{0} is not yet supported")]
+    #[diagnostic(code(E0206))]
     NotYetSupported(String),
 
     #[error(transparent)]
@@ -91,11 +539,56 @@ pub enum ResolverError {
     Wit(#[from] WitError),
 }
 
+#[derive(Error, Debug, Diagnostic, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[diagnostic(severity(Warning))]
+pub enum ResolverWarning {
+    #[error("Unused variable \"{ident}\"")]
+    #[diagnostic(code(W0201))]
+    UnusedLocal {
+        #[source_code]
+        src: Source,
+        #[label("Defined here but never read")]
+        span: SourceSpan,
+        ident: String,
+    },
+    #[error("Unreachable code")]
+    #[diagnostic(code(W0202))]
+    UnreachableCode {
+        #[source_code]
+        src: Source,
+        #[label("Any code following this statement is unreachable")]
+        terminator_span: SourceSpan,
+        #[label("Unreachable code")]
+        unreachable_span: SourceSpan,
+    },
+    #[error("Literal defaults to `{primitive}`")]
+    #[diagnostic(
+        code(W0203),
+        help("Add a type annotation if a different type is intended")
+    )]
+    DefaultedLiteral {
+        #[source_code]
+        src: Source,
+        #[label("Nothing here determines its type")]
+        span: SourceSpan,
+        primitive: ast::PrimitiveType,
+    },
+}
+
 pub fn resolve(
     comp: &ast::Component,
     wit: wit::ResolvedWit,
+    diagnostics: &mut Diagnostics,
 ) -> Result<ResolvedComponent, ResolverError> {
+    definitions::check_duplicate_definitions(comp)?;
+    accessors::check_accessors(comp)?;
+
     let mut mappings: HashMap<String, ItemId> = Default::default();
+    // Builtins are added first so that any other item with the same name shadows them
+    for builtin in Builtin::all() {
+        mappings.insert(builtin.name().to_owned(), ItemId::Builtin(builtin));
+    }
 
     let mut imports = ImportResolver::default();
     imports.resolve_imports(comp, &wit)?;
@@ -107,6 +600,9 @@ pub fn resolve(
             ImportItemId::Func(func) => {
                 mappings.insert(name.to_owned(), ItemId::ImportFunc(*func));
             }
+            ImportItemId::Value(func) => {
+                mappings.insert(name.to_owned(), ItemId::ImportValue(*func));
+            }
         }
     }
 
@@ -119,21 +615,17 @@ pub fn resolve(
         mappings.insert(name.to_owned(), ItemId::Function(id));
     }
 
-    let mut global_vals: HashMap<GlobalId, ast::Literal> = HashMap::new();
-
-    for (id, global) in comp.iter_globals() {
-        let global_val = match comp.get_expression(global.init_value) {
-            ast::Expression::Literal(literal) => literal.clone(),
-            _ => panic!("Only literal expressions allowed in global initializer"),
-        };
-        global_vals.insert(id, global_val);
+    let mut evaluator = ConstEvaluator::new(comp, &mappings);
+    for (id, _) in comp.iter_globals() {
+        evaluator.eval_global(id)?;
     }
+    let global_vals = evaluator.finish();
 
     let mut funcs: HashMap<FunctionId, ResolvedFunction> = HashMap::new();
 
     for (id, function) in comp.iter_functions() {
         let resolver = FunctionResolver::new(comp, &imports, function, &mappings);
-        funcs.insert(id, resolver.resolve()?);
+        funcs.insert(id, resolver.resolve(diagnostics)?);
     }
 
     Ok(ResolvedComponent {
@@ -0,0 +1,287 @@
+use ast::{ExpressionId, FunctionId, NameId, Span, Statement, StatementId};
+use claw_ast as ast;
+
+use crate::expression::enum_case;
+use crate::mir::{Block, Body, Callee, Constant, Inst, Place, TempId};
+use crate::{
+    Builtin, ItemId, ResolvedComponent, ResolvedFunction, ResolverError, SimdOp, TailCall,
+};
+
+/// Lower the body of a function that wasn't removed as dead code into the [crate::mir].
+///
+/// Each expression is computed into its own temporary, and the temporaries
+/// are created in the order the expressions appear in the source.
+/// Calls in `tail_calls` become [Inst::ReturnCall]s.
+pub fn lower_function(
+    comp: &ast::Component,
+    rcomp: &ResolvedComponent,
+    function: FunctionId,
+    tail_calls: &[TailCall],
+) -> Result<Body, ResolverError> {
+    let mut lowerer = Lowerer {
+        comp,
+        rcomp,
+        resolved: &rcomp.funcs[&function],
+        tail_calls,
+        body: Body::default(),
+    };
+    let block = lowerer.lower_block(&comp.get_function(function).body)?;
+    lowerer.body.block = block;
+    Ok(lowerer.body)
+}
+
+struct Lowerer<'a> {
+    comp: &'a ast::Component,
+    rcomp: &'a ResolvedComponent,
+    resolved: &'a ResolvedFunction,
+    tail_calls: &'a [TailCall],
+    body: Body,
+}
+
+impl<'a> Lowerer<'a> {
+    fn lower_block(&mut self, statements: &[StatementId]) -> Result<Block, ResolverError> {
+        let mut block = Vec::new();
+        for statement in statements {
+            self.lower_statement(*statement, &mut block)?;
+        }
+        Ok(block)
+    }
+
+    fn lower_statement(
+        &mut self,
+        statement: StatementId,
+        block: &mut Block,
+    ) -> Result<(), ResolverError> {
+        block.push(Inst::Statement(self.comp.statement_span(statement)));
+        match self.comp.get_statement(statement) {
+            Statement::Let(ast::Let {
+                ident,
+                expression: Some(expression),
+                ..
+            })
+            | Statement::Assign(ast::Assign { ident, expression }) => {
+                let src = self.lower_expression(*expression, block)?;
+                let place = self.place(*ident)?;
+                block.push(Inst::Write { place, src });
+            }
+            // The local is assigned before it's read, see [crate::assignment]
+            Statement::Let(ast::Let {
+                expression: None, ..
+            }) => {}
+            Statement::Call(call) => self.lower_call(call, None, block)?,
+            Statement::If(if_statement) => {
+                let condition = self.lower_expression(if_statement.condition, block)?;
+                let inner = self.lower_block(&if_statement.block)?;
+                block.push(Inst::If {
+                    condition,
+                    block: inner,
+                });
+            }
+            Statement::Return(ast::Return { expression }) => {
+                let value = match expression {
+                    Some(expression) => {
+                        if let Some(tail_call) =
+                            self.tail_calls.iter().find(|call| call.call == *expression)
+                        {
+                            return self.lower_tail_call(tail_call, block);
+                        }
+                        Some(self.lower_expression(*expression, block)?)
+                    }
+                    None => None,
+                };
+                block.push(Inst::Return { value });
+            }
+        }
+        Ok(())
+    }
+
+    fn lower_tail_call(
+        &mut self,
+        tail_call: &TailCall,
+        block: &mut Block,
+    ) -> Result<(), ResolverError> {
+        let dest = self.temp(tail_call.call)?;
+        let call = match self.comp.get_expression(tail_call.call) {
+            ast::Expression::Call(call) => call,
+            _ => return Err(self.unexpected(self.comp.expression_span(tail_call.call))),
+        };
+        let args = self.lower_args(&call.args, block)?;
+        block.push(Inst::ReturnCall {
+            dest,
+            callee: tail_call.callee,
+            args,
+        });
+        Ok(())
+    }
+
+    fn lower_expression(
+        &mut self,
+        expression: ExpressionId,
+        block: &mut Block,
+    ) -> Result<TempId, ResolverError> {
+        let dest = self.temp(expression)?;
+        match self.comp.get_expression(expression) {
+            ast::Expression::Identifier(identifier) => match self.lookup(identifier.ident)? {
+                // Imported values are read by calling their accessor
+                ItemId::ImportValue(import) => block.push(Inst::Call {
+                    dest: Some(dest),
+                    callee: Callee::Import(import),
+                    args: vec![],
+                }),
+                _ => {
+                    let place = self.place(identifier.ident)?;
+                    block.push(Inst::Read { dest, place });
+                }
+            },
+            ast::Expression::Enum(literal) => {
+                let rtype = self.body.temps[dest];
+                let case = enum_case(literal, rtype, self.comp, &self.rcomp.imports)?;
+                let value = Constant::Integer(case as u64);
+                block.push(Inst::Const { dest, value });
+            }
+            ast::Expression::Literal(literal) => {
+                let value = match literal {
+                    ast::Literal::Integer(int) => Constant::Integer(*int),
+                    ast::Literal::Float(float) => Constant::Float(*float),
+                    ast::Literal::String(string) => Constant::String(string.clone()),
+                };
+                block.push(Inst::Const { dest, value });
+            }
+            ast::Expression::Call(call) => self.lower_call(call, Some(dest), block)?,
+            ast::Expression::Unary(unary) => {
+                let operand = self.lower_expression(unary.inner, block)?;
+                block.push(Inst::Unary {
+                    dest,
+                    op: unary.op,
+                    operand,
+                });
+            }
+            ast::Expression::Binary(binary) if binary.op.short_circuits() => {
+                let left = self.lower_expression(binary.left, block)?;
+                let mut right_block = Vec::new();
+                let right = self.lower_expression(binary.right, &mut right_block)?;
+                block.push(Inst::ShortCircuit {
+                    dest,
+                    op: binary.op,
+                    left,
+                    block: right_block,
+                    right,
+                });
+            }
+            ast::Expression::Binary(binary) => {
+                let left = self.lower_expression(binary.left, block)?;
+                let right = self.lower_expression(binary.right, block)?;
+                block.push(Inst::Binary {
+                    dest,
+                    op: binary.op,
+                    left,
+                    right,
+                });
+            }
+        }
+        match self.resolved.coercions.get(&expression) {
+            Some(rtype) => {
+                let widened = self.body.temp(*rtype);
+                block.push(Inst::Widen {
+                    dest: widened,
+                    src: dest,
+                });
+                Ok(widened)
+            }
+            None => Ok(dest),
+        }
+    }
+
+    /// Lower a call, keeping its result in `dest` if it's used.
+    fn lower_call(
+        &mut self,
+        call: &ast::Call,
+        dest: Option<TempId>,
+        block: &mut Block,
+    ) -> Result<(), ResolverError> {
+        let item = self.lookup(call.ident)?;
+        // The lane is part of the instruction rather than a value
+        if let ItemId::Builtin(Builtin::Simd(SimdOp::ExtractLane, shape)) = item {
+            let (vector, lane) = match call.args.as_slice() {
+                [vector, lane] => (*vector, *lane),
+                _ => return Err(self.unexpected(self.comp.name_span(call.ident))),
+            };
+            let lane = match self.comp.get_expression(lane) {
+                ast::Expression::Literal(ast::Literal::Integer(lane)) => *lane as u8,
+                _ => return Err(self.unexpected(self.comp.expression_span(lane))),
+            };
+            let vector = self.lower_expression(vector, block)?;
+            if let Some(dest) = dest {
+                block.push(Inst::ExtractLane {
+                    dest,
+                    shape,
+                    vector,
+                    lane,
+                });
+            }
+            return Ok(());
+        }
+
+        let args = self.lower_args(&call.args, block)?;
+        let callee = match item {
+            ItemId::Function(function) => Callee::Function(function),
+            ItemId::ImportFunc(import) => Callee::Import(import),
+            ItemId::Builtin(builtin) => {
+                // Builtins have no side effects, so an unused call does nothing
+                if let Some(dest) = dest {
+                    block.push(Inst::Builtin {
+                        dest,
+                        builtin,
+                        args,
+                    });
+                }
+                return Ok(());
+            }
+            _ => return Err(self.unexpected(self.comp.name_span(call.ident))),
+        };
+        block.push(Inst::Call { dest, callee, args });
+        Ok(())
+    }
+
+    fn lower_args(
+        &mut self,
+        args: &[ExpressionId],
+        block: &mut Block,
+    ) -> Result<Vec<TempId>, ResolverError> {
+        args.iter()
+            .map(|arg| self.lower_expression(*arg, block))
+            .collect()
+    }
+
+    /// A new temporary for the value of `expression`.
+    fn temp(&mut self, expression: ExpressionId) -> Result<TempId, ResolverError> {
+        let rtype = self.resolved.expression_type(expression, self.comp)?;
+        Ok(self.body.temp(rtype))
+    }
+
+    fn lookup(&self, ident: NameId) -> Result<ItemId, ResolverError> {
+        match self.resolved.bindings.get(&ident) {
+            Some(item) => Ok(*item),
+            None => Err(self.unexpected(self.comp.name_span(ident))),
+        }
+    }
+
+    /// The variable a name refers to.
+    fn place(&self, ident: NameId) -> Result<Place, ResolverError> {
+        match self.lookup(ident)? {
+            ItemId::Param(param) => Ok(Place::Param(param)),
+            ItemId::Local(local) => Ok(Place::Local(local)),
+            ItemId::Global(global) => Ok(Place::Global(global)),
+            _ => Err(self.unexpected(self.comp.name_span(ident))),
+        }
+    }
+
+    /// Resolution rejects any code that can't be lowered,
+    /// so this is only reached if a pass broke that.
+    fn unexpected(&self, span: Span) -> ResolverError {
+        ResolverError::Base {
+            src: self.comp.source(),
+            span,
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! The typed mid-level IR that function bodies are lowered into for code generation.
+//!
+//! Expressions are flattened into instructions that each compute one value into
+//! a fresh temporary, so the order of evaluation and every intermediate value
+//! are explicit. Control flow stays structured, as it is in Wasm.
+
+use ast::{FunctionId, GlobalId, Span};
+use claw_ast as ast;
+use cranelift_entity::{entity_impl, PrimaryMap};
+
+use crate::{Builtin, ImportFuncId, LocalId, ParamId, ResolvedType, Shape};
+
+/// A value computed by one instruction and read by later ones.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TempId(u32);
+entity_impl!(TempId, "temp");
+
+/// The lowered body of a function.
+#[derive(Debug, Clone, Default)]
+pub struct Body {
+    /// The type of each temporary, in the order they were created
+    pub temps: PrimaryMap<TempId, ResolvedType>,
+    /// The instructions run when the function is called
+    pub block: Block,
+}
+
+/// A sequence of instructions run in order.
+pub type Block = Vec<Inst>;
+
+/// Where a variable lives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Place {
+    Param(ParamId),
+    Local(LocalId),
+    Global(GlobalId),
+}
+
+/// A function that can be called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Callee {
+    Function(FunctionId),
+    Import(ImportFuncId),
+}
+
+/// A value known at compile time.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    /// An integer, which is also how enum cases are represented
+    Integer(u64),
+    Float(f64),
+    String(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inst {
+    /// The instructions up to the next marker implement the statement at `span`
+    Statement(Span),
+    /// Set a temporary to a constant
+    Const { dest: TempId, value: Constant },
+    /// Read the value of a variable
+    Read { dest: TempId, place: Place },
+    /// Overwrite the value of a variable
+    Write { place: Place, src: TempId },
+    /// Call a function, keeping its result if `dest` is set
+    Call {
+        dest: Option<TempId>,
+        callee: Callee,
+        args: Vec<TempId>,
+    },
+    /// Compute the result of a builtin from its arguments
+    Builtin {
+        dest: TempId,
+        builtin: Builtin,
+        args: Vec<TempId>,
+    },
+    /// Read one lane of a vector
+    ExtractLane {
+        dest: TempId,
+        shape: Shape,
+        vector: TempId,
+        lane: u8,
+    },
+    /// Convert a value to a wider type without losing information
+    Widen { dest: TempId, src: TempId },
+    Unary {
+        dest: TempId,
+        op: ast::UnaryOp,
+        operand: TempId,
+    },
+    Binary {
+        dest: TempId,
+        op: ast::BinaryOp,
+        left: TempId,
+        right: TempId,
+    },
+    /// Set `dest` to `left`, then if that doesn't decide the result of `op`,
+    /// which is `and` or `or`, run `block` and set `dest` to `right` instead.
+    ///
+    /// The right operand's calls and traps only happen when `block` runs.
+    ShortCircuit {
+        dest: TempId,
+        op: ast::BinaryOp,
+        left: TempId,
+        block: Block,
+        right: TempId,
+    },
+    /// Run `block` if `condition` is true
+    If { condition: TempId, block: Block },
+    /// Return from the function
+    Return { value: Option<TempId> },
+    /// Return the result of a recursive call in tail position, see [crate::TailCall].
+    ///
+    /// Backends that can't reuse the caller's frame call the function
+    /// into `dest` and return that instead.
+    ReturnCall {
+        dest: TempId,
+        callee: FunctionId,
+        args: Vec<TempId>,
+    },
+}
+
+impl Body {
+    /// Create a new temporary of the given type.
+    pub fn temp(&mut self, rtype: ResolvedType) -> TempId {
+        self.temps.push(rtype)
+    }
+}
+
+impl Inst {
+    /// The temporary this instruction sets, if any.
+    pub fn dest(&self) -> Option<TempId> {
+        match self {
+            Inst::Const { dest, .. }
+            | Inst::Read { dest, .. }
+            | Inst::Builtin { dest, .. }
+            | Inst::ExtractLane { dest, .. }
+            | Inst::Widen { dest, .. }
+            | Inst::Unary { dest, .. }
+            | Inst::Binary { dest, .. }
+            | Inst::ShortCircuit { dest, .. }
+            | Inst::ReturnCall { dest, .. } => Some(*dest),
+            Inst::Call { dest, .. } => *dest,
+            Inst::Statement(_) | Inst::Write { .. } | Inst::If { .. } | Inst::Return { .. } => None,
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use claw_ast as ast;
+use claw_common::Diagnostics;
+use miette::Diagnostic;
+use thiserror::Error;
+
+use crate::{eliminate_dead_items, fold_constants, inline_functions, DeadItem, ResolvedComponent};
+
+/// A transformation of a resolved component that doesn't change its behavior.
+///
+/// Passes run after resolution and before code generation,
+/// so they may rewrite the AST as long as the resolved information is kept in sync.
+pub trait Pass {
+    /// A short name for the pass, used when selecting passes.
+    fn name(&self) -> &'static str;
+
+    /// Transform the component, pushing notes about what was done into `notes`.
+    fn run(
+        &self,
+        comp: &mut ast::Component,
+        rcomp: &mut ResolvedComponent,
+        notes: &mut Diagnostics,
+    );
+}
+
+/// Notes reported by optimization passes.
+#[derive(Error, Debug, Diagnostic, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[diagnostic(severity(Advice))]
+pub enum PassNote {
+    #[error("Removed unused {0}")]
+    RemovedDeadItem(DeadItem),
+}
+
+/// Runs a sequence of passes in order.
+#[derive(Default)]
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>>,
+}
+
+impl PassManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a pass to run after the ones already added.
+    pub fn add(&mut self, pass: Box<dyn Pass>) {
+        self.passes.push(pass);
+    }
+
+    /// The names of the passes in the order they run.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.passes.iter().map(|pass| pass.name())
+    }
+
+    pub fn run(
+        &self,
+        comp: &mut ast::Component,
+        rcomp: &mut ResolvedComponent,
+        notes: &mut Diagnostics,
+    ) {
+        for pass in self.passes.iter() {
+            pass.run(comp, rcomp, notes);
+        }
+    }
+}
+
+/// Replaces constant subexpressions with literals, see [fold_constants].
+pub struct FoldConstants;
+
+impl Pass for FoldConstants {
+    fn name(&self) -> &'static str {
+        "fold"
+    }
+
+    fn run(&self, comp: &mut ast::Component, rcomp: &mut ResolvedComponent, _: &mut Diagnostics) {
+        fold_constants(comp, rcomp);
+    }
+}
+
+/// Inlines small functions into their callers, see [inline_functions].
+pub struct InlineFunctions {
+    /// The largest function, in expression nodes, that is inlined
+    pub threshold: usize,
+}
+
+impl Pass for InlineFunctions {
+    fn name(&self) -> &'static str {
+        "inline"
+    }
+
+    fn run(&self, comp: &mut ast::Component, rcomp: &mut ResolvedComponent, _: &mut Diagnostics) {
+        inline_functions(comp, rcomp, self.threshold);
+    }
+}
+
+/// Removes functions and globals that aren't reachable, see [eliminate_dead_items].
+pub struct EliminateDeadItems;
+
+impl Pass for EliminateDeadItems {
+    fn name(&self) -> &'static str {
+        "dce"
+    }
+
+    fn run(
+        &self,
+        comp: &mut ast::Component,
+        rcomp: &mut ResolvedComponent,
+        notes: &mut Diagnostics,
+    ) {
+        for item in eliminate_dead_items(comp, rcomp) {
+            notes.push(PassNote::RemovedDeadItem(item));
+        }
+    }
+}
@@ -1,5 +1,8 @@
+use ast::StatementId;
 use claw_ast as ast;
+use claw_common::Suggestion;
 
+use crate::expression::call_signature;
 use crate::types::{ResolvedType, RESOLVED_BOOL};
 use crate::{FunctionResolver, ItemId, LocalInfo, ResolverError};
 
@@ -13,7 +16,11 @@ pub(crate) trait ResolveStatement {
     /// * Links identifiers to their targets in resolver.bindings
     ///
     /// Record expression parents
-    fn setup_resolve(&self, resolver: &mut FunctionResolver) -> Result<(), ResolverError>;
+    fn setup_resolve(
+        &self,
+        statement: StatementId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError>;
 }
 
 macro_rules! gen_resolve_statement {
@@ -21,12 +28,13 @@ macro_rules! gen_resolve_statement {
         impl ResolveStatement for ast::Statement {
             fn setup_resolve(
                 &self,
+                statement: StatementId,
                 resolver: &mut FunctionResolver,
             ) -> Result<(), ResolverError> {
                 match self {
                     $(ast::Statement::$expr_type(inner) => {
                         let inner: &dyn ResolveStatement = inner;
-                        inner.setup_resolve(resolver)
+                        inner.setup_resolve(statement, resolver)
                     },)*
                 }
             }
@@ -37,7 +45,11 @@ macro_rules! gen_resolve_statement {
 gen_resolve_statement!([Let, Assign, Call, If, Return]);
 
 impl ResolveStatement for ast::Let {
-    fn setup_resolve(&self, resolver: &mut FunctionResolver) -> Result<(), ResolverError> {
+    fn setup_resolve(
+        &self,
+        _statement: StatementId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
         let info = LocalInfo {
             ident: self.ident.to_owned(),
             mutable: self.mutable,
@@ -46,57 +58,79 @@ impl ResolveStatement for ast::Let {
         let local = resolver.locals.push(info);
         let span = resolver.component.name_span(self.ident);
         resolver.local_spans.insert(local, span);
-        let item = ItemId::Local(local);
-        resolver.define_name(self.ident, item)?;
-
-        resolver.setup_expression(self.expression)?;
-        resolver.use_local(local, self.expression);
-
         if let Some(annotation) = self.annotation {
-            resolver.set_local_type(local, ResolvedType::Defined(annotation))
+            resolver.require_local_type(local, ResolvedType::Defined(annotation));
+        }
+        if let Some(expression) = self.expression {
+            // The initializer can't refer to the local being defined
+            resolver.setup_expression(expression)?;
+            match self.annotation {
+                // The initializer may widen to the annotated type
+                Some(annotation) => {
+                    resolver.expect_type(expression, ResolvedType::Defined(annotation))
+                }
+                None => {
+                    resolver.use_local(local, expression);
+                    resolver.stored_in.insert(expression, local);
+                }
+            }
         }
 
+        let item = ItemId::Local(local);
+        resolver.define_name(self.ident, item)?;
+
         Ok(())
     }
 }
 
 impl ResolveStatement for ast::Assign {
-    fn setup_resolve(&self, resolver: &mut FunctionResolver) -> Result<(), ResolverError> {
+    fn setup_resolve(
+        &self,
+        _statement: StatementId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
         let item = resolver.use_name(self.ident)?;
 
         match item {
             ItemId::Global(global) => {
                 let global = resolver.component.get_global(global);
-                resolver.set_expr_type(self.expression, ResolvedType::Defined(global.type_id));
+                resolver.expect_type(self.expression, ResolvedType::Defined(global.type_id));
 
                 if !global.mutable {
-                    return Err(ResolverError::AssignedToImmutable {
-                        src: resolver.component.source(),
-                        defined_span: resolver.component.name_span(global.ident),
-                        assigned_span: resolver.component.name_span(self.ident),
-                        ident: resolver.component.get_name(self.ident).to_string(),
-                    });
+                    return Err(resolver.assigned_to_immutable(self.ident, global.ident));
                 }
             }
-            ItemId::Param(param) => {
-                let param_type = *resolver.params.get(param).unwrap();
-                resolver.set_expr_type(self.expression, ResolvedType::Defined(param_type));
+            // Parameters can't be assigned to, like imported values
+            ItemId::ImportValue(_) | ItemId::Param(_) => {
+                let defined_span = resolver.item_span(item).unwrap();
+                return Err(ResolverError::AssignedToImmutable {
+                    src: resolver.component.source(),
+                    defined_span,
+                    assigned_span: resolver.component.name_span(self.ident),
+                    ident: resolver.component.get_name(self.ident).to_string(),
+                    suggestion: None,
+                });
             }
             ItemId::Local(local) => {
-                resolver.use_local(local, self.expression);
-
-                let local = resolver.locals.get(local).unwrap();
-
-                if !local.mutable {
-                    return Err(ResolverError::AssignedToImmutable {
-                        src: resolver.component.source(),
-                        defined_span: resolver.component.name_span(local.ident),
-                        assigned_span: resolver.component.name_span(self.ident),
-                        ident: resolver.component.get_name(self.ident).to_string(),
-                    });
+                let info = resolver.locals.get(local).unwrap();
+                match info.annotation {
+                    // Values may widen to the annotated type, but an inferred
+                    // type is the type of the values stored in the local
+                    Some(annotation) => {
+                        resolver.expect_type(self.expression, ResolvedType::Defined(annotation))
+                    }
+                    None => {
+                        resolver.use_local(local, self.expression);
+                        resolver.stored_in.insert(self.expression, local);
+                    }
                 }
+                // Whether an immutable local may be assigned depends on
+                // whether it already has a value, see [crate::assignment]
+            }
+            ItemId::Function(_) | ItemId::ImportFunc(_) | ItemId::Builtin(_) | ItemId::Type(_) => {
+                let span = resolver.component.name_span(self.ident);
+                return Err(resolver.not_a_value(self.ident, span));
             }
-            _ => {}
         }
 
         resolver.setup_expression(self.expression)
@@ -104,8 +138,20 @@ impl ResolveStatement for ast::Assign {
 }
 
 impl ResolveStatement for ast::Call {
-    fn setup_resolve(&self, resolver: &mut FunctionResolver) -> Result<(), ResolverError> {
-        resolver.use_name(self.ident)?;
+    fn setup_resolve(
+        &self,
+        statement: StatementId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        let item = resolver.use_name(self.ident)?;
+        let span = resolver.component.statement_span(statement);
+        // The result is unused, so functions without one can be called too
+        let signature = call_signature(self, item, span, resolver)?;
+        if let Some(signature) = signature {
+            for (arg, rtype) in self.args.iter().zip(signature.params) {
+                resolver.expect_type(*arg, rtype);
+            }
+        }
         for arg in self.args.iter() {
             resolver.setup_expression(*arg)?;
         }
@@ -114,28 +160,45 @@ impl ResolveStatement for ast::Call {
 }
 
 impl ResolveStatement for ast::If {
-    fn setup_resolve(&self, resolver: &mut FunctionResolver) -> Result<(), ResolverError> {
-        resolver.set_expr_type(self.condition, RESOLVED_BOOL);
+    fn setup_resolve(
+        &self,
+        _statement: StatementId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        resolver.require_type(self.condition, RESOLVED_BOOL);
         resolver.setup_expression(self.condition)?;
         resolver.setup_block(&self.block)
     }
 }
 
 impl ResolveStatement for ast::Return {
-    fn setup_resolve(&self, resolver: &mut FunctionResolver) -> Result<(), ResolverError> {
+    fn setup_resolve(
+        &self,
+        statement: StatementId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
         let return_type = resolver.function.results;
         match (return_type, self.expression) {
             (Some(return_type), Some(expression)) => {
                 let rtype = ResolvedType::Defined(return_type);
-                resolver.set_expr_type(expression, rtype);
+                resolver.require_type(expression, rtype);
                 resolver.setup_expression(expression)?;
             }
-            (Some(_), None) => panic!(
-                "Return statements must contain an expression when function has a return type"
-            ),
-            (None, Some(_)) => panic!(
-                "Return statements can't contain an expression when function has no return type"
-            ),
+            (Some(return_type), None) => {
+                let comp = resolver.component;
+                return Err(ResolverError::MissingReturnValue {
+                    src: comp.source(),
+                    span: comp.statement_span(statement),
+                    result_span: comp.type_span(return_type),
+                    expected: comp.get_type(return_type).name(comp),
+                });
+            }
+            (None, Some(expression)) => {
+                return Err(ResolverError::UnexpectedReturnValue {
+                    src: resolver.component.source(),
+                    span: resolver.component.expression_span(expression),
+                });
+            }
             (None, None) => {
                 // No child expression or return type, so do nothing
             }
@@ -144,3 +207,26 @@ impl ResolveStatement for ast::Return {
         Ok(())
     }
 }
+
+impl<'ctx> FunctionResolver<'ctx> {
+    /// An assignment through `assigned` to the immutable `let` that defined `defined`,
+    /// suggesting that it's made mutable.
+    pub(crate) fn assigned_to_immutable(
+        &self,
+        assigned: ast::NameId,
+        defined: ast::NameId,
+    ) -> ResolverError {
+        let comp = self.component;
+        let defined_span = comp.name_span(defined);
+        let ident = comp.get_name(assigned).to_string();
+        let message = format!("Make `{}` mutable with `let mut`", ident);
+        let suggestion = Suggestion::insert(defined_span.offset(), "mut ", &message);
+        ResolverError::AssignedToImmutable {
+            src: comp.source(),
+            defined_span,
+            assigned_span: comp.name_span(assigned),
+            ident,
+            suggestion: Some(Box::new(suggestion)),
+        }
+    }
+}
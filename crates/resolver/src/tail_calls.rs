@@ -0,0 +1,91 @@
+use std::collections::{HashMap, HashSet};
+
+use ast::{Expression, ExpressionId, FunctionId, Statement, StatementId};
+use claw_ast as ast;
+
+use crate::{ItemId, ResolvedComponent, ResolvedFunction};
+
+/// A call whose result is returned directly by its caller, as in `return f(x);`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TailCall {
+    /// The call expression
+    pub call: ExpressionId,
+    /// The function being called
+    pub callee: FunctionId,
+}
+
+/// Find the recursive calls in tail position in each function.
+///
+/// A call is recursive when the function it calls can call back into the
+/// caller, directly or through other functions. Making these calls reuse
+/// their caller's stack frame keeps deep recursion from overflowing the stack.
+/// Functions without any are left out.
+pub fn recursive_tail_calls(
+    comp: &ast::Component,
+    rcomp: &ResolvedComponent,
+) -> HashMap<FunctionId, Vec<TailCall>> {
+    let mut result = HashMap::new();
+    for (id, function) in comp.iter_functions() {
+        let resolved = match rcomp.funcs.get(&id) {
+            Some(resolved) => resolved,
+            None => continue,
+        };
+        let mut calls = Vec::new();
+        find_tail_calls(comp, resolved, &function.body, &mut calls);
+        calls.retain(|call| calls_back(rcomp, call.callee, id));
+        if !calls.is_empty() {
+            result.insert(id, calls);
+        }
+    }
+    result
+}
+
+fn find_tail_calls(
+    comp: &ast::Component,
+    resolved: &ResolvedFunction,
+    block: &[StatementId],
+    calls: &mut Vec<TailCall>,
+) {
+    for statement in block.iter() {
+        match comp.get_statement(*statement) {
+            Statement::Return(ast::Return {
+                expression: Some(expression),
+            }) => {
+                if let Expression::Call(call) = comp.get_expression(*expression) {
+                    if let Some(ItemId::Function(callee)) = resolved.bindings.get(&call.ident) {
+                        calls.push(TailCall {
+                            call: *expression,
+                            callee: *callee,
+                        });
+                    }
+                }
+            }
+            Statement::If(if_statement) => {
+                find_tail_calls(comp, resolved, &if_statement.block, calls);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether `from` can call `to`, directly or through other functions.
+fn calls_back(rcomp: &ResolvedComponent, from: FunctionId, to: FunctionId) -> bool {
+    let mut seen: HashSet<FunctionId> = HashSet::new();
+    let mut queue = vec![from];
+    while let Some(id) = queue.pop() {
+        if id == to {
+            return true;
+        }
+        if !seen.insert(id) {
+            continue;
+        }
+        if let Some(function) = rcomp.funcs.get(&id) {
+            for item in function.bindings.values() {
+                if let ItemId::Function(function) = item {
+                    queue.push(*function);
+                }
+            }
+        }
+    }
+    false
+}
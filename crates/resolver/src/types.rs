@@ -1,7 +1,7 @@
-use ast::TypeId;
+use ast::{Span, TypeId};
 use claw_ast as ast;
 
-use crate::imports::ImportTypeId;
+use crate::imports::{ImportResolver, ImportType, ImportTypeId};
 
 #[derive(Clone, Copy, Debug)]
 pub enum ResolvedType {
@@ -29,6 +29,37 @@ impl std::fmt::Display for ResolvedType {
 }
 
 impl ResolvedType {
+    /// The name of this type as it would be written in source code.
+    pub fn name(&self, comp: &ast::Component, imports: &ImportResolver) -> String {
+        match self {
+            ResolvedType::Primitive(primitive) => primitive.to_string(),
+            ResolvedType::Import(import_type) => match &imports.types[*import_type] {
+                ImportType::Enum(import_enum) => import_enum.name.clone(),
+            },
+            ResolvedType::Defined(type_id) => comp.get_type(*type_id).name(comp),
+        }
+    }
+
+    /// The span of the annotation this type came from, if it has one.
+    pub fn span(&self, comp: &ast::Component) -> Option<Span> {
+        match self {
+            ResolvedType::Defined(type_id) => Some(comp.type_span(*type_id)),
+            _ => None,
+        }
+    }
+
+    /// The primitive type this type refers to, if it is one.
+    pub fn primitive(&self, comp: &ast::Component) -> Option<ast::PrimitiveType> {
+        match self {
+            ResolvedType::Primitive(primitive) => Some(*primitive),
+            ResolvedType::Defined(type_id) => match comp.get_type(*type_id) {
+                ast::ValType::Primitive(primitive) => Some(*primitive),
+                _ => None,
+            },
+            ResolvedType::Import(_) => None,
+        }
+    }
+
     pub fn type_eq(&self, other: &ResolvedType, comp: &ast::Component) -> bool {
         match (*self, *other) {
             // Both primitive
@@ -48,7 +79,10 @@ impl ResolvedType {
                     _ => false,
                 }
             }
-            _ => todo!(),
+            // Both imported
+            (ResolvedType::Import(left), ResolvedType::Import(right)) => left == right,
+            // One imported, the other not
+            (ResolvedType::Import(_), _) | (_, ResolvedType::Import(_)) => false,
         }
     }
 }
@@ -7,16 +7,20 @@ pub use wit_parser::{
     TypeOwner,
 };
 
-#[derive(Error, Debug, Diagnostic)]
+#[derive(Error, Debug, Diagnostic, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum WitError {
     #[error("Package {package} does not exist")]
+    #[diagnostic(code(E0301))]
     NoSuchPackage { package: PackageName },
     #[error("Interface {interface} does not exist in package {package}")]
+    #[diagnostic(code(E0302))]
     NoSuchInterface {
         package: PackageName,
         interface: String,
     },
     #[error("Item {item} does not exists in interface {interface}")]
+    #[diagnostic(code(E0303))]
     NoSuchItem { interface: String, item: String },
 }
 
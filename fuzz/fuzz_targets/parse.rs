@@ -0,0 +1,48 @@
+//! Check and compile arbitrary text, which can fail but must never panic.
+//!
+//! Checking resolves the source and evaluates its constants, and compiling
+//! with optimizations also folds the constants in function bodies.
+//!
+//! Source that can be formatted must format the same way a second time.
+
+#![no_main]
+
+use claw_common::Diagnostics;
+use compile_claw::{
+    check_with_options, compile_with_options, format_source, CompileOptions, OptLevel,
+};
+use libfuzzer_sys::fuzz_target;
+use wit_parser::Resolve;
+
+fuzz_target!(|data: &[u8]| {
+    let source = match std::str::from_utf8(data) {
+        Ok(source) => source,
+        Err(_) => return,
+    };
+
+    let _ = check_with_options(
+        "fuzz.claw".to_owned(),
+        source,
+        Resolve::new(),
+        &CompileOptions::default(),
+        &mut Diagnostics::new(),
+    );
+
+    let options = CompileOptions {
+        opt_level: OptLevel::O1,
+        ..Default::default()
+    };
+    let _ = compile_with_options(
+        "fuzz.claw".to_owned(),
+        source,
+        Resolve::new(),
+        &options,
+        &mut Diagnostics::new(),
+    );
+
+    if let Ok(formatted) = format_source("fuzz.claw".to_owned(), source) {
+        let again = format_source("fuzz.claw".to_owned(), &formatted)
+            .expect("Formatted source should parse");
+        assert_eq!(formatted, again, "Formatting should be idempotent");
+    }
+});
@@ -0,0 +1,59 @@
+//! Differential fuzzing of the parser and the formatter.
+//!
+//! Each generated [Component] comes with the source text it should parse from,
+//! so parsing that text must give back the same tree, spans included.
+//! Formatting the text and parsing it again must give the same tree
+//! with different spans. Printing the tree without its source must
+//! give code that parses into the same structure.
+
+#![no_main]
+
+use claw_ast::Component;
+use claw_common::Diagnostics;
+use compile_claw::{format_source, parse_component};
+use claw_parser::check_round_trip;
+use libfuzzer_sys::fuzz_target;
+use serde_json::Value;
+
+fuzz_target!(|comp: Component| {
+    let source = comp.source();
+    let source = source.inner();
+
+    let parsed = parse_component("arbitrary.claw".to_owned(), source, &mut Diagnostics::new())
+        .expect("Generated source should parse");
+    assert_eq!(to_json(&comp), to_json(&parsed), "source:\n{}", source);
+
+    let formatted = format_source("arbitrary.claw".to_owned(), source)
+        .expect("Generated source should format");
+    let reparsed = parse_component("arbitrary.claw".to_owned(), &formatted, &mut Diagnostics::new())
+        .expect("Formatted source should parse");
+    let mut expected = to_json(&comp);
+    let mut found = to_json(&reparsed);
+    strip_spans(&mut expected);
+    strip_spans(&mut found);
+    assert_eq!(expected, found, "formatted:\n{}", formatted);
+
+    let again = format_source("arbitrary.claw".to_owned(), &formatted)
+        .expect("Formatted source should format");
+    assert_eq!(formatted, again, "Formatting should be idempotent");
+
+    if let Err(error) = check_round_trip(&comp) {
+        panic!("{}", error);
+    }
+});
+
+fn to_json(comp: &Component) -> Value {
+    serde_json::to_value(comp).expect("Components can be serialized")
+}
+
+/// Remove the spans from serialized nodes, which change when the source is reformatted.
+fn strip_spans(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            fields.remove("span");
+            fields.values_mut().for_each(strip_spans);
+        }
+        Value::Array(values) => values.iter_mut().for_each(strip_spans),
+        _ => {}
+    }
+}
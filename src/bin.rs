@@ -1,14 +1,36 @@
-use std::{fs, path::PathBuf, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
 
 use clap::Parser;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
 
-use claw_codegen::generate;
-use claw_common::OkPretty;
-use claw_parser::{parse, tokenize};
-use claw_resolver::{resolve, wit::ResolvedWit};
-use miette::NamedSource;
+use claw_common::Diagnostics;
+use compile_claw::{
+    analyze, check_with_options, compile_project, compile_with_options, compile_with_timings,
+    compose, dump_ast, dump_tokens, explain, format_source, generate_wit, link, parse_component,
+    CompileOptions, CompileTimings, Dependency, FsProvider, IncrementalCompiler, LinkError,
+    OptLevel, OutputFormat, PassKind, PostProcess, SourceProvider, TargetWorld, WarningLevel,
+    WasmFeatures, DEFAULT_INLINE_THRESHOLD,
+};
+use miette::{Diagnostic, GraphicalReportHandler, JSONReportHandler};
 use wit_parser::Resolve;
 
+mod config;
+#[cfg(feature = "repl")]
+mod repl;
+#[cfg(feature = "test-runner")]
+mod test;
+
+use config::{BuildConfig, Project, CONFIG_FILE};
+
 #[derive(Parser, Debug)]
 struct Arguments {
     #[clap(subcommand)]
@@ -17,51 +39,846 @@ struct Arguments {
 
 #[derive(Parser, Debug)]
 enum Command {
-    Compile(Compile),
+    #[clap(alias = "compile")]
+    Build(Build),
+    Check(Check),
+    Explain(Explain),
+    Ast(Ast),
+    Tokens(Tokens),
+    Fmt(Fmt),
+    Wit(Wit),
+    #[cfg(feature = "repl")]
+    Repl(repl::Repl),
+    #[cfg(feature = "test-runner")]
+    Test(test::Test),
 }
 
+/// Compile a Claw source file into a WebAssembly component
+///
+/// Without an input file, the project is built as configured in the closest `claw.toml`,
+/// with any options given here taking precedence.
 #[derive(Parser, Debug)]
-struct Compile {
-    #[clap(short, long)]
-    input: PathBuf,
+struct Build {
+    /// The source file to compile, or `-` to read it from stdin
+    #[clap(conflicts_with = "input-flag")]
+    input: Option<PathBuf>,
+    /// The source file to compile, for compatibility with older versions
+    #[clap(short = 'i', long = "input", hide = true)]
+    input_flag: Option<PathBuf>,
+    /// A WIT file or directory describing the imports
     #[clap(long)]
     wit: Option<PathBuf>,
+    /// A WIT world the exports must conform to, as `path/to/file.wit#world`
+    #[clap(long)]
+    world: Option<String>,
+    /// Where to write the component, or `-` to write it to stdout
     #[clap(short, long)]
+    output: Option<PathBuf>,
+    /// The format to write the component in (wasm or wat) [default: wasm]
+    #[clap(long)]
+    emit: Option<OutputFormat>,
+    /// How to treat warnings (allow, warn, or deny) [default: warn]
+    #[clap(short = 'W', long)]
+    warnings: Option<WarningLevel>,
+    /// Fail compilation if any warnings are produced (same as `-W deny`)
+    #[clap(long)]
+    deny_warnings: bool,
+    /// Trap on integer overflow instead of wrapping
+    #[clap(long)]
+    overflow_checks: bool,
+    /// How much to optimize the generated code (0, 1, or 2) [default: 0]
+    #[clap(short = 'O', long)]
+    opt_level: Option<OptLevel>,
+    /// The largest function, in expression nodes, that is inlined into its callers
+    /// [default: 8]
+    #[clap(long)]
+    inline_threshold: Option<usize>,
+    /// Run these passes in order instead of the ones chosen by the optimization level
+    /// (comma separated list of inline, fold, or dce)
+    #[clap(long, use_value_delimiter = true)]
+    passes: Option<Vec<PassKind>>,
+    /// Name functions, locals, and globals in the output so it's easier to debug
+    #[clap(long)]
+    debug_names: bool,
+    /// Embed a source map so debuggers can step through the Claw source
+    #[clap(short = 'g', long)]
+    debug_info: bool,
+    /// Record the compiler version, options, and source files in the output
+    #[clap(long)]
+    metadata: bool,
+    /// Reject values that are allocated on the heap, like strings
+    #[clap(long)]
+    no_alloc: bool,
+    /// Make recursive calls in tail position with the tail call proposal's return_call
+    /// (same as `--enable tail-call`)
+    #[clap(long)]
+    tail_calls: bool,
+    /// Allow the v128 type and SIMD builtins, which need the fixed-width SIMD proposal
+    /// (same as `--enable simd`)
+    #[clap(long)]
+    simd: bool,
+    /// Copy memory with byte loops instead of the bulk memory proposal's instructions
+    /// (same as `--disable bulk-memory`)
+    #[clap(long)]
+    no_bulk_memory: bool,
+    /// WebAssembly features the output may use, on top of the defaults and the
+    /// configuration (comma separated list of mutable-globals, sign-ext, bulk-memory,
+    /// simd, tail-call, or multi-value)
+    #[clap(long, use_value_delimiter = true)]
+    enable: Vec<String>,
+    /// WebAssembly features the output must not use, so it runs on runtimes without them
+    /// (a feature can't be both enabled and disabled)
+    #[clap(long, use_value_delimiter = true)]
+    disable: Vec<String>,
+    /// Optimize the generated code with wasm-opt, passing it these space separated
+    /// arguments (for example "-O2")
+    #[clap(long, allow_hyphen_values = true)]
+    wasm_opt: Option<String>,
+    /// Report what the optimizations did
+    #[clap(short, long)]
+    verbose: bool,
+    /// How to print diagnostics (human or json)
+    #[clap(long, default_value = "human")]
+    message_format: MessageFormat,
+    /// Rebuild whenever the input or WIT changes, reusing unchanged code
+    #[clap(long)]
+    watch: bool,
+    /// Print how long each phase of compilation took
+    #[clap(long, conflicts_with = "watch")]
+    timings: bool,
+}
+
+/// What to build, from the arguments and the project configuration.
+struct BuildPlan {
+    /// The name the inputs are compiled as when there are several of them
+    name: String,
+    inputs: Vec<PathBuf>,
     output: PathBuf,
+    emit: OutputFormat,
+    wit: Vec<PathBuf>,
+    options: CompileOptions,
+    /// The compiled components the output is linked against
+    dependencies: Vec<(String, PathBuf)>,
+    /// Whether the output is composed with its dependencies
+    compose: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum MessageFormat {
+    Human,
+    Json,
 }
 
-impl Compile {
+impl MessageFormat {
+    fn print(self, diagnostic: &dyn Diagnostic) {
+        println!("{}", self.render(diagnostic));
+    }
+
+    /// Print to stderr, for when stdout is used for the output.
+    fn eprint(self, diagnostic: &dyn Diagnostic) {
+        eprintln!("{}", self.render(diagnostic));
+    }
+
+    fn render(self, diagnostic: &dyn Diagnostic) -> String {
+        let mut output = String::new();
+        let result = match self {
+            MessageFormat::Human => {
+                GraphicalReportHandler::new().render_report(&mut output, diagnostic)
+            }
+            MessageFormat::Json => JSONReportHandler::new().render_report(&mut output, diagnostic),
+        };
+        match result {
+            Ok(_) => output,
+            Err(err) => format!("Error: {:?}", err),
+        }
+    }
+}
+
+impl FromStr for MessageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(MessageFormat::Human),
+            "json" => Ok(MessageFormat::Json),
+            _ => Err(format!(
+                "Unknown message format \"{}\", expected human or json",
+                s
+            )),
+        }
+    }
+}
+
+impl Build {
     fn run(self) -> Option<()> {
-        let file_name = self.input.file_name()?.to_string_lossy().to_string();
-        let file_string = std::fs::read_to_string(&self.input).ok()?;
-        let src = Arc::new(NamedSource::new(file_name, file_string.clone()));
+        let plan = match self.plan() {
+            Ok(plan) => plan,
+            Err(message) => {
+                println!("Error: {}", message);
+                return None;
+            }
+        };
+
+        if self.watch {
+            return self.watch(&plan);
+        }
+        self.build(&plan, None)
+    }
+
+    /// The features from the configuration, changed by the arguments.
+    ///
+    /// The arguments always win over the configuration, and there's no order between
+    /// the arguments themselves, so a feature that's both enabled and disabled is an error.
+    fn features(&self, config: &BuildConfig) -> Result<WasmFeatures, String> {
+        let mut features = config.features()?;
+        for (name, enabled) in self.feature_changes()? {
+            features.set(name, enabled)?;
+        }
+        Ok(features)
+    }
+
+    /// The features enabled or disabled by the arguments,
+    /// including by the shorthands like `--simd`.
+    fn feature_changes(&self) -> Result<BTreeMap<&str, bool>, String> {
+        let shorthands = [
+            ("tail-call", self.tail_calls, true),
+            ("simd", self.simd, true),
+            ("bulk-memory", self.no_bulk_memory, false),
+        ];
+        let shorthands = shorthands
+            .iter()
+            .filter(|(_, given, _)| *given)
+            .map(|&(name, _, enabled)| (name, enabled));
+        let enabled = self.enable.iter().map(|name| (name.as_str(), true));
+        let disabled = self.disable.iter().map(|name| (name.as_str(), false));
+
+        let mut changes = BTreeMap::new();
+        for (name, enabled) in shorthands.chain(enabled).chain(disabled) {
+            if changes.insert(name, enabled) == Some(!enabled) {
+                return Err(format!("the {} feature is both enabled and disabled", name));
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Combine the arguments with the project configuration, if there is no input file.
+    fn plan(&self) -> Result<BuildPlan, String> {
+        let input = self.input.as_ref().or(self.input_flag.as_ref());
+        let (project, config, dependencies) = match input {
+            Some(_) => (None, BuildConfig::default(), BTreeMap::new()),
+            None => {
+                let mut project = Project::find()?;
+                let config = std::mem::take(&mut project.config.build);
+                let dependencies = std::mem::take(&mut project.config.dependencies);
+                (Some(project), config, dependencies)
+            }
+        };
+        let path = |path: &PathBuf| match &project {
+            Some(project) => project.path(path),
+            None => path.clone(),
+        };
 
-        let tokens = tokenize(src.clone(), &file_string).ok_pretty()?;
+        let inputs = match input {
+            Some(input) => vec![input.clone()],
+            None => config.entry.iter().map(path).collect(),
+        };
+        if inputs.is_empty() {
+            return Err(format!("no entry files are listed in {}", CONFIG_FILE));
+        }
+        let output = match (&self.output, &config.output) {
+            (Some(output), _) => output.clone(),
+            (None, Some(output)) => path(output),
+            (None, None) => return Err("no output file given".to_owned()),
+        };
+        let wit = match &self.wit {
+            Some(wit) => vec![wit.clone()],
+            None => config.wit.iter().map(path).collect(),
+        };
+
+        let warnings = match self.deny_warnings {
+            true => WarningLevel::Deny,
+            false => self.warnings.or(config.warnings()?).unwrap_or_default(),
+        };
+        let options = CompileOptions {
+            warnings,
+            overflow_checks: self.overflow_checks || config.overflow_checks.unwrap_or(false),
+            opt_level: self.opt_level.or(config.opt_level()?).unwrap_or_default(),
+            verbose: self.verbose,
+            inline_threshold: self
+                .inline_threshold
+                .or(config.inline_threshold)
+                .unwrap_or(DEFAULT_INLINE_THRESHOLD),
+            passes: self.passes.clone().or(config.passes()?),
+            debug_names: self.debug_names || config.debug_names.unwrap_or(false),
+            debug_info: self.debug_info || config.debug_info.unwrap_or(false),
+            metadata: self.metadata || config.metadata.unwrap_or(false),
+            no_alloc: self.no_alloc || config.no_alloc.unwrap_or(false),
+            features: self.features(&config)?,
+            post_process: match (&self.wasm_opt, &config.wasm_opt) {
+                (Some(args), _) => {
+                    let args = args.split_whitespace().map(str::to_owned).collect();
+                    vec![PostProcess::wasm_opt(args)]
+                }
+                (None, Some(args)) => vec![PostProcess::wasm_opt(args.clone())],
+                (None, None) => Vec::new(),
+            },
+            world: self.world.as_deref().map(load_world).transpose()?,
+        };
+
+        if inputs.len() > 1 && (self.watch || self.timings) {
+            return Err("--watch and --timings only support a single entry file".to_owned());
+        }
 
-        let comp = parse(src.clone(), tokens).ok_pretty()?;
+        let name = match &project {
+            Some(project) => project.name(),
+            None => "project".to_owned(),
+        };
+        let dependencies = dependencies
+            .iter()
+            .map(|(name, dependency)| (name.clone(), path(dependency)))
+            .collect();
+        Ok(BuildPlan {
+            name,
+            inputs,
+            output,
+            emit: self.emit.or(config.emit()?).unwrap_or_default(),
+            wit,
+            options,
+            dependencies,
+            compose: config.compose.unwrap_or(false),
+        })
+    }
 
-        let mut wit = Resolve::new();
-        if let Some(wit_path) = self.wit {
-            wit.push_path(wit_path).unwrap();
+    /// Build the inputs once, reusing code from `compiler` if there is one.
+    fn build(&self, plan: &BuildPlan, compiler: Option<&mut IncrementalCompiler>) -> Option<()> {
+        let mut sources = Vec::new();
+        for input in plan.inputs.iter() {
+            sources.push(read_source(input)?);
         }
-        let wit = ResolvedWit::new(wit);
-        let rcomp = resolve(&comp, wit).ok_pretty()?;
+        let wit = load_wit(&plan.wit)?;
+        let options = &plan.options;
+
+        let to_stdout = plan.output == Path::new(STDIO);
+        let report = |diagnostic: &dyn Diagnostic| match to_stdout {
+            true => self.message_format.eprint(diagnostic),
+            false => self.message_format.print(diagnostic),
+        };
 
-        let wasm = generate(&comp, &rcomp).ok_pretty()?;
+        let mut diagnostics = Diagnostics::new();
+        let mut timings = CompileTimings::default();
+        let result = match (compiler, sources.as_slice()) {
+            (Some(compiler), [(file_name, file_string)]) => {
+                compiler.compile(file_name.clone(), file_string, wit, &mut diagnostics)
+            }
+            (None, [(file_name, file_string)]) if self.timings => compile_with_timings(
+                file_name.clone(),
+                file_string,
+                wit,
+                options,
+                &mut diagnostics,
+                &mut timings,
+            ),
+            (None, [(file_name, file_string)]) => compile_with_options(
+                file_name.clone(),
+                file_string,
+                wit,
+                options,
+                &mut diagnostics,
+            ),
+            _ => compile_project(plan.name.clone(), &sources, wit, options, &mut diagnostics),
+        };
+        for diagnostic in diagnostics.iter() {
+            report(diagnostic.as_ref());
+        }
+        if self.timings {
+            match to_stdout {
+                true => eprintln!("{}", timings),
+                false => println!("{}", timings),
+            }
+        }
+        let wasm = match result {
+            Ok(wasm) => wasm,
+            Err(error) => {
+                report(&error);
+                return None;
+            }
+        };
+        let wasm = match link_dependencies(plan, wasm)? {
+            Ok(wasm) => wasm,
+            Err(error) => {
+                report(&error);
+                return None;
+            }
+        };
+        let output = match plan.emit.encode(wasm) {
+            Ok(output) => output,
+            Err(error) => {
+                report(&error);
+                return None;
+            }
+        };
 
-        match fs::write(&self.output, wasm) {
+        if to_stdout {
+            if let Err(err) = io::stdout().write_all(&output) {
+                eprintln!("Error: {:?}", err);
+                return None;
+            }
+            return Some(());
+        }
+        match fs::write(&plan.output, output) {
             Ok(_) => println!("Done"),
-            Err(err) => println!("Error: {:?}", err),
+            Err(err) => {
+                println!("Error: {:?}", err);
+                return None;
+            }
         }
 
         Some(())
     }
+
+    /// Build the input every time it or the WIT changes, until interrupted.
+    ///
+    /// The operating system reports changes through [notify].
+    fn watch(&self, plan: &BuildPlan) -> Option<()> {
+        let input = &plan.inputs[0];
+        if input == Path::new(STDIO) || plan.output == Path::new(STDIO) {
+            println!("Error: --watch can't be used with stdin or stdout");
+            return None;
+        }
+
+        let mut paths = plan.inputs.clone();
+        paths.extend(plan.wit.iter().cloned());
+        let paths: Vec<PathBuf> = paths.iter().map(|path| absolute(path)).collect();
+
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = match notify::recommended_watcher(sender) {
+            Ok(watcher) => watcher,
+            Err(err) => {
+                println!("Error: could not watch for changes: {}", err);
+                return None;
+            }
+        };
+        for path in paths.iter() {
+            // Editors often save by replacing a file, which ends watches on the file itself
+            let result = match path.is_dir() {
+                true => watcher.watch(path, RecursiveMode::Recursive),
+                false => match path.parent() {
+                    Some(parent) => watcher.watch(parent, RecursiveMode::NonRecursive),
+                    None => watcher.watch(path, RecursiveMode::NonRecursive),
+                },
+            };
+            if let Err(err) = result {
+                println!("Error: could not watch {}: {}", path.display(), err);
+                return None;
+            }
+        }
+
+        let mut compiler = IncrementalCompiler::new(plan.options.clone());
+        loop {
+            let built = self.build(plan, Some(&mut compiler));
+            if built.is_some() && self.verbose {
+                println!(
+                    "Reused {} function(s) and generated {}",
+                    compiler.reused(),
+                    compiler.generated()
+                );
+            }
+            println!("Watching {} for changes", input.display());
+
+            loop {
+                match receiver.recv() {
+                    Ok(Ok(event)) if is_change(&event, &paths) => break,
+                    Ok(Ok(_)) => {}
+                    Ok(Err(err)) => println!("Error: {}", err),
+                    Err(_) => return None,
+                }
+            }
+            // A save can take several events, so wait for the rest before building
+            thread::sleep(SETTLE_TIME);
+            while receiver.try_recv().is_ok() {}
+        }
+    }
+}
+
+/// Check the output's imports against its dependencies, composing them if configured to.
+fn link_dependencies(plan: &BuildPlan, wasm: Vec<u8>) -> Option<Result<Vec<u8>, LinkError>> {
+    if plan.dependencies.is_empty() {
+        return Some(Ok(wasm));
+    }
+
+    let mut dependencies = Vec::new();
+    for (name, path) in plan.dependencies.iter() {
+        match fs::read(path) {
+            Ok(component) => dependencies.push(Dependency {
+                name: name.clone(),
+                component,
+            }),
+            Err(err) => {
+                println!("Error: could not read {}: {}", path.display(), err);
+                return None;
+            }
+        }
+    }
+
+    Some(match plan.compose {
+        true => compose(&wasm, &dependencies),
+        false => link(&wasm, &dependencies).map(|_| wasm),
+    })
+}
+
+/// How long to wait after a change for the rest of the events it caused.
+const SETTLE_TIME: Duration = Duration::from_millis(50);
+
+/// Whether `event` changed a file at or under one of the paths.
+fn is_change(event: &Event, paths: &[PathBuf]) -> bool {
+    if let EventKind::Access(_) = event.kind {
+        return false;
+    }
+    event
+        .paths
+        .iter()
+        .any(|changed| paths.iter().any(|path| changed.starts_with(path)))
+}
+
+/// `path` made absolute, so it can be compared with the paths of events.
+fn absolute(path: &Path) -> PathBuf {
+    match fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(_) => match std::env::current_dir() {
+            Ok(dir) => dir.join(path),
+            Err(_) => path.to_owned(),
+        },
+    }
+}
+
+/// Check a Claw source file for errors without generating code
+#[derive(Parser, Debug)]
+struct Check {
+    /// The source file to check, or `-` to read it from stdin
+    input: PathBuf,
+    /// A WIT file or directory describing the imports
+    #[clap(long)]
+    wit: Option<PathBuf>,
+    /// A WIT world the exports must conform to, as `path/to/file.wit#world`
+    #[clap(long)]
+    world: Option<String>,
+    /// How to treat warnings (allow, warn, or deny)
+    #[clap(short = 'W', long, default_value = "warn")]
+    warnings: WarningLevel,
+    /// Fail if any warnings are produced (same as `-W deny`)
+    #[clap(long)]
+    deny_warnings: bool,
+    /// Reject values that are allocated on the heap, like strings
+    #[clap(long)]
+    no_alloc: bool,
+    /// Allow the v128 type and SIMD builtins
+    #[clap(long)]
+    simd: bool,
+    /// How to print diagnostics (human or json)
+    #[clap(long, default_value = "human")]
+    message_format: MessageFormat,
+}
+
+impl Check {
+    fn run(self) -> Option<()> {
+        let (file_name, file_string) = read_source(&self.input)?;
+        let wit = load_wit(self.wit.as_slice())?;
+        let world = match self.world.as_deref().map(load_world).transpose() {
+            Ok(world) => world,
+            Err(message) => {
+                println!("Error: {}", message);
+                return None;
+            }
+        };
+
+        let options = CompileOptions {
+            warnings: warning_level(self.warnings, self.deny_warnings),
+            no_alloc: self.no_alloc,
+            features: WasmFeatures {
+                simd: self.simd,
+                ..Default::default()
+            },
+            world,
+            ..Default::default()
+        };
+        let mut diagnostics = Diagnostics::new();
+        let result = check_with_options(file_name, &file_string, wit, &options, &mut diagnostics);
+        for diagnostic in diagnostics.iter() {
+            self.message_format.print(diagnostic.as_ref());
+        }
+        if let Err(error) = result {
+            self.message_format.print(&error);
+            return None;
+        }
+
+        Some(())
+    }
+}
+
+/// The path that means stdin when used as an input, or stdout when used as an output.
+const STDIO: &str = "-";
+
+/// The name used in diagnostics for source read from stdin.
+const STDIN_NAME: &str = "<stdin>";
+
+/// Read a source file, or stdin when the path is `-`, returning its name and contents.
+fn read_source(path: &Path) -> Option<(String, String)> {
+    if path == Path::new(STDIO) {
+        let mut file_string = String::new();
+        return match io::stdin().read_to_string(&mut file_string) {
+            Ok(_) => Some((STDIN_NAME.to_owned(), file_string)),
+            Err(err) => {
+                eprintln!("Error: could not read stdin: {}", err);
+                None
+            }
+        };
+    }
+
+    read_file(path)
+}
+
+/// Read a source file, returning its name and contents.
+fn read_file(path: &Path) -> Option<(String, String)> {
+    let file_name = match path.file_name() {
+        Some(file_name) => file_name.to_string_lossy().to_string(),
+        None => {
+            println!("Error: {} is not a file", path.display());
+            return None;
+        }
+    };
+    match FsProvider::default().read(path) {
+        Ok(file_string) => Some((file_name, file_string)),
+        Err(err) => {
+            println!("Error: could not read {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+/// Load the WIT at each of the paths.
+fn load_wit(paths: &[PathBuf]) -> Option<Resolve> {
+    match compile_claw::load_wit(&FsProvider::default(), paths) {
+        Ok(wit) => Some(wit),
+        Err(error) => {
+            MessageFormat::Human.print(&error);
+            None
+        }
+    }
+}
+
+/// Load the world named after the last `#` of `spec` from the WIT at the path before it.
+///
+/// Without a `#`, the package's only world is used.
+fn load_world(spec: &str) -> Result<TargetWorld, String> {
+    let (path, name) = match spec.rsplit_once('#') {
+        Some((path, name)) => (path, Some(name)),
+        None => (spec, None),
+    };
+    let mut wit = Resolve::new();
+    let (package, _) = wit
+        .push_path(path)
+        .map_err(|err| format!("could not load WIT from {}: {:?}", path, err))?;
+    let world = wit
+        .select_world(package, name)
+        .map_err(|err| format!("could not find the world in {}: {}", path, err))?;
+    Ok(TargetWorld { wit, world })
+}
+
+fn warning_level(warnings: WarningLevel, deny_warnings: bool) -> WarningLevel {
+    if deny_warnings {
+        WarningLevel::Deny
+    } else {
+        warnings
+    }
+}
+
+/// Print the syntax tree of a Claw source file
+#[derive(Parser, Debug)]
+struct Ast {
+    /// The source file to parse, or `-` to read it from stdin
+    #[clap(conflicts_with = "input-flag", required_unless_present = "input-flag")]
+    input: Option<PathBuf>,
+    /// The source file to parse, for compatibility with older versions
+    #[clap(short = 'i', long = "input", hide = true)]
+    input_flag: Option<PathBuf>,
+    /// Print the tree as JSON, including the span of each node
+    #[clap(long)]
+    json: bool,
+}
+
+/// Print the tokens of a Claw source file with where each one is
+#[derive(Parser, Debug)]
+struct Tokens {
+    /// The source file to lex, or `-` to read it from stdin
+    input: PathBuf,
+}
+
+impl Ast {
+    fn run(self) -> Option<()> {
+        let input = self.input.as_ref().or(self.input_flag.as_ref())?;
+        let (file_name, file_string) = read_source(input)?;
+
+        let mut diagnostics = Diagnostics::new();
+        let result = parse_component(file_name, &file_string, &mut diagnostics);
+        for diagnostic in diagnostics.iter() {
+            MessageFormat::Human.print(diagnostic.as_ref());
+        }
+        let comp = match result {
+            Ok(comp) => comp,
+            Err(error) => {
+                MessageFormat::Human.print(&error);
+                return None;
+            }
+        };
+
+        if self.json {
+            match serde_json::to_string_pretty(&comp) {
+                Ok(json) => println!("{}", json),
+                Err(err) => println!("Error: {:?}", err),
+            }
+        } else {
+            print!("{}", dump_ast(&comp));
+        }
+
+        Some(())
+    }
+}
+
+impl Tokens {
+    fn run(self) -> Option<()> {
+        let (file_name, file_string) = read_source(&self.input)?;
+        print!("{}", dump_tokens(file_name, &file_string));
+        Some(())
+    }
+}
+
+/// Generate a WIT world describing the imports and exports of a Claw source file
+#[derive(Parser, Debug)]
+struct Wit {
+    /// The source file to describe, or `-` to read it from stdin
+    input: PathBuf,
+    /// A WIT file or directory describing the imports
+    #[clap(long)]
+    wit: Option<PathBuf>,
+    /// Where to write the world instead of stdout
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl Wit {
+    fn run(self) -> Option<()> {
+        let (file_name, file_string) = read_source(&self.input)?;
+        let wit = load_wit(self.wit.as_slice())?;
+
+        let (comp, rcomp) = match analyze(
+            file_name.clone(),
+            &file_string,
+            wit,
+            &mut Diagnostics::new(),
+        ) {
+            Ok(analyzed) => analyzed,
+            Err(error) => {
+                MessageFormat::Human.eprint(&error);
+                return None;
+            }
+        };
+        let world = generate_wit(&comp, &rcomp, &file_name);
+
+        match &self.output {
+            Some(output) => {
+                if let Err(err) = fs::write(output, world) {
+                    eprintln!("Error: could not write {}: {}", output.display(), err);
+                    return None;
+                }
+            }
+            None => print!("{}", world),
+        }
+        Some(())
+    }
+}
+
+/// Format Claw source files in place
+#[derive(Parser, Debug)]
+struct Fmt {
+    /// The files to format
+    #[clap(required = true)]
+    inputs: Vec<PathBuf>,
+    /// Don't write the files, only fail if any aren't formatted
+    #[clap(long)]
+    check: bool,
+}
+
+impl Fmt {
+    fn run(self) -> Option<()> {
+        let mut unformatted = false;
+        for input in self.inputs.iter() {
+            let (file_name, file_string) = read_file(input)?;
+
+            let formatted = match format_source(file_name, &file_string) {
+                Ok(formatted) => formatted,
+                Err(error) => {
+                    MessageFormat::Human.print(&error);
+                    return None;
+                }
+            };
+            if formatted == file_string {
+                continue;
+            }
+
+            if self.check {
+                println!("{} is not formatted", input.display());
+                unformatted = true;
+            } else if let Err(err) = fs::write(input, formatted) {
+                println!("Error: {:?}", err);
+                return None;
+            }
+        }
+
+        if unformatted {
+            return None;
+        }
+        Some(())
+    }
+}
+
+#[derive(Parser, Debug)]
+struct Explain {
+    /// The diagnostic code to explain (e.g. E0203)
+    code: String,
+}
+
+impl Explain {
+    fn run(self) -> Option<()> {
+        match explain(&self.code.to_uppercase()) {
+            Some(explanation) => {
+                println!("{}", explanation);
+                Some(())
+            }
+            None => {
+                println!("Error: no explanation exists for code {}", self.code);
+                None
+            }
+        }
+    }
 }
 
 fn main() {
     let args = Arguments::parse();
 
-    match args.command {
-        Command::Compile(compile) => compile.run(),
+    let result = match args.command {
+        Command::Build(build) => build.run(),
+        Command::Check(check) => check.run(),
+        Command::Explain(explain) => explain.run(),
+        Command::Ast(ast) => ast.run(),
+        Command::Tokens(tokens) => tokens.run(),
+        Command::Fmt(fmt) => fmt.run(),
+        Command::Wit(wit) => wit.run(),
+        #[cfg(feature = "repl")]
+        Command::Repl(repl) => repl.run(),
+        #[cfg(feature = "test-runner")]
+        Command::Test(test) => test.run(),
     };
+    if result.is_none() {
+        std::process::exit(1);
+    }
 }
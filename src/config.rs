@@ -0,0 +1,161 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use compile_claw::{OptLevel, OutputFormat, PassKind, WarningLevel, WasmFeatures};
+use serde::Deserialize;
+
+/// The name of the project configuration file.
+pub const CONFIG_FILE: &str = "claw.toml";
+
+/// A project configuration file.
+///
+/// ```toml
+/// [build]
+/// entry = ["src/main.claw", "src/math.claw"]
+/// output = "out/main.wasm"
+/// wit = ["wit"]
+/// opt-level = 1
+///
+/// [dependencies]
+/// math = "deps/math.wasm"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub build: BuildConfig,
+    /// The compiled components the project is linked against, by name
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, PathBuf>,
+}
+
+/// The `[build]` section of a project configuration file.
+///
+/// Paths are relative to the directory containing the file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct BuildConfig {
+    /// The source files compiled together into the component
+    #[serde(default)]
+    pub entry: Vec<PathBuf>,
+    /// Where to write the component
+    pub output: Option<PathBuf>,
+    /// The format to write the component in
+    pub emit: Option<String>,
+    /// The WIT files and directories describing the imports
+    #[serde(default)]
+    pub wit: Vec<PathBuf>,
+    pub opt_level: Option<u8>,
+    pub overflow_checks: Option<bool>,
+    pub warnings: Option<String>,
+    pub inline_threshold: Option<usize>,
+    pub passes: Option<Vec<String>>,
+    pub debug_names: Option<bool>,
+    pub debug_info: Option<bool>,
+    pub metadata: Option<bool>,
+    pub no_alloc: Option<bool>,
+    pub tail_calls: Option<bool>,
+    pub simd: Option<bool>,
+    pub bulk_memory: Option<bool>,
+    /// WebAssembly features to enable or disable, by name, like `sign-ext = false`
+    #[serde(default)]
+    pub features: BTreeMap<String, bool>,
+    /// Optimize the generated code with wasm-opt, passing it these arguments
+    pub wasm_opt: Option<Vec<String>>,
+    /// Compose the component with its dependencies instead of only checking its imports
+    pub compose: Option<bool>,
+}
+
+impl BuildConfig {
+    pub fn opt_level(&self) -> Result<Option<OptLevel>, String> {
+        self.opt_level
+            .map(|level| OptLevel::from_str(&level.to_string()))
+            .transpose()
+    }
+
+    /// The default features, changed by `tail-calls`, `simd`, and `bulk-memory`,
+    /// and then by the `features` table.
+    pub fn features(&self) -> Result<WasmFeatures, String> {
+        let mut features = WasmFeatures::default();
+        let shorthands = [
+            ("tail-call", self.tail_calls),
+            ("simd", self.simd),
+            ("bulk-memory", self.bulk_memory),
+        ];
+        for (name, enabled) in shorthands {
+            if let Some(enabled) = enabled {
+                features.set(name, enabled)?;
+            }
+        }
+        for (name, enabled) in self.features.iter() {
+            features.set(name, *enabled)?;
+        }
+        Ok(features)
+    }
+
+    pub fn emit(&self) -> Result<Option<OutputFormat>, String> {
+        self.emit.as_deref().map(OutputFormat::from_str).transpose()
+    }
+
+    pub fn warnings(&self) -> Result<Option<WarningLevel>, String> {
+        self.warnings
+            .as_deref()
+            .map(WarningLevel::from_str)
+            .transpose()
+    }
+
+    pub fn passes(&self) -> Result<Option<Vec<PassKind>>, String> {
+        self.passes
+            .as_ref()
+            .map(|passes| passes.iter().map(|pass| PassKind::from_str(pass)).collect())
+            .transpose()
+    }
+}
+
+/// A loaded project configuration file and the directory it's in.
+pub struct Project {
+    pub root: PathBuf,
+    pub config: Config,
+}
+
+impl Project {
+    /// Load the configuration file in the current directory or the closest one above it.
+    pub fn find() -> Result<Self, String> {
+        let current = env::current_dir().map_err(|err| err.to_string())?;
+        let root = current
+            .ancestors()
+            .find(|dir| dir.join(CONFIG_FILE).is_file())
+            .ok_or_else(|| format!("no input file given and no {} found", CONFIG_FILE))?;
+        Self::load(root)
+    }
+
+    /// Load the configuration file in `root`.
+    pub fn load(root: &Path) -> Result<Self, String> {
+        let path = root.join(CONFIG_FILE);
+        let contents = fs::read_to_string(&path)
+            .map_err(|err| format!("could not read {}: {}", path.display(), err))?;
+        let config = toml::from_str(&contents)
+            .map_err(|err| format!("invalid {}: {}", path.display(), err))?;
+        Ok(Project {
+            root: root.to_owned(),
+            config,
+        })
+    }
+
+    /// Resolve a path from the configuration file.
+    pub fn path(&self, path: &Path) -> PathBuf {
+        self.root.join(path)
+    }
+
+    /// The name of the project, after its directory.
+    pub fn name(&self) -> String {
+        match self.root.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => "project".to_owned(),
+        }
+    }
+}
@@ -0,0 +1,511 @@
+//! A language server for Claw, built with `--features lsp`.
+//!
+//! It speaks LSP over stdio and re-analyzes a document whenever it's needed,
+//! which is fast enough for the size of source files Claw programs have.
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use claw_ast::{self as ast, NodeId, SymbolKind};
+use claw_common::{Diagnostics, LineCol, LineIndex};
+use claw_resolver::{ItemId, ResolvedComponent};
+use compile_claw::{analyze, semantic_tokens, SemanticKind, SemanticToken};
+use cranelift_entity::EntityRef;
+use lsp_server::{Connection, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{
+    DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as RequestTrait,
+    SemanticTokensFullRequest,
+};
+use lsp_types::{
+    DiagnosticSeverity, DocumentSymbolResponse, GotoDefinitionResponse, Hover, HoverContents,
+    HoverProviderCapability, Location, MarkupContent, MarkupKind, NumberOrString, OneOf, Position,
+    PublishDiagnosticsParams, Range, SemanticToken as LspSemanticToken, SemanticTokenType,
+    SemanticTokens, SemanticTokensFullOptions, SemanticTokensLegend, SemanticTokensOptions,
+    SemanticTokensResult, SemanticTokensServerCapabilities, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use miette::{Diagnostic, Severity, SourceSpan};
+use wit_parser::Resolve;
+
+fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+            SemanticTokensOptions {
+                legend: SemanticTokensLegend {
+                    token_types: TOKEN_TYPES.iter().map(|(_, lsp)| lsp.clone()).collect(),
+                    token_modifiers: Vec::new(),
+                },
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                ..Default::default()
+            },
+        )),
+        ..Default::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    main_loop(connection)?;
+    io_threads.join()?;
+    Ok(())
+}
+
+type LspResult = Result<(), Box<dyn Error + Sync + Send>>;
+
+/// Handle messages until the client shuts the server down.
+///
+/// This takes the connection so it's closed before waiting for the IO threads.
+fn main_loop(connection: Connection) -> LspResult {
+    let mut server = Server {
+        connection: &connection,
+        documents: HashMap::new(),
+    };
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    break;
+                }
+                server.request(request)?;
+            }
+            Message::Notification(notification) => server.notification(notification)?,
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+struct Server<'a> {
+    connection: &'a Connection,
+    /// The text of each open document
+    documents: HashMap<Url, String>,
+}
+
+/// The result of analyzing a document.
+struct Analysis {
+    index: LineIndex,
+    result: Result<(ast::Component, ResolvedComponent), compile_claw::Error>,
+    warnings: Diagnostics,
+}
+
+impl<'a> Server<'a> {
+    fn notification(&mut self, notification: Notification) -> LspResult {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: lsp_types::DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let document = params.text_document;
+                self.documents.insert(document.uri.clone(), document.text);
+                self.publish_diagnostics(document.uri)
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: lsp_types::DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                // With full syncing the last change has the whole text
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    let uri = params.text_document.uri;
+                    self.documents.insert(uri.clone(), change.text);
+                    self.publish_diagnostics(uri)?;
+                }
+                Ok(())
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: lsp_types::DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                self.send_diagnostics(uri, Vec::new())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn request(&mut self, request: Request) -> LspResult {
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            HoverRequest::METHOD => {
+                let params: lsp_types::HoverParams = serde_json::from_value(request.params)?;
+                let position = params.text_document_position_params;
+                let hover = self.hover(&position.text_document.uri, position.position);
+                serde_json::to_value(hover)?
+            }
+            GotoDefinition::METHOD => {
+                let params: lsp_types::GotoDefinitionParams =
+                    serde_json::from_value(request.params)?;
+                let position = params.text_document_position_params;
+                let definition = self.definition(position.text_document.uri, position.position);
+                serde_json::to_value(definition)?
+            }
+            DocumentSymbolRequest::METHOD => {
+                let params: lsp_types::DocumentSymbolParams =
+                    serde_json::from_value(request.params)?;
+                let symbols = self.document_symbols(&params.text_document.uri);
+                serde_json::to_value(symbols)?
+            }
+            SemanticTokensFullRequest::METHOD => {
+                let params: lsp_types::SemanticTokensParams =
+                    serde_json::from_value(request.params)?;
+                let tokens = self.semantic_tokens(&params.text_document.uri);
+                serde_json::to_value(tokens)?
+            }
+            _ => return self.respond(Response::new_err(id, -32601, "Unknown method".to_owned())),
+        };
+        self.respond(Response::new_ok(id, result))
+    }
+
+    fn respond(&self, response: Response) -> LspResult {
+        self.connection.sender.send(Message::Response(response))?;
+        Ok(())
+    }
+
+    fn analyze(&self, uri: &Url) -> Option<Analysis> {
+        let text = self.documents.get(uri)?;
+        let name = uri
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .unwrap_or("<document>")
+            .to_owned();
+
+        // Use the WIT in a `wit` directory next to the file, like the CLI's `--wit`
+        let mut wit = Resolve::new();
+        if let Ok(path) = uri.to_file_path() {
+            if let Some(wit_dir) = path.parent().map(|parent| parent.join("wit")) {
+                if wit_dir.is_dir() {
+                    let _ = wit.push_path(wit_dir);
+                }
+            }
+        }
+
+        let mut warnings = Diagnostics::new();
+        let result = analyze(name, text, wit, &mut warnings);
+        Some(Analysis {
+            index: LineIndex::new(text),
+            result,
+            warnings,
+        })
+    }
+
+    fn publish_diagnostics(&self, uri: Url) -> LspResult {
+        let analysis = match self.analyze(&uri) {
+            Some(analysis) => analysis,
+            None => return Ok(()),
+        };
+        let mut diagnostics = Vec::new();
+        if let Err(error) = &analysis.result {
+            push_diagnostics(&analysis.index, error, &mut diagnostics);
+        }
+        for warning in analysis.warnings.iter() {
+            push_diagnostics(&analysis.index, warning.as_ref(), &mut diagnostics);
+        }
+        self.send_diagnostics(uri, diagnostics)
+    }
+
+    fn send_diagnostics(&self, uri: Url, diagnostics: Vec<lsp_types::Diagnostic>) -> LspResult {
+        let params = PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version: None,
+        };
+        let notification = Notification::new(PublishDiagnostics::METHOD.to_owned(), params);
+        self.connection
+            .sender
+            .send(Message::Notification(notification))?;
+        Ok(())
+    }
+
+    fn hover(&self, uri: &Url, position: Position) -> Option<Hover> {
+        let analysis = self.analyze(uri)?;
+        let (comp, rcomp) = analysis.result.as_ref().ok()?;
+        let offset = to_offset(&analysis.index, position)?;
+        let text = describe(comp, rcomp, offset)?;
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```claw\n{}\n```", text),
+            }),
+            range: None,
+        })
+    }
+
+    fn definition(&self, uri: Url, position: Position) -> Option<GotoDefinitionResponse> {
+        let analysis = self.analyze(&uri)?;
+        let (comp, rcomp) = analysis.result.as_ref().ok()?;
+        let offset = to_offset(&analysis.index, position)?;
+        let span = rcomp.definition(comp, offset)?;
+        Some(GotoDefinitionResponse::Scalar(Location {
+            uri,
+            range: to_range(&analysis.index, span),
+        }))
+    }
+
+    fn semantic_tokens(&self, uri: &Url) -> Option<SemanticTokensResult> {
+        let analysis = self.analyze(uri)?;
+        let (comp, rcomp) = analysis.result.as_ref().ok()?;
+        let tokens = semantic_tokens(comp, rcomp);
+        Some(SemanticTokensResult::Tokens(SemanticTokens {
+            result_id: None,
+            data: encode_semantic_tokens(&analysis.index, tokens),
+        }))
+    }
+
+    fn document_symbols(&self, uri: &Url) -> Option<DocumentSymbolResponse> {
+        let analysis = self.analyze(uri)?;
+        let (comp, _) = analysis.result.as_ref().ok()?;
+        let symbols = comp
+            .document_symbols()
+            .into_iter()
+            .map(|symbol| {
+                let range = to_range(&analysis.index, symbol.span);
+                #[allow(deprecated)]
+                lsp_types::DocumentSymbol {
+                    name: symbol.name,
+                    detail: Some(symbol.detail),
+                    kind: match symbol.kind {
+                        SymbolKind::Import => lsp_types::SymbolKind::MODULE,
+                        SymbolKind::Global => lsp_types::SymbolKind::VARIABLE,
+                        SymbolKind::Function => lsp_types::SymbolKind::FUNCTION,
+                    },
+                    tags: None,
+                    deprecated: None,
+                    range,
+                    selection_range: range,
+                    children: None,
+                }
+            })
+            .collect();
+        Some(DocumentSymbolResponse::Nested(symbols))
+    }
+}
+
+/// The semantic token types in the order of the legend sent to the client.
+const TOKEN_TYPES: [(SemanticKind, SemanticTokenType); 10] = [
+    (SemanticKind::Keyword, SemanticTokenType::KEYWORD),
+    (SemanticKind::Type, SemanticTokenType::TYPE),
+    (SemanticKind::Function, SemanticTokenType::FUNCTION),
+    (SemanticKind::Parameter, SemanticTokenType::PARAMETER),
+    (SemanticKind::Variable, SemanticTokenType::VARIABLE),
+    (SemanticKind::EnumMember, SemanticTokenType::ENUM_MEMBER),
+    (SemanticKind::Number, SemanticTokenType::NUMBER),
+    (SemanticKind::String, SemanticTokenType::STRING),
+    (SemanticKind::Operator, SemanticTokenType::OPERATOR),
+    (SemanticKind::Comment, SemanticTokenType::COMMENT),
+];
+
+/// Encode semantic tokens relative to the previous one, as LSP expects.
+///
+/// Tokens spanning several lines are cut off at the end of their first line.
+fn encode_semantic_tokens(index: &LineIndex, tokens: Vec<SemanticToken>) -> Vec<LspSemanticToken> {
+    let mut encoded = Vec::with_capacity(tokens.len());
+    let mut previous = Position::default();
+    for token in tokens {
+        let (start, end) = index.range(token.span);
+        let end = match end.line == start.line {
+            true => end,
+            false => index.line_col(index.line_end(start.line)),
+        };
+        let (start, end) = (to_position(index, start), to_position(index, end));
+        let token_type = TOKEN_TYPES
+            .iter()
+            .position(|(kind, _)| *kind == token.kind)
+            .unwrap_or_default();
+        let delta_line = start.line - previous.line;
+        let delta_start = match delta_line {
+            0 => start.character - previous.character,
+            _ => start.character,
+        };
+        encoded.push(LspSemanticToken {
+            delta_line,
+            delta_start,
+            length: end.character - start.character,
+            token_type: token_type as u32,
+            token_modifiers_bitset: 0,
+        });
+        previous = start;
+    }
+    encoded
+}
+
+/// Convert a diagnostic and the ones related to it into LSP diagnostics.
+fn push_diagnostics(
+    index: &LineIndex,
+    diagnostic: &dyn Diagnostic,
+    out: &mut Vec<lsp_types::Diagnostic>,
+) {
+    let span = diagnostic
+        .labels()
+        .and_then(|mut labels| labels.next())
+        .map(|label| *label.inner());
+    let range = match span {
+        Some(span) => to_range(index, span),
+        None => Range::default(),
+    };
+    let severity = match diagnostic.severity().unwrap_or(Severity::Error) {
+        Severity::Error => DiagnosticSeverity::ERROR,
+        Severity::Warning => DiagnosticSeverity::WARNING,
+        Severity::Advice => DiagnosticSeverity::HINT,
+    };
+    out.push(lsp_types::Diagnostic {
+        range,
+        severity: Some(severity),
+        code: diagnostic
+            .code()
+            .map(|code| NumberOrString::String(code.to_string())),
+        source: Some("claw".to_owned()),
+        message: diagnostic.to_string(),
+        ..Default::default()
+    });
+    if let Some(related) = diagnostic.related() {
+        for related in related {
+            push_diagnostics(index, related, out);
+        }
+    }
+}
+
+/// A short description of the name or expression at `offset`.
+fn describe(comp: &ast::Component, rcomp: &ResolvedComponent, offset: usize) -> Option<String> {
+    let imports = &rcomp.imports;
+    match comp.node_at(offset)? {
+        NodeId::Name(name) => {
+            for (function, resolved) in rcomp.funcs.iter() {
+                if let Some(item) = resolved.bindings.get(&name) {
+                    return describe_item(comp, rcomp, *function, *item);
+                }
+                // The name a local is declared with isn't bound to it
+                let local = resolved
+                    .locals
+                    .iter()
+                    .find(|(_, local)| local.ident == name);
+                if let Some((local, _)) = local {
+                    return describe_item(comp, rcomp, *function, ItemId::Local(local));
+                }
+            }
+            let span = comp.name_span(name);
+            let symbol = comp
+                .document_symbols()
+                .into_iter()
+                .find(|symbol| symbol.span == span);
+            if let Some(symbol) = symbol {
+                return Some(format!("{} {}", symbol.name, symbol.detail));
+            }
+            comp.iter_functions()
+                .flat_map(|(_, function)| function.params.iter())
+                .find(|(param, _)| *param == name)
+                .map(|(param, type_id)| {
+                    format!(
+                        "{}: {}",
+                        comp.get_name(*param),
+                        comp.get_type(*type_id).name(comp)
+                    )
+                })
+        }
+        NodeId::Expression(expression) => rcomp.funcs.values().find_map(|resolved| {
+            let rtype = resolved.expression_types.get(&expression)?;
+            Some(rtype.name(comp, imports))
+        }),
+        NodeId::Type(type_id) => Some(comp.get_type(type_id).name(comp)),
+        NodeId::Statement(_) => None,
+    }
+}
+
+/// Describe an item that a name used in `function` refers to.
+fn describe_item(
+    comp: &ast::Component,
+    rcomp: &ResolvedComponent,
+    function: ast::FunctionId,
+    item: ItemId,
+) -> Option<String> {
+    let imports = &rcomp.imports;
+    let text = match item {
+        ItemId::Global(global) => {
+            let global = comp.get_global(global);
+            let mutable = if global.mutable { "mut " } else { "" };
+            format!(
+                "let {}{}: {}",
+                mutable,
+                comp.get_name(global.ident),
+                comp.get_type(global.type_id).name(comp)
+            )
+        }
+        ItemId::Function(function) => {
+            let function = comp.get_function(function);
+            format!(
+                "func {}{}",
+                comp.get_name(function.ident),
+                ast::signature(comp, &function.params, function.results)
+            )
+        }
+        ItemId::Param(param) => {
+            let (name, type_id) = &comp.get_function(function).params[param.index()];
+            format!(
+                "{}: {}",
+                comp.get_name(*name),
+                comp.get_type(*type_id).name(comp)
+            )
+        }
+        ItemId::Local(local) => {
+            let resolved = rcomp.function(function)?;
+            let info = &resolved.locals[local];
+            let mutable = if info.mutable { "mut " } else { "" };
+            let rtype = resolved.local_types.get(&local)?;
+            format!(
+                "let {}{}: {}",
+                mutable,
+                comp.get_name(info.ident),
+                rtype.name(comp, imports)
+            )
+        }
+        ItemId::ImportFunc(import) => {
+            let import = &imports.funcs[import];
+            let params: Vec<String> = import
+                .params
+                .iter()
+                .map(|(name, rtype)| format!("{}: {}", name, rtype.name(comp, imports)))
+                .collect();
+            let mut text = format!("import func {}({})", import.alias, params.join(", "));
+            if let Some(results) = import.results {
+                text.push_str(&format!(" -> {}", results.name(comp, imports)));
+            }
+            text
+        }
+        ItemId::ImportValue(import) => {
+            let import = &imports.funcs[import];
+            let rtype = import.results?;
+            format!("import {}: {}", import.alias, rtype.name(comp, imports))
+        }
+        ItemId::Type(rtype) => format!("type {}", rtype.name(comp, imports)),
+        ItemId::Builtin(builtin) => builtin.describe(),
+    };
+    Some(text)
+}
+
+fn to_range(index: &LineIndex, span: SourceSpan) -> Range {
+    let (start, end) = index.range(span);
+    Range {
+        start: to_position(index, start),
+        end: to_position(index, end),
+    }
+}
+
+fn to_position(index: &LineIndex, position: LineCol) -> Position {
+    let position = index.to_utf16(position);
+    Position {
+        line: position.line as u32,
+        character: position.column as u32,
+    }
+}
+
+fn to_offset(index: &LineIndex, position: Position) -> Option<usize> {
+    let position = index.from_utf16(LineCol {
+        line: position.line as usize,
+        column: position.character as usize,
+    })?;
+    index.offset(position)
+}
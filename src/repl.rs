@@ -0,0 +1,306 @@
+use std::io::{self, BufRead, Write};
+
+use clap::Parser;
+
+use claw_common::{make_source, Diagnostics, Interner};
+use claw_parser::{tokenize, Token};
+use compile_claw::{
+    analyze, ast, compile_with_options, parse_component, CompileOptions, OptLevel, WarningLevel,
+};
+use wasmtime::component::{Component, Linker, Val};
+use wasmtime::{Config, Engine, Store};
+use wit_parser::Resolve;
+
+use crate::MessageFormat;
+
+/// The name of the synthetic function that input is compiled into
+const EVAL: &str = "repl-eval";
+/// The name of the function used to find the type of an expression
+const PROBE: &str = "repl-probe";
+
+/// Evaluate Claw expressions and statements interactively
+///
+/// Items (globals, functions, and imports) are kept for later inputs.
+/// Everything else is wrapped in an exported function, compiled,
+/// and run in a new instance, so assignments to globals don't persist.
+#[derive(Parser, Debug)]
+pub struct Repl {
+    /// Trap on integer overflow instead of wrapping
+    #[clap(long)]
+    overflow_checks: bool,
+    /// How much to optimize the generated code (0, 1, or 2)
+    #[clap(short = 'O', long, default_value = "0")]
+    opt_level: OptLevel,
+}
+
+impl Repl {
+    pub fn run(self) -> Option<()> {
+        let mut config = Config::new();
+        config.wasm_component_model(true);
+        let engine = match Engine::new(&config) {
+            Ok(engine) => engine,
+            Err(err) => {
+                println!("Error: {:?}", err);
+                return None;
+            }
+        };
+
+        let mut session = Session {
+            options: CompileOptions {
+                warnings: WarningLevel::Allow,
+                overflow_checks: self.overflow_checks,
+                opt_level: self.opt_level,
+                ..Default::default()
+            },
+            engine,
+            items: String::new(),
+        };
+
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        loop {
+            let input = match read_input(&mut lines) {
+                Some(input) => input,
+                None => return Some(()),
+            };
+            match input.trim() {
+                "" => continue,
+                ":quit" => return Some(()),
+                input => session.enter(input),
+            }
+        }
+    }
+}
+
+/// Read lines until the braces in them are balanced, or [None] at the end of input.
+fn read_input(lines: &mut impl Iterator<Item = io::Result<String>>) -> Option<String> {
+    let mut input = String::new();
+    let mut depth = 0;
+    loop {
+        print!("{}", if input.is_empty() { "> " } else { ". " });
+        io::stdout().flush().ok()?;
+
+        let line = lines.next()?.ok()?;
+        for c in line.chars() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        input.push_str(&line);
+        input.push('\n');
+        if depth <= 0 {
+            return Some(input);
+        }
+    }
+}
+
+struct Session {
+    options: CompileOptions,
+    engine: Engine,
+    /// The source of the items entered so far
+    items: String,
+}
+
+impl Session {
+    /// Handle one input, printing its result or any errors.
+    fn enter(&mut self, input: &str) {
+        let first_word = input.split_whitespace().next().unwrap_or_default();
+        if matches!(first_word, "let" | "func" | "export" | "import") {
+            let items = format!("{}{}\n", self.items, input);
+            if self.compile(&items, true).is_some() {
+                self.items = items;
+            }
+            return;
+        }
+
+        let expression = input.trim_end_matches(';');
+        let type_name = match self.expression_type(expression) {
+            Ok(type_name) => type_name,
+            Err(None) => return self.execute(input),
+            Err(Some(error)) => {
+                // Calls to functions without results are statements
+                let statement = format!("{};", expression);
+                match self.compile(&self.wrap(&statement, None), false) {
+                    Some(wasm) => {
+                        self.call(&wasm);
+                    }
+                    None => MessageFormat::Human.print(error.as_ref()),
+                }
+                return;
+            }
+        };
+
+        let body = format!("return {};", expression);
+        let wasm = match self.compile(&self.wrap(&body, Some(&type_name)), true) {
+            Some(wasm) => wasm,
+            None => return,
+        };
+        if let Some(value) = self.call(&wasm) {
+            println!("{}: {}", display_value(&value), type_name);
+        }
+    }
+
+    /// Run statements, which have no result to print.
+    fn execute(&self, statements: &str) {
+        if let Some(wasm) = self.compile(&self.wrap(statements, None), true) {
+            self.call(&wasm);
+        }
+    }
+
+    /// The source of the items followed by `body` in the synthetic function.
+    fn wrap(&self, body: &str, result: Option<&str>) -> String {
+        let result = match result {
+            Some(result) => format!(" -> {}", result),
+            None => String::new(),
+        };
+        format!(
+            "{}export func {}(){} {{\n{}\n}}\n",
+            self.items, EVAL, result, body
+        )
+    }
+
+    /// Find the name of the type of an expression.
+    ///
+    /// Fails with [None] if the input isn't an expression, or with
+    /// the error from resolving it with the last fallback type.
+    fn expression_type(
+        &self,
+        expression: &str,
+    ) -> Result<String, Option<Box<compile_claw::Error>>> {
+        let probe = |annotation: Option<&str>| {
+            let annotation = match annotation {
+                Some(annotation) => format!(": {}", annotation),
+                None => String::new(),
+            };
+            format!(
+                "{}export func {}() {{\nlet value{} = {};\n}}\n",
+                self.items, PROBE, annotation, expression
+            )
+        };
+
+        let source = probe(None);
+        if parse_component("repl".to_owned(), &source, &mut Diagnostics::new()).is_err() {
+            return Err(None);
+        }
+
+        let fallbacks = fallback_types(expression);
+        let annotations = std::iter::once(None).chain(fallbacks.iter().copied().map(Some));
+        let mut last_error = None;
+        for annotation in annotations {
+            let source = probe(annotation);
+            match analyze(
+                "repl".to_owned(),
+                &source,
+                Resolve::new(),
+                &mut Diagnostics::new(),
+            ) {
+                Ok((comp, rcomp)) => {
+                    let (id, function) = comp
+                        .iter_functions()
+                        .find(|(_, function)| comp.get_name(function.ident) == PROBE)
+                        .unwrap();
+                    let expression = match comp.get_statement(function.body[0]) {
+                        ast::Statement::Let(ast::Let {
+                            expression: Some(expression),
+                            ..
+                        }) => *expression,
+                        _ => unreachable!("The probe starts with an initialized let statement"),
+                    };
+                    if let Some(expression_type) = rcomp.expression_type(id, expression) {
+                        return Ok(expression_type.name(&comp, &rcomp.imports));
+                    }
+                }
+                Err(error) => last_error = Some(Box::new(error)),
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Compile a source file, optionally printing any errors.
+    fn compile(&self, source: &str, print_errors: bool) -> Option<Vec<u8>> {
+        let result = compile_with_options(
+            "repl".to_owned(),
+            source,
+            Resolve::new(),
+            &self.options,
+            &mut Diagnostics::new(),
+        );
+        match result {
+            Ok(wasm) => Some(wasm),
+            Err(error) => {
+                if print_errors {
+                    MessageFormat::Human.print(&error);
+                }
+                None
+            }
+        }
+    }
+
+    /// Instantiate a compiled component and call its synthetic function,
+    /// printing any errors.
+    fn call(&self, wasm: &[u8]) -> Option<Val> {
+        let result = (|| -> wasmtime::Result<Option<Val>> {
+            let component = Component::new(&self.engine, wasm)?;
+            let linker = Linker::new(&self.engine);
+            let mut store = Store::new(&self.engine, ());
+            let instance = linker.instantiate(&mut store, &component)?;
+            let func = match instance.get_func(&mut store, EVAL) {
+                Some(func) => func,
+                None => {
+                    let message = format!("the component doesn't export {}", EVAL);
+                    return Err(wasmtime::Error::msg(message));
+                }
+            };
+            let mut results = vec![Val::Bool(false); func.results(&store).len()];
+            func.call(&mut store, &[], &mut results)?;
+            func.post_return(&mut store)?;
+            Ok(results.pop())
+        })();
+        match result {
+            Ok(value) => value,
+            Err(err) => {
+                println!("Error: {:?}", err);
+                None
+            }
+        }
+    }
+}
+
+/// The types tried, in order, for expressions whose type can't be inferred.
+///
+/// Literals default to `s64`, or to `f64` if any of them are floats.
+fn fallback_types(expression: &str) -> [&'static str; 2] {
+    let src = make_source("repl", expression);
+    let has_float = match tokenize(src, expression, &mut Interner::new()) {
+        Ok(tokens) => tokens
+            .iter()
+            .any(|data| matches!(data.token, Token::FloatLiteral(_))),
+        Err(_) => false,
+    };
+    match has_float {
+        true => ["f64", "s64"],
+        false => ["s64", "f64"],
+    }
+}
+
+/// Format a value the way it would be written in Claw.
+fn display_value(value: &Val) -> String {
+    match value {
+        Val::Bool(value) => value.to_string(),
+        Val::S8(value) => value.to_string(),
+        Val::U8(value) => value.to_string(),
+        Val::S16(value) => value.to_string(),
+        Val::U16(value) => value.to_string(),
+        Val::S32(value) => value.to_string(),
+        Val::U32(value) => value.to_string(),
+        Val::S64(value) => value.to_string(),
+        Val::U64(value) => value.to_string(),
+        Val::Float32(value) => value.to_string(),
+        Val::Float64(value) => value.to_string(),
+        Val::String(value) => format!("{:?}", value),
+        Val::Enum(case) => case.clone(),
+        value => format!("{:?}", value),
+    }
+}
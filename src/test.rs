@@ -0,0 +1,322 @@
+use std::ops::Range;
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use claw_common::Diagnostics;
+use compile_claw::{compile_with_options, CompileOptions, OptLevel, WarningLevel};
+use wasmparser::{BinaryReader, Payload};
+use wasmtime::component::{Component, Linker, Val};
+use wasmtime::{Config, Engine, Store, WasmBacktrace};
+
+use crate::{load_wit, read_source, MessageFormat};
+
+/// The prefix of the names of exported test functions
+const TEST_PREFIX: &str = "test-";
+
+/// Run the test functions of a Claw source file
+///
+/// Every exported function named `test-*` without parameters is a test.
+/// It passes if it returns without trapping, and if it has a `bool` result,
+/// that result is `true`. Each test runs in a new instance, so changes to
+/// globals don't carry over between tests.
+#[derive(Parser, Debug)]
+pub struct Test {
+    /// The source file to test
+    input: PathBuf,
+    /// A WIT file or directory describing the imports
+    #[clap(long)]
+    wit: Option<PathBuf>,
+    /// Only run the tests whose names contain this
+    filter: Option<String>,
+    /// Wrap on integer overflow instead of trapping
+    #[clap(long)]
+    no_overflow_checks: bool,
+    /// How much to optimize the generated code (0, 1, or 2)
+    #[clap(short = 'O', long, default_value = "0")]
+    opt_level: OptLevel,
+}
+
+impl Test {
+    pub fn run(self) -> Option<()> {
+        let (file_name, file_string) = read_source(&self.input)?;
+        let wit = load_wit(self.wit.as_slice())?;
+
+        let options = CompileOptions {
+            warnings: WarningLevel::Allow,
+            overflow_checks: !self.no_overflow_checks,
+            opt_level: self.opt_level,
+            debug_names: true,
+            debug_info: true,
+            ..Default::default()
+        };
+        let result = compile_with_options(
+            file_name,
+            &file_string,
+            wit,
+            &options,
+            &mut Diagnostics::new(),
+        );
+        let wasm = match result {
+            Ok(wasm) => wasm,
+            Err(error) => {
+                MessageFormat::Human.print(&error);
+                return None;
+            }
+        };
+        let source_map = SourceMap::find(&wasm);
+
+        let mut config = Config::new();
+        config.wasm_component_model(true);
+        let engine = Engine::new(&config).ok()?;
+        let component = match Component::new(&engine, &wasm) {
+            Ok(component) => component,
+            Err(err) => {
+                println!("Error: {:?}", err);
+                return None;
+            }
+        };
+
+        let names = test_names(&engine, &component, self.filter.as_deref());
+        let plural = if names.len() == 1 { "" } else { "s" };
+        println!("running {} test{}", names.len(), plural);
+        let mut failures = Vec::new();
+        for name in names.iter() {
+            match run_test(&engine, &component, name) {
+                Ok(()) => println!("test {} ... ok", name),
+                Err(failure) => {
+                    println!("test {} ... FAILED", name);
+                    failures.push((name, failure));
+                }
+            }
+        }
+
+        if !failures.is_empty() {
+            println!();
+            println!("failures:");
+            for (name, failure) in failures.iter() {
+                println!();
+                println!("---- {} ----", name);
+                failure.print(source_map.as_ref());
+            }
+        }
+
+        let passed = names.len() - failures.len();
+        let status = match failures.is_empty() {
+            true => "ok",
+            false => "FAILED",
+        };
+        println!();
+        println!(
+            "test result: {}. {} passed; {} failed",
+            status,
+            passed,
+            failures.len()
+        );
+        failures.is_empty().then_some(())
+    }
+}
+
+/// The names of the exported test functions, in the order they're exported.
+fn test_names(engine: &Engine, component: &Component, filter: Option<&str>) -> Vec<String> {
+    component
+        .component_type()
+        .exports(engine)
+        .map(|(name, _)| name.to_owned())
+        .filter(|name| name.starts_with(TEST_PREFIX))
+        .filter(|name| match filter {
+            Some(filter) => name.contains(filter),
+            None => true,
+        })
+        .collect()
+}
+
+/// Why a test failed.
+enum Failure {
+    /// It couldn't be run, like when it has parameters or needs imports
+    Error(wasmtime::Error),
+    /// It trapped, with the backtrace when there is one
+    Trap(wasmtime::Error),
+    /// It returned `false`
+    False,
+}
+
+impl Failure {
+    fn print(&self, source_map: Option<&SourceMap>) {
+        match self {
+            Failure::Error(err) => println!("Error: {:?}", err),
+            Failure::False => println!("returned false"),
+            Failure::Trap(err) => {
+                match err.downcast_ref::<wasmtime::Trap>() {
+                    Some(trap) => println!("trapped: {}", trap),
+                    None => println!("trapped: {}", err),
+                }
+                if let Some(backtrace) = err.downcast_ref::<WasmBacktrace>() {
+                    print_backtrace(backtrace, source_map);
+                }
+            }
+        }
+    }
+}
+
+/// Instantiate the component and call one of its test functions.
+fn run_test(engine: &Engine, component: &Component, name: &str) -> Result<(), Failure> {
+    let linker = Linker::new(engine);
+    let mut store = Store::new(engine, ());
+    let instance = linker
+        .instantiate(&mut store, component)
+        .map_err(Failure::Error)?;
+    let func = instance
+        .get_func(&mut store, name)
+        .ok_or_else(|| Failure::Error(wasmtime::Error::msg("the test isn't a function")))?;
+    if !func.params(&store).is_empty() {
+        let message = "test functions can't have parameters";
+        return Err(Failure::Error(wasmtime::Error::msg(message)));
+    }
+
+    let mut results = vec![Val::Bool(false); func.results(&store).len()];
+    func.call(&mut store, &[], &mut results)
+        .map_err(Failure::Trap)?;
+    func.post_return(&mut store).map_err(Failure::Error)?;
+    match results.as_slice() {
+        [Val::Bool(false)] => Err(Failure::False),
+        _ => Ok(()),
+    }
+}
+
+fn print_backtrace(backtrace: &WasmBacktrace, source_map: Option<&SourceMap>) {
+    println!("backtrace:");
+    for (i, frame) in backtrace.frames().iter().enumerate() {
+        let name = frame.func_name().unwrap_or("<unknown>");
+        let location = frame
+            .module_offset()
+            .and_then(|offset| source_map?.location(offset));
+        match location {
+            Some(location) => println!("  {}: {} at {}", i, name, location),
+            None => println!("  {}: {}", i, name),
+        }
+    }
+}
+
+/// The source map embedded in the core module with the generated code,
+/// for finding the statement an instruction came from.
+struct SourceMap {
+    source: String,
+    /// The line and column of the statement at each offset, starting from zero and
+    /// sorted by offset. Offsets are into the component, like those in backtraces.
+    entries: Vec<(usize, i64, i64)>,
+    /// The range of the module's code section, by offset into the component
+    code: Range<usize>,
+}
+
+impl SourceMap {
+    fn find(component: &[u8]) -> Option<Self> {
+        for payload in wasmparser::Parser::new(0).parse_all(component) {
+            if let Ok(Payload::ModuleSection {
+                unchecked_range, ..
+            }) = payload
+            {
+                let start = unchecked_range.start;
+                if let Some(source_map) = Self::parse(&component[unchecked_range], start) {
+                    return Some(source_map);
+                }
+            }
+        }
+        None
+    }
+
+    /// Parse the source map of a core module that starts at `start` in the component.
+    fn parse(module: &[u8], start: usize) -> Option<Self> {
+        let mut code: Option<Range<usize>> = None;
+        let mut url = None;
+        for payload in wasmparser::Parser::new(0).parse_all(module) {
+            match payload.ok()? {
+                Payload::CodeSectionStart { range, .. } => code = Some(range),
+                Payload::CustomSection(reader) if reader.name() == "sourceMappingURL" => {
+                    let mut data = BinaryReader::new(reader.data());
+                    url = Some(data.read_string().ok()?.to_owned());
+                }
+                _ => {}
+            }
+        }
+
+        let code = code?;
+        let map = url?;
+        let map = map.strip_prefix("data:application/json;base64,")?;
+        let map: serde_json::Value = serde_json::from_slice(&decode_base64(map)?).ok()?;
+        let source = map["sources"][0].as_str()?.to_owned();
+
+        let mut entries = Vec::new();
+        let (mut offset, mut line, mut column) = (0, 0, 0);
+        for segment in map["mappings"].as_str()?.split(',') {
+            let fields = decode_vlq(segment)?;
+            offset += *fields.first()?;
+            line += *fields.get(2)?;
+            column += *fields.get(3)?;
+            entries.push((start + offset as usize, line, column));
+        }
+        Some(Self {
+            source,
+            entries,
+            code: start + code.start..start + code.end,
+        })
+    }
+
+    /// The location of the statement that the instruction at an offset into
+    /// the component came from.
+    fn location(&self, offset: usize) -> Option<String> {
+        if !self.code.contains(&offset) {
+            return None;
+        }
+        let index = self.entries.partition_point(|(start, ..)| *start <= offset);
+        let (_, line, column) = self.entries[index.checked_sub(1)?];
+        Some(format!("{}:{}:{}", self.source, line + 1, column + 1))
+    }
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_digit(c: u8) -> Option<u32> {
+    BASE64
+        .iter()
+        .position(|digit| *digit == c)
+        .map(|i| i as u32)
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text.bytes().filter(|c| *c != b'=') {
+        buffer = (buffer << 6) | base64_digit(c)?;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+/// Decode the base64 VLQ numbers in a source map segment.
+fn decode_vlq(segment: &str) -> Option<Vec<i64>> {
+    let mut values = Vec::new();
+    let (mut value, mut shift) = (0u64, 0);
+    for c in segment.bytes() {
+        let digit = base64_digit(c)? as u64;
+        value |= (digit & 0b11111) << shift;
+        shift += 5;
+        // Without the continuation bit, this is the last digit
+        if digit & 0b100000 == 0 {
+            let magnitude = (value >> 1) as i64;
+            values.push(if value & 1 == 1 {
+                -magnitude
+            } else {
+                magnitude
+            });
+            value = 0;
+            shift = 0;
+        }
+    }
+    Some(values)
+}